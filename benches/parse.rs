@@ -0,0 +1,45 @@
+//! How long it takes to parse a big config, since it's parsed on every run.
+//!
+//! Run with `cargo bench --bench parse`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use feedburst::parser::parse_config;
+
+/// A config with `feeds` feeds, using a spread of the policies and directives
+/// people actually write.
+fn big_config(feeds: usize) -> String {
+    let mut config = String::from("# Comics\nroot /home/me/comics\nlocale fr\n");
+    for i in 0..feeds {
+        if i % 100 == 0 {
+            config.push_str(&format!(
+                "\n# Section {}\ncommand firefox --new-tab\n",
+                i / 100
+            ));
+        }
+        config.push_str(&format!(
+            "\"Comic Number {}\" <http://comic{}.example.com/feed.xml>",
+            i, i
+        ));
+        config.push_str(match i % 4 {
+            0 => " @ on Samedi @ 2 new comics",
+            1 => " @ every 3 days @ overlap 1 comic",
+            2 => " @ check every 2 days @ remind after 7 days @ open all",
+            _ => " @ ON friday @ max 20 comics per fetch",
+        });
+        if i % 10 == 0 {
+            config.push_str(" @ ignore title /Filler/");
+        }
+        config.push('\n');
+    }
+    config
+}
+
+fn parse(c: &mut Criterion) {
+    let config = big_config(1000);
+    c.bench_function("parse 1,000 feeds", |b| {
+        b.iter(|| parse_config(black_box(&config)).unwrap())
+    });
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,422 @@
+use structopt::clap::AppSettings;
+use structopt::StructOpt;
+
+/// The command line, which doubles as the source for `--help` and the man page.
+#[derive(StructOpt, Debug)]
+#[structopt(
+    name = "feedburst",
+    about = "Presents you your RSS feeds in chunks",
+    raw(max_term_width = "120"),
+    raw(setting = "AppSettings::DisableHelpSubcommand")
+)]
+pub struct Opt {
+    /// The config file to load feeds from. Defaults to feedburst/config.feeds
+    /// in your platform's config directory.
+    #[structopt(long = "config", value_name = "FILE", raw(global = "true"))]
+    pub config: Option<String>,
+
+    /// The folder where feeds are stored. Overrides any `root` lines in the
+    /// config.
+    #[structopt(long = "feeds", value_name = "PATH")]
+    pub feeds: Option<String>,
+
+    /// The command to open the comic with. Any instance of @URL or {url} will
+    /// be replaced with the comic URL, and {title} with its title. If the URL
+    /// isn't mentioned, it will be placed at the end of the command. Overrides
+    /// any `command` and `open with` lines in the config.
+    #[structopt(long = "open-with", value_name = "COMMAND")]
+    pub open_with: Option<String>,
+
+    /// Download every feed through this proxy, like http://localhost:8080 or
+    /// socks5://localhost:1080. Overrides any `proxy` lines in the config.
+    #[structopt(long = "proxy", value_name = "URL", raw(global = "true"))]
+    pub proxy: Option<String>,
+
+    /// Check the time-based policies of every feed by the clock in this time
+    /// zone, like America/New_York. Overrides any `timezone` lines in the
+    /// config.
+    #[structopt(long = "timezone", value_name = "ZONE", raw(global = "true"))]
+    pub timezone: Option<String>,
+
+    /// Don't show the progress of the downloads, which is only shown in a
+    /// terminal anyway.
+    #[structopt(long = "quiet", raw(global = "true"))]
+    pub quiet: bool,
+
+    /// Say what's going on and why, like which policy is holding a feed back:
+    /// -v for the details of each feed, -vv for everything. The progress of
+    /// the downloads isn't shown, so it doesn't get in the way.
+    #[structopt(
+        short = "v",
+        long = "verbose",
+        parse(from_occurrences),
+        raw(global = "true")
+    )]
+    pub verbose: u64,
+
+    /// Only download feeds, don't view them.
+    #[structopt(long = "fetch")]
+    pub fetch: bool,
+
+    /// Download feeds and list the comics that would be opened, without
+    /// opening them or marking anything read.
+    #[structopt(long = "dry-run", raw(conflicts_with = r#""fetch""#))]
+    pub dry_run: bool,
+
+    /// Print the settings each feed ends up with, after every directive and
+    /// command-line flag, and which line or flag each one came from.
+    #[structopt(long = "explain-config")]
+    pub explain_config: bool,
+
+    /// Only run the feeds in this group, as named by a `group` line in the
+    /// config.
+    #[structopt(long = "group", value_name = "NAME")]
+    pub group: Option<String>,
+
+    /// How many feeds to download at once. Overrides any `jobs` line in the
+    /// config. Defaults to 4.
+    #[structopt(long = "jobs", value_name = "N", raw(validator = "at_least_one"))]
+    pub jobs: Option<usize>,
+
+    /// Run even if the last run was less than `min_run_interval` ago, and
+    /// download feeds that are backing off after failing again and again.
+    #[structopt(long = "force")]
+    pub force: bool,
+
+    /// Pretend it's this time instead of now, like 2018-10-01T12:00:00+00:00
+    /// or 2018-10-01 12:00 in local time. Anything read or fetched is recorded
+    /// at this time too, so try it with a copy of your feeds.
+    #[structopt(long = "now", value_name = "TIME", raw(global = "true"))]
+    pub now: Option<String>,
+
+    /// Pick the comics for `@ random N comics` with this number, so a run can
+    /// be repeated with the same picks.
+    #[structopt(long = "seed", value_name = "NUMBER", raw(global = "true"))]
+    pub seed: Option<u64>,
+
+    /// Try out a config from someone else safely: ignore its `command`,
+    /// `root`, `output_feed`, `log_file`, and `deliver` lines, and `open with`
+    /// lines other than `browser` and `clipboard`, and don't rewrite it.
+    #[structopt(long = "sandbox", raw(global = "true"))]
+    pub sandbox: bool,
+
+    /// What to print on stdout: "human" for people, or "json" for a summary
+    /// of the run for scripts, with messages for people moved to stderr. Or
+    /// "html" to write the comics that are ready to one page, with a section
+    /// for each feed, and open that instead of a tab per comic.
+    #[structopt(
+        long = "output",
+        value_name = "FORMAT",
+        default_value = "human",
+        raw(possible_values = r#"&["human", "json", "html"]"#)
+    )]
+    pub output: String,
+
+    /// Where `--output html` writes its page. Defaults to digest.html next to
+    /// the feeds.
+    #[structopt(long = "digest", value_name = "FILE")]
+    pub digest: Option<String>,
+
+    /// Show the images from the feeds' entries in the `--output html` page.
+    #[structopt(long = "thumbnails")]
+    pub thumbnails: bool,
+
+    /// How to print errors: "human" for people, or "json" for one JSON object
+    /// per line, for tools that run feedburst.
+    #[structopt(
+        long = "error-format",
+        value_name = "FORMAT",
+        default_value = "human",
+        raw(possible_values = r#"&["human", "json"]"#),
+        raw(global = "true")
+    )]
+    pub error_format: String,
+
+    /// When to color errors: "auto" when stderr is a terminal and NO_COLOR
+    /// isn't set, "always", or "never".
+    #[structopt(
+        long = "color",
+        value_name = "WHEN",
+        default_value = "auto",
+        raw(possible_values = r#"&["auto", "always", "never"]"#),
+        raw(global = "true")
+    )]
+    pub color: String,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum Command {
+    /// Add a feed to the end of the config file, after checking that it
+    /// downloads
+    #[structopt(name = "add")]
+    Add {
+        /// What to call the feed.
+        #[structopt(value_name = "NAME")]
+        name: String,
+        /// The feed's URL. The URL of a web page that links to its feed works
+        /// too.
+        #[structopt(value_name = "URL")]
+        url: String,
+        /// Only read the feed on these days, like "saturday" or "monday,
+        /// friday".
+        #[structopt(long = "on", value_name = "DAYS")]
+        on: Option<String>,
+        /// Wait at least this many days between reads.
+        #[structopt(long = "every", value_name = "N")]
+        every: Option<usize>,
+        /// Add the feed without downloading it first.
+        #[structopt(long = "no-check")]
+        no_check: bool,
+    },
+
+    /// Take a feed out of the config file. Its history is kept, in case it's
+    /// added back, unless it's archived or deleted
+    #[structopt(name = "remove")]
+    Remove {
+        /// The name of the feed, as written in the config.
+        #[structopt(value_name = "FEED")]
+        feed: String,
+        /// Move the feed's history out of the way, into the `archive` folder
+        /// next to the feeds.
+        #[structopt(long = "archive", conflicts_with = "delete_history")]
+        archive: bool,
+        /// Delete the feed's history.
+        #[structopt(long = "delete-history")]
+        delete_history: bool,
+    },
+
+    /// Give a feed a new name in the config file, taking its history along
+    #[structopt(name = "rename")]
+    Rename {
+        /// The name of the feed, as written in the config.
+        #[structopt(value_name = "FEED")]
+        feed: String,
+        /// What to call the feed from now on.
+        #[structopt(value_name = "NEW_NAME")]
+        new_name: String,
+    },
+
+    /// Show where a feed's history and other files are kept
+    #[structopt(name = "where")]
+    Where {
+        /// The name of the feed, as written in the config.
+        #[structopt(value_name = "FEED")]
+        feed: String,
+    },
+
+    /// Roll the config file back to a backup made when feedburst rewrote it
+    #[structopt(name = "config-restore")]
+    ConfigRestore {
+        /// Which backup to restore, 1 being the most recent.
+        #[structopt(value_name = "N", default_value = "1")]
+        backup: usize,
+    },
+
+    /// Show when a feed would have opened recently, based on its publish
+    /// history
+    #[structopt(name = "simulate")]
+    Simulate {
+        /// The name of the feed to simulate, as written in the config.
+        #[structopt(value_name = "FEED")]
+        feed: String,
+        /// How many days back to start the simulation.
+        #[structopt(long = "days", value_name = "N", default_value = "30")]
+        days: i64,
+        /// Policies to try instead of the ones in the config, like
+        /// "@ every 3 days @ overlap 1 comic".
+        #[structopt(long = "policies", value_name = "POLICIES")]
+        policies: Option<String>,
+    },
+
+    /// Unpause a feed that seemed to republish its archive, taking what's in
+    /// it now as already read
+    #[structopt(name = "accept-reset")]
+    AcceptReset {
+        /// The name of the feed, as written in the config.
+        #[structopt(value_name = "FEED")]
+        feed: String,
+    },
+
+    /// Mark a feed read as if you'd just read it, or only one comic in it,
+    /// for comics read somewhere else
+    #[structopt(name = "mark-read")]
+    MarkRead {
+        /// The name of the feed, as written in the config.
+        #[structopt(value_name = "FEED")]
+        feed: String,
+        /// Just the unread comic at this URL, leaving the rest unread.
+        #[structopt(value_name = "URL")]
+        url: Option<String>,
+    },
+
+    /// Undo the last time a feed, or a comic in it, was marked read
+    #[structopt(name = "mark-unread")]
+    MarkUnread {
+        /// The name of the feed, as written in the config.
+        #[structopt(value_name = "FEED")]
+        feed: String,
+    },
+
+    /// Add, remove, or replace a policy on every feed whose name or URL
+    /// matches a pattern, rewriting the config file
+    #[structopt(name = "policy")]
+    Policy {
+        /// What to do with the policy. `replace` swaps out any policies of the
+        /// same kind, and adds the policy if there were none.
+        #[structopt(
+            value_name = "ACTION",
+            raw(possible_values = r#"&["add", "remove", "replace"]"#)
+        )]
+        action: String,
+        /// The policy, like "@ overlap 1 comic".
+        #[structopt(value_name = "POLICY")]
+        policy: String,
+        /// A regular expression picking out the feeds to change by name or
+        /// URL. Use "." to change every feed.
+        #[structopt(long = "matching", value_name = "PATTERN")]
+        matching: String,
+    },
+
+    /// Put the new URLs of feeds that have permanently moved into the config
+    /// file, in place of the old ones
+    #[structopt(name = "fix-urls")]
+    FixUrls,
+
+    /// Write out the feeds you follow in a form you can share
+    #[structopt(name = "export")]
+    Export {
+        #[structopt(subcommand)]
+        what: Export,
+    },
+
+    /// Add the feeds from another feed reader to the end of the config file
+    #[structopt(name = "import")]
+    Import {
+        #[structopt(subcommand)]
+        what: Import,
+    },
+
+    /// Show each feed's unread comics and when it will next open, without
+    /// downloading or opening anything
+    #[structopt(name = "status")]
+    Status,
+
+    /// Check the config and every feed's history for mistakes, without
+    /// downloading anything, and list them all
+    #[structopt(name = "validate")]
+    Validate,
+
+    /// List the feeds that keep failing to download, haven't had a new comic
+    /// in a long time, or have moved, without downloading anything
+    #[structopt(name = "doctor")]
+    Doctor {
+        /// Point out feeds whose last N downloads failed.
+        #[structopt(long = "failures", value_name = "N", default_value = "3")]
+        failures: usize,
+        /// Point out feeds without a new comic in this many days.
+        #[structopt(long = "stale", value_name = "DAYS", default_value = "90")]
+        stale: i64,
+    },
+
+    /// Download the feeds, then pick which of the ready comics to open from a
+    /// list in the terminal
+    #[structopt(name = "tui")]
+    Tui,
+
+    /// List the groups in the config, and how many feeds are in each
+    #[structopt(name = "groups")]
+    Groups,
+
+    /// Type in config lines and see how feedburst understands them
+    #[structopt(name = "playground")]
+    Playground,
+
+    /// Keep running, and do a run whenever a feed is due, instead of being
+    /// run from cron. SIGHUP reloads the config, and Ctrl-C or SIGTERM stops
+    /// it after the current run.
+    #[structopt(name = "daemon")]
+    Daemon {
+        /// Serve the config's `output_feed` over HTTP at this address, like
+        /// 127.0.0.1:8080, for a feed reader to subscribe to
+        #[structopt(long = "serve", value_name = "ADDR")]
+        serve: Option<String>,
+    },
+
+    /// Check that feedburst works, by fetching and reading feeds from a mock
+    /// server on this machine. Doesn't need a network connection or touch your
+    /// config.
+    #[structopt(name = "selftest")]
+    Selftest,
+
+    /// Collect what's needed for a bug report into a zip file: the config and
+    /// run journal with secrets blanked out, the version and platform, and
+    /// what goes wrong with any feed that fails to download or read
+    #[structopt(name = "debug-bundle")]
+    DebugBundle {
+        /// Where to write the zip file.
+        #[structopt(
+            long = "output",
+            value_name = "FILE",
+            default_value = "feedburst-debug.zip"
+        )]
+        output: String,
+    },
+
+    /// Describe the config language, for tools that read or write configs
+    #[structopt(name = "schema")]
+    Schema {
+        /// Print a machine-readable description of every policy and
+        /// directive as JSON, instead of the text from `help syntax`.
+        #[structopt(long = "json")]
+        json: bool,
+    },
+
+    /// Show help for a subcommand, or one of the topics `syntax` (the config
+    /// file language) or `man` (a man page for feedburst)
+    #[structopt(name = "help")]
+    Help {
+        #[structopt(value_name = "TOPIC")]
+        topic: Option<String>,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub enum Export {
+    /// A list of the comics you read, linking to their sites. Each feed is
+    /// downloaded to find its site.
+    #[structopt(name = "blogroll")]
+    Blogroll {
+        /// Write Markdown instead of HTML.
+        #[structopt(long = "markdown")]
+        markdown: bool,
+    },
+
+    /// An OPML subscription list, which most feed readers can import.
+    #[structopt(name = "opml")]
+    Opml,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum Import {
+    /// An OPML subscription list, which most feed readers can export. Folder
+    /// names become comments above the feeds in them.
+    #[structopt(name = "opml")]
+    Opml {
+        /// The OPML file to read.
+        #[structopt(value_name = "FILE")]
+        file: String,
+    },
+}
+
+fn at_least_one(value: String) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(n) if n >= 1 => Ok(()),
+        _ => Err(format!(
+            "expected a number of at least 1, got \"{}\"",
+            value
+        )),
+    }
+}
@@ -0,0 +1,126 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+/// Where feedburst gets the current time. Everything that asks what time it
+/// is goes through one of these, so a run can be replayed as if it happened
+/// at some other moment.
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// The current time in the local timezone, for checking schedules.
+    fn local(&self) -> DateTime<Local> {
+        self.now().with_timezone(&Local)
+    }
+}
+
+/// The real time, from the system.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock stopped at one moment, for `--now` and for tests.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+/// Read a timestamp like `2018-10-01T12:00:00+00:00`, or one without a
+/// timezone like `2018-10-01 12:00` or `2018-10-01`, which is taken as local
+/// time. Besides RFC 3339, the other ISO 8601 forms people tend to write by
+/// hand work too, like `2018-10-01T12:00Z`, `+0200` offsets, and
+/// `20181001T120000Z`.
+pub fn parse_timestamp(text: &str) -> Option<DateTime<Utc>> {
+    let text = text.trim().to_ascii_uppercase();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(&text) {
+        return Some(datetime.with_timezone(&Utc));
+    }
+    // %z doesn't take a Z for UTC
+    let with_offset = match text.strip_suffix('Z') {
+        Some(rest) => format!("{}+00:00", rest),
+        None => text.clone(),
+    };
+    let offset = [
+        "%Y-%m-%dT%H:%M:%S%.f%z",
+        "%Y-%m-%d %H:%M:%S%.f%z",
+        "%Y-%m-%dT%H:%M%z",
+        "%Y-%m-%d %H:%M%z",
+        "%Y%m%dT%H%M%S%.f%z",
+        "%Y%m%dT%H%M%z",
+    ]
+    .iter()
+    .find_map(|format| DateTime::parse_from_str(&with_offset, format).ok());
+    if let Some(datetime) = offset {
+        return Some(datetime.with_timezone(&Utc));
+    }
+
+    let naive = [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+        "%Y%m%dT%H%M%S",
+    ]
+    .iter()
+    .filter_map(|format| NaiveDateTime::parse_from_str(&text, format).ok())
+    .next()
+    .or_else(|| {
+        NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+            .ok()
+            .map(|date| date.and_hms(0, 0, 0))
+    })?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|datetime| datetime.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        let noon = Utc.ymd(2018, 10, 1).and_hms(12, 0, 0);
+        assert_eq!(parse_timestamp("2018-10-01T12:00:00+00:00"), Some(noon));
+        assert_eq!(parse_timestamp("2018-10-01T14:00:00+02:00"), Some(noon));
+
+        let local = |h, m| Local.ymd(2018, 10, 1).and_hms(h, m, 0).with_timezone(&Utc);
+        assert_eq!(parse_timestamp("2018-10-01 12:00"), Some(local(12, 0)));
+        assert_eq!(parse_timestamp("2018-10-01T08:30:00"), Some(local(8, 30)));
+        assert_eq!(parse_timestamp("2018-10-01"), Some(local(0, 0)));
+
+        // Hand-written ISO 8601
+        assert_eq!(parse_timestamp("2018-10-01t12:00:00z"), Some(noon));
+        assert_eq!(parse_timestamp("2018-10-01T12:00Z"), Some(noon));
+        assert_eq!(parse_timestamp("2018-10-01T14:00:00+0200"), Some(noon));
+        assert_eq!(parse_timestamp("2018-10-01 12:00:00+00:00"), Some(noon));
+        assert_eq!(parse_timestamp("20181001T120000Z"), Some(noon));
+        assert_eq!(
+            parse_timestamp("2018-10-01T12:00:00.250Z"),
+            Some(noon + chrono::Duration::milliseconds(250))
+        );
+        assert_eq!(
+            parse_timestamp("2018-10-01T08:30:00.5"),
+            Some(local(8, 30) + chrono::Duration::milliseconds(500))
+        );
+
+        assert_eq!(parse_timestamp("next tuesday"), None);
+        assert_eq!(parse_timestamp("2018-13-01"), None);
+    }
+
+    #[test]
+    fn test_fixed_clock() {
+        let noon = Utc.ymd(2018, 10, 1).and_hms(12, 0, 0);
+        let clock = FixedClock(noon);
+        assert_eq!(clock.now(), noon);
+        assert_eq!(clock.local(), noon.with_timezone(&Local));
+    }
+}
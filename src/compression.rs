@@ -0,0 +1,143 @@
+//! Compressed downloads. Feeds are asked for with `Accept-Encoding`, and the
+//! body is decoded here by its `Content-Encoding`, rather than by reqwest,
+//! which only knows gzip.
+
+use std::io::{Read, Write};
+
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+
+use crate::error::Error;
+
+/// The `Accept-Encoding` header to send with every download.
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br";
+
+/// The most a download can decode to, far more than any real feed, so a small
+/// download can't decode to fill the memory.
+const MAX_DECODED: u64 = 64 * 1024 * 1024;
+
+/// Undo the `Content-Encoding` of a download. Encodings applied one after
+/// another, like `gzip, br`, are undone in reverse. Fails if it decodes to
+/// more than `MAX_DECODED`.
+pub fn decode(content_encoding: Option<&str>, mut body: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let encodings: Vec<_> = content_encoding
+        .unwrap_or_default()
+        .split(',')
+        .map(|encoding| encoding.trim().to_ascii_lowercase())
+        .filter(|encoding| !encoding.is_empty())
+        .collect();
+    for encoding in encodings.iter().rev() {
+        let mut decoded = Vec::new();
+        // One byte over, to tell a download that's too big from one that fits
+        let limit = MAX_DECODED + 1;
+        let result = match encoding.as_str() {
+            "identity" => continue,
+            "gzip" | "x-gzip" => GzDecoder::new(&body[..])
+                .take(limit)
+                .read_to_end(&mut decoded),
+            // It's meant to be zlib, but some servers send raw deflate
+            "deflate" => match ZlibDecoder::new(&body[..])
+                .take(limit)
+                .read_to_end(&mut decoded)
+            {
+                Ok(n) => Ok(n),
+                Err(_) => {
+                    decoded.clear();
+                    DeflateDecoder::new(&body[..])
+                        .take(limit)
+                        .read_to_end(&mut decoded)
+                }
+            },
+            "br" => brotli::Decompressor::new(&body[..], 4096)
+                .take(limit)
+                .read_to_end(&mut decoded),
+            _ => {
+                return Err(Error::Msg(format!(
+                    "The server sent the feed in an encoding feedburst doesn't know: \"{}\"",
+                    encoding
+                )))
+            }
+        };
+        result.map_err(|err| {
+            Error::Msg(format!(
+                "The server sent a broken {} download: {}",
+                encoding, err
+            ))
+        })?;
+        if decoded.len() as u64 > MAX_DECODED {
+            return Err(Error::Msg(format!(
+                "The server sent a {} download that decodes to more than {} MB",
+                encoding,
+                MAX_DECODED / 1024 / 1024
+            )));
+        }
+        body = decoded;
+    }
+    Ok(body)
+}
+
+/// Compress `body` with `encoding`, the way a server would. `None` if it
+/// isn't one that `decode` knows.
+pub fn encode(encoding: &str, body: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    match encoding {
+        "gzip" => {
+            let mut encoder = GzEncoder::new(&mut out, Compression::default());
+            encoder.write_all(body).ok()?;
+            encoder.finish().ok()?;
+        }
+        "deflate" => {
+            let mut encoder = ZlibEncoder::new(&mut out, Compression::default());
+            encoder.write_all(body).ok()?;
+            encoder.finish().ok()?;
+        }
+        "br" => {
+            // The stream is finished when the writer is dropped
+            let mut encoder = brotli::CompressorWriter::new(&mut out, 4096, 5, 22);
+            encoder.write_all(body).ok()?;
+        }
+        _ => return None,
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let feed = include_bytes!("../tests/fixtures/atom.xml");
+        for &encoding in &["gzip", "deflate", "br"] {
+            let body = encode(encoding, feed).unwrap();
+            assert_ne!(&body[..], &feed[..], "{}", encoding);
+            assert_eq!(
+                decode(Some(encoding), body).unwrap(),
+                &feed[..],
+                "{}",
+                encoding
+            );
+        }
+        assert_eq!(decode(None, feed.to_vec()).unwrap(), &feed[..]);
+        assert_eq!(decode(Some("identity"), feed.to_vec()).unwrap(), &feed[..]);
+
+        // Raw deflate, without the zlib header
+        let mut raw = flate2::write::DeflateEncoder::new(Vec::new(), Compression::default());
+        raw.write_all(feed).unwrap();
+        let raw = raw.finish().unwrap();
+        assert_eq!(decode(Some("deflate"), raw).unwrap(), &feed[..]);
+
+        // One encoding after another
+        let twice = encode("br", &encode("gzip", feed).unwrap()).unwrap();
+        assert_eq!(decode(Some("GZIP, br"), twice).unwrap(), &feed[..]);
+
+        assert!(decode(Some("gzip"), feed.to_vec()).is_err());
+        assert!(decode(Some("zstd"), feed.to_vec()).is_err());
+
+        // A small download that decodes to too much
+        let bomb = encode("gzip", &vec![b' '; MAX_DECODED as usize + 1]).unwrap();
+        assert!(bomb.len() < 1024 * 1024);
+        assert!(decode(Some("gzip"), bomb).is_err());
+    }
+}
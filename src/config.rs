@@ -1,12 +1,19 @@
 use std::env;
-use std::fs::{File, OpenOptions};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::sync::Arc;
 
+use chrono_tz::Tz;
+
+use crate::clock::{self, Clock, FixedClock, SystemClock};
 use crate::error::{Error, ParseError};
-use crate::feed::FeedInfo;
-use crate::parser;
+use crate::feed::{FeedInfo, OpenWith};
+use crate::opener;
+use crate::output::OutputFormat;
+use crate::parser::{self, Backend, Settings};
 use crate::platform;
+use crate::progress::Progress;
 
 #[derive(Debug, Clone)]
 enum PathWrapper {
@@ -20,6 +27,22 @@ pub struct Args {
     feed_root: Option<PathBuf>,
     config: PathWrapper,
     open_command: Option<Vec<String>>,
+    clock: Arc<dyn Clock + Send + Sync>,
+    sandbox: bool,
+    /// Whether stdout is kept for `--output json`.
+    json_output: bool,
+    /// Where feed histories are kept, from the `storage` directive.
+    storage: Backend,
+    /// The proxy given with `--proxy`.
+    proxy: Option<String>,
+    /// The time zone given with `--timezone`.
+    timezone: Option<Tz>,
+    /// What `@ random N comics` picks with, from `--seed` or made up for
+    /// the run.
+    seed: u64,
+    /// The progress of the downloads, kept out of the way of what `say`
+    /// prints.
+    progress: Option<Arc<Progress>>,
 }
 
 impl Args {
@@ -28,6 +51,8 @@ impl Args {
         feed_root: Option<&str>,
         config: Option<&str>,
         command: Option<&str>,
+        now: Option<&str>,
+        sandbox: bool,
     ) -> Result<Self, Error> {
         let command = if let Some(command) = command {
             match parser::parse_command(command) {
@@ -41,20 +66,221 @@ impl Args {
             None
         };
 
+        let clock: Arc<dyn Clock + Send + Sync> = match now {
+            Some(now) => match clock::parse_timestamp(now) {
+                Some(now) => Arc::new(FixedClock(now)),
+                None => {
+                    let msg = format!(
+                        "Error parsing --now: expected a time like 2018-10-01T12:00:00+00:00 \
+                         or 2018-10-01 12:00, got \"{}\"",
+                        now
+                    );
+                    return Err(Error::Msg(msg));
+                }
+            },
+            None => Arc::new(SystemClock),
+        };
+
         Ok(Args {
             only_fetch,
             feed_root: feed_root.map(From::from),
             config: config_path(config)?,
             open_command: command,
+            clock,
+            sandbox,
+            json_output: false,
+            storage: Backend::Files,
+            proxy: None,
+            timezone: None,
+            seed: 0,
+            progress: None,
         })
     }
 
+    /// Keep stdout for `--output json`, sending what `say` prints to stderr.
+    pub fn with_json_output(mut self, json_output: bool) -> Self {
+        self.json_output = json_output;
+        self
+    }
+
+    /// Keep feed histories where the config's `storage` line says.
+    pub fn with_storage(mut self, storage: Backend) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Pick comics for `@ random N comics` with `seed`.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Download every feed through `proxy`, if given.
+    pub fn with_proxy(mut self, proxy: Option<String>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// Check the time-based policies of every feed in `timezone`, if given.
+    pub fn with_timezone(mut self, timezone: Option<Tz>) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Where feed histories are kept.
+    pub fn storage(&self) -> Backend {
+        self.storage
+    }
+
+    /// Print a message for people: on stdout, unless it's kept for JSON.
+    pub fn say(&self, message: &str) {
+        let print = || {
+            if self.json_output {
+                eprintln!("{}", message);
+            } else {
+                println!("{}", message);
+            }
+        };
+        match self.progress {
+            Some(ref progress) => progress.suspend(print),
+            None => print(),
+        }
+    }
+
+    /// Show `progress` while downloading, moving it out of the way of `say`.
+    pub fn with_progress(mut self, progress: Arc<Progress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn progress(&self) -> Option<&Arc<Progress>> {
+        self.progress.as_ref()
+    }
+
+    /// The folder a feed is stored in, if not the platform data directory.
+    /// `root` lines in the config don't count in the sandbox, and have `~`
+    /// and environment variables expanded.
+    fn feed_root(&self, info: &FeedInfo) -> Result<Option<PathBuf>, Error> {
+        match (&self.feed_root, &info.root) {
+            (Some(root), _) => Ok(Some(root.clone())),
+            (None, Some(root)) if !self.sandbox => {
+                Ok(Some(expand(&root.to_string_lossy())?.into()))
+            }
+            (None, _) => Ok(None),
+        }
+    }
+
+    /// Where the feed called `name` keeps its files. Under a root, each feed
+    /// gets a folder of its own (see `feed_dir`); otherwise they all share the
+    /// platform data directory. With `create`, the feed's folder is made if it
+    /// isn't there yet. Files left from when every feed shared the root are
+    /// moved into the folder either way.
+    fn feed_paths(&self, info: &FeedInfo, name: &str, create: bool) -> Result<FeedPaths, Error> {
+        if self.sandbox && name.contains(['/', '\\']) {
+            return Err(Error::Msg(format!(
+                "Feed name \"{}\" would be stored outside the feed folder, which \
+                 --sandbox doesn't allow",
+                name
+            )));
+        }
+        let root = match self.feed_root(info)? {
+            Some(root) => root,
+            None => {
+                let history = platform::data_path(&format!("feeds/{}.feed", name))?;
+                debug!("Using platform data: {:?}", history);
+                return Ok(FeedPaths {
+                    dir: None,
+                    cache: history.with_extension("http"),
+                    health: history.with_extension("health"),
+                    history,
+                });
+            }
+        };
+        if !root.is_dir() {
+            return Err(Error::Msg(format!(
+                "Error: {} is not a directory",
+                root.display()
+            )));
+        }
+        let old_history = root.join(format!("{}.feed", name));
+        let old_cache = old_history.with_extension("http");
+        let dir = feed_dir(&root, name, create || old_history.exists())?;
+        let paths = FeedPaths {
+            history: dir.join(HISTORY_FILE),
+            cache: dir.join(CACHE_FILE),
+            health: dir.join(HEALTH_FILE),
+            dir: Some(dir),
+        };
+        for (old, new) in [(&old_history, &paths.history), (&old_cache, &paths.cache)] {
+            if old.exists() && !new.exists() {
+                debug!("Moving {} to {}", old.display(), new.display());
+                fs::rename(old, new)?;
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Where the feed keeps its files, without making anything that isn't
+    /// there yet.
+    pub fn paths(&self, info: &FeedInfo) -> Result<FeedPaths, Error> {
+        self.feed_paths(info, &info.name, false)
+    }
+
+    /// The folder given with `--feeds`, which wins over `root` lines.
+    pub fn feeds_flag(&self) -> Option<&PathBuf> {
+        self.feed_root.as_ref()
+    }
+
+    /// The proxy given with `--proxy`, which wins over `proxy` lines.
+    pub fn proxy_flag(&self) -> Option<&String> {
+        self.proxy.as_ref()
+    }
+
+    /// The time zone given with `--timezone`, which wins over `timezone`
+    /// lines.
+    pub fn timezone_flag(&self) -> Option<Tz> {
+        self.timezone
+    }
+
+    /// The command given with `--open-with`, which wins over `command` and
+    /// `open with` lines.
+    pub fn open_with_flag(&self) -> Option<&Vec<String>> {
+        self.open_command.as_ref()
+    }
+
+    /// Whether `--sandbox` is ignoring the config's `root`, `command`,
+    /// `output_feed`, `log_file`, and `deliver` lines, and `open with` lines
+    /// other than `browser` and `clipboard`.
+    pub fn is_sandbox(&self) -> bool {
+        self.sandbox
+    }
+
+    /// What time feedburst thinks it is, which `--now` can override.
+    pub fn clock(&self) -> &dyn Clock {
+        &*self.clock
+    }
+
     pub fn config_path(&self) -> &PathBuf {
         match self.config {
             PathWrapper::CreateIfMissing(ref path) | PathWrapper::ErrorIfMissing(ref path) => path,
         }
     }
 
+    /// The config path, for rewriting the config, which `--sandbox` doesn't
+    /// allow.
+    pub fn writable_config_path(&self) -> Result<&PathBuf, Error> {
+        if self.sandbox {
+            return Err(Error::Msg(
+                "--sandbox doesn't allow rewriting the config".into(),
+            ));
+        }
+        Ok(self.config_path())
+    }
+
     pub fn config_file(&self) -> Result<File, Error> {
         match self.config {
             PathWrapper::CreateIfMissing(ref path) => Ok(OpenOptions::new()
@@ -72,8 +298,7 @@ impl Args {
     }
 
     pub fn feed_file(&self, info: &FeedInfo) -> Result<File, Error> {
-        let root = self.feed_root.as_ref().or_else(|| info.root.as_ref());
-        let path = feed_path(root, &info.name)?;
+        let path = self.feed_paths(info, &info.name, true)?.history;
         OpenOptions::new()
             .read(true)
             .write(true)
@@ -88,65 +313,421 @@ impl Args {
             })
     }
 
-    pub fn open_url(&self, feed: &FeedInfo, url: &str) -> Result<(), Error> {
-        if let Some(command) = self.open_command.as_ref().or_else(|| feed.command.as_ref()) {
-            let mut found_url = false;
-            let command_str = command.join(" ");
-            let mut command: Vec<String> = (*command).clone();
-            for (i, item) in command.iter_mut().enumerate() {
-                if item.to_uppercase() == "@URL" {
-                    if i == 0 {
-                        let msg = format!(
-                            "@URL can't be the first part of the command (in `{}`)",
-                            command_str
-                        );
-                        return Err(Error::Msg(msg));
+    /// Where the headers for conditional downloads of a feed are kept, next to
+    /// its history.
+    pub fn cache_path(&self, info: &FeedInfo) -> Result<PathBuf, Error> {
+        Ok(self.feed_paths(info, &info.name, false)?.cache)
+    }
+
+    /// Where how the feed's downloads have been going is kept, next to its
+    /// history.
+    pub fn health_path(&self, info: &FeedInfo) -> Result<PathBuf, Error> {
+        Ok(self.feed_paths(info, &info.name, false)?.health)
+    }
+
+    /// Where the history recorded under `name` would be kept in a feed file,
+    /// for `info` or one of its aliases.
+    pub fn history_path(&self, info: &FeedInfo, name: &str) -> Result<PathBuf, Error> {
+        Ok(self.feed_paths(info, name, false)?.history)
+    }
+
+    /// Where the database of feed histories is kept with `storage sqlite`:
+    /// in the folder the feed would be in, or the platform data directory.
+    pub fn database_path(&self, info: &FeedInfo) -> Result<PathBuf, Error> {
+        match self.feed_root(info)? {
+            Some(root) => Ok(root.join(DATABASE_FILE)),
+            None => platform::data_path(DATABASE_FILE),
+        }
+    }
+
+    /// Where to write the `output_feed` of `settings`, and what kind, with a
+    /// relative path taken from the config's folder. The sandbox ignores it,
+    /// since it could overwrite anything.
+    pub fn output_feed(&self, settings: &Settings) -> Option<(OutputFormat, PathBuf)> {
+        if self.sandbox {
+            return None;
+        }
+        let (format, ref path) = *settings.output_feed.as_ref()?;
+        let dir = self.config_path().parent().unwrap_or_else(|| Path::new(""));
+        Some((format, dir.join(path)))
+    }
+
+    /// Where to log to from the `log_file` of `settings`, with a relative path
+    /// taken from the config's folder. The sandbox ignores it, like
+    /// `output_feed`.
+    pub fn log_file(&self, settings: &Settings) -> Option<PathBuf> {
+        if self.sandbox {
+            return None;
+        }
+        let path = settings.log_file.as_ref()?;
+        let dir = self.config_path().parent().unwrap_or_else(|| Path::new(""));
+        Some(dir.join(path))
+    }
+
+    /// Where the journal of recent runs is kept: next to the feeds given with
+    /// `--feeds`, or in the platform data directory.
+    pub fn journal_path(&self) -> Result<PathBuf, Error> {
+        match self.feed_root {
+            Some(ref root) => Ok(root.join("runs.journal")),
+            None => platform::data_path("runs.journal"),
+        }
+    }
+
+    /// Where `--output html` writes its page: `digest`, or digest.html next to
+    /// the journal.
+    pub fn digest_path(&self, digest: Option<&str>) -> Result<PathBuf, Error> {
+        match digest {
+            Some(path) => Ok(PathBuf::from(path)),
+            None => Ok(self.journal_path()?.with_file_name("digest.html")),
+        }
+    }
+
+    /// Move the files the feed keeps to where a feed called `name` would keep
+    /// them, for renaming it. Fails if that feed already has files.
+    pub fn move_feed_files(&self, info: &FeedInfo, name: &str) -> Result<(), Error> {
+        let old = self.paths(info)?;
+        let new = self.feed_paths(info, name, false)?;
+        let taken = |path: &Path| {
+            Error::Msg(format!(
+                "There's already a history for \"{}\" at {}",
+                name,
+                path.display()
+            ))
+        };
+        if let (Some(old_dir), Some(new_dir)) = (&old.dir, &new.dir) {
+            if !old_dir.exists() {
+                return Ok(());
+            }
+            if new_dir.exists() {
+                return Err(taken(new_dir));
+            }
+            debug!("Moving {} to {}", old_dir.display(), new_dir.display());
+            fs::rename(old_dir, new_dir)?;
+            fs::write(new_dir.join(NAME_FILE), format!("{}\n", name))?;
+            return Ok(());
+        }
+        let moves = [
+            (&old.history, &new.history),
+            (&old.cache, &new.cache),
+            (&old.health, &new.health),
+        ];
+        if let Some((_, new)) = moves.iter().find(|(_, new)| new.exists()) {
+            return Err(taken(new));
+        }
+        for (old, new) in moves.iter().filter(|(old, _)| old.exists()) {
+            debug!("Moving {} to {}", old.display(), new.display());
+            fs::rename(old, new)?;
+        }
+        Ok(())
+    }
+
+    /// Delete the files the feed keeps, and its folder if it has one.
+    pub fn remove_feed_files(&self, info: &FeedInfo) -> Result<(), Error> {
+        let paths = self.paths(info)?;
+        match paths.dir {
+            Some(ref dir) if dir.exists() => {
+                debug!("Removing {}", dir.display());
+                fs::remove_dir_all(dir)?;
+            }
+            Some(_) => (),
+            None => {
+                for path in [&paths.history, &paths.cache, &paths.health] {
+                    if path.exists() {
+                        debug!("Removing {}", path.display());
+                        fs::remove_file(path)?;
                     }
-                    *item = url.into();
-                    found_url = true;
                 }
             }
+        }
+        Ok(())
+    }
 
-            if !found_url {
-                command.push(url.into());
+    /// Where to keep the history of a feed taken out of the config with
+    /// `feedburst remove --archive`: an `archive` folder next to the feeds,
+    /// in a file named for the feed that isn't taken yet.
+    pub fn archive_path(&self, info: &FeedInfo) -> Result<PathBuf, Error> {
+        let archive = match self.feed_root(info)? {
+            Some(root) => root.join("archive"),
+            None => platform::data_path("archive")?,
+        };
+        fs::create_dir_all(&archive)?;
+        let slug = slug(&info.name);
+        let mut n = 1;
+        loop {
+            let path = match n {
+                1 => archive.join(format!("{}.feed", slug)),
+                n => archive.join(format!("{}-{}.feed", slug, n)),
+            };
+            if !path.exists() {
+                return Ok(path);
             }
+            n += 1;
+        }
+    }
 
-            let exit_status = Command::new(&command[0])
-                .args(&command[1..])
-                .spawn()?
-                .wait()?;
+    /// The feed files left behind under the feed's `@ alias` names, for the
+    /// ones that exist.
+    pub fn alias_files(&self, info: &FeedInfo) -> Result<Vec<(String, File)>, Error> {
+        let mut files = Vec::new();
+        for alias in info.aliases() {
+            let path = self.feed_paths(info, alias, false)?.history;
+            if !path.exists() {
+                debug!("No history for alias \"{}\" at {}", alias, path.display());
+                continue;
+            }
+            let file = File::open(&path).map_err(|err| {
+                Error::Msg(format!(
+                    "Error opening feed file {}: {}",
+                    path.display(),
+                    err
+                ))
+            })?;
+            files.push((alias.to_string(), file));
+        }
+        Ok(files)
+    }
 
-            if exit_status.success() {
-                Ok(())
-            } else {
-                let msg = format!("Error running open command `{}`", command_str);
-                Err(Error::Msg(msg))
+    /// How to open the comics of `feed`: `--open-with`, the feed's `open
+    /// with` or `command`, or the default browser. The sandbox ignores
+    /// commands, files, and read-it-later services from the config, which
+    /// could run or overwrite anything, or send what's read to someone else.
+    pub fn open_with(&self, feed: &FeedInfo) -> OpenWith {
+        if let Some(ref command) = self.open_command {
+            return OpenWith::Command(command.clone());
+        }
+        match feed.open_with {
+            None => OpenWith::Browser,
+            Some(OpenWith::Browser) | Some(OpenWith::Clipboard) => feed.open_with.clone().unwrap(),
+            Some(_) if self.sandbox => OpenWith::Browser,
+            Some(ref open_with) => open_with.clone(),
+        }
+    }
+
+    /// Open `url` the way `open_with` says for `feed`. `title` is the comic's
+    /// title, for a command that uses `{title}`.
+    pub fn open_url(&self, feed: &FeedInfo, url: &str, title: Option<&str>) -> Result<(), Error> {
+        opener::opener(&self.open_with(feed)).open(url, title)
+    }
+
+    /// Where to email the comics of `feed` instead of opening them, from its
+    /// `deliver email` line, which the sandbox ignores so a config can't send
+    /// what's read to someone else.
+    pub fn deliver_to<'a>(&self, feed: &'a FeedInfo) -> Option<&'a str> {
+        if self.sandbox {
+            return None;
+        }
+        feed.deliver_to.as_deref()
+    }
+
+    /// Open a page feedburst wrote, like the `--output html` digest, with
+    /// `--open-with` or the default browser.
+    pub fn open_page(&self, path: &Path) -> Result<(), Error> {
+        let open_with = match self.open_command {
+            Some(ref command) => OpenWith::Command(command.clone()),
+            None => OpenWith::Browser,
+        };
+        opener::opener(&open_with).open(&path.to_string_lossy(), None)
+    }
+}
+
+/// The files a feed keeps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeedPaths {
+    /// The feed's own folder, if it has one.
+    pub dir: Option<PathBuf>,
+    /// The feed's history, with the comics seen and when it was read.
+    pub history: PathBuf,
+    /// The headers for downloading the feed only if it changed.
+    pub cache: PathBuf,
+    /// How the feed's downloads have been going, for `doctor`.
+    pub health: PathBuf,
+}
+
+/// What the files in a feed's own folder are called.
+const HISTORY_FILE: &str = "history.feed";
+const CACHE_FILE: &str = "cache.http";
+const HEALTH_FILE: &str = "downloads.health";
+/// Holds every feed's history with `storage sqlite`, next to the feed folders.
+const DATABASE_FILE: &str = "feeds.sqlite";
+/// Holds the full name of the feed a folder belongs to.
+const NAME_FILE: &str = "name.txt";
+
+/// A feed name made safe and readable as a folder name: lowercase, with runs
+/// of anything but letters and digits turned into a single `-`.
+pub fn slug(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "feed".into()
+    } else {
+        slug.into()
+    }
+}
+
+/// `text` with `~` at its start replaced by the home folder, and `$NAME` or
+/// `${NAME}` by the environment variable NAME, for `root` and `command` lines.
+pub fn expand(text: &str) -> Result<String, Error> {
+    expand_vars(text, |name| env::var(name).ok())
+}
+
+/// `expand`, looking variables up with `var`.
+pub fn expand_vars(text: &str, var: impl Fn(&str) -> Option<String>) -> Result<String, Error> {
+    expand_with(text, var).map_err(|name| {
+        Error::Msg(format!(
+            "`{}` uses the environment variable {}, which isn't set",
+            text, name
+        ))
+    })
+}
+
+/// `expand`, looking variables up with `var`. The name of the first one that
+/// isn't set, if one isn't. `$$` is a `$`, and a `$` without a name after it,
+/// like in `$1`, stays as it is.
+fn expand_with(text: &str, var: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    if rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\") {
+        out.push_str(&var(platform::HOME_VAR).ok_or_else(|| platform::HOME_VAR.to_string())?);
+        rest = &rest[1..];
+    }
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(after.len());
+                (&after[..end], end)
             }
+        };
+        if let Some(escaped) = after.strip_prefix('$') {
+            out.push('$');
+            rest = escaped;
+        } else if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            out.push('$');
+            rest = after;
         } else {
-            platform::open_url(url)
+            out.push_str(&var(name).ok_or_else(|| name.to_string())?);
+            rest = &after[len..];
         }
     }
+    out.push_str(rest);
+    Ok(out)
 }
 
-fn feed_path(root: Option<&PathBuf>, name: &str) -> Result<PathBuf, Error> {
-    if let Some(root) = root {
-        debug!("Using feed specified on the command line: {:?}", root);
-        let root = Path::new(root);
-        if !root.is_dir() {
-            Err(Error::Msg(format!(
-                "Error: {} is not a directory",
-                root.display()
-            )))
-        } else {
-            Ok(root.join(format!("{}.feed", name)))
+/// The folder under `root` for the feed called `name`: its slug, or the slug
+/// with `-2`, `-3`, and so on after it if a feed with another name that has
+/// the same slug already took it. Each folder keeps the full name of its feed
+/// in `name.txt`, so which feed gets which folder doesn't depend on the order
+/// of the config. With `create`, a folder that isn't taken yet is made and
+/// taken.
+fn feed_dir(root: &Path, name: &str, create: bool) -> Result<PathBuf, Error> {
+    let slug = slug(name);
+    let mut n = 1;
+    loop {
+        let dir = match n {
+            1 => root.join(&slug),
+            n => root.join(format!("{}-{}", slug, n)),
+        };
+        let owner = dir.join(NAME_FILE);
+        match fs::read_to_string(&owner) {
+            Ok(owner) if owner.trim_end_matches('\n') == name => return Ok(dir),
+            // Someone else's, or a folder that isn't a feed's at all
+            Ok(_) => (),
+            Err(_) if dir.exists() => (),
+            Err(_) => {
+                if create {
+                    debug!("Making {} for \"{}\"", dir.display(), name);
+                    fs::create_dir_all(&dir)?;
+                    fs::write(&owner, format!("{}\n", name))?;
+                }
+                return Ok(dir);
+            }
         }
-    } else {
-        let path = platform::data_path(&format!("feeds/{}.feed", name))?;
-        debug!("Using platform data: {:?}", path);
-        Ok(path)
+        n += 1;
     }
 }
 
+/// How many old copies of the config to keep around when feedburst rewrites it.
+const CONFIG_BACKUPS: usize = 5;
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// The path of the `n`th most recent backup of the config, starting at 1.
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    sibling_path(path, &format!(".bak.{}", n))
+}
+
+/// Replace the config file with `text`.
+///
+/// The new contents are written to a temporary file first and renamed into
+/// place, so the config is never left half-written. The old contents are kept
+/// as `config.feeds.bak.1`, pushing older backups back until there are
+/// `CONFIG_BACKUPS` of them.
+pub fn write_config(path: &Path, text: &str) -> Result<(), Error> {
+    let write_err = |err: std::io::Error| {
+        Error::Msg(format!(
+            "Error writing config file {}: {}",
+            path.display(),
+            err
+        ))
+    };
+
+    let temp_path = sibling_path(path, ".tmp");
+    {
+        let mut file = File::create(&temp_path).map_err(write_err)?;
+        file.write_all(text.as_bytes()).map_err(write_err)?;
+        file.sync_all().map_err(write_err)?;
+    }
+
+    if path.exists() {
+        for n in (1..CONFIG_BACKUPS).rev() {
+            let backup = backup_path(path, n);
+            if backup.exists() {
+                fs::rename(&backup, backup_path(path, n + 1)).map_err(write_err)?;
+            }
+        }
+        fs::copy(path, backup_path(path, 1)).map_err(write_err)?;
+    }
+
+    debug!("Replacing {} with {}", path.display(), temp_path.display());
+    fs::rename(&temp_path, path).map_err(write_err)
+}
+
+/// Roll the config back to its `n`th most recent backup. The current config
+/// becomes the newest backup, so a restore can itself be undone.
+pub fn restore_config(path: &Path, n: usize) -> Result<PathBuf, Error> {
+    let backup = backup_path(path, n);
+    let mut text = String::new();
+    File::open(&backup)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|err| {
+            Error::Msg(format!(
+                "Cannot read config backup {}: {}",
+                backup.display(),
+                err
+            ))
+        })?;
+    write_config(path, &text)?;
+    Ok(backup)
+}
+
 fn config_path(path: Option<&str>) -> Result<PathWrapper, Error> {
     if let Some(path) = path {
         debug!("Using config specified on command line: {}", path);
@@ -166,3 +747,122 @@ fn config_path(path: Option<&str>) -> Result<PathWrapper, Error> {
         Ok(PathWrapper::CreateIfMissing(path))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sandbox() {
+        let infos = parser::parse_config(
+            r#"
+root /tmp/elsewhere
+command sh -c "rm -rf ~" @URL
+"Comic" <http://example.com/rss>
+"../Escape" <http://example.com/rss>
+"#,
+        )
+        .unwrap();
+
+        let args = Args::new(false, None, Some("config.feeds"), None, None, false).unwrap();
+        assert_eq!(
+            args.feed_root(&infos[0]).unwrap(),
+            Some(PathBuf::from("/tmp/elsewhere"))
+        );
+        assert!(args.writable_config_path().is_ok());
+        assert!(matches!(args.open_with(&infos[0]), OpenWith::Command(_)));
+
+        let args = Args::new(false, None, Some("config.feeds"), None, None, true).unwrap();
+        assert_eq!(args.feed_root(&infos[0]).unwrap(), None);
+        assert_eq!(args.open_with(&infos[0]), OpenWith::Browser);
+        assert!(args.feed_paths(&infos[1], &infos[1].name, false).is_err());
+        assert!(args.writable_config_path().is_err());
+
+        let args = Args::new(false, Some("/tmp"), Some("config.feeds"), None, None, true).unwrap();
+        assert_eq!(
+            args.feed_root(&infos[0]).unwrap(),
+            Some(PathBuf::from("/tmp"))
+        );
+    }
+
+    #[test]
+    fn test_expand() {
+        let var = |name: &str| match name {
+            "HOME" | "USERPROFILE" => Some("/home/me".to_string()),
+            "XDG_DATA_HOME" => Some("/data".to_string()),
+            _ => None,
+        };
+        let expand = |text| expand_with(text, var);
+        assert_eq!(expand("~/comics"), Ok("/home/me/comics".into()));
+        assert_eq!(expand("~"), Ok("/home/me".into()));
+        assert_eq!(expand("/srv/~/comics"), Ok("/srv/~/comics".into()));
+        assert_eq!(expand("~other/comics"), Ok("~other/comics".into()));
+        assert_eq!(
+            expand("$XDG_DATA_HOME/feedburst"),
+            Ok("/data/feedburst".into())
+        );
+        assert_eq!(
+            expand("${XDG_DATA_HOME}feedburst"),
+            Ok("/datafeedburst".into())
+        );
+        assert_eq!(expand("cost: $$5, $1, $"), Ok("cost: $5, $1, $".into()));
+        assert_eq!(expand("${XDG_DATA_HOME"), Ok("${XDG_DATA_HOME".into()));
+        assert_eq!(expand("$NOPE/comics"), Err("NOPE".into()));
+        assert_eq!(expand("${NOPE}"), Err("NOPE".into()));
+        assert_eq!(
+            expand_with("~/comics", |_| None),
+            Err(platform::HOME_VAR.into())
+        );
+    }
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(slug("Goodbye to Halos"), "goodbye-to-halos");
+        assert_eq!(slug("Eth's Skin!"), "eth-s-skin");
+        assert_eq!(slug("../Escape"), "escape");
+        assert_eq!(slug("Café"), "café");
+        assert_eq!(slug("???"), "feed");
+    }
+
+    #[test]
+    fn test_feed_dirs() {
+        let root = env::temp_dir().join(format!("feedburst-dirs-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("Old Comic.feed"), "read 2018-01-01T00:00:00Z\n").unwrap();
+        let infos = parser::parse_config(&format!(
+            "root {}\n\"Comic!\" <http://a.example.com/rss>\n\"Comic?\" <http://b.example.com/rss>\n\
+             \"Old Comic\" <http://c.example.com/rss>",
+            root.display()
+        ))
+        .unwrap();
+        let args = Args::new(false, None, Some("config.feeds"), None, None, false).unwrap();
+
+        // Nothing is made just to look
+        let paths = args.paths(&infos[1]).unwrap();
+        assert_eq!(paths.dir, Some(root.join("comic")));
+        assert!(!root.join("comic").exists());
+
+        // The first to come along takes the folder, however the config is ordered
+        args.feed_file(&infos[1]).unwrap();
+        args.feed_file(&infos[0]).unwrap();
+        assert_eq!(args.paths(&infos[1]).unwrap().dir, Some(root.join("comic")));
+        assert_eq!(
+            args.paths(&infos[0]).unwrap().history,
+            root.join("comic-2").join("history.feed")
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("comic-2").join("name.txt")).unwrap(),
+            "Comic!\n"
+        );
+
+        // Histories from before feeds had folders are moved into them
+        let paths = args.paths(&infos[2]).unwrap();
+        assert!(!root.join("Old Comic.feed").exists());
+        assert_eq!(
+            fs::read_to_string(&paths.history).unwrap(),
+            "read 2018-01-01T00:00:00Z\n"
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+//! `@ crawl archive`: following links from comic to comic back through a
+//! site's archive, for feeds that only list the latest few.
+
+use std::collections::HashSet;
+
+use crate::extract::resolve_link;
+use crate::feed::FeedInfo;
+use crate::fetch::Fetcher;
+use crate::html::{tags, Selector};
+
+/// The most pages to follow back through one archive, in case the links go
+/// on forever.
+pub const MAX_PAGES: usize = 5000;
+
+/// The link on `page` to the comic before it: the first `<a>` or `<link>`
+/// that `selector` picks out, or with no selector, that has `rel="prev"`.
+/// As written, so it may be relative to the page.
+pub fn previous_link(page: &str, selector: Option<&Selector>) -> Option<String> {
+    tags(page)
+        .into_iter()
+        .filter(|tag| !tag.closing && (tag.name == "a" || tag.name == "link"))
+        .filter(|tag| match selector {
+            Some(selector) => selector.matches(tag),
+            None => tag.attribute("rel").is_some_and(|rel| {
+                rel.split_whitespace().any(|rel| {
+                    rel.eq_ignore_ascii_case("prev") || rel.eq_ignore_ascii_case("previous")
+                })
+            }),
+        })
+        // Links within the page, like "back to top", don't lead anywhere
+        .filter_map(|tag| tag.attribute("href").map(|href| href.trim().to_string()))
+        .find(|href| !href.is_empty() && !href.starts_with('#'))
+}
+
+/// The comics before the one at `start`, oldest first, found by following
+/// `previous_link` from page to page. Stops at a page without one, at a page
+/// already in `known` or already visited, after `MAX_PAGES` pages, or at a
+/// page that can't be downloaded, keeping what was found before it.
+pub fn crawl<F: Fetcher + ?Sized>(
+    fetcher: &F,
+    info: &FeedInfo,
+    start: &str,
+    selector: Option<&Selector>,
+    known: &HashSet<&str>,
+) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut seen = HashSet::new();
+    let mut url = start.to_string();
+    seen.insert(url.clone());
+    while found.len() < MAX_PAGES {
+        let page = match fetcher.fetch_page(info, &url) {
+            Ok(page) => page,
+            Err(err) => {
+                warn!(
+                    "\"{}\": stopped crawling the archive at <{}>: {}",
+                    info.name, url, err
+                );
+                break;
+            }
+        };
+        let previous =
+            match previous_link(&page, selector).and_then(|link| resolve_link(&url, &link)) {
+                Some(previous) => previous,
+                None => break,
+            };
+        if known.contains(previous.as_str()) || !seen.insert(previous.clone()) {
+            break;
+        }
+        trace!("\"{}\": <{}> comes before <{}>", info.name, previous, url);
+        found.push(previous.clone());
+        url = previous;
+    }
+    found.reverse();
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Error;
+    use crate::parser::parse_config;
+
+    /// An archive of five pages, each linking to the one before.
+    struct Archive;
+
+    impl Fetcher for Archive {
+        fn fetch(&self, info: &FeedInfo) -> Result<String, Error> {
+            let n: usize = info
+                .url
+                .trim_start_matches("http://example.com/")
+                .parse()
+                .unwrap();
+            if n == 1 {
+                return Ok("<html><a href='/2' rel='next'>Next</a></html>".into());
+            }
+            Ok(format!(
+                "<html><a class='nav back' href='{}' rel='prev'>Back</a></html>",
+                n - 1
+            ))
+        }
+    }
+
+    #[test]
+    fn test_previous_link() {
+        let page = r##"<html><head><link rel="next" href="/3"></head>
+<body><a href="#top" rel="prev">Top</a><a class="comic-nav-previous" href="/1">Previous</a>
+<a rel="Previous nofollow" href="/2">Back</a></body></html>"##;
+        assert_eq!(previous_link(page, None), Some("/2".into()));
+        let selector = Selector::parse("a.comic-nav-previous").unwrap();
+        assert_eq!(previous_link(page, Some(&selector)), Some("/1".into()));
+        assert_eq!(previous_link("<a href='/1'>", None), None);
+    }
+
+    #[test]
+    fn test_crawl() {
+        let info = parse_config(r#""Archive" <http://example.com/feed> @ crawl archive"#)
+            .unwrap()
+            .remove(0);
+        let urls = |ns: &[usize]| -> Vec<String> {
+            ns.iter()
+                .map(|n| format!("http://example.com/{}", n))
+                .collect()
+        };
+        let start = "http://example.com/5";
+        assert_eq!(
+            crawl(&Archive, &info, start, None, &HashSet::new()),
+            urls(&[1, 2, 3, 4])
+        );
+        let known = vec!["http://example.com/2"].into_iter().collect();
+        assert_eq!(crawl(&Archive, &info, start, None, &known), urls(&[3, 4]));
+        let selector = Selector::parse("a.back").unwrap();
+        assert_eq!(
+            crawl(&Archive, &info, start, Some(&selector), &HashSet::new()),
+            urls(&[1, 2, 3, 4])
+        );
+        let selector = Selector::parse("a.forward").unwrap();
+        assert!(crawl(&Archive, &info, start, Some(&selector), &HashSet::new()).is_empty());
+    }
+}
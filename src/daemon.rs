@@ -0,0 +1,283 @@
+//! `feedburst daemon`: staying resident and doing a run whenever a feed is
+//! due, instead of being run from cron, and serving the `output_feed` that
+//! each run writes.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time;
+
+use chrono::{DateTime, Duration, Local};
+
+use crate::cli::Opt;
+use crate::config::Args;
+use crate::error::Error;
+use crate::feed::Feed;
+use crate::output::OutputFormat;
+use crate::platform;
+use crate::reload::diff_configs;
+use crate::report::Reporter;
+
+/// The longest to wait between runs, so feeds that are already scheduled get
+/// checked for new comics.
+const POLL_INTERVAL: time::Duration = time::Duration::from_secs(60 * 60);
+
+/// How often to wake up while waiting, to notice signals.
+const SLEEP_STEP: time::Duration = time::Duration::from_secs(1);
+
+/// How long to wait on a client of the served feed before hanging up on it.
+const CLIENT_TIMEOUT: time::Duration = time::Duration::from_secs(10);
+
+/// The most of a request that's read, its request line and headers together.
+const MAX_REQUEST: u64 = 8 * 1024;
+
+/// The `output_feed` being served, which changes when the config is reloaded.
+type Served = Arc<Mutex<Option<(OutputFormat, PathBuf)>>>;
+
+/// Do a run, sleep until the next feed is due, and repeat, until Ctrl-C or
+/// SIGTERM. SIGHUP reloads the config and updates the schedule to match,
+/// running straight away only if that adds feeds. With `serve`, the config's
+/// `output_feed` is served over HTTP at that address too.
+pub fn daemon(
+    opt: &Opt,
+    args: &Args,
+    serve: Option<&str>,
+    reporter: Reporter,
+) -> Result<(), Error> {
+    if opt.now.is_some() {
+        return Err(Error::Msg(
+            "The daemon keeps to the real time, so it can't be used with --now".into(),
+        ));
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    let reload = Arc::new(AtomicBool::new(false));
+    platform::watch_signals(&stop, &reload)?;
+
+    let mut infos = crate::load_config(args)?;
+    let mut settings = crate::load_settings(args)?;
+    let served: Served = Arc::new(Mutex::new(args.output_feed(&settings)));
+    if let Some(addr) = serve {
+        if served.lock().unwrap().is_none() {
+            return Err(Error::Msg(
+                "There's no feed to serve. Add an `output_feed` line to the config first.".into(),
+            ));
+        }
+        serve_output_feed(addr, served.clone())?;
+        args.say(&format!("Serving the output feed at http://{}/", addr));
+    }
+    args.say(&format!(
+        "Watching {} {}. Send SIGHUP to reload the config, or press Ctrl-C to stop.",
+        infos.len(),
+        if infos.len() == 1 { "feed" } else { "feeds" }
+    ));
+    let mut feeds = Vec::new();
+    let mut run_now = true;
+    while !stop.load(Ordering::SeqCst) {
+        if reload.swap(false, Ordering::SeqCst) {
+            // A config with a mistake in it keeps the old one going
+            match crate::load_config(args).and_then(|new| Ok((new, crate::load_settings(args)?))) {
+                Ok((new, new_settings)) => {
+                    let diff = diff_configs(&infos, &new);
+                    if diff.is_empty() {
+                        args.say("Reloaded the config. No feeds changed.");
+                    } else {
+                        args.say(&format!(
+                            "Reloaded the config:\n{}",
+                            diff.to_string().trim_end()
+                        ));
+                    }
+                    // Only new feeds need a run straight away. The rest are
+                    // rescheduled by their new policies
+                    let order: Vec<_> = new.iter().map(|info| info.name.as_str()).collect();
+                    let applied =
+                        diff.apply(&mut feeds, &order, |info| crate::load_feed(args, info));
+                    if let Some(ref group) = opt.group {
+                        feeds.retain(|feed| feed.info.group.as_ref() == Some(group));
+                    }
+                    run_now = !diff.added.is_empty() || applied.is_err();
+                    if let Err(err) = applied {
+                        reporter.error(&err);
+                    }
+                    infos = new;
+                    settings = new_settings;
+                    *served.lock().unwrap() = args.output_feed(&settings);
+                }
+                Err(err) => reporter.error(&err),
+            }
+        }
+
+        if run_now {
+            feeds = match crate::run_feeds(opt, args, &settings, reporter, infos.clone()) {
+                Ok(feeds) => feeds,
+                Err(err) => {
+                    reporter.error(&err);
+                    Vec::new()
+                }
+            };
+        }
+        let wake = next_wake(&feeds, Local::now());
+        args.say(&format!(
+            "Next run at {}",
+            wake.format("%H:%M on %a %Y-%m-%d")
+        ));
+        while Local::now() < wake && !stop.load(Ordering::SeqCst) && !reload.load(Ordering::SeqCst)
+        {
+            thread::sleep(SLEEP_STEP);
+        }
+        run_now = !reload.load(Ordering::SeqCst);
+    }
+    args.say("Stopping.");
+    Ok(())
+}
+
+/// Start serving the `output_feed` on `addr`, in the background until the
+/// process exits. Every path gets the feed, read afresh each time.
+fn serve_output_feed(addr: &str, served: Served) -> Result<(), Error> {
+    let listener = TcpListener::bind(addr)
+        .map_err(|err| Error::Msg(format!("Couldn't serve at {}: {}", addr, err)))?;
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let output = served.lock().unwrap().clone();
+            // Each on its own thread, so a slow client doesn't hold up the rest
+            thread::spawn(move || {
+                if let Err(err) = stream.and_then(|stream| respond(stream, output)) {
+                    warn!("Error serving the output feed: {}", err);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// The request line of the request `reader` starts with, read up to the
+/// blank line after its headers. `None` if that's more than `MAX_REQUEST`
+/// bytes.
+fn read_request<R: BufRead>(reader: R) -> io::Result<Option<String>> {
+    let mut reader = reader.take(MAX_REQUEST);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut line = request_line.clone();
+    while line.ends_with('\n') {
+        line.clear();
+        reader.read_line(&mut line)?;
+        if line.ends_with('\n') && line.trim().is_empty() {
+            return Ok(Some(request_line));
+        }
+    }
+    // Cut off by the limit, or by the client hanging up
+    Ok(Some(request_line).filter(|_| reader.limit() > 0))
+}
+
+fn respond(stream: TcpStream, output: Option<(OutputFormat, PathBuf)>) -> io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let request = read_request(BufReader::new(stream.try_clone()?))?;
+    let method = request
+        .as_deref()
+        .and_then(|line| line.split_whitespace().next())
+        .unwrap_or_default();
+    let feed = output.and_then(|(format, path)| Some((format, fs::read(path).ok()?)));
+    let (status, content_type, body) = match feed {
+        _ if request.is_none() => (
+            "431 Request Header Fields Too Large",
+            "text/plain",
+            b"The request is too long".to_vec(),
+        ),
+        _ if method != "GET" && method != "HEAD" => (
+            "405 Method Not Allowed",
+            "text/plain",
+            b"Only GET works here".to_vec(),
+        ),
+        Some((format, body)) => ("200 OK", format.content_type(), body),
+        None => (
+            "404 Not Found",
+            "text/plain",
+            b"The output feed hasn't been written yet".to_vec(),
+        ),
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len(),
+    )?;
+    if method != "HEAD" {
+        stream.write_all(&body)?;
+    }
+    stream.flush()
+}
+
+/// When to do the next run: when the first feed that isn't scheduled yet
+/// becomes scheduled, or after `POLL_INTERVAL`, whichever is sooner.
+pub fn next_wake(feeds: &[Feed], now: DateTime<Local>) -> DateTime<Local> {
+    let poll = now + Duration::from_std(POLL_INTERVAL).unwrap();
+    feeds
+        .iter()
+        .filter(|feed| !feed.is_scheduled(now))
+        .filter_map(|feed| feed.next_scheduled(now))
+        .min()
+        .map_or(poll, |next| next.min(poll))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_config;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_next_wake() {
+        let events = "read 2018-10-01T12:00:00+00:00\n";
+        let feed = |config: &str| {
+            let info = parse_config(config).unwrap().remove(0);
+            info.read_feed(&mut events.as_bytes()).unwrap()
+        };
+        let at = |d, h, m| Utc.ymd(2018, 10, d).and_hms(h, m, 0).with_timezone(&Local);
+        let daily = feed(r#""Daily" <http://example.com> @ every 1 day"#);
+        let weekly = feed(r#""Weekly" <http://example.com> @ every 7 days"#);
+        let ready = feed(r#""Ready" <http://example.com>"#);
+
+        // Daily is due in half an hour, sooner than the next poll
+        let now = at(2, 11, 30);
+        assert_eq!(
+            next_wake(&[daily.clone(), ready.clone()], now),
+            at(2, 12, 0)
+        );
+        // Weekly is a long way off, and Ready is already scheduled
+        assert_eq!(next_wake(&[weekly, ready], now), at(2, 12, 30));
+        // Once Daily is scheduled, only polling is left
+        assert_eq!(next_wake(&[daily], at(2, 13, 0)), at(2, 14, 0));
+        assert_eq!(next_wake(&[], now), at(2, 12, 30));
+    }
+
+    #[test]
+    fn test_read_request() {
+        let request = "GET /feed.xml HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        assert_eq!(
+            read_request(request.as_bytes()).unwrap().as_deref(),
+            Some("GET /feed.xml HTTP/1.1\r\n")
+        );
+        // A client that hangs up early still gets an answer
+        assert_eq!(
+            read_request("GET / HTTP/1.0\r\n".as_bytes())
+                .unwrap()
+                .as_deref(),
+            Some("GET / HTTP/1.0\r\n")
+        );
+
+        // Lines that never end, or headers that never do
+        let endless = "G".repeat(MAX_REQUEST as usize + 1);
+        assert_eq!(read_request(endless.as_bytes()).unwrap(), None);
+        let headers = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X-Padding: 0123456789\r\n".repeat(1000)
+        );
+        assert_eq!(read_request(headers.as_bytes()).unwrap(), None);
+    }
+}
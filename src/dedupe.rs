@@ -0,0 +1,232 @@
+//! `dedupe on`: opening a comic only once when several feeds carry it, like
+//! collectives that syndicate their members' comics. Comics are matched by
+//! URL, after `normalize_url` irons out the differences feeds tend to have.
+//! Every comic's URL loses its tracking parameters, dedupe or not.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::feed::{Feed, FeedInfo};
+
+/// Query parameters that only track where a link was clicked, which are taken
+/// out of every comic's URL, along with any starting with `utm_`.
+const TRACKING_PARAMS: &[&str] = &["fbclid", "gclid", "mc_cid", "mc_eid"];
+
+/// Query parameters that only say where a link was shared, which could mean
+/// something to some sites, so they're only ignored when matching comics.
+const SHARING_PARAMS: &[&str] = &["ref", "source"];
+
+fn param_name(param: &str) -> String {
+    param.split('=').next().unwrap_or_default().to_lowercase()
+}
+
+fn is_tracking(name: &str) -> bool {
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name)
+}
+
+/// `url` without tracking parameters like `utm_source` in its query, but
+/// otherwise as it was.
+pub fn strip_tracking(url: &str) -> String {
+    let (rest, fragment) = match url.find('#') {
+        Some(start) => url.split_at(start),
+        None => (url, ""),
+    };
+    let (address, query) = match rest.find('?') {
+        Some(start) => (&rest[..start], &rest[start + 1..]),
+        None => return url.into(),
+    };
+    let params: Vec<_> = query
+        .split('&')
+        .filter(|param| !is_tracking(&param_name(param)))
+        .collect();
+    if params.len() == query.split('&').count() {
+        return url.into();
+    }
+    let mut stripped = address.to_string();
+    if params.iter().any(|param| !param.is_empty()) {
+        stripped.push('?');
+        stripped.push_str(&params.join("&"));
+    }
+    stripped.push_str(fragment);
+    stripped
+}
+
+/// `url` in the form comics are matched by: without the scheme, a `www.`,
+/// a trailing `/`, the fragment, or tracking parameters like `utm_source`
+/// and `ref`, and with the host in lowercase.
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim();
+    let url = url.split('#').next().unwrap_or_default();
+    let rest = match url.find("://") {
+        Some(end) => &url[end + 3..],
+        None => url,
+    };
+    let (address, query) = match rest.find('?') {
+        Some(start) => (&rest[..start], Some(&rest[start + 1..])),
+        None => (rest, None),
+    };
+    let (host, path) = match address.find('/') {
+        Some(start) => address.split_at(start),
+        None => (address, ""),
+    };
+    let host = host.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let mut normal = format!("{}{}", host, path.trim_end_matches('/'));
+    let params: Vec<_> = query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| {
+            let name = param_name(param);
+            !name.is_empty() && !is_tracking(&name) && !SHARING_PARAMS.contains(&&*name)
+        })
+        .collect();
+    if !params.is_empty() {
+        normal.push('?');
+        normal.push_str(&params.join("&"));
+    }
+    normal
+}
+
+/// The comics read in any feed so far, by normalized URL.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReadComics {
+    urls: HashSet<String>,
+    /// The feeds each unread comic is in, by normalized URL, with the URL it
+    /// has in each.
+    unread: HashMap<String, Vec<(FeedInfo, String)>>,
+}
+
+impl ReadComics {
+    /// The comics already read in `feeds`.
+    pub fn new(feeds: &[Feed]) -> Self {
+        let mut urls = HashSet::new();
+        let mut unread_in: HashMap<String, Vec<(FeedInfo, String)>> = HashMap::new();
+        for feed in feeds {
+            let unread: HashSet<&str> = feed
+                .unread_comics()
+                .into_iter()
+                .map(|comic| comic.url.as_str())
+                .collect();
+            urls.extend(
+                feed.comics()
+                    .filter(|comic| !unread.contains(comic.url.as_str()))
+                    .map(|comic| normalize_url(&comic.url)),
+            );
+            for url in unread {
+                unread_in
+                    .entry(normalize_url(url))
+                    .or_default()
+                    .push((feed.info.clone(), url.to_string()));
+            }
+        }
+        ReadComics {
+            urls,
+            unread: unread_in,
+        }
+    }
+
+    /// Record that the comic at `url` is being read, returning whether it's
+    /// the first time in any feed.
+    pub fn first_read(&mut self, url: &str) -> bool {
+        self.urls.insert(normalize_url(url))
+    }
+
+    /// The feeds other than the one called `name` that have the comic at
+    /// `url` unread, with the URL it has in each, so reading it in one can be
+    /// recorded in them all.
+    pub fn unread_elsewhere(&self, name: &str, url: &str) -> Vec<(&FeedInfo, &str)> {
+        self.unread
+            .get(&normalize_url(url))
+            .into_iter()
+            .flatten()
+            .filter(|(info, _)| info.name != name)
+            .map(|(info, url)| (info, url.as_str()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_config;
+
+    #[test]
+    fn test_normalize_url() {
+        let same = [
+            "http://example.com/comic/1",
+            "https://www.example.com/comic/1/",
+            "https://Example.COM/comic/1#comments",
+            "https://example.com/comic/1?utm_source=rss&utm_medium=feed",
+            "  https://example.com/comic/1?fbclid=abc ",
+        ];
+        for url in &same {
+            assert_eq!(normalize_url(url), "example.com/comic/1", "{}", url);
+        }
+        assert_eq!(
+            normalize_url("https://example.com/?p=12&utm_campaign=x&page=2"),
+            "example.com?p=12&page=2"
+        );
+        // Paths can be case sensitive
+        assert_ne!(
+            normalize_url("https://example.com/Comic/1"),
+            normalize_url("https://example.com/comic/1")
+        );
+    }
+
+    #[test]
+    fn test_strip_tracking() {
+        assert_eq!(
+            strip_tracking("http://example.com/comic/1?utm_source=rss&utm_medium=feed"),
+            "http://example.com/comic/1"
+        );
+        assert_eq!(
+            strip_tracking("http://example.com/?p=12&fbclid=abc&ref=home#comic"),
+            "http://example.com/?p=12&ref=home#comic"
+        );
+        let untouched = [
+            "http://example.com/comic/1",
+            "http://example.com/?p=12",
+            "http://example.com/?",
+        ];
+        for url in &untouched {
+            assert_eq!(strip_tracking(url), *url);
+        }
+    }
+
+    #[test]
+    fn test_read_comics() {
+        let infos = parse_config(
+            r#""Collective" <http://collective.example.com/rss>
+"Member" <http://member.example.com/rss>
+"#,
+        )
+        .unwrap();
+        let collective = infos[0]
+            .read_feed(
+                &mut "
+<http://member.example.com/1> 2018-10-01T12:00:00+00:00
+read 2018-10-02T12:00:00+00:00
+<http://member.example.com/2> 2018-10-03T12:00:00+00:00
+"
+                .as_bytes(),
+            )
+            .unwrap();
+        let member = infos[1]
+            .read_feed(
+                &mut "<https://www.member.example.com/2/> 2018-10-03T12:00:00+00:00\n".as_bytes(),
+            )
+            .unwrap();
+        let mut read = ReadComics::new(&[collective, member]);
+        assert!(!read.first_read("https://www.member.example.com/1"));
+        assert!(read.first_read("http://member.example.com/2"));
+        assert!(!read.first_read("http://member.example.com/2/"));
+
+        // Reading it in the collective is recorded in the member too
+        let elsewhere = read.unread_elsewhere("Collective", "http://member.example.com/2");
+        assert_eq!(elsewhere.len(), 1);
+        assert_eq!(elsewhere[0].0.name, "Member");
+        assert_eq!(elsewhere[0].1, "https://www.member.example.com/2/");
+        assert!(read
+            .unread_elsewhere("Collective", "http://member.example.com/1")
+            .is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+//! `--output html`: one page listing every comic a run would have opened,
+//! with a section for each feed, to open once instead of a tab per comic.
+
+use chrono::{DateTime, Utc};
+
+use crate::feed::Feed;
+use crate::output::escape;
+
+/// One feed's part of the digest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Section {
+    /// The feed's name, as written in the config.
+    pub feed: String,
+    pub comics: Vec<DigestComic>,
+}
+
+/// One comic in the digest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DigestComic {
+    pub url: String,
+    pub title: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+    /// An image from the comic's feed entry, if it had one.
+    pub thumbnail: Option<String>,
+}
+
+impl Section {
+    /// The section for `urls` of `feed`, with what its history knows about
+    /// each of them.
+    pub fn new(feed: &Feed, urls: &[String]) -> Section {
+        let comics = urls
+            .iter()
+            .map(|url| {
+                let comic = feed.comic(url);
+                DigestComic {
+                    url: url.clone(),
+                    title: comic.and_then(|comic| comic.title.clone()),
+                    published: comic.and_then(|comic| comic.published),
+                    thumbnail: feed.thumbnail(url).map(String::from),
+                }
+            })
+            .collect();
+        Section {
+            feed: feed.info.name.clone(),
+            comics,
+        }
+    }
+}
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 40em; margin: 2em auto; \
+                     padding: 0 1em; line-height: 1.4; }
+h2 { border-bottom: 1px solid #ccc; }
+ul { list-style: none; padding: 0; }
+li { margin: 0.5em 0; }
+.published { color: #666; font-size: 0.9em; }
+img { display: block; max-width: 100%; max-height: 20em; margin-top: 0.25em; }
+";
+
+/// The page for `sections`, made by a run at `when`. With `thumbnails`, each
+/// comic that has an image shows it under its link.
+pub fn write_digest(sections: &[Section], when: DateTime<Utc>, thumbnails: bool) -> String {
+    let mut out = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    let title = format!("Comics for {}", when.format("%Y-%m-%d"));
+    out.push_str(&format!("<title>{}</title>\n", escape(&title)));
+    out.push_str(&format!("<style>\n{}</style>\n", STYLE));
+    out.push_str("</head>\n<body>\n");
+    out.push_str(&format!("<h1>{}</h1>\n", escape(&title)));
+    for section in sections {
+        out.push_str(&format!("<h2>{}</h2>\n<ul>\n", escape(&section.feed)));
+        for comic in &section.comics {
+            let title = comic.title.as_ref().unwrap_or(&comic.url);
+            out.push_str(&format!(
+                "<li><a href=\"{}\">{}</a>",
+                escape(&comic.url),
+                escape(title)
+            ));
+            if let Some(published) = comic.published {
+                out.push_str(&format!(
+                    " <span class=\"published\">{}</span>",
+                    published.format("%a %Y-%m-%d")
+                ));
+            }
+            if let (true, Some(image)) = (thumbnails, &comic.thumbnail) {
+                out.push_str(&format!(
+                    "\n<a href=\"{}\"><img src=\"{}\" alt=\"\" loading=\"lazy\"></a>",
+                    escape(&comic.url),
+                    escape(image)
+                ));
+            }
+            out.push_str("</li>\n");
+        }
+        out.push_str("</ul>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_config;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_write_digest() {
+        let infos = parse_config("\"Tom & Jerry\" <http://example.com/rss>\n").unwrap();
+        let feed = infos[0]
+            .read_feed(
+                &mut "
+<http://example.com/1> 2018-10-01T12:00:00+00:00 <Page 1>
+thumbnail <http://example.com/1> <http://example.com/thumbs/1.png>
+<http://example.com/2?a=1&b=2>
+"
+                .as_bytes(),
+            )
+            .unwrap();
+        let urls = vec![
+            "http://example.com/1".to_string(),
+            "http://example.com/2?a=1&b=2".to_string(),
+        ];
+        let sections = vec![Section::new(&feed, &urls)];
+        assert_eq!(
+            sections[0].comics[0].thumbnail.as_deref(),
+            Some("http://example.com/thumbs/1.png")
+        );
+        let when = Utc.ymd(2018, 10, 3).and_hms(12, 0, 0);
+
+        let page = write_digest(&sections, when, false);
+        assert!(page.contains("<title>Comics for 2018-10-03</title>"));
+        assert!(page.contains("<h2>Tom &amp; Jerry</h2>"));
+        assert!(page.contains("<a href=\"http://example.com/1\">&lt;Page 1&gt;</a>"));
+        assert!(page.contains("<span class=\"published\">Mon 2018-10-01</span>"));
+        assert!(page.contains(
+            "<a href=\"http://example.com/2?a=1&amp;b=2\">http://example.com/2?a=1&amp;b=2</a>"
+        ));
+        assert!(!page.contains("<img"));
+
+        let page = write_digest(&sections, when, true);
+        assert_eq!(page.matches("<img").count(), 1);
+        assert!(page.contains("<img src=\"http://example.com/thumbs/1.png\""));
+    }
+}
@@ -0,0 +1,525 @@
+//! Changing a config file the way someone would by hand: only the lines and
+//! columns being changed are touched, so comments, blank lines, spacing, and
+//! the order of everything else stay as they were.
+
+use std::ops::Range;
+
+use crate::error::ParseError;
+use crate::feed::{FeedInfo, UpdateSpec};
+use crate::parser::{self, FeedSpans, Machine};
+
+/// A config file as its lines, to be changed and then written back out with
+/// everything that wasn't changed exactly as it was. Rows count from 1, like
+/// the rows in errors and `FeedSpans`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    lines: Vec<String>,
+}
+
+impl Document {
+    pub fn new(text: &str) -> Self {
+        Document {
+            lines: text.split('\n').map(String::from).collect(),
+        }
+    }
+
+    /// The text of the config, with any changes.
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// The line at `row`.
+    pub fn line(&self, row: usize) -> &str {
+        self.lines[row - 1].trim_end_matches('\r')
+    }
+
+    /// The feeds read on `machine`, with where each of them is.
+    pub fn feeds(&self, machine: &Machine) -> Result<Vec<(FeedInfo, FeedSpans)>, ParseError> {
+        parser::parse_config_spans(&self.text(), machine)
+    }
+
+    /// The first feed read on `machine` called `name`, with where it is.
+    pub fn find_feed(
+        &self,
+        machine: &Machine,
+        name: &str,
+    ) -> Result<Option<(FeedInfo, FeedSpans)>, ParseError> {
+        Ok(self
+            .feeds(machine)?
+            .into_iter()
+            .find(|(info, _)| info.name == name))
+    }
+
+    /// Put `text` in place of the columns `span` of the line at `row`.
+    pub fn replace(&mut self, row: usize, span: Range<usize>, text: &str) {
+        self.lines[row - 1].replace_range(span, text);
+    }
+
+    /// Put `line` in place of the line at `row`.
+    pub fn set_line(&mut self, row: usize, line: &str) {
+        let cr = if self.lines[row - 1].ends_with('\r') {
+            "\r"
+        } else {
+            ""
+        };
+        self.lines[row - 1] = format!("{}{}", line, cr);
+    }
+
+    /// Put `line` before the line at `row`, moving it and the lines after it
+    /// down one.
+    pub fn insert_line(&mut self, row: usize, line: &str) {
+        let line = format!("{}{}", line, self.cr());
+        self.lines.insert(row - 1, line);
+    }
+
+    /// Take out the line at `row`, moving the lines after it up one.
+    pub fn remove_line(&mut self, row: usize) {
+        self.lines.remove(row - 1);
+    }
+
+    /// Add `line` after the last line, keeping the file ending in a newline.
+    pub fn push_line(&mut self, line: &str) {
+        let last = self.lines.len();
+        if !self.lines[last - 1].is_empty() {
+            let cr = self.cr();
+            self.lines[last - 1].push_str(cr);
+            self.lines.push(String::new());
+        }
+        self.insert_line(self.lines.len(), line);
+    }
+
+    /// Add the feed lines in `lines` to the end. If the config ends in an
+    /// `only on` section that skips `machine`, an `only` line ending the
+    /// section goes first, so the new feeds are read here.
+    pub fn push_feeds(&mut self, machine: &Machine, lines: &str) -> Result<(), ParseError> {
+        let before = self.feeds(machine)?.len();
+        let mut appended = self.clone();
+        for line in lines.trim_end().lines() {
+            appended.push_line(line);
+        }
+        if appended.feeds(machine)?.len() == before {
+            self.push_line("only");
+            for line in lines.trim_end().lines() {
+                self.push_line(line);
+            }
+        } else {
+            *self = appended;
+        }
+        Ok(())
+    }
+
+    /// Take out the first feed read on `machine` called `name`. Whether there
+    /// was one.
+    pub fn remove_feed(&mut self, machine: &Machine, name: &str) -> Result<bool, ParseError> {
+        match self.find_feed(machine, name)? {
+            Some((_, spans)) => {
+                self.remove_line(spans.row);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Call the first feed read on `machine` called `name` `new_name`
+    /// instead. Whether there was one.
+    pub fn rename_feed(
+        &mut self,
+        machine: &Machine,
+        name: &str,
+        new_name: &str,
+    ) -> Result<bool, ParseError> {
+        match self.find_feed(machine, name)? {
+            Some((_, spans)) => {
+                self.replace(spans.row, spans.name, new_name);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Set the directive `keyword` to `value`, or back to its default if
+    /// `value` is empty, where it was last set for `machine`. If it's never
+    /// set, the line goes before the first directive or feed, after any
+    /// comments at the top.
+    pub fn set_directive(
+        &mut self,
+        machine: &Machine,
+        keyword: &str,
+        value: &str,
+    ) -> Result<(), ParseError> {
+        let line = format!("{} {}", keyword, value);
+        let line = line.trim_end();
+        let settings = parser::parse_settings_for(&self.text(), machine)?;
+        match settings.rows.get(keyword) {
+            Some(&row) => {
+                let indent = self.line(row).len() - self.line(row).trim_start().len();
+                let line = format!("{}{}", &self.line(row)[..indent], line);
+                self.set_line(row, &line);
+            }
+            None => {
+                let first = (1..=self.lines.len())
+                    .find(|&row| {
+                        let text = self.line(row).trim();
+                        !text.is_empty() && !text.starts_with('#')
+                    })
+                    .unwrap_or(self.lines.len());
+                self.insert_line(first, line);
+            }
+        }
+        Ok(())
+    }
+
+    /// Take out every line setting the directive `keyword` for `machine`.
+    /// How many there were.
+    pub fn remove_directive(
+        &mut self,
+        machine: &Machine,
+        keyword: &str,
+    ) -> Result<usize, ParseError> {
+        let mut removed = 0;
+        while let Some(&row) = parser::parse_settings_for(&self.text(), machine)?
+            .rows
+            .get(keyword)
+        {
+            self.remove_line(row);
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    /// What goes before the newline at the end of a new line: a carriage
+    /// return if the file was written with Windows line endings.
+    fn cr(&self) -> &'static str {
+        if self.lines.iter().any(|line| line.ends_with('\r')) {
+            "\r"
+        } else {
+            ""
+        }
+    }
+}
+
+/// A change to make to the policies of a feed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PolicyEdit {
+    /// Add the policy, unless the feed already has it.
+    Add(UpdateSpec),
+    /// Remove the policy wherever it's written.
+    Remove(UpdateSpec),
+    /// Swap out every policy of the same kind, like any `@ every # days`,
+    /// for this one. Adds it if there were none.
+    Replace(UpdateSpec),
+}
+
+/// The result of editing a config: the new text, and the names of the feeds
+/// whose lines changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Edited {
+    pub text: String,
+    pub changed: Vec<String>,
+}
+
+/// Apply `edit` to every feed in `config` that `matches` picks out. Only the
+/// policies being changed are touched. Feeds skipped by `only on` sections
+/// for other machines are left alone.
+pub fn edit_policies<F: Fn(&FeedInfo) -> bool>(
+    config: &str,
+    machine: &Machine,
+    matches: F,
+    edit: &PolicyEdit,
+) -> Result<Edited, ParseError> {
+    let mut document = Document::new(config);
+    let mut changed = Vec::new();
+    for (info, spans) in document.feeds(machine)? {
+        if !matches(&info) {
+            continue;
+        }
+        let line = document.line(spans.row);
+        // Later changes come first, so earlier columns stay where they were
+        let mut changes: Vec<(Range<usize>, String)> = Vec::new();
+        let append = |changes: &mut Vec<_>, policy: &UpdateSpec| {
+            changes.push((spans.end..spans.end, format!(" {}", policy)));
+        };
+        match *edit {
+            PolicyEdit::Add(ref policy) => {
+                if !info.update_policies.contains(policy) {
+                    append(&mut changes, policy);
+                }
+            }
+            PolicyEdit::Remove(ref policy) => {
+                for (existing, span) in spans.policies.iter().rev() {
+                    if existing == policy {
+                        changes.push((with_space_before(line, span), String::new()));
+                    }
+                }
+            }
+            PolicyEdit::Replace(ref policy) => {
+                let mut same = spans
+                    .policies
+                    .iter()
+                    .filter(|(existing, _)| existing.same_kind(policy));
+                match same.next() {
+                    None => append(&mut changes, policy),
+                    Some((first, first_span)) => {
+                        let rest: Vec<_> = same.collect();
+                        if first != policy || !rest.is_empty() {
+                            for (_, span) in rest.iter().rev() {
+                                changes.push((with_space_before(line, span), String::new()));
+                            }
+                            changes.push((first_span.clone(), policy.to_string()));
+                        }
+                    }
+                }
+            }
+        }
+        if changes.is_empty() {
+            continue;
+        }
+        for (span, replacement) in changes {
+            document.replace(spans.row, span, &replacement);
+        }
+        changed.push(info.name);
+    }
+    Ok(Edited {
+        text: document.text(),
+        changed,
+    })
+}
+
+/// Give every feed in `config` that `new_url` has a new URL for that URL
+/// instead. A new URL with what an `{env:NAME}` holds in it is left out, so
+/// the secret isn't written into the config; `FeedInfo::saved_url` gives one
+/// with `{env:NAME}` put back.
+pub fn replace_urls<F: Fn(&FeedInfo) -> Option<String>>(
+    config: &str,
+    machine: &Machine,
+    new_url: F,
+) -> Result<Edited, ParseError> {
+    let mut document = Document::new(config);
+    let mut changed = Vec::new();
+    for (info, spans) in document.feeds(machine)? {
+        let url = match new_url(&info) {
+            Some(url)
+                if url != info.written_url() && info.saved_url(&url).as_ref() == Some(&url) =>
+            {
+                url
+            }
+            _ => continue,
+        };
+        if document.line(spans.row)[spans.url.clone()] != *info.written_url() {
+            continue;
+        }
+        document.replace(spans.row, spans.url, &url);
+        changed.push(info.name);
+    }
+    Ok(Edited {
+        text: document.text(),
+        changed,
+    })
+}
+
+/// A config line for the feed called `name` at `url`, with `policies`.
+pub fn feed_line(name: &str, url: &str, policies: &[UpdateSpec]) -> String {
+    let mut line = format!("\"{}\" <{}>", name, url);
+    for policy in policies {
+        line.push(' ');
+        line.push_str(&policy.to_string());
+    }
+    line
+}
+
+/// Add `lines`, one or more feed lines from `feed_line`, to the end of
+/// `config`, like `Document::push_feeds`.
+pub fn append_feed(config: &str, machine: &Machine, lines: &str) -> Result<String, ParseError> {
+    let mut document = Document::new(config);
+    document.push_feeds(machine, lines)?;
+    Ok(document.text())
+}
+
+/// Grow `span` to take the whitespace before it, so removing a policy doesn't
+/// leave a gap behind.
+fn with_space_before(line: &str, span: &Range<usize>) -> Range<usize> {
+    let start = line[..span.start].trim_end().len();
+    start..span.end
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_policy_list;
+
+    const CONFIG: &str = r#"# My comics
+root /comics
+
+"Witchy" <http://feeds.feedburner.com/WitchyComic?format=xml>   @ every 7 days
+"Cucumber Quest" <http://cucumber.tumblr.com/rss> @ every 3 days @ on Monday @ every 4 days
+"Lilith" <http://lilith.tumblr.com/rss>    @ overlap 1 comic
+"#;
+
+    fn edit(make: fn(UpdateSpec) -> PolicyEdit, policy: &str, pattern: &str) -> Edited {
+        let policy = parse_policy_list(policy).unwrap().remove(0);
+        let matches = |info: &FeedInfo| info.url.contains(pattern);
+        edit_policies(CONFIG, &Machine::current(None), matches, &make(policy)).unwrap()
+    }
+
+    #[test]
+    fn test_add() {
+        let edited = edit(PolicyEdit::Add, "@ overlap 1 comic", "tumblr");
+        assert_eq!(edited.changed, vec!["Cucumber Quest"]);
+        assert_eq!(
+            edited.text,
+            CONFIG.replace("@ every 4 days", "@ every 4 days @ overlap 1 comic")
+        );
+    }
+
+    #[test]
+    fn test_append_feed() {
+        let machine = Machine::current(None);
+        let policies = parse_policy_list("@ on saturday @ every 7 days").unwrap();
+        let line = feed_line("New Comic", "http://new.example.com/rss", &policies);
+        assert_eq!(
+            line,
+            r#""New Comic" <http://new.example.com/rss> @ on Saturday @ every 7 days"#
+        );
+
+        let appended = append_feed(CONFIG, &machine, &line).unwrap();
+        assert_eq!(appended, format!("{}{}\n", CONFIG, line));
+        let feeds = parser::parse_config_for(&appended, &machine).unwrap();
+        assert_eq!(feeds[3].name, "New Comic");
+        assert_eq!(feeds[3].update_policies.len(), 2);
+
+        let unterminated = append_feed("\"Old\" <http://old.example.com/rss>", &machine, &line);
+        assert_eq!(
+            unterminated.unwrap(),
+            format!("\"Old\" <http://old.example.com/rss>\n{}\n", line)
+        );
+
+        // Out of a section for some other machine
+        let guarded = "only on host nowhere.invalid\n\"Old\" <http://old.example.com/rss>\n";
+        assert_eq!(
+            append_feed(guarded, &machine, &line).unwrap(),
+            format!("{}only\n{}\n", guarded, line)
+        );
+    }
+
+    #[test]
+    fn test_replace_urls() {
+        let machine = Machine::current(None);
+        let moved = |info: &FeedInfo| {
+            if info.url.starts_with("http://cucumber") {
+                Some(info.url.replace("http:", "https:"))
+            } else {
+                None
+            }
+        };
+        let edited = replace_urls(CONFIG, &machine, moved).unwrap();
+        assert_eq!(edited.changed, vec!["Cucumber Quest"]);
+        assert_eq!(
+            edited.text,
+            CONFIG.replace("<http://cucumber", "<https://cucumber")
+        );
+
+        // What the variable holds stays out of the config
+        std::env::set_var("FEEDBURST_TEST_REPLACE_URLS", "secret");
+        let config = r#""Private" <http://example.com/rss?key={env:FEEDBURST_TEST_REPLACE_URLS}>"#;
+        let edited = replace_urls(config, &machine, |info| {
+            Some(info.url.replace("http:", "https:"))
+        })
+        .unwrap();
+        assert!(edited.changed.is_empty());
+        assert_eq!(edited.text, config);
+
+        // Unless it's put back as `{env:NAME}`
+        let edited = replace_urls(config, &machine, |info| {
+            info.saved_url(&info.url.replace("http:", "https:"))
+        })
+        .unwrap();
+        assert_eq!(edited.changed, vec!["Private"]);
+        assert_eq!(edited.text, config.replace("<http:", "<https:"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for text in &[
+            CONFIG,
+            "",
+            "\n\n",
+            "\"No Newline\" <http://example.com/rss>",
+            "# Windows\r\n\r\n\"Comic\" <http://example.com/rss>\r\n",
+        ] {
+            assert_eq!(Document::new(text).text(), *text);
+        }
+    }
+
+    #[test]
+    fn test_document() {
+        let machine = Machine::current(None);
+        let mut document = Document::new(CONFIG);
+        document.set_directive(&machine, "root", "/other").unwrap();
+        document.set_directive(&machine, "jobs", "2").unwrap();
+        assert!(document.remove_feed(&machine, "Lilith").unwrap());
+        assert!(!document.remove_feed(&machine, "Lilith").unwrap());
+        let (_, spans) = document.find_feed(&machine, "Witchy").unwrap().unwrap();
+        document.set_line(spans.row, r#""Witchy" <http://witchy.example.com/rss>"#);
+        assert!(document
+            .rename_feed(&machine, "Cucumber Quest", "CQ")
+            .unwrap());
+        document.push_line("# The end");
+        assert_eq!(
+            document.text(),
+            r#"# My comics
+jobs 2
+root /other
+
+"Witchy" <http://witchy.example.com/rss>
+"CQ" <http://cucumber.tumblr.com/rss> @ every 3 days @ on Monday @ every 4 days
+# The end
+"#
+        );
+
+        assert_eq!(document.remove_directive(&machine, "root").unwrap(), 1);
+        document.set_directive(&machine, "jobs", "").unwrap();
+        assert_eq!(document.line(2), "jobs");
+        assert!(parser::parse_config_for(&document.text(), &machine).is_ok());
+
+        // New lines match the line endings already there
+        let mut document = Document::new("# Windows\r\n\"Comic\" <http://example.com/rss>");
+        document.push_line("# The end");
+        document.set_directive(&machine, "jobs", "2").unwrap();
+        assert_eq!(
+            document.text(),
+            "# Windows\r\njobs 2\r\n\"Comic\" <http://example.com/rss>\r\n# The end\r\n"
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let edited = edit(PolicyEdit::Remove, "@ every 7 days", "");
+        assert_eq!(edited.changed, vec!["Witchy"]);
+        assert_eq!(edited.text, CONFIG.replace("   @ every 7 days", ""));
+
+        let edited = edit(PolicyEdit::Remove, "@ on monday", "");
+        assert_eq!(edited.changed, vec!["Cucumber Quest"]);
+        assert_eq!(edited.text, CONFIG.replace(" @ on Monday", ""));
+    }
+
+    #[test]
+    fn test_replace() {
+        let edited = edit(PolicyEdit::Replace, "@ every 2 days", "");
+        assert_eq!(edited.changed, vec!["Witchy", "Cucumber Quest", "Lilith"]);
+        assert_eq!(
+            edited.text,
+            CONFIG
+                .replace("@ every 7 days", "@ every 2 days")
+                .replace(
+                    "@ every 3 days @ on Monday @ every 4 days",
+                    "@ every 2 days @ on Monday"
+                )
+                .replace("@ overlap 1 comic", "@ overlap 1 comic @ every 2 days")
+        );
+
+        // Nothing to do when the policy is already the only one of its kind
+        let edited = edit(PolicyEdit::Replace, "@ every 7 days", "Witchy");
+        assert!(edited.changed.is_empty());
+        assert_eq!(edited.text, CONFIG);
+    }
+}
@@ -1,11 +1,22 @@
-use reqwest;
 use std::{fmt, io};
 
 #[derive(Debug)]
 pub enum Error {
     Msg(String),
     Io(io::Error),
+    /// A mistake in some text feedburst parsed, like the config, along with
+    /// the line it's on so it can be shown in context. `what` names the text,
+    /// like the path of the config file.
+    Parse {
+        what: String,
+        line: String,
+        error: ParseError,
+    },
+    #[cfg(feature = "native")]
     Request(reqwest::Error),
+    /// From the database of feed histories, with `storage sqlite`.
+    #[cfg(feature = "native")]
+    Database(rusqlite::Error),
 }
 
 impl fmt::Display for Error {
@@ -13,7 +24,15 @@ impl fmt::Display for Error {
         match *self {
             Error::Io(ref err) => write!(fmt, "Error performing IO: {}", err),
             Error::Msg(ref err) => write!(fmt, "{}", err),
+            Error::Parse {
+                ref what,
+                ref line,
+                ref error,
+            } => write!(fmt, "{}", error.render_line(what, line)),
+            #[cfg(feature = "native")]
             Error::Request(ref err) => write!(fmt, "Error making request: {}", err),
+            #[cfg(feature = "native")]
+            Error::Database(ref err) => write!(fmt, "Error using the feed database: {}", err),
         }
     }
 }
@@ -35,6 +54,48 @@ impl ParseError {
     }
 }
 
+impl ParseError {
+    /// Describe the error the way the CLI shows it: which line of `text` it's
+    /// on, with the offending part underlined. `what` names the thing being
+    /// parsed, like the path of the config file.
+    pub fn render(&self, what: &str, text: &str) -> String {
+        self.render_line(what, self.line_in(text))
+    }
+
+    /// Like `render`, given just the line the error is on.
+    pub fn render_line(&self, what: &str, line: &str) -> String {
+        let ParseError::Expected { ref msg, row, span } = *self;
+        let mut message = format!("Line {}: Error parsing {}\n\n", row, what);
+        message.push_str(&format!("{}\n", line));
+        match span {
+            None => message.push('\n'),
+            Some((l, r)) => {
+                let underline = format!("{}{}\n", " ".repeat(l), "^".repeat(r - l + 1));
+                message.push_str(&underline);
+            }
+        }
+        message.push_str(&format!("Expected {}", msg));
+        message
+    }
+
+    /// The line of `text` the error is on.
+    pub fn line_in<'a>(&self, text: &'a str) -> &'a str {
+        let ParseError::Expected { row, .. } = *self;
+        text.lines().nth(row.saturating_sub(1)).unwrap_or_default()
+    }
+}
+
+impl Error {
+    /// An error from parsing `text`, kept with the line it's on.
+    pub fn parse<S: Into<String>>(what: S, text: &str, error: ParseError) -> Error {
+        Error::Parse {
+            what: what.into(),
+            line: error.line_in(text).into(),
+            error,
+        }
+    }
+}
+
 pub trait IntoSpan {
     fn into_span(self) -> Span;
 }
@@ -63,8 +124,16 @@ impl From<io::Error> for Error {
     }
 }
 
+#[cfg(feature = "native")]
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Error {
         Error::Request(err)
     }
 }
+
+#[cfg(feature = "native")]
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Error {
+        Error::Database(err)
+    }
+}
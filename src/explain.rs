@@ -0,0 +1,369 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use crate::bundle::redact;
+use crate::config::Args;
+use crate::error::Error;
+use crate::feed::{interval_text, Auth, FeedInfo, ReadMarker};
+use crate::include::Config;
+use crate::output::OutputFormat;
+use crate::parser::{Backend, FeedSpans, Settings};
+use feedburst::format::Format;
+
+/// Describe the settings each feed ends up with once every directive and
+/// command-line flag has had its say, and where each one came from, for
+/// `--explain-config`. `jobs` is the `--jobs` flag, if given.
+pub fn explain_config(
+    args: &Args,
+    config: &Config,
+    settings: &Settings,
+    feeds: &[(FeedInfo, FeedSpans)],
+    jobs: Option<usize>,
+) -> Result<String, Error> {
+    let line = |row: usize| {
+        let (path, row) = config.locate(row);
+        format!("{}:{}", path.display(), row)
+    };
+    let directive = |rows: &BTreeMap<&str, usize>, keyword: &str| {
+        rows.get(keyword)
+            .map_or_else(|| "default".into(), |&row| line(row))
+    };
+
+    let mut out = String::new();
+    out.push_str("Every run\n");
+    let (jobs, origin) = match (jobs, settings.jobs) {
+        (Some(jobs), _) => (jobs, "--jobs".into()),
+        (None, Some(jobs)) => (jobs, directive(&settings.rows, "jobs")),
+        (None, None) => (crate::DEFAULT_JOBS, "default".into()),
+    };
+    setting(&mut out, "jobs", jobs, &origin);
+    let interval = settings.min_run_interval.map_or_else(
+        || "none".into(),
+        |x| interval_text(x.num_seconds() as usize),
+    );
+    let origin = directive(&settings.rows, "min_run_interval");
+    setting(&mut out, "min_run_interval", interval, &origin);
+    let storage = match settings.storage {
+        Backend::Files => "files",
+        Backend::Sqlite => "sqlite",
+    };
+    let origin = directive(&settings.rows, "storage");
+    setting(&mut out, "storage", storage, &origin);
+    let dedupe = if settings.dedupe { "on" } else { "off" };
+    let origin = directive(&settings.rows, "dedupe");
+    setting(&mut out, "dedupe", dedupe, &origin);
+    let output_feed = match args.output_feed(settings) {
+        Some((OutputFormat::Atom, path)) => format!("atom {}", path.display()),
+        Some((OutputFormat::Rss, path)) => format!("rss {}", path.display()),
+        None => "none".into(),
+    };
+    let mut origin = directive(&settings.rows, "output_feed");
+    if settings.output_feed.is_some() && args.is_sandbox() {
+        origin = format!("output_feed at {} ignored by --sandbox", origin);
+    }
+    setting(&mut out, "output_feed", output_feed, &origin);
+    let log_file = args
+        .log_file(settings)
+        .map_or_else(|| "none".into(), |path| path.display().to_string());
+    let mut origin = directive(&settings.rows, "log_file");
+    if settings.log_file.is_some() && args.is_sandbox() {
+        origin = format!("log_file at {} ignored by --sandbox", origin);
+    }
+    setting(&mut out, "log_file", log_file, &origin);
+    // Without the password, like `auth`
+    let smtp = settings.smtp.as_ref().map_or_else(
+        || "none".into(),
+        |smtp| match smtp.login {
+            Some((ref user, _)) => format!("{}:{} {}", smtp.host, smtp.port, user),
+            None => format!("{}:{}", smtp.host, smtp.port),
+        },
+    );
+    setting(&mut out, "smtp", smtp, &directive(&settings.rows, "smtp"));
+
+    for (info, spans) in feeds {
+        let rows = &spans.directives;
+        out.push_str(&format!(
+            "\n\"{}\" <{}> ({})\n",
+            info.name,
+            info.written_url(),
+            line(spans.row)
+        ));
+
+        if let Some(ref group) = info.group {
+            setting(&mut out, "group", group, &directive(rows, "group"));
+        }
+        for (policy, _) in &spans.policies {
+            setting(&mut out, "policy", policy, &line(spans.row));
+        }
+        if info.default_policy {
+            let origin = match rows.get("default_policy") {
+                Some(&row) => format!("default_policy at {}", line(row)),
+                None => "default".into(),
+            };
+            let mut policies: Vec<_> = info
+                .update_policies
+                .iter()
+                .filter(|&policy| !spans.inherited.contains(policy))
+                .map(|x| x.to_string())
+                .collect();
+            policies.sort();
+            for policy in policies {
+                setting(&mut out, "policy", policy, &origin);
+            }
+        }
+        let origin = format!("default at {}", directive(rows, "default"));
+        for policy in &spans.inherited {
+            setting(&mut out, "policy", policy, &origin);
+        }
+
+        let ignored = |keyword| {
+            format!(
+                "{} at {} ignored by --sandbox",
+                keyword,
+                directive(rows, keyword)
+            )
+        };
+        let origin = match (args.feeds_flag(), &info.root) {
+            (Some(_), _) => "--feeds".into(),
+            (None, Some(_)) if args.is_sandbox() => ignored("root"),
+            (None, _) => directive(rows, "root"),
+        };
+        let paths = args.paths(info)?;
+        setting(&mut out, "history", paths.history.display(), &origin);
+
+        // `command` and `open with` replace each other, so the later one wins
+        let open_row = ["command", "open"]
+            .iter()
+            .filter_map(|keyword| rows.get(keyword).map(|&row| (row, *keyword)))
+            .max();
+        let open_with = args.open_with(info);
+        let origin = match (args.open_with_flag(), open_row) {
+            (Some(_), _) => "--open-with".into(),
+            (None, Some((_, keyword)))
+                if info.open_with.as_ref().is_some_and(|x| *x != open_with) =>
+            {
+                ignored(keyword)
+            }
+            (None, Some((row, _))) => line(row),
+            (None, None) => "default".into(),
+        };
+        setting(&mut out, "open with", open_with, &origin);
+        let deliver = args
+            .deliver_to(info)
+            .map_or_else(|| "none".into(), |to| format!("email {}", to));
+        let origin = match (spans.route, &info.deliver_to) {
+            (Some(row), Some(_)) if args.is_sandbox() => {
+                format!("route at {} ignored by --sandbox", line(row))
+            }
+            (Some(row), _) => line(row),
+            (None, Some(_)) if args.is_sandbox() => ignored("deliver"),
+            (None, _) => directive(rows, "deliver"),
+        };
+        setting(&mut out, "deliver", deliver, &origin);
+        for (name, value) in &info.headers {
+            // Only the user agent's value, since the rest are often logins
+            // or cookies, like `auth`
+            let (keyword, header) = if name == "User-Agent" {
+                ("user_agent", format!("{}: {}", name, value))
+            } else {
+                ("header", format!("{}: (hidden)", name))
+            };
+            setting(&mut out, "header", header, &directive(rows, keyword));
+        }
+        if let Some(ref auth) = info.auth {
+            // Without the secret, since this is often pasted into bug reports
+            let auth = match *auth {
+                Auth::Basic { ref user, .. } => format!("basic {}", user),
+                Auth::Bearer(_) => "bearer".into(),
+            };
+            setting(&mut out, "auth", auth, &directive(rows, "auth"));
+        }
+
+        let origin = match args.proxy_flag() {
+            Some(_) => "--proxy".into(),
+            None => directive(rows, "proxy"),
+        };
+        // Without any password in it, like `auth`
+        let proxy = args
+            .proxy_flag()
+            .or(info.proxy.as_ref())
+            .map_or_else(|| "none".into(), |proxy| redact(proxy));
+        setting(&mut out, "proxy", proxy, &origin);
+        let origin = match args.timezone_flag() {
+            Some(_) => "--timezone".into(),
+            None => directive(rows, "timezone"),
+        };
+        let timezone = args
+            .timezone_flag()
+            .or(info.timezone)
+            .map_or_else(|| "local".into(), |zone| zone.name().to_string());
+        setting(&mut out, "timezone", timezone, &origin);
+
+        let marker = match info.read_marker {
+            ReadMarker::Now => "now",
+            ReadMarker::PublishDate => "publish-date",
+        };
+        setting(
+            &mut out,
+            "read_marker",
+            marker,
+            &directive(rows, "read_marker"),
+        );
+        setting(
+            &mut out,
+            "format",
+            format_text(&info.format),
+            &directive(rows, "format"),
+        );
+
+        let (limit, origin) = match (info.item_limit(), info.max_items_per_fetch) {
+            (None, _) => ("none".into(), directive(rows, "max_items_per_fetch")),
+            (Some(limit), Some(max)) if limit == max => {
+                (limit.to_string(), directive(rows, "max_items_per_fetch"))
+            }
+            (Some(limit), _) => (limit.to_string(), line(spans.row)),
+        };
+        setting(&mut out, "max_items_per_fetch", limit, &origin);
+
+        let (timeout, origin) = match (info.timeout(), settings.timeout) {
+            (Some(timeout), _) => (timeout, line(spans.row)),
+            (None, Some(timeout)) => (timeout, directive(&settings.rows, "timeout")),
+            (None, None) => (
+                chrono::Duration::from_std(crate::FETCH_TIMEOUT).unwrap(),
+                "default".into(),
+            ),
+        };
+        let timeout = interval_text(timeout.num_seconds() as usize);
+        setting(&mut out, "timeout", timeout, &origin);
+
+        let (retries, origin) = match (info.retries(), settings.retries) {
+            (Some(retries), _) => (retries, line(spans.row)),
+            (None, Some(retries)) => (retries, directive(&settings.rows, "retries")),
+            (None, None) => (0, "default".into()),
+        };
+        setting(&mut out, "retries", retries, &origin);
+    }
+    Ok(out)
+}
+
+/// One line of the explanation, with where the value came from lined up on
+/// the right.
+fn setting<T: Display>(out: &mut String, name: &str, value: T, origin: &str) {
+    let value = format!("{}: {}", name, value);
+    out.push_str(&format!("  {:<50} ({})\n", value, origin));
+}
+
+/// A feed's format settings the way a `format` line would write them.
+fn format_text(format: &Format) -> String {
+    let mut parts = Vec::new();
+    if let Some(length) = format.max_title_length {
+        parts.push(format!("title_length {}", length));
+    }
+    if format.hide_urls {
+        parts.push("urls hide".into());
+    }
+    if let Some(ref prefix) = format.prefix {
+        parts.push(format!("prefix {}", prefix));
+    }
+    if parts.is_empty() {
+        "defaults".into()
+    } else {
+        parts.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{parse_config_spans, parse_settings_for, Machine};
+
+    #[test]
+    fn test_explain_config() {
+        let config = r#"jobs 8
+root /tmp
+command firefox @URL
+default_policy @ on saturday
+"Weekly" <http://weekly.example.com/rss>
+max_items_per_fetch 100
+timeout 20s
+"Own" <http://own.example.com/rss> @ every 2 days @ retries 2
+default @ overlap 1 comic @ every 1 day
+"Inherits" <http://inherits.example.com/rss> @ every 3 days
+"#;
+        let machine = Machine::current(None);
+        let settings = parse_settings_for(config, &machine).unwrap();
+        let feeds = parse_config_spans(config, &machine).unwrap();
+        let args = Args::new(false, None, Some("config.feeds"), None, None, false).unwrap();
+        let config = Config::single(args.config_path(), config);
+        let text = explain_config(&args, &config, &settings, &feeds, None).unwrap();
+        let has = |name: &str, value: &str, origin: &str| {
+            let value = format!("{}: {}", name, value);
+            let expected = format!("  {:<50} ({})\n", value, origin);
+            assert!(text.contains(&expected), "{:?} not in\n{}", expected, text);
+        };
+        has("jobs", "8", "config.feeds:1");
+        has("min_run_interval", "none", "default");
+        has("dedupe", "off", "default");
+        has("output_feed", "none", "default");
+        has("smtp", "none", "default");
+        has("deliver", "none", "default");
+        has(
+            "policy",
+            "@ on Saturday",
+            "default_policy at config.feeds:4",
+        );
+        has("history", "/tmp/weekly/history.feed", "config.feeds:2");
+        has("open with", "firefox @URL", "config.feeds:3");
+        has("read_marker", "now", "default");
+        has("retries", "0", "default");
+        has("proxy", "none", "default");
+        has("timezone", "local", "default");
+        has("policy", "@ every 2 days", "config.feeds:8");
+        has("max_items_per_fetch", "100", "config.feeds:6");
+        has("timeout", "20s", "config.feeds:7");
+        has("retries", "2", "config.feeds:8");
+        has("policy", "@ overlap 1 comic", "default at config.feeds:9");
+        has("policy", "@ every 3 days", "config.feeds:10");
+        assert!(!text.contains("@ every 1 day"));
+
+        let args = Args::new(
+            false,
+            Some("/tmp"),
+            Some("config.feeds"),
+            Some("echo"),
+            None,
+            true,
+        )
+        .unwrap()
+        .with_proxy(Some("socks5://localhost:1080".into()))
+        .with_timezone(Some(chrono_tz::Europe::Paris));
+        let text = explain_config(&args, &config, &settings, &feeds, Some(2)).unwrap();
+        assert!(text.contains("(--jobs)"));
+        assert!(text.contains("(--feeds)"));
+        assert!(text.contains("open with: echo"));
+        assert!(text.contains("(--open-with)"));
+        assert!(text.contains("proxy: socks5://localhost:1080"));
+        assert!(text.contains("(--proxy)"));
+        assert!(text.contains("timezone: Europe/Paris"));
+        assert!(text.contains("(--timezone)"));
+    }
+
+    #[test]
+    fn test_explain_secrets() {
+        std::env::set_var("FEEDBURST_TEST_EXPLAIN_TOKEN", "hunter2");
+        let config = r#"user_agent Feedburst
+header Cookie: session={env:FEEDBURST_TEST_EXPLAIN_TOKEN}
+"Secret" <http://example.com/rss?key={env:FEEDBURST_TEST_EXPLAIN_TOKEN}>
+"#;
+        let machine = Machine::current(None);
+        let settings = parse_settings_for(config, &machine).unwrap();
+        let feeds = parse_config_spans(config, &machine).unwrap();
+        let args = Args::new(false, None, Some("config.feeds"), None, None, false).unwrap();
+        let config = Config::single(args.config_path(), config);
+        let text = explain_config(&args, &config, &settings, &feeds, None).unwrap();
+        assert!(!text.contains("hunter2"), "{}", text);
+        assert!(text.contains("<http://example.com/rss?key={env:FEEDBURST_TEST_EXPLAIN_TOKEN}>"));
+        assert!(text.contains("header: Cookie: (hidden)"));
+        assert!(text.contains("header: User-Agent: Feedburst"));
+    }
+}
@@ -0,0 +1,173 @@
+use feedburst::feed::FeedInfo;
+
+/// One comic in a blogroll.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    /// The comic's site, or its feed if the site couldn't be found.
+    pub link: String,
+}
+
+/// A list of comics linking to their sites, sorted by name, as an HTML
+/// fragment or Markdown ready to paste into a page.
+pub fn blogroll(entries: &[Entry], markdown: bool) -> String {
+    let mut entries: Vec<_> = entries.iter().collect();
+    entries.sort_by_key(|entry| entry.name.to_lowercase());
+
+    let mut out = String::new();
+    if markdown {
+        for entry in entries {
+            out.push_str(&format!(
+                "- [{}](<{}>)\n",
+                markdown_escape(&entry.name),
+                entry.link
+            ));
+        }
+    } else {
+        out.push_str("<ul class=\"blogroll\">\n");
+        for entry in entries {
+            out.push_str(&format!(
+                "  <li><a href=\"{}\">{}</a></li>\n",
+                html_escape(&entry.link),
+                html_escape(&entry.name)
+            ));
+        }
+        out.push_str("</ul>\n");
+    }
+    out
+}
+
+/// The feeds as an OPML 2.0 subscription list, in config order, for moving to
+/// another feed reader. URLs are as written in the config, so any secrets
+/// they get from `{env:NAME}` stay out of it.
+pub fn opml(feeds: &[FeedInfo]) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<opml version=\"2.0\">\n");
+    out.push_str("  <head>\n    <title>Feedburst feeds</title>\n  </head>\n");
+    out.push_str("  <body>\n");
+    for info in feeds {
+        out.push_str(&format!(
+            "    <outline type=\"rss\" text=\"{name}\" title=\"{name}\" xmlUrl=\"{url}\"/>\n",
+            name = html_escape(&info.name),
+            url = html_escape(info.written_url()),
+        ));
+    }
+    out.push_str("  </body>\n</opml>\n");
+    out
+}
+
+/// The front page of the site a feed is hosted on, like `https://example.com/`
+/// for `https://example.com/comic/rss`.
+pub fn origin(url: &str) -> String {
+    let start = url.find("://").map_or(0, |i| i + 3);
+    match url[start..].find('/') {
+        Some(end) => url[..=start + end].into(),
+        None => format!("{}/", url),
+    }
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn markdown_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\[]*_`<>".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            Entry {
+                name: "Witchy".into(),
+                link: "http://witchycomic.com/".into(),
+            },
+            Entry {
+                name: "*ella* & Friends".into(),
+                link: "http://example.com/?a=1&b=2".into(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_blogroll() {
+        assert_eq!(
+            blogroll(&entries(), true),
+            concat!(
+                "- [\\*ella\\* & Friends](<http://example.com/?a=1&b=2>)\n",
+                "- [Witchy](<http://witchycomic.com/>)\n",
+            )
+        );
+        assert_eq!(
+            blogroll(&entries(), false),
+            concat!(
+                "<ul class=\"blogroll\">\n",
+                "  <li><a href=\"http://example.com/?a=1&amp;b=2\">*ella* &amp; Friends</a></li>\n",
+                "  <li><a href=\"http://witchycomic.com/\">Witchy</a></li>\n",
+                "</ul>\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_opml() {
+        let feeds = feedburst::parser::parse_config(
+            r#"
+"Witchy" <http://witchycomic.com/rss> @ every 7 days
+"Tom & Jerry" <http://example.com/?a=1&b=2>
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            opml(&feeds),
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                "<opml version=\"2.0\">\n",
+                "  <head>\n    <title>Feedburst feeds</title>\n  </head>\n",
+                "  <body>\n",
+                "    <outline type=\"rss\" text=\"Witchy\" title=\"Witchy\" ",
+                "xmlUrl=\"http://witchycomic.com/rss\"/>\n",
+                "    <outline type=\"rss\" text=\"Tom &amp; Jerry\" title=\"Tom &amp; Jerry\" ",
+                "xmlUrl=\"http://example.com/?a=1&amp;b=2\"/>\n",
+                "  </body>\n</opml>\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_opml_keeps_env_secrets() {
+        std::env::set_var("FEEDBURST_TEST_OPML_TOKEN", "hunter2");
+        let feeds = feedburst::parser::parse_config(
+            "\"Secret\" <http://example.com/rss?key={env:FEEDBURST_TEST_OPML_TOKEN}>\n",
+        )
+        .unwrap();
+        assert_eq!(feeds[0].url, "http://example.com/rss?key=hunter2");
+        let out = opml(&feeds);
+        assert!(
+            out.contains("xmlUrl=\"http://example.com/rss?key={env:FEEDBURST_TEST_OPML_TOKEN}\"")
+        );
+        assert!(!out.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_origin() {
+        assert_eq!(
+            origin("https://example.com/comic/rss"),
+            "https://example.com/"
+        );
+        assert_eq!(origin("http://example.com"), "http://example.com/");
+    }
+}
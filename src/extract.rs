@@ -0,0 +1,795 @@
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::str::FromStr;
+use url::Url;
+
+use crate::error::Error;
+use crate::feed::{FeedInfo, UpdateSpec};
+use crate::html::{first_image, link_tags, strip_html};
+
+/// A single comic pulled out of a downloaded feed document. The title has
+/// already been converted from HTML to plain text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+    pub url: String,
+    pub title: String,
+    pub guid: Option<String>,
+    pub published: DateTime<Utc>,
+    /// The language the comic is in, like `en` or `fr-ca`, if the feed says
+    /// or its URL suggests.
+    pub language: Option<String>,
+    /// A picture of the comic: an attached image, or the first image in the
+    /// entry's description.
+    pub thumbnail: Option<String>,
+}
+
+/// Which way a feed document lists its items.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListOrder {
+    /// The usual way, with the latest comic at the top.
+    NewestFirst,
+    /// Like an archive, with the first comic at the top.
+    OldestFirst,
+}
+
+/// Parse an RSS or Atom document into the comics it lists, oldest first. An
+/// Atom entry's URL is its `alternate` link, or its first link if it has none.
+///
+/// With `@ open enclosure`, an entry's attachment (an RSS `<enclosure>` or an
+/// Atom link with `rel="enclosure"`) is used as its URL in place of its link,
+/// for entries that have one. Entries without a link are dropped, as are
+/// entries that repeat a GUID or link already seen in a more recent entry, so
+/// the most recent copy wins. The feed's `keep`/`ignore` filters and
+/// `@ language` policies are applied last.
+///
+/// Publish dates are normalized against `fetched`, the time the feed was
+/// downloaded: entries with a missing or unparseable date are treated as
+/// published at `fetched`, and entries dated after `fetched` are clamped to it.
+/// Whether the document lists newest or oldest first is guessed from the
+/// dates, and taken to be newest first if they don't tell. Otherwise the order
+/// of the document is kept as-is, so a bad date never reorders comics.
+pub fn extract_items(
+    info: &FeedInfo,
+    content: &str,
+    fetched: DateTime<Utc>,
+) -> Result<Vec<Item>, Error> {
+    extract_items_in(info, content, fetched, None).map(|(items, _)| items)
+}
+
+/// Like `extract_items`, for a feed already known to list its items in
+/// `order`. Also returns the order used: `order` if given, otherwise the one
+/// the dates point to, if they point either way.
+pub fn extract_items_in(
+    info: &FeedInfo,
+    content: &str,
+    fetched: DateTime<Utc>,
+    order: Option<ListOrder>,
+) -> Result<(Vec<Item>, Option<ListOrder>), Error> {
+    use syndication::Feed;
+    let use_enclosure = info.update_policies.contains(&UpdateSpec::OpenEnclosure);
+    let mut items: Vec<Item> = match Feed::from_str(content).map_err(|x| Error::Msg(x.into()))? {
+        Feed::Atom(feed) => {
+            debug!("Parsed feed <{}> as Atom", info.written_url());
+            feed.entries
+                .into_iter()
+                .filter_map(|x| {
+                    let enclosure = x
+                        .links
+                        .iter()
+                        .find(|link| link.rel.as_deref() == Some("enclosure"))
+                        .filter(|_| use_enclosure);
+                    // Blogger and friends list comment and edit links
+                    // before the page itself
+                    let link = x
+                        .links
+                        .iter()
+                        .find(|link| link.rel.as_deref().unwrap_or("alternate") == "alternate")
+                        .or(x.links.first());
+                    let url = enclosure.or(link)?.href.clone();
+                    let thumbnail = x
+                        .links
+                        .iter()
+                        .find(|link| {
+                            link.rel.as_deref() == Some("enclosure")
+                                && is_image(link.mediatype.as_deref())
+                        })
+                        .map(|link| link.href.clone())
+                        .or_else(|| x.summary.as_deref().and_then(first_image))
+                        .and_then(|src| resolve_link(&url, &src));
+                    let guid = if x.id.is_empty() { None } else { Some(x.id) };
+                    let date = x.published.as_ref().unwrap_or(&x.updated);
+                    let language =
+                        link.and_then(|link| item_language(&link.href, link.hreflang.as_deref()));
+                    Some(Item {
+                        published: publish_date(info, &url, Some(date), fetched),
+                        language,
+                        thumbnail,
+                        url,
+                        title: strip_html(&x.title),
+                        guid,
+                    })
+                })
+                .collect()
+        }
+        Feed::RSS(feed) => {
+            debug!("Parsed feed <{}> as RSS", info.written_url());
+            let channel_language = feed.language;
+            feed.items
+                .into_iter()
+                .filter_map(|x| {
+                    let language = x
+                        .link
+                        .as_ref()
+                        .and_then(|link| item_language(link, channel_language.as_deref()));
+                    let thumbnail = x
+                        .enclosure
+                        .as_ref()
+                        .filter(|enclosure| is_image(Some(&enclosure.mime_type)))
+                        .map(|enclosure| enclosure.url.clone())
+                        .or_else(|| x.description.as_deref().and_then(first_image));
+                    let url = match x.enclosure {
+                        Some(enclosure) if use_enclosure => enclosure.url,
+                        _ => x.link?,
+                    };
+                    Some(Item {
+                        published: publish_date(info, &url, x.pub_date.as_ref(), fetched),
+                        language,
+                        thumbnail: thumbnail.and_then(|src| resolve_link(&url, &src)),
+                        url,
+                        title: strip_html(&x.title.unwrap_or_default()),
+                        guid: x.guid.map(|guid| guid.value),
+                    })
+                })
+                .collect()
+        }
+    };
+
+    let order = order.or_else(|| guess_order(&items, fetched));
+    if order == Some(ListOrder::OldestFirst) {
+        debug!("\"{}\" lists its oldest comics first", info.name);
+        items.reverse();
+    }
+
+    for item in &mut items {
+        item.url = info.rewrite_url(&item.url);
+    }
+
+    let mut seen_guids = HashSet::new();
+    let mut seen_urls = HashSet::new();
+    let mut items: Vec<Item> = items
+        .into_iter()
+        .filter(|item| {
+            let new_guid = match item.guid {
+                Some(ref guid) => seen_guids.insert(guid.clone()),
+                None => true,
+            };
+            let new_url = seen_urls.insert(item.url.clone());
+            if !(new_guid && new_url) {
+                debug!("skipping duplicate: {}", item.url);
+            }
+            new_guid && new_url
+        })
+        .collect();
+    items.reverse();
+
+    let items = items
+        .into_iter()
+        .filter(|x| {
+            let keep = info.filter_title(&x.title);
+            if !keep {
+                debug!("skipping by title: {}", x.title);
+            }
+            keep
+        })
+        .filter(|x| info.filter_url(&x.url))
+        .filter(|x| {
+            let keep = info.filter_language(x.language.as_deref());
+            if !keep {
+                debug!("skipping by language: {}", x.url);
+            }
+            keep
+        })
+        // After filtering, so `@ keep title` and `@ ignore title` see the
+        // titles as the feed has them
+        .map(|mut x| {
+            x.title = info.rewrite_title(&x.title);
+            x
+        })
+        .collect();
+    Ok((items, order))
+}
+
+/// Guess which way `items`, in document order, are listed from their publish
+/// dates, by whether more of them go up or down from one to the next. Items
+/// dated `fetched` are left out, since that's usually a missing date. `None`
+/// if it's a tie, like when there are fewer than two real dates.
+fn guess_order(items: &[Item], fetched: DateTime<Utc>) -> Option<ListOrder> {
+    let dates: Vec<DateTime<Utc>> = items
+        .iter()
+        .map(|item| item.published)
+        .filter(|&published| published != fetched)
+        .collect();
+    let mut rising = 0;
+    let mut falling = 0;
+    for pair in dates.windows(2) {
+        match pair[0].cmp(&pair[1]) {
+            Ordering::Less => rising += 1,
+            Ordering::Greater => falling += 1,
+            Ordering::Equal => (),
+        }
+    }
+    match rising.cmp(&falling) {
+        Ordering::Greater => Some(ListOrder::OldestFirst),
+        Ordering::Less => Some(ListOrder::NewestFirst),
+        Ordering::Equal => None,
+    }
+}
+
+/// The two-letter language codes of ISO 639-1, in order.
+const ISO_639_1: &[&str] = &[
+    "aa", "ab", "ae", "af", "ak", "am", "an", "ar", "as", "av", "ay", "az", "ba", "be", "bg", "bi",
+    "bm", "bn", "bo", "br", "bs", "ca", "ce", "ch", "co", "cr", "cs", "cu", "cv", "cy", "da", "de",
+    "dv", "dz", "ee", "el", "en", "eo", "es", "et", "eu", "fa", "ff", "fi", "fj", "fo", "fr", "fy",
+    "ga", "gd", "gl", "gn", "gu", "gv", "ha", "he", "hi", "ho", "hr", "ht", "hu", "hy", "hz", "ia",
+    "id", "ie", "ig", "ii", "ik", "io", "is", "it", "iu", "ja", "jv", "ka", "kg", "ki", "kj", "kk",
+    "kl", "km", "kn", "ko", "kr", "ks", "ku", "kv", "kw", "ky", "la", "lb", "lg", "li", "ln", "lo",
+    "lt", "lu", "lv", "mg", "mh", "mi", "mk", "ml", "mn", "mr", "ms", "mt", "my", "na", "nb", "nd",
+    "ne", "ng", "nl", "nn", "no", "nr", "nv", "ny", "oc", "oj", "om", "or", "os", "pa", "pi", "pl",
+    "ps", "pt", "qu", "rm", "rn", "ro", "ru", "rw", "sa", "sc", "sd", "se", "sg", "si", "sk", "sl",
+    "sm", "sn", "so", "sq", "sr", "ss", "st", "su", "sv", "sw", "ta", "te", "tg", "th", "ti", "tk",
+    "tl", "tn", "to", "tr", "ts", "tt", "tw", "ty", "ug", "uk", "ur", "uz", "ve", "vi", "vo", "wa",
+    "wo", "xh", "yi", "yo", "za", "zh", "zu",
+];
+
+/// Guess an item's language. A language marked in its URL, like
+/// `/fr/comic/1` or `?lang=fr`, wins over `declared`, since feeds that
+/// interleave translations usually declare only the main language.
+fn item_language(url: &str, declared: Option<&str>) -> Option<String> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let from_query = query
+        .split('&')
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some("lang"), Some(code)) | (Some("hl"), Some(code)) => Some(code),
+                _ => None,
+            }
+        })
+        .next();
+    // Only a first path segment that starts with a real two-letter language
+    // code, since anything else is as likely to be a chapter name, or `/go/`
+    let from_path = path.split('/').nth(1).filter(|segment| {
+        let primary = &segment[..segment.find(['-', '_']).unwrap_or(segment.len())];
+        ISO_639_1
+            .binary_search(&primary.to_lowercase().as_str())
+            .is_ok()
+    });
+    from_query
+        .into_iter()
+        .chain(from_path)
+        .find(|code| is_language_code(code))
+        .or_else(|| declared.map(str::trim))
+        .map(|code| code.to_lowercase().replace('_', "-"))
+}
+
+/// Whether `code` looks like a language tag, like `en`, `pt-BR` or `zh_Hant`.
+pub fn is_language_code(code: &str) -> bool {
+    let mut parts = code.split(['-', '_']);
+    let primary = parts.next().unwrap_or_default();
+    (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && parts.all(|part| match part.len() {
+            // A region like `BR`, or `419` for Latin America
+            2 => part.chars().all(|c| c.is_ascii_alphabetic()),
+            3 => part.chars().all(|c| c.is_ascii_digit()),
+            // A script like `Hant`
+            4 => part.chars().all(|c| c.is_ascii_alphabetic()),
+            _ => false,
+        })
+}
+
+/// The link to the site a feed belongs to, like an RSS channel's `<link>` or
+/// an Atom feed's `alternate` link, if the document has one.
+pub fn site_link(content: &str) -> Option<String> {
+    use syndication::Feed;
+    let link = match Feed::from_str(content).ok()? {
+        Feed::Atom(feed) => {
+            feed.links
+                .into_iter()
+                .find(|link| link.rel.as_deref().unwrap_or("alternate") == "alternate")?
+                .href
+        }
+        Feed::RSS(channel) => channel.link,
+    };
+    if link.trim().is_empty() {
+        None
+    } else {
+        Some(link.trim().into())
+    }
+}
+
+/// The RSS and Atom feeds an HTML page links to with `<link rel="alternate">`
+/// tags, in the order they're listed. The links are as written, so they may be
+/// relative to the page.
+pub fn feed_links(page: &str) -> Vec<String> {
+    link_tags(page)
+        .into_iter()
+        .filter_map(|attrs| {
+            let attr = |name| {
+                attrs
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.trim())
+            };
+            let alternate = attr("rel").is_some_and(|rel| {
+                rel.split_whitespace()
+                    .any(|rel| rel.eq_ignore_ascii_case("alternate"))
+            });
+            let feed = attr("type").is_some_and(|kind| {
+                ["application/rss+xml", "application/atom+xml"]
+                    .iter()
+                    .any(|feed| kind.eq_ignore_ascii_case(feed))
+            });
+            match attr("href") {
+                Some(href) if alternate && feed && !href.is_empty() => Some(href.to_string()),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Whether an attachment's MIME type is a picture.
+fn is_image(mime_type: Option<&str>) -> bool {
+    mime_type.is_some_and(|mime_type| mime_type.starts_with("image/"))
+}
+
+/// `href` from a page at `base`, made absolute the way a browser would, or
+/// `None` if either isn't a URL.
+pub fn resolve_link(base: &str, href: &str) -> Option<String> {
+    let joined = Url::parse(base).and_then(|base| base.join(href));
+    match joined {
+        Ok(url) => Some(url.into_string()),
+        Err(err) => {
+            debug!("Skipping link {:?} from <{}>: {}", href, base, err);
+            None
+        }
+    }
+}
+
+fn publish_date(
+    info: &FeedInfo,
+    url: &str,
+    date: Option<&String>,
+    fetched: DateTime<Utc>,
+) -> DateTime<Utc> {
+    let date = match date {
+        Some(date) => date.trim(),
+        None => return fetched,
+    };
+    let parsed = DateTime::parse_from_rfc2822(date).or_else(|_| DateTime::parse_from_rfc3339(date));
+    match parsed {
+        Ok(date) if date > fetched => {
+            warn!(
+                "\"{}\": <{}> is dated in the future ({}), using {} instead",
+                info.name, url, date, fetched,
+            );
+            fetched
+        }
+        Ok(date) => date.with_timezone(&Utc),
+        Err(_) => {
+            debug!(
+                "\"{}\": <{}> has an invalid date {:?}",
+                info.name, url, date
+            );
+            fetched
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_config;
+    use chrono::TimeZone;
+
+    // Golden tests for the feeds in tests/fixtures. Each one lists exactly the
+    // (url, title) pairs that feedburst should take from that feed.
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(&path).expect("missing fixture")
+    }
+
+    fn fetched() -> DateTime<Utc> {
+        Utc.ymd(2018, 1, 5).and_hms(0, 0, 0)
+    }
+
+    fn extract(config: &str, name: &str) -> Vec<Item> {
+        let info = parse_config(config).unwrap().remove(0);
+        extract_items(&info, &fixture(name), fetched()).unwrap()
+    }
+
+    fn check(config: &str, name: &str, expected: &[(&str, &str)]) {
+        let items: Vec<_> = extract(config, name)
+            .into_iter()
+            .map(|x| (x.url, x.title))
+            .collect();
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|&(url, title)| (url.to_string(), title.to_string()))
+            .collect();
+        assert_eq!(items, expected, "fixture {}", name);
+    }
+
+    const PLAIN: &str = r#""Fixture" <http://example.com/feed>"#;
+
+    #[test]
+    fn test_missing_dates() {
+        check(
+            PLAIN,
+            "missing-dates.rss",
+            &[
+                ("http://comic.example.com/page/1", "Page 1"),
+                ("http://comic.example.com/page/2", "Page 2"),
+                ("http://comic.example.com/page/3", "Page 3"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_html_entity_titles() {
+        check(
+            PLAIN,
+            "html-entity-titles.rss",
+            &[
+                (
+                    "http://entities.example.com/?p=1&ref=rss",
+                    "Tom & Jerry\u{2019}s \"Day\" Out",
+                ),
+                (
+                    "http://entities.example.com/?p=2&ref=rss",
+                    "Chapter 2: Cats & Dogs",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_cdata() {
+        check(
+            PLAIN,
+            "cdata.rss",
+            &[
+                ("http://cdata.example.com/comic/1", "Page 1 & Prologue"),
+                ("http://cdata.example.com/comic/2", "Page 2 <3"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_duplicate_guids() {
+        check(
+            PLAIN,
+            "duplicate-guids.rss",
+            &[
+                ("http://dupes.example.com/1", "Update 1"),
+                ("http://dupes.example.com/2", "Update 2"),
+                ("http://dupes.example.com/3", "Update 3 (edited)"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_future_dates() {
+        check(
+            PLAIN,
+            "future-dates.rss",
+            &[
+                ("http://future.example.com/comic/1", "Today-ish"),
+                ("http://future.example.com/comic/2", "Bogus Date"),
+                ("http://future.example.com/comic/3", "From The Future"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_publish_dates() {
+        let dates = |name| -> Vec<_> {
+            extract(PLAIN, name)
+                .into_iter()
+                .map(|x| x.published)
+                .collect()
+        };
+        let jan_2 = Utc.ymd(2018, 1, 2).and_hms(12, 0, 0);
+        let jan_3 = Utc.ymd(2018, 1, 3).and_hms(12, 0, 0);
+
+        // Missing dates inherit the fetch time, without reordering anything
+        assert_eq!(
+            dates("missing-dates.rss"),
+            vec![fetched(), jan_2, fetched()]
+        );
+        // Future dates are clamped, unparseable dates act like missing ones
+        assert_eq!(dates("future-dates.rss"), vec![jan_2, fetched(), fetched()]);
+        // Atom falls back to <updated> when there's no <published>
+        assert_eq!(dates("atom.xml"), vec![jan_2, jan_3]);
+    }
+
+    #[test]
+    fn test_atom() {
+        check(
+            PLAIN,
+            "atom.xml",
+            &[
+                ("http://atom.example.com/comic/1", "Page 1"),
+                ("http://atom.example.com/comic/2", "Page 2"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_atom_link_order() {
+        check(
+            PLAIN,
+            "blogger.xml",
+            &[
+                ("http://blogger.example.com/2018/01/page-1.html", "Page 1"),
+                ("http://blogger.example.com/2018/01/page-2.html", "Page 2"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_republished() {
+        let pages: Vec<_> = (1..=7)
+            .map(|n| {
+                (
+                    format!("http://moved.example.com/comics/page-{}/", n),
+                    format!("Page {}", n),
+                )
+            })
+            .collect();
+        let expected: Vec<_> = pages
+            .iter()
+            .map(|(url, title)| (url.as_str(), title.as_str()))
+            .collect();
+        check(PLAIN, "republished.rss", &expected);
+    }
+
+    #[test]
+    fn test_enclosures() {
+        // Without the policy every item has the same link, so all but the
+        // newest look like duplicates
+        check(
+            PLAIN,
+            "enclosures.rss",
+            &[("http://enclosure.example.com/", "Page 3")],
+        );
+        let config = r#""Fixture" <http://example.com/feed> @ open enclosure"#;
+        check(
+            config,
+            "enclosures.rss",
+            &[
+                ("http://enclosure.example.com/pages/1.png", "Page 1"),
+                ("http://enclosure.example.com/pages/2.pdf", "Page 2"),
+                ("http://enclosure.example.com/", "Page 3"),
+            ],
+        );
+        check(
+            config,
+            "enclosures.xml",
+            &[
+                ("http://atom-enclosure.example.com/pages/1.png", "Page 1"),
+                ("http://atom-enclosure.example.com/pages/2.png", "Page 2"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_thumbnails() {
+        let thumbnails: Vec<_> = extract(PLAIN, "thumbnails.rss")
+            .into_iter()
+            .map(|x| x.thumbnail)
+            .collect();
+        assert_eq!(
+            thumbnails,
+            vec![
+                Some("http://thumbnail.example.com/pages/1.jpg".into()),
+                Some("http://thumbnail.example.com/thumbs/2.png".into()),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_language() {
+        // Without the policy every translation comes through
+        check(
+            PLAIN,
+            "multilingual.rss",
+            &[
+                ("http://bilingual.example.com/comic/1", "Page 1"),
+                ("http://bilingual.example.com/fr/comic/1", "Page 1 (FR)"),
+                ("http://bilingual.example.com/comic/2", "Page 2"),
+                (
+                    "http://bilingual.example.com/comic/2?lang=fr",
+                    "Page 2 (FR)",
+                ),
+            ],
+        );
+        // The channel says en-us, except where the links say otherwise
+        check(
+            r#""Fixture" <http://example.com/feed> @ language en"#,
+            "multilingual.rss",
+            &[
+                ("http://bilingual.example.com/comic/1", "Page 1"),
+                ("http://bilingual.example.com/comic/2", "Page 2"),
+            ],
+        );
+        check(
+            r#""Fixture" <http://example.com/feed> @ language fr"#,
+            "multilingual.rss",
+            &[
+                ("http://bilingual.example.com/fr/comic/1", "Page 1 (FR)"),
+                (
+                    "http://bilingual.example.com/comic/2?lang=fr",
+                    "Page 2 (FR)",
+                ),
+            ],
+        );
+        check(
+            r#""Fixture" <http://example.com/feed> @ language de"#,
+            "multilingual.xml",
+            &[("http://atom-bilingual.example.com/comic/1-de", "Seite 1")],
+        );
+        // Feeds that don't say are left alone
+        check(
+            r#""Fixture" <http://example.com/feed> @ language de"#,
+            "missing-dates.rss",
+            &[
+                ("http://comic.example.com/page/1", "Page 1"),
+                ("http://comic.example.com/page/2", "Page 2"),
+                ("http://comic.example.com/page/3", "Page 3"),
+            ],
+        );
+
+        // Two letters aren't enough to be a language
+        assert_eq!(
+            item_language("http://example.com/go/comic/1", Some("en")),
+            Some("en".into())
+        );
+        assert_eq!(item_language("http://example.com/us/comic/1", None), None);
+        assert_eq!(
+            item_language("http://example.com/pt_BR/comic/1", Some("en")),
+            Some("pt-br".into())
+        );
+        assert_eq!(
+            item_language("http://example.com/FR/comic/1", None),
+            Some("fr".into())
+        );
+
+        assert!(is_language_code("pt-BR"));
+        assert!(is_language_code("zh_Hant"));
+        assert!(is_language_code("es-419"));
+        assert!(!is_language_code("wp-content"));
+        assert!(!is_language_code("comic"));
+    }
+
+    #[test]
+    fn test_oldest_first() {
+        check(
+            PLAIN,
+            "oldest-first.rss",
+            &[
+                ("http://archive.example.com/1", "Page 1"),
+                ("http://archive.example.com/2", "Page 2"),
+                ("http://archive.example.com/3", "Page 3 (fixed)"),
+                ("http://archive.example.com/4", "Page 4"),
+            ],
+        );
+
+        let info = parse_config(PLAIN).unwrap().remove(0);
+        let content = fixture("oldest-first.rss");
+        let (_, order) = extract_items_in(&info, &content, fetched(), None).unwrap();
+        assert_eq!(order, Some(ListOrder::OldestFirst));
+        let (_, order) = extract_items_in(&info, &fixture("atom.xml"), fetched(), None).unwrap();
+        assert_eq!(order, Some(ListOrder::NewestFirst));
+        // Only one real date, so no telling
+        let (_, order) =
+            extract_items_in(&info, &fixture("missing-dates.rss"), fetched(), None).unwrap();
+        assert_eq!(order, None);
+
+        // A known order wins over the dates
+        let (items, order) =
+            extract_items_in(&info, &content, fetched(), Some(ListOrder::NewestFirst)).unwrap();
+        assert_eq!(order, Some(ListOrder::NewestFirst));
+        assert_eq!(items[0].url, "http://archive.example.com/4");
+    }
+
+    #[test]
+    fn test_site_link() {
+        let link = |name| site_link(&fixture(name));
+        assert_eq!(link("cdata.rss"), Some("http://cdata.example.com/".into()));
+        assert_eq!(link("atom.xml"), Some("http://atom.example.com/".into()));
+        assert_eq!(site_link("not a feed"), None);
+    }
+
+    #[test]
+    fn test_feed_links() {
+        let page = r#"<!DOCTYPE html>
+<html><head>
+<link rel="stylesheet" type="text/css" href="/style.css">
+<link rel="alternate" type="application/rss+xml" title="Comic" href="/feed/">
+<link rel="alternate" type="text/html" hreflang="fr" href="/fr/">
+<link rel="alternate" type="application/atom+xml" href="https://example.com/atom.xml">
+</head><body><link rel="alternate" type="application/rss+xml" href=""></body></html>"#;
+        assert_eq!(
+            feed_links(page),
+            vec!["/feed/", "https://example.com/atom.xml"]
+        );
+        assert!(feed_links(&fixture("atom.xml")).is_empty());
+    }
+
+    #[test]
+    fn test_resolve_link() {
+        let base = "https://example.com/comic/page?id=1";
+        let resolve = |base, href| resolve_link(base, href).unwrap();
+        assert_eq!(
+            resolve(base, "http://other.com/rss"),
+            "http://other.com/rss"
+        );
+        assert_eq!(
+            resolve(base, "//cdn.example.com/rss"),
+            "https://cdn.example.com/rss"
+        );
+        assert_eq!(resolve(base, "/feed/"), "https://example.com/feed/");
+        assert_eq!(
+            resolve(base, "rss.xml"),
+            "https://example.com/comic/rss.xml"
+        );
+        assert_eq!(
+            resolve("https://example.com", "rss.xml"),
+            "https://example.com/rss.xml"
+        );
+        assert_eq!(
+            resolve(base, "?id=2"),
+            "https://example.com/comic/page?id=2"
+        );
+        assert_eq!(resolve(base, "../feed/"), "https://example.com/feed/");
+        assert_eq!(
+            resolve(base, "#top"),
+            "https://example.com/comic/page?id=1#top"
+        );
+
+        // Neither panics on a base that isn't a URL
+        assert_eq!(resolve_link("example.com", "//cdn.example.com/rss"), None);
+        assert_eq!(resolve_link("", "rss.xml"), None);
+    }
+
+    #[test]
+    fn test_filters() {
+        check(
+            r#""Fixture" <http://example.com/feed> @ ignore title /Page 2/"#,
+            "missing-dates.rss",
+            &[
+                ("http://comic.example.com/page/1", "Page 1"),
+                ("http://comic.example.com/page/3", "Page 3"),
+            ],
+        );
+        check(
+            r#""Fixture" <http://example.com/feed> @ keep url "comic/2""#,
+            "cdata.rss",
+            &[("http://cdata.example.com/comic/2", "Page 2 <3")],
+        );
+        // Filters see the decoded title, not the entity soup
+        check(
+            r#""Fixture" <http://example.com/feed> @ keep title /Cats & Dogs$/"#,
+            "html-entity-titles.rss",
+            &[(
+                "http://entities.example.com/?p=2&ref=rss",
+                "Chapter 2: Cats & Dogs",
+            )],
+        );
+    }
+}
@@ -1,20 +1,300 @@
-use chrono::{DateTime, Local, Utc, Weekday};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, SecondsFormat, Utc, Weekday};
+use chrono_tz::Tz;
 use regex::Regex;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::io::{self, Read, Seek, Write};
+use std::mem;
 use std::path::PathBuf;
 
-use crate::error::{Error, ParseError, Span};
+use crate::clock::Clock;
+use crate::dedupe::strip_tracking;
+use crate::error::Error;
+use crate::extract::{Item, ListOrder};
+use crate::format::Format;
 use crate::parser::parse_events;
+use crate::sample;
+use crate::state::{self, Progress, State, Trigger};
+
+/// The version of the feed history format that feedburst writes. Histories
+/// from a newer version won't load, rather than being misread.
+pub const EVENTS_VERSION: usize = 1;
+
+/// The most moves followed from a feed's URL, in case they go round in
+/// circles.
+const MAX_MOVES: usize = 10;
+
+/// A date the way feed histories record it: RFC 3339 in UTC, with fractions
+/// of a second only when there are any.
+fn event_date(date: DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::AutoSi, false)
+}
+
+/// `history`, the text of a feed's history, without the last time the feed
+/// or a comic in it was read: its `read` line, along with the `held` lines
+/// written with it. Also the event taken out, or `None` if nothing was read.
+pub fn without_last_read(history: &str) -> Option<(String, FeedEvent)> {
+    let lines: Vec<&str> = history.lines().collect();
+    let events: Vec<Option<FeedEvent>> = lines
+        .iter()
+        .map(|line| parse_events(line).ok().and_then(|mut events| events.pop()))
+        .collect();
+    let last = events.iter().rposition(|event| {
+        matches!(
+            *event,
+            Some(FeedEvent::Read(_)) | Some(FeedEvent::ReadComic(..))
+        )
+    })?;
+    let removed = events[last].clone()?;
+    let mut text = String::new();
+    for (i, (line, event)) in lines.iter().zip(&events).enumerate() {
+        let goes = match (&removed, event) {
+            _ if i == last => true,
+            (&FeedEvent::Read(read), &Some(FeedEvent::Held(held, _))) => i > last && held == read,
+            _ => false,
+        };
+        if !goes {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    Some((text, removed))
+}
 
 #[derive(Hash, Clone, Debug, PartialEq, Eq)]
 pub enum UpdateSpec {
-    On(Weekday),
+    /// The days to show comics on, in order from Monday, without repeats.
+    On(Vec<Weekday>),
+    /// The day of the month to show comics on, from 1 to 31. The last day of
+    /// a month too short for it stands in.
+    MonthDay(u8),
     Every(usize),
     Comics(usize),
     Overlap(usize),
+    OverlapDays(usize),
+    CheckEvery(usize),
+    RemindAfter(usize),
     Filter(FilterType, String),
     OpenAll,
+    OpenEnclosure,
+    Alias(String),
+    Surprise(usize),
+    MaxPerFetch(usize),
+    Language(String),
+    /// The pattern that finds the chapter number in a title, and how many
+    /// pages of later chapters finish a chapter.
+    ByChapter(String, usize),
+    /// The SHA-256 fingerprint of the only certificate to accept from the
+    /// feed's server, in lowercase hex.
+    Pin(String),
+    /// How many seconds to wait on the feed's server before giving up.
+    Timeout(usize),
+    /// How many more times to try downloading the feed when its server can't
+    /// be reached or is overloaded.
+    Retries(usize),
+    /// The most unread comics to open in one run, leaving the rest for later
+    /// runs.
+    MaxPerSession(usize),
+    /// Comics published before this day are already read when the feed is
+    /// first downloaded.
+    SkipBefore(NaiveDate),
+    /// Follow links back through the archive from the oldest comic in the
+    /// first download, so a new feed starts with its whole backlog. The links
+    /// followed are those the CSS selector picks out, or else `rel="prev"`
+    /// ones.
+    CrawlArchive(Option<String>),
+    /// A pattern and what to replace its matches in each comic's title with,
+    /// which can refer to the pattern's groups as `$1` and so on.
+    RewriteTitle(String, String),
+    /// The same for each comic's URL.
+    RewriteUrl(String, String),
+    /// How many unread comics to pick at random to open in each run, leaving
+    /// the rest for later runs.
+    Random(usize),
+    /// Don't download or open the feed before this day, like while the comic
+    /// is on hiatus.
+    PauseUntil(NaiveDate),
+    /// Only download and open the feed from the first day through the second,
+    /// for a comic that only runs part of the year.
+    Between(NaiveDate, NaiveDate),
+    /// Only show the feed from this time of day on, in local time.
+    After(NaiveTime),
+    /// Only show the feed before this time of day, in local time.
+    Before(NaiveTime),
+}
+
+impl UpdateSpec {
+    /// Whether this policy takes the place of `other`, when one is set over
+    /// the other. Filters only count as the same kind if they keep or ignore
+    /// the same thing. An overlap in comics or days is the same kind either
+    /// way, and so are `@ max` and `@ random`, which both say how many comics
+    /// to open at once.
+    pub fn same_kind(&self, other: &UpdateSpec) -> bool {
+        use UpdateSpec::*;
+        match (self, other) {
+            (Filter(a, _), Filter(b, _)) => a == b,
+            (Overlap(_) | OverlapDays(_), Overlap(_) | OverlapDays(_)) => true,
+            (MaxPerSession(_) | Random(_), MaxPerSession(_) | Random(_)) => true,
+            _ => mem::discriminant(self) == mem::discriminant(other),
+        }
+    }
+
+    /// Whether this is an `@ rewrite title` or `@ rewrite url` policy.
+    pub fn is_rewrite(&self) -> bool {
+        matches!(
+            *self,
+            UpdateSpec::RewriteTitle(..) | UpdateSpec::RewriteUrl(..)
+        )
+    }
+
+    /// A plain-English explanation of what the policy does.
+    pub fn describe(&self) -> String {
+        match *self {
+            UpdateSpec::On(ref days) => {
+                let mut names: Vec<_> = days.iter().map(|&day| weekday_name(day)).collect();
+                let last = names.pop().unwrap_or_default();
+                let names = match names.len() {
+                    0 => last.to_string(),
+                    1 => format!("{} or {}", names[0], last),
+                    _ => format!("{}, or {}", names.join(", "), last),
+                };
+                format!(
+                    "Show new comics once a {} has passed since you last read them",
+                    names
+                )
+            }
+            UpdateSpec::MonthDay(day) => format!(
+                "Show new comics once the {} of a month has passed since you last read them",
+                ordinal(day)
+            ),
+            UpdateSpec::Every(n) => format!(
+                "Wait at least {} {} after you last read them",
+                n,
+                plural(n, "day", "days")
+            ),
+            UpdateSpec::Comics(n) => format!(
+                "Wait until there {} at least {} new {}",
+                plural(n, "is", "are"),
+                n,
+                plural(n, "comic", "comics")
+            ),
+            UpdateSpec::Overlap(n) => format!(
+                "Also show the last {} {} you already read",
+                n,
+                plural(n, "comic", "comics")
+            ),
+            UpdateSpec::OverlapDays(n) => format!(
+                "Also show comics you already read that came out in the {} {} before you last read",
+                n,
+                plural(n, "day", "days")
+            ),
+            UpdateSpec::CheckEvery(n) => format!(
+                "Only download the feed every {} {}",
+                n,
+                plural(n, "day", "days")
+            ),
+            UpdateSpec::RemindAfter(n) => format!(
+                "Remind you if comics have been waiting unread for {} {}",
+                n,
+                plural(n, "day", "days")
+            ),
+            UpdateSpec::Filter(ref kind, ref pattern) => {
+                let (verb, target) = match *kind {
+                    FilterType::KeepTitle => ("Only include", "title"),
+                    FilterType::IgnoreTitle => ("Skip", "title"),
+                    FilterType::KeepUrl => ("Only include", "URL"),
+                    FilterType::IgnoreUrl => ("Skip", "URL"),
+                };
+                format!("{} comics whose {} matches /{}/", verb, target, pattern)
+            }
+            UpdateSpec::OpenAll => "Open every new comic, not just the first".into(),
+            UpdateSpec::OpenEnclosure => {
+                "Open the file attached to each comic instead of its link".into()
+            }
+            UpdateSpec::Surprise(n) => format!(
+                "Also open {} random {} you already read each week, alongside new ones",
+                n,
+                plural(n, "comic", "comics")
+            ),
+            UpdateSpec::Alias(ref name) => format!(
+                "Also count the history of the feed when it was called \"{}\"",
+                name
+            ),
+            UpdateSpec::MaxPerFetch(n) => format!(
+                "Only take the newest {} {} each time the feed is downloaded",
+                n,
+                plural(n, "comic", "comics")
+            ),
+            UpdateSpec::ByChapter(ref pattern, n) => format!(
+                "Wait for whole chapters, numbered by /{}/ in the title, and open each one at once. A chapter is done once {} {} of a later one {} out",
+                pattern,
+                n,
+                plural(n, "page", "pages"),
+                plural(n, "is", "are")
+            ),
+            UpdateSpec::Language(ref code) => format!(
+                "Skip comics that the feed or their links mark as in a language other than {}",
+                code
+            ),
+            UpdateSpec::Pin(ref fingerprint) => format!(
+                "Only download the feed over HTTPS from a server whose certificate has the SHA-256 fingerprint {}",
+                fingerprint
+            ),
+            UpdateSpec::Timeout(seconds) => format!(
+                "Give up on downloading the feed after waiting {} on its server",
+                interval_text(seconds)
+            ),
+            UpdateSpec::Retries(n) => format!(
+                "Try downloading the feed up to {} more {} when its server can't be reached or is overloaded",
+                n,
+                plural(n, "time", "times")
+            ),
+            UpdateSpec::MaxPerSession(n) => format!(
+                "Open at most {} unread {} in one run, leaving the rest for next time",
+                n,
+                plural(n, "comic", "comics")
+            ),
+            UpdateSpec::Random(n) => format!(
+                "Open {} unread {} picked at random in each run, instead of the oldest",
+                n,
+                plural(n, "comic", "comics")
+            ),
+            UpdateSpec::SkipBefore(date) => format!(
+                "The first time the feed is downloaded, count the comics published before {} as already read",
+                date.format("%Y-%m-%d")
+            ),
+            UpdateSpec::PauseUntil(date) => format!(
+                "Don't download or show the comics before {}",
+                date.format("%Y-%m-%d")
+            ),
+            UpdateSpec::Between(start, end) => format!(
+                "Only download and show the comics from {} through {}",
+                start.format("%Y-%m-%d"),
+                end.format("%Y-%m-%d")
+            ),
+            UpdateSpec::After(time) => format!(
+                "Only show the comics from {} on each day",
+                time.format("%H:%M")
+            ),
+            UpdateSpec::Before(time) => format!(
+                "Only show the comics before {} each day",
+                time.format("%H:%M")
+            ),
+            UpdateSpec::CrawlArchive(None) => "The first time the feed is downloaded, follow each comic's link to the one before it, back to the start of the archive".into(),
+            UpdateSpec::CrawlArchive(Some(ref selector)) => format!(
+                "The first time the feed is downloaded, follow the links that {} picks out on each comic's page back to the start of the archive",
+                selector
+            ),
+            UpdateSpec::RewriteTitle(ref pattern, ref replacement) => format!(
+                "Replace /{}/ in comic titles with \"{}\"",
+                pattern, replacement
+            ),
+            UpdateSpec::RewriteUrl(ref pattern, ref replacement) => format!(
+                "Replace /{}/ in comic URLs with \"{}\"",
+                pattern, replacement
+            ),
+        }
+    }
 }
 
 #[derive(Hash, Clone, Debug, PartialEq, Eq)]
@@ -25,67 +305,744 @@ pub enum FilterType {
     IgnoreUrl,
 }
 
+fn plural(count: usize, singular: &'static str, plural: &'static str) -> &'static str {
+    if count == 1 {
+        singular
+    } else {
+        plural
+    }
+}
+
+/// A number of seconds the way it's written in the config, like `30s`, `2m`,
+/// or `1h`, in the largest unit that fits exactly.
+pub fn interval_text(seconds: usize) -> String {
+    match seconds {
+        0 => "0s".into(),
+        s if s % (24 * 60 * 60) == 0 => format!("{}d", s / (24 * 60 * 60)),
+        s if s % (60 * 60) == 0 => format!("{}h", s / (60 * 60)),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+/// `day` with its English ordinal suffix, like `1st` or `12th`.
+fn ordinal(day: u8) -> String {
+    let suffix = match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", day, suffix)
+}
+
+/// Formats the policy the same way it would be written in the config.
+impl fmt::Display for UpdateSpec {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            UpdateSpec::On(ref days) => {
+                let names: Vec<_> = days.iter().map(|&day| weekday_name(day)).collect();
+                write!(fmt, "@ on {}", names.join(", "))
+            }
+            UpdateSpec::MonthDay(day) => write!(fmt, "@ on the {}", ordinal(day)),
+            UpdateSpec::Every(n) => write!(fmt, "@ every {} {}", n, plural(n, "day", "days")),
+            UpdateSpec::Comics(n) => write!(fmt, "@ {} new {}", n, plural(n, "comic", "comics")),
+            UpdateSpec::Overlap(n) => {
+                write!(fmt, "@ overlap {} {}", n, plural(n, "comic", "comics"))
+            }
+            UpdateSpec::OverlapDays(n) => {
+                write!(fmt, "@ overlap {} {}", n, plural(n, "day", "days"))
+            }
+            UpdateSpec::CheckEvery(n) => {
+                write!(fmt, "@ check every {} {}", n, plural(n, "day", "days"))
+            }
+            UpdateSpec::RemindAfter(n) => {
+                write!(fmt, "@ remind after {} {}", n, plural(n, "day", "days"))
+            }
+            UpdateSpec::Filter(ref kind, ref pattern) => {
+                let kind = match *kind {
+                    FilterType::KeepTitle => "keep title",
+                    FilterType::IgnoreTitle => "ignore title",
+                    FilterType::KeepUrl => "keep url",
+                    FilterType::IgnoreUrl => "ignore url",
+                };
+                let delim = pattern_delimiter(pattern);
+                write!(fmt, "@ {} {}{}{}", kind, delim, pattern, delim)
+            }
+            UpdateSpec::ByChapter(ref pattern, lookahead) => {
+                let delim = pattern_delimiter(pattern);
+                write!(fmt, "@ by chapter {}{}{}", delim, pattern, delim)?;
+                if lookahead != 1 {
+                    write!(
+                        fmt,
+                        " lookahead {} {}",
+                        lookahead,
+                        plural(lookahead, "page", "pages")
+                    )?;
+                }
+                Ok(())
+            }
+            UpdateSpec::OpenAll => write!(fmt, "@ open all"),
+            UpdateSpec::OpenEnclosure => write!(fmt, "@ open enclosure"),
+            UpdateSpec::Alias(ref name) => write!(fmt, "@ alias \"{}\"", name),
+            UpdateSpec::Surprise(n) => write!(fmt, "@ surprise {} per week", n),
+            UpdateSpec::MaxPerFetch(n) => {
+                write!(
+                    fmt,
+                    "@ max {} {} per fetch",
+                    n,
+                    plural(n, "comic", "comics")
+                )
+            }
+            UpdateSpec::Language(ref code) => write!(fmt, "@ language {}", code),
+            UpdateSpec::Pin(ref fingerprint) => write!(fmt, "@ pin sha256 {}", fingerprint),
+            UpdateSpec::Timeout(seconds) => write!(fmt, "@ timeout {}", interval_text(seconds)),
+            UpdateSpec::Retries(n) => write!(fmt, "@ retries {}", n),
+            UpdateSpec::MaxPerSession(n) => {
+                write!(fmt, "@ max {} {}", n, plural(n, "comic", "comics"))
+            }
+            UpdateSpec::Random(n) => {
+                write!(fmt, "@ random {} {}", n, plural(n, "comic", "comics"))
+            }
+            UpdateSpec::SkipBefore(date) => {
+                write!(fmt, "@ skip before {}", date.format("%Y-%m-%d"))
+            }
+            UpdateSpec::PauseUntil(date) => {
+                write!(fmt, "@ pause until {}", date.format("%Y-%m-%d"))
+            }
+            UpdateSpec::Between(start, end) => write!(
+                fmt,
+                "@ between {} and {}",
+                start.format("%Y-%m-%d"),
+                end.format("%Y-%m-%d")
+            ),
+            UpdateSpec::After(time) => write!(fmt, "@ after {}", time.format("%H:%M")),
+            UpdateSpec::Before(time) => write!(fmt, "@ before {}", time.format("%H:%M")),
+            UpdateSpec::CrawlArchive(None) => write!(fmt, "@ crawl archive"),
+            UpdateSpec::CrawlArchive(Some(ref selector)) => {
+                let c = ['"', '\'', '|']
+                    .iter()
+                    .cloned()
+                    .find(|&c| !selector.contains(c))
+                    .unwrap_or('"');
+                write!(fmt, "@ crawl archive {}{}{}", c, selector, c)
+            }
+            UpdateSpec::RewriteTitle(ref pattern, ref replacement)
+            | UpdateSpec::RewriteUrl(ref pattern, ref replacement) => {
+                let target = match *self {
+                    UpdateSpec::RewriteTitle(..) => "title",
+                    _ => "url",
+                };
+                let delim = pattern_delimiter(&format!("{}{}", pattern, replacement));
+                write!(
+                    fmt,
+                    "@ rewrite {} {}{}{}{}{}",
+                    target, delim, pattern, delim, replacement, delim
+                )
+            }
+        }
+    }
+}
+
+/// A character to write around `pattern` that doesn't appear in it.
+fn pattern_delimiter(pattern: &str) -> char {
+    ['/', '"', '|', '#']
+        .iter()
+        .cloned()
+        .find(|&c| !pattern.contains(c))
+        .unwrap_or('/')
+}
+
+/// What time a `read` event records, set with the `read_marker` directive.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReadMarker {
+    /// When the comics were opened.
+    Now,
+    /// When the newest comic being marked read was published, so that
+    /// `@ every` counts from when the feed updated rather than when you read
+    /// it. Falls back to the current time if no comic has a publish date.
+    PublishDate,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct FeedInfo {
     pub name: String,
     pub url: String,
     pub update_policies: HashSet<UpdateSpec>,
+    /// The feed's `@ rewrite` policies again, in the order they're written,
+    /// since each one works on what the ones before it left.
+    pub rewrites: Vec<UpdateSpec>,
     pub root: Option<PathBuf>,
-    pub command: Option<Vec<String>>,
+    /// How to open the feed's comics, from the `command` and `open with`
+    /// directives. The default browser if it's `None`.
+    pub open_with: Option<OpenWith>,
+    pub read_marker: ReadMarker,
+    pub format: Format,
+    /// Set by the `max_items_per_fetch` directive. `@ max # comics per fetch`
+    /// overrides it.
+    pub max_items_per_fetch: Option<usize>,
+    /// Whether the feed has no policies of its own, so `update_policies` came
+    /// from the `default_policy` directive.
+    pub default_policy: bool,
+    /// Set by the `group` directive, so a run can be limited to some feeds.
+    pub group: Option<String>,
+    /// Sent with every download of the feed, from the `header` and
+    /// `user_agent` directives, as `(name, value)`.
+    pub headers: Vec<(String, String)>,
+    /// How to log in to the feed's server, from the `auth` directive.
+    pub auth: Option<Auth>,
+    /// The proxy to download the feed through, from the `proxy` directive or
+    /// `--proxy`.
+    pub proxy: Option<String>,
+    /// The address to email the feed's comics to in a digest, instead of
+    /// opening them, from the `deliver email` directive or a `route` for its
+    /// group.
+    pub deliver_to: Option<String>,
+    /// The time zone the time-based policies go by, from the `timezone`
+    /// directive or `--timezone`. The computer's own if it's `None`.
+    pub timezone: Option<Tz>,
+    /// The URL as written in the config, if `{env:NAME}` in it was filled in
+    /// to make `url`. Anything shown or saved uses this instead, so the secret
+    /// isn't.
+    pub url_template: Option<String>,
+}
+
+/// When a feed will next open, from `Feed::next_due`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Due {
+    /// It's ready now.
+    Now,
+    /// It's waiting for `feedburst accept-reset`.
+    Paused,
+    /// Once its time-based policies allow it, from `from`, and `comics` more
+    /// comics have arrived. `from` is `None` if that's more than a year away.
+    Later {
+        from: Option<DateTime<Local>>,
+        comics: usize,
+    },
+}
+
+/// How to open the comics of a feed that's ready.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OpenWith {
+    /// The default browser.
+    Browser,
+    /// A command, split into its parts, where `@URL` or `{url}` stand for the
+    /// comic's URL and `{title}` for its title.
+    Command(Vec<String>),
+    /// Copy the URLs to the clipboard, one per line.
+    Clipboard,
+    /// Add the URLs to the end of the file at this path, one per line.
+    File(PathBuf),
+    /// Queue the comics on a read-it-later service.
+    ReadLater(ReadLater),
+}
+
+impl fmt::Display for OpenWith {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OpenWith::Browser => write!(f, "the default browser"),
+            OpenWith::Command(ref command) => write!(f, "{}", command.join(" ")),
+            OpenWith::Clipboard => write!(f, "the clipboard"),
+            OpenWith::File(ref path) => write!(f, "the file {}", path.display()),
+            OpenWith::ReadLater(ref service) => write!(f, "{}", service),
+        }
+    }
+}
+
+/// A read-it-later service, with the login to queue comics with, set with
+/// `open with pocket` and the like.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReadLater {
+    Pocket {
+        consumer_key: String,
+        access_token: String,
+    },
+    Instapaper {
+        user: String,
+        password: String,
+    },
+    /// A Wallabag instance at `url`, logged in to with an API client.
+    Wallabag {
+        url: String,
+        client_id: String,
+        client_secret: String,
+        user: String,
+        password: String,
+    },
+}
+
+impl fmt::Display for ReadLater {
+    // Without the secrets, since this is shown by `--explain-config`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReadLater::Pocket { .. } => write!(f, "Pocket"),
+            ReadLater::Instapaper { ref user, .. } => write!(f, "Instapaper as {}", user),
+            ReadLater::Wallabag {
+                ref url, ref user, ..
+            } => write!(f, "Wallabag at {} as {}", url, user),
+        }
+    }
+}
+
+/// A login for a private feed, set with the `auth` directive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Auth {
+    Basic { user: String, password: String },
+    Bearer(String),
+}
+
+impl Auth {
+    /// The value of the `Authorization` header that logs in this way.
+    pub fn header_value(&self) -> String {
+        match *self {
+            Auth::Basic {
+                ref user,
+                ref password,
+            } => format!(
+                "Basic {}",
+                base64(format!("{}:{}", user, password).as_bytes())
+            ),
+            Auth::Bearer(ref token) => format!("Bearer {}", token),
+        }
+    }
+}
+
+/// Standard base64 with padding, for HTTP basic auth and email.
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | u32::from(byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Every way `url` splits into `literals` with a value between each pair, up
+/// to two, added to `found`. `values` holds the values picked so far.
+fn line_up(url: &str, literals: &[&str], values: &mut Vec<String>, found: &mut Vec<Vec<String>>) {
+    let rest = match url.strip_prefix(literals[0]) {
+        Some(rest) => rest,
+        None => return,
+    };
+    if literals.len() == 1 {
+        if rest.is_empty() {
+            found.push(values.clone());
+        }
+        return;
+    }
+    for (end, _) in rest.char_indices().chain(Some((rest.len(), ' '))) {
+        if found.len() > 1 {
+            return;
+        }
+        if rest[end..].starts_with(literals[1]) {
+            values.push(rest[..end].to_string());
+            line_up(&rest[end..], &literals[1..], values, found);
+            values.pop();
+        }
+    }
 }
 
 impl FeedInfo {
+    /// The URL as written in the config, which is `url` unless it has
+    /// `{env:NAME}` in it.
+    pub fn written_url(&self) -> &str {
+        self.url_template.as_deref().unwrap_or(&self.url)
+    }
+
+    /// Each secret filled in from `{env:NAME}` in the URL, paired with the
+    /// `{env:NAME}` it came from, worked out by lining `url_template` up with
+    /// `url`. `None` if they line up more than one way, so which part of the
+    /// URL is the secret can't be told.
+    fn url_secrets(&self) -> Option<Vec<(String, String)>> {
+        let template = match self.url_template {
+            Some(ref template) => template,
+            None => return Some(Vec::new()),
+        };
+        let mut literals = Vec::new();
+        let mut names = Vec::new();
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find("{env:") {
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => break,
+            };
+            literals.push(&rest[..start]);
+            names.push(&rest[start..=end]);
+            rest = &rest[end + 1..];
+        }
+        literals.push(rest);
+
+        let mut found = Vec::new();
+        line_up(&self.url, &literals, &mut Vec::new(), &mut found);
+        match found.len() {
+            1 => Some(
+                found
+                    .remove(0)
+                    .into_iter()
+                    .zip(names.into_iter().map(String::from))
+                    .filter(|(value, _)| !value.is_empty())
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// `url` as it's kept in the history or written to the config, with any
+    /// secret from `{env:NAME}` in it put back as `{env:NAME}`. `None` if the
+    /// feed's URL has secrets that can't be told apart from the rest of it.
+    pub fn saved_url(&self, url: &str) -> Option<String> {
+        if url == self.url {
+            return Some(self.written_url().into());
+        }
+        self.hide_secrets(url)
+    }
+
+    /// `text` with any secret from `{env:NAME}` in the feed's URL put back
+    /// as `{env:NAME}`, like an error that repeats the URL. `None` if the
+    /// secrets can't be told apart from the rest of the URL.
+    pub fn hide_secrets(&self, text: &str) -> Option<String> {
+        let mut secrets = self.url_secrets()?;
+        secrets.sort_by_key(|(value, _)| std::cmp::Reverse(value.len()));
+        Some(
+            secrets
+                .iter()
+                .fold(text.to_string(), |text, (value, name)| {
+                    text.replace(value, name)
+                }),
+        )
+    }
+
+    /// This info with `url` as its URL, and `written_url` giving it with
+    /// any secret from `{env:NAME}` put back.
+    pub fn at(&self, url: &str) -> FeedInfo {
+        FeedInfo {
+            url: url.into(),
+            url_template: self.saved_url(url).filter(|saved| saved != url),
+            ..self.clone()
+        }
+    }
+
+    /// A URL from the history, with any `{env:NAME}` from the feed's URL
+    /// filled in.
+    fn loaded_url(&self, url: &str) -> String {
+        if url == self.written_url() {
+            return self.url.clone();
+        }
+        self.url_secrets()
+            .unwrap_or_default()
+            .iter()
+            .fold(url.to_string(), |url, (value, name)| {
+                url.replace(name, value)
+            })
+    }
+
     pub fn read_feed<R: Read>(&self, reader: &mut R) -> Result<Feed, Error> {
-        let mut string = String::new();
-        reader.read_to_string(&mut string)?;
+        let events = self.read_events(&self.name, reader)?;
+        Ok(self.replay(events))
+    }
 
-        let make_error_message = |row: usize, span: Span, msg: &str| -> Error {
-            let mut message = format!("Line {}: Error parsing feed \"{}\"\n\n", row, self.name);
-            let line = string.lines().nth(row).unwrap_or_default();
-            message.push_str(&format!("{}\n", line));
-            match span {
-                None => message.push('\n'),
-                Some((l, r)) => {
-                    let underline = format!("{}{}\n", " ".repeat(l), "^".repeat(r - l + 1));
-                    message.push_str(&underline);
+    /// Like `read_feed`, but with the history the feed built up under its
+    /// `@ alias` names replayed first. `aliases` pairs each old name with its
+    /// event log, oldest first. Comics that show up again in the feed's own
+    /// log, like when a renamed feed downloaded its whole archive, are only
+    /// counted the first time. Where the logs disagree about when the feed
+    /// was last read, its own log wins, since it's replayed last.
+    pub fn read_feed_with_aliases<R: Read, A: Read>(
+        &self,
+        reader: &mut R,
+        aliases: &mut [(String, A)],
+    ) -> Result<Feed, Error> {
+        let mut events = Vec::new();
+        for (name, alias_reader) in aliases.iter_mut() {
+            events.extend(self.read_events(name, alias_reader)?);
+        }
+        let mut seen: HashSet<String> = events
+            .iter()
+            .filter_map(|event| match *event {
+                FeedEvent::Comic(ref comic) => Some(comic.url.clone()),
+                _ => None,
+            })
+            .collect();
+        for event in self.read_events(&self.name, reader)? {
+            match event {
+                FeedEvent::Comic(ref comic) if !seen.insert(comic.url.clone()) => {
+                    trace!(
+                        "\"{}\": already have <{}> from an alias",
+                        self.name,
+                        comic.url
+                    );
                 }
+                event => events.push(event),
             }
+        }
+        Ok(self.replay(events))
+    }
 
-            message.push_str(&format!("Expected {}", msg));
-            Error::Msg(message)
-        };
+    /// The most comics to take from one download of the feed, if there's a
+    /// limit.
+    pub fn item_limit(&self) -> Option<usize> {
+        self.update_policies
+            .iter()
+            .filter_map(|policy| match *policy {
+                UpdateSpec::MaxPerFetch(n) => Some(n),
+                _ => None,
+            })
+            .min()
+            .or(self.max_items_per_fetch)
+    }
 
-        let events = match parse_events(&string) {
-            Ok(events) => events,
-            Err(ParseError::Expected { msg, row, span }) => {
-                return Err(make_error_message(row, span, &msg));
-            }
-        };
+    /// The pattern from `@ by chapter` and its lookahead, if the feed is read a
+    /// chapter at a time.
+    pub fn chapter_pacing(&self) -> Option<(Regex, usize)> {
+        self.update_policies
+            .iter()
+            .find_map(|policy| match *policy {
+                UpdateSpec::ByChapter(ref pattern, lookahead) => {
+                    Some((Regex::new(pattern).unwrap(), lookahead))
+                }
+                _ => None,
+            })
+    }
+
+    /// The certificate fingerprint from `@ pin`, if the feed's server has to
+    /// present that certificate.
+    pub fn pinned_certificate(&self) -> Option<&str> {
+        self.update_policies
+            .iter()
+            .find_map(|policy| match *policy {
+                UpdateSpec::Pin(ref fingerprint) => Some(fingerprint.as_str()),
+                _ => None,
+            })
+    }
+
+    /// How long to wait on the feed's server, from `@ timeout`, if the feed
+    /// has its own limit.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.update_policies
+            .iter()
+            .find_map(|policy| match *policy {
+                UpdateSpec::Timeout(seconds) => Some(Duration::seconds(seconds as i64)),
+                _ => None,
+            })
+    }
+
+    /// How many more times to try downloading the feed, from `@ retries`, if
+    /// the feed has its own number.
+    pub fn retries(&self) -> Option<usize> {
+        self.update_policies
+            .iter()
+            .find_map(|policy| match *policy {
+                UpdateSpec::Retries(n) => Some(n),
+                _ => None,
+            })
+    }
+
+    /// The most unread comics to open in one run, from `@ max N comics` or
+    /// `@ random N comics`, if the feed has a limit.
+    pub fn max_per_session(&self) -> Option<usize> {
+        self.update_policies
+            .iter()
+            .filter_map(|policy| match *policy {
+                UpdateSpec::MaxPerSession(n) | UpdateSpec::Random(n) => Some(n),
+                _ => None,
+            })
+            .min()
+    }
+
+    /// Whether `@ random N comics` picks the comics to open.
+    pub fn is_random(&self) -> bool {
+        self.update_policies
+            .iter()
+            .any(|policy| matches!(*policy, UpdateSpec::Random(_)))
+    }
+
+    /// When `@ skip before` starts the feed, as the start of that day here, or
+    /// in the feed's `timezone`.
+    pub fn skip_before(&self) -> Option<DateTime<Utc>> {
+        self.update_policies
+            .iter()
+            .filter_map(|policy| match *policy {
+                UpdateSpec::SkipBefore(date) => date
+                    .and_hms_opt(0, 0, 0)
+                    .and_then(|start| state::from_wall_clock(self, start)),
+                _ => None,
+            })
+            .max()
+            .map(|date| date.with_timezone(&Utc))
+    }
+
+    /// Whether to crawl the archive of a new feed with `@ crawl archive`, and
+    /// the selector for the links to follow if it gives one.
+    pub fn crawl_archive(&self) -> Option<Option<&str>> {
+        self.update_policies
+            .iter()
+            .find_map(|policy| match *policy {
+                UpdateSpec::CrawlArchive(ref selector) => Some(selector.as_deref()),
+                _ => None,
+            })
+    }
 
+    /// The headers to send when downloading the feed: those from `header`
+    /// and `user_agent` lines, and the login from `auth`.
+    pub fn request_headers(&self) -> Vec<(String, String)> {
+        let mut headers = self.headers.clone();
+        if let Some(ref auth) = self.auth {
+            headers.retain(|(name, _)| !name.eq_ignore_ascii_case("Authorization"));
+            headers.push(("Authorization".into(), auth.header_value()));
+        }
+        headers
+    }
+
+    /// The names this feed's history was recorded under before, from its
+    /// `@ alias` policies.
+    pub fn aliases(&self) -> impl Iterator<Item = &str> {
+        self.update_policies
+            .iter()
+            .filter_map(|policy| match *policy {
+                UpdateSpec::Alias(ref name) => Some(name.as_str()),
+                _ => None,
+            })
+    }
+
+    fn read_events<R: Read>(&self, name: &str, reader: &mut R) -> Result<Vec<FeedEvent>, Error> {
+        let mut string = String::new();
+        reader.read_to_string(&mut string)?;
+
+        parse_events(&string)
+            .map_err(|err| Error::parse(format!("feed \"{}\"", name), &string, err))
+    }
+
+    fn replay(&self, events: Vec<FeedEvent>) -> Feed {
         let mut last_read = None;
-        let mut new_comics = 0;
+        let mut last_checked = None;
+        let mut ready_since = None;
+        let mut state = State::Unseen;
         let mut seen_comics = HashSet::new();
+        let mut unread = HashSet::new();
+        let mut withdrawn = HashSet::new();
+        let mut reset_pending = false;
+        let mut list_order = None;
+        let mut discovered = None;
+        let mut moves = HashMap::new();
+        let mut thumbnails = HashMap::new();
         for event in &events {
-            match *event {
-                FeedEvent::ComicUrl(ref url) => {
-                    new_comics += 1;
-                    seen_comics.insert(url.clone());
+            let trigger = match *event {
+                FeedEvent::Comic(ref comic) => {
+                    seen_comics.insert(comic.url.clone());
+                    // Recorded before its URL would have been rewritten, the
+                    // comic is still the same one
+                    seen_comics.insert(self.rewrite_url(&comic.url));
+                    unread.insert(comic.url.clone());
+                    Trigger::Fetched(1)
                 }
                 FeedEvent::Read(date) => {
                     last_read = Some(date);
-                    new_comics = 0;
+                    ready_since = None;
+                    unread.clear();
+                    Trigger::Read(date)
                 }
-            }
+                FeedEvent::ReadComic(_, ref url) => {
+                    unread.remove(url);
+                    continue;
+                }
+                FeedEvent::Checked(date) => {
+                    last_checked = Some(date);
+                    continue;
+                }
+                FeedEvent::Ready(date) => {
+                    ready_since = Some(date);
+                    continue;
+                }
+                FeedEvent::Surprise(..) => continue,
+                FeedEvent::Withdrawn(_, ref url) => {
+                    unread.remove(url);
+                    withdrawn.insert(url.clone());
+                    continue;
+                }
+                FeedEvent::Reset(_) => {
+                    reset_pending = true;
+                    continue;
+                }
+                FeedEvent::AcceptReset(date) => {
+                    reset_pending = false;
+                    ready_since = None;
+                    unread.clear();
+                    Trigger::Read(date)
+                }
+                // Comics left unread by the last read count as just fetched
+                FeedEvent::Held(_, ref url) => {
+                    if !seen_comics.contains(url) || withdrawn.contains(url) {
+                        continue;
+                    }
+                    unread.insert(url.clone());
+                    Trigger::Fetched(1)
+                }
+                FeedEvent::Order(order) => {
+                    list_order = Some(order);
+                    continue;
+                }
+                FeedEvent::Discovered(ref page, ref url) => {
+                    discovered = Some((self.loaded_url(page), self.loaded_url(url)));
+                    continue;
+                }
+                FeedEvent::Moved(ref from, ref to) => {
+                    moves.insert(self.loaded_url(from), self.loaded_url(to));
+                    continue;
+                }
+                FeedEvent::Thumbnail(ref url, ref image) => {
+                    thumbnails.insert(url.clone(), image.clone());
+                    continue;
+                }
+            };
+            let progress = Progress {
+                last_read,
+                new_comics: unread.len(),
+            };
+            state = state.next(trigger, self, progress);
         }
 
-        Ok(Feed {
+        Feed {
             info: self.clone(),
             new_events: Vec::new(),
             seen_comics,
+            withdrawn,
+            reset_pending,
+            list_order,
+            discovered,
+            moves,
+            thumbnails,
             last_read,
-            new_comics,
+            last_checked,
+            ready_since,
+            unread,
+            seed: 0,
+            state,
             events,
-        })
+        }
     }
 
     pub fn filter_title(&self, title: &str) -> bool {
@@ -108,6 +1065,63 @@ impl FeedInfo {
         true
     }
 
+    /// `title` with the feed's `@ rewrite title` policies applied, in the
+    /// order they're written.
+    pub fn rewrite_title(&self, title: &str) -> String {
+        let mut title = title.to_string();
+        for policy in &self.rewrites {
+            if let UpdateSpec::RewriteTitle(ref pat, ref replacement) = *policy {
+                title = Regex::new(pat)
+                    .unwrap()
+                    .replace_all(&title, replacement.as_str())
+                    .into_owned();
+            }
+        }
+        title
+    }
+
+    /// `url` the way it's recorded: without tracking parameters like
+    /// `utm_source`, and with the feed's `@ rewrite url` policies applied, in
+    /// the order they're written.
+    pub fn rewrite_url(&self, url: &str) -> String {
+        let mut url = strip_tracking(url);
+        // @Performance: Avoid compiling so many regexes
+        for policy in &self.rewrites {
+            if let UpdateSpec::RewriteUrl(ref pat, ref replacement) = *policy {
+                url = Regex::new(pat)
+                    .unwrap()
+                    .replace_all(&url, replacement.as_str())
+                    .into_owned();
+            }
+        }
+        url
+    }
+
+    /// Whether a comic in `language` passes the feed's `@ language` policies.
+    /// Comics in an unknown language always do, as do all comics if there are
+    /// no such policies. `en` matches regional variants like `en-us` too.
+    pub fn filter_language(&self, language: Option<&str>) -> bool {
+        let language = match language {
+            Some(language) => language,
+            None => return true,
+        };
+        let mut wanted = self
+            .update_policies
+            .iter()
+            .filter_map(|policy| match *policy {
+                UpdateSpec::Language(ref code) => Some(code),
+                _ => None,
+            })
+            .peekable();
+        if wanted.peek().is_none() {
+            return true;
+        }
+        wanted.any(|code| {
+            language == code
+                || (language.starts_with(code.as_str()) && language[code.len()..].starts_with('-'))
+        })
+    }
+
     pub fn filter_url(&self, url: &str) -> bool {
         // @Performance: Avoid compiling so many regexes
         for policy in &self.update_policies {
@@ -129,171 +1143,891 @@ impl FeedInfo {
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comic {
+    pub url: String,
+    /// When the comic was published, as normalized by `extract_items`. Events
+    /// written by older versions of feedburst don't have this.
+    pub published: Option<DateTime<Utc>>,
+    /// The plain-text title, only ever stored alongside a publish date.
+    pub title: Option<String>,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum FeedEvent {
-    ComicUrl(String),
+    Comic(Comic),
     Read(DateTime<Utc>),
+    /// Just the one unread comic was read, leaving the rest of the feed
+    /// unread.
+    ReadComic(DateTime<Utc>, String),
+    /// The feed was downloaded successfully.
+    Checked(DateTime<Utc>),
+    /// The feed was first seen ready, but not read.
+    Ready(DateTime<Utc>),
+    /// A comic that was already read was opened again by `@ surprise`.
+    Surprise(DateTime<Utc>, String),
+    /// An unread comic disappeared from the feed and its link stopped working,
+    /// so it won't be opened.
+    Withdrawn(DateTime<Utc>, String),
+    /// The feed seemed to republish its archive under new URLs, so it's
+    /// paused until the reset is accepted.
+    Reset(DateTime<Utc>),
+    /// The reset was accepted. Every comic before this counts as read, without
+    /// counting as a time the feed was read.
+    AcceptReset(DateTime<Utc>),
+    /// A comic from a chapter that wasn't finished yet was held back when the
+    /// feed was read, so it's still unread.
+    Held(DateTime<Utc>, String),
+    /// Which way the feed lists its comics, as worked out from their dates
+    /// the first time they made it clear.
+    Order(ListOrder),
+    /// The feed's URL turned out to be a web page, and this is the feed it
+    /// links to, as the page's URL and then the feed's.
+    Discovered(String, String),
+    /// The server said the feed at the first URL has moved for good to the
+    /// second.
+    Moved(String, String),
+    /// A picture of the comic at the first URL, at the second.
+    Thumbnail(String, String),
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Feed {
     pub info: FeedInfo,
     last_read: Option<DateTime<Utc>>,
-    new_comics: usize,
+    last_checked: Option<DateTime<Utc>>,
+    ready_since: Option<DateTime<Utc>>,
+    /// The URLs of the comics that haven't been read yet.
+    unread: HashSet<String>,
+    /// What `@ random N comics` picks with.
+    seed: u64,
+    state: State,
     seen_comics: HashSet<String>,
+    withdrawn: HashSet<String>,
+    reset_pending: bool,
+    list_order: Option<ListOrder>,
+    discovered: Option<(String, String)>,
+    /// Where feeds have permanently moved, from their old URLs to their new.
+    moves: HashMap<String, String>,
+    /// Pictures of the comics, by comic URL.
+    thumbnails: HashMap<String, String>,
     new_events: Vec<FeedEvent>,
     events: Vec<FeedEvent>,
 }
 
 impl Feed {
-    pub fn add_new_comics<S: ::std::borrow::Borrow<String>>(&mut self, urls: &[S]) {
-        for url in urls {
-            let url = url.borrow();
-            if !self.seen_comics.contains(url) {
-                self.new_events.push(FeedEvent::ComicUrl(url.clone()));
-                self.new_comics += 1;
+    pub fn add_new_comics(&mut self, items: &[Item]) {
+        // A new feed starts at `@ skip before`, with everything older read
+        if let (Some(start), true) = (self.info.skip_before(), self.seen_comics.is_empty()) {
+            let (old, new): (Vec<Item>, Vec<Item>) = items
+                .iter()
+                .cloned()
+                .partition(|item| item.published < start);
+            if let Some(newest) = old.iter().map(|item| item.published).max() {
+                debug!(
+                    "\"{}\": starting after {} comics published before {}",
+                    self.info.name,
+                    old.len(),
+                    start
+                );
+                self.record_comics(&old);
+                self.new_events.push(FeedEvent::Read(newest));
+                self.last_read = Some(newest);
+                self.unread.clear();
+                self.transition(Trigger::Read(newest));
+                return self.add_new_comics(&new);
+            }
+        }
+        let before = self.unread.len();
+        self.record_comics(items);
+        self.transition(Trigger::Fetched(self.unread.len() - before));
+    }
+
+    /// Add the comics that haven't been seen before to the history.
+    fn record_comics(&mut self, items: &[Item]) {
+        for item in items {
+            if self.seen_comics.insert(item.url.clone()) {
+                let title = if item.title.is_empty() {
+                    None
+                } else {
+                    Some(item.title.clone())
+                };
+                self.new_events.push(FeedEvent::Comic(Comic {
+                    url: item.url.clone(),
+                    published: Some(item.published),
+                    title,
+                }));
+                if let Some(ref image) = item.thumbnail {
+                    self.new_events
+                        .push(FeedEvent::Thumbnail(item.url.clone(), image.clone()));
+                    self.thumbnails.insert(item.url.clone(), image.clone());
+                }
+                self.unread.insert(item.url.clone());
             }
         }
     }
 
     pub fn is_scheduled(&self, datetime: DateTime<Local>) -> bool {
-        let last_read = match self.last_read {
-            Some(last_read) => last_read,
-            None => return true,
-        };
+        state::is_scheduled(&self.info, self.last_read, datetime)
+    }
+
+    /// The first time from `datetime` on that the time-based policies allow
+    /// showing the feed, if that's within a year. It still won't be shown then
+    /// without enough new comics. The policies count whole days since the feed
+    /// was last read, so that's always a whole number of days after it, or
+    /// when `@ after` or `@ before` let it open on one of those days.
+    pub fn next_scheduled(&self, datetime: DateTime<Local>) -> Option<DateTime<Local>> {
+        if self.is_scheduled(datetime) {
+            return Some(datetime);
+        }
+        // Only a pause or the time of day keep a feed that was never read
+        // from being scheduled
+        let last_read = self
+            .last_read
+            .unwrap_or_else(|| datetime.with_timezone(&Utc));
+        std::iter::once(datetime)
+            .chain(
+                (1..=366).map(|days| {
+                    state::days_later(&self.info, last_read, days).with_timezone(&Local)
+                }),
+            )
+            .filter(|&when| when >= datetime)
+            .map(|when| state::next_window(&self.info, when))
+            .find(|&when| self.is_scheduled(when))
+    }
+
+    /// When the feed will next open, going by its policies at `datetime`,
+    /// without opening it or changing anything.
+    pub fn next_due(&self, datetime: DateTime<Local>) -> Due {
+        if self.reset_pending {
+            return Due::Paused;
+        }
+        if self.is_ready_at(datetime) {
+            return Due::Now;
+        }
+        let needed = self
+            .info
+            .update_policies
+            .iter()
+            .filter_map(|policy| match *policy {
+                UpdateSpec::Comics(n) => Some(n),
+                _ => None,
+            })
+            .fold(1, std::cmp::max);
+        Due::Later {
+            from: self.next_scheduled(datetime),
+            comics: needed.saturating_sub(self.progress().new_comics),
+        }
+    }
 
-        let last_read = last_read.with_timezone(&Local);
-        let elapsed_time = datetime.signed_duration_since(last_read);
-        let mut day_passed = false;
-        let mut day_relevant = false;
+    /// When the feed was last marked read, if ever.
+    pub fn last_read(&self) -> Option<DateTime<Utc>> {
+        self.last_read
+    }
 
+    /// When the feed was first seen ready without being read, if it's been
+    /// waiting since it was last read.
+    pub fn ready_since(&self) -> Option<DateTime<Utc>> {
+        self.ready_since
+    }
+
+    /// When the feed was last downloaded, if ever.
+    pub fn last_checked(&self) -> Option<DateTime<Utc>> {
+        self.last_checked
+    }
+
+    pub fn needs_check(&self, clock: &dyn Clock) -> bool {
+        self.needs_check_at(clock.now())
+    }
+
+    /// Whether the feed should be downloaded again, according to its
+    /// `@ check every` policies, and not while `@ pause until` or `@ between`
+    /// say it's off. Independent of whether it's ready to read.
+    pub fn needs_check_at(&self, datetime: DateTime<Utc>) -> bool {
+        if state::is_paused(&self.info, datetime.with_timezone(&Local)) {
+            debug!("Not fetching \"{}\" because it's paused", self.info.name);
+            return false;
+        }
+        let last_checked = match self.last_checked {
+            Some(last_checked) => last_checked,
+            None => return true,
+        };
+        let elapsed_time = datetime.signed_duration_since(last_checked);
         for policy in &self.info.update_policies {
-            match *policy {
-                UpdateSpec::Every(num_days) => {
-                    trace!(
-                        "Rule for \"{}\": @ every {} days (has been {})",
-                        self.info.name,
-                        num_days,
-                        elapsed_time.num_days()
-                    );
-                    if elapsed_time.num_days() < num_days as i64 {
-                        debug!("Skipping \"{}\" because of @every", self.info.name);
-                        return false;
-                    }
-                    trace!("Rule passed!");
-                }
-                UpdateSpec::On(day) => {
-                    trace!("Rule for \"{}\": @ on {:?}", self.info.name, day);
-                    day_relevant = true;
-                    use chrono::Datelike;
-                    let mut last_day = last_read.weekday();
-                    for _ in 0..elapsed_time.num_days() {
-                        last_day = last_day.succ();
-                        if last_day == day {
-                            day_passed = true;
-                            trace!("Rule passed!");
-                            break;
-                        }
-                    }
+            if let UpdateSpec::CheckEvery(num_days) = *policy {
+                trace!(
+                    "Rule for \"{}\": @ check every {} days (has been {})",
+                    self.info.name,
+                    num_days,
+                    elapsed_time.num_days()
+                );
+                if elapsed_time.num_days() < num_days as i64 {
+                    debug!("Not fetching \"{}\" because of @check", self.info.name);
+                    return false;
                 }
-                UpdateSpec::Overlap(_)
-                | UpdateSpec::Comics(_)
-                | UpdateSpec::Filter(_, _)
-                | UpdateSpec::OpenAll => (),
             }
         }
+        true
+    }
+
+    /// Record that the feed was downloaded at `datetime`. Only the last of a
+    /// run of downloads is kept, so checking a quiet feed doesn't make its
+    /// history grow.
+    pub fn checked_at(&mut self, datetime: DateTime<Utc>) {
+        if let Some(FeedEvent::Checked(_)) = self.new_events.last() {
+            self.new_events.pop();
+        }
+        self.new_events.push(FeedEvent::Checked(datetime));
+        self.last_checked = Some(datetime);
+    }
 
-        if day_relevant && !day_passed {
-            debug!("Skipping \"{}\" because of @on", self.info.name);
-            false
+    /// Whether the events since the feed was loaded start with it being
+    /// downloaded, so a `checked` line at the end of its history is out of
+    /// date, and storage drops it.
+    pub fn replaces_checked(&self) -> bool {
+        matches!(self.new_events.first(), Some(FeedEvent::Checked(_)))
+    }
+
+    /// Record that the feed is ready but wasn't read at `datetime`, unless
+    /// that's already been recorded since it was last read.
+    pub fn waiting_at(&mut self, datetime: DateTime<Utc>) {
+        if self.ready_since.is_none() {
+            self.new_events.push(FeedEvent::Ready(datetime));
+            self.ready_since = Some(datetime);
+        }
+    }
+
+    /// How long the feed has been waiting to be read, if that's long enough
+    /// for its `@ remind after` policy.
+    pub fn overdue_at(&self, datetime: DateTime<Utc>) -> Option<Duration> {
+        let waiting = datetime.signed_duration_since(self.ready_since?);
+        let remind_after = self
+            .info
+            .update_policies
+            .iter()
+            .filter_map(|policy| match *policy {
+                UpdateSpec::RemindAfter(n) => Some(n),
+                _ => None,
+            })
+            .min()?;
+        if waiting.num_days() >= remind_after as i64 {
+            Some(waiting)
         } else {
-            true
+            None
         }
     }
 
-    pub fn is_ready(&self) -> bool {
-        if self.new_comics < 1 {
-            return false;
+    pub fn is_ready(&self, clock: &dyn Clock) -> bool {
+        self.is_ready_at(clock.local())
+    }
+
+    /// Whether the feed should be read at `datetime`. Never while it's paused
+    /// by a reset.
+    pub fn is_ready_at(&self, datetime: DateTime<Local>) -> bool {
+        !self.reset_pending && self.state_at(datetime) == State::Ready
+    }
+
+    /// The feed's state, once the policies have been checked at `datetime`.
+    pub fn state_at(&self, datetime: DateTime<Local>) -> State {
+        self.state
+            .next(Trigger::Tick(datetime), &self.info, self.progress())
+    }
+
+    /// Record that the reading list has been opened, so it's no longer ready
+    /// even before it's marked read.
+    pub fn open(&mut self) {
+        self.transition(Trigger::Open);
+    }
+
+    /// Comics held back by `@ by chapter` don't count towards being ready.
+    fn progress(&self) -> Progress {
+        Progress {
+            last_read: self.last_read,
+            new_comics: self.unread.len() - self.held_back().len(),
         }
+    }
 
-        if !self.is_scheduled(Local::now()) {
-            return false;
+    fn transition(&mut self, trigger: Trigger) {
+        self.state = self.state.next(trigger, &self.info, self.progress());
+    }
+
+    pub fn read(&mut self, clock: &dyn Clock) {
+        self.read_at(clock.now())
+    }
+
+    /// Mark everything read, as if it happened at `now`. The time actually
+    /// recorded depends on the feed's `ReadMarker`.
+    pub fn read_at(&mut self, now: DateTime<Utc>) {
+        let held = self
+            .left_for_later()
+            .into_iter()
+            .map(|comic| comic.url.clone())
+            .collect();
+        self.read_leaving_at(now, held);
+    }
+
+    /// Mark the unread comics read up to and including `url`, as if at
+    /// `now`, leaving the ones after it for next time along with any
+    /// `left_for_later` would. A `url` that isn't unread, like one brought
+    /// back by `@ overlap`, leaves every unread comic for next time.
+    pub fn read_up_to_at(&mut self, now: DateTime<Utc>, url: &str) {
+        let unread = self.unread_comics();
+        let start = match unread.iter().position(|comic| comic.url == url) {
+            Some(i) => i + 1,
+            None => 0,
+        };
+        let later: HashSet<&str> = self
+            .left_for_later()
+            .into_iter()
+            .map(|comic| comic.url.as_str())
+            .collect();
+        let held = unread
+            .iter()
+            .enumerate()
+            .filter(|&(i, comic)| i >= start || later.contains(comic.url.as_str()))
+            .map(|(_, comic)| comic.url.clone())
+            .collect();
+        self.read_leaving_at(now, held);
+    }
+
+    /// Mark just the unread comic at `url` read, as if at `now`, leaving the
+    /// rest of the feed as it was. Returns whether it was unread.
+    pub fn read_comic_at(&mut self, url: &str, now: DateTime<Utc>) -> bool {
+        let was_unread = self.unread_comics().iter().any(|comic| comic.url == url);
+        if was_unread {
+            trace!(
+                "Marking <{}> in \"{}\" read at {}",
+                url,
+                self.info.name,
+                now
+            );
+            self.new_events.push(FeedEvent::ReadComic(now, url.into()));
+            self.unread.remove(url);
         }
+        was_unread
+    }
 
-        for policy in &self.info.update_policies {
-            match *policy {
-                UpdateSpec::Comics(num_comics) => {
-                    trace!(
-                        "Rule for \"{}\": @ {} new comics (has {})",
-                        self.info.name,
-                        num_comics,
-                        self.new_comics
-                    );
-                    if self.new_comics < num_comics {
-                        debug!("Skipping \"{}\" because of @comics", self.info.name);
-                        return false;
-                    }
-                    trace!("Rule passed!");
+    /// Mark everything read but the unread comics at the `held` URLs.
+    fn read_leaving_at(&mut self, now: DateTime<Utc>, held: Vec<String>) {
+        let when = match self.info.read_marker {
+            ReadMarker::Now => now,
+            ReadMarker::PublishDate => self.newest_unread_date(&held).unwrap_or(now),
+        };
+        trace!("Marking \"{}\" read at {}", self.info.name, when);
+        self.new_events.push(FeedEvent::Read(when));
+        self.last_read = Some(when);
+        self.ready_since = None;
+        for url in &held {
+            self.new_events.push(FeedEvent::Held(when, url.clone()));
+        }
+        let count = held.len();
+        self.unread = held.into_iter().collect();
+        self.transition(Trigger::Read(when));
+        self.transition(Trigger::Fetched(count));
+    }
+
+    /// The unread comics that `@ by chapter` is holding back, oldest first:
+    /// everything from the first chapter that doesn't have enough pages of a
+    /// later chapter after it yet. Comics whose titles don't name a chapter
+    /// belong to the chapter before them.
+    pub fn held_back(&self) -> Vec<&Comic> {
+        let (pattern, lookahead) = match self.info.chapter_pacing() {
+            Some(pacing) => pacing,
+            None => return Vec::new(),
+        };
+        let comics: Vec<_> = self.comics().collect();
+        let mut chapter = None;
+        let chapters: Vec<Option<usize>> = comics
+            .iter()
+            .map(|comic| {
+                let number = comic
+                    .title
+                    .as_ref()
+                    .and_then(|title| pattern.captures(title))
+                    .and_then(|captures| captures.get(1))
+                    .and_then(|number| number.as_str().parse().ok());
+                if number.is_some() {
+                    chapter = number;
+                }
+                chapter
+            })
+            .collect();
+        let unread: Vec<usize> = (0..comics.len())
+            .filter(|&i| self.unread.contains(&comics[i].url))
+            .collect();
+        let unfinished = unread.iter().enumerate().position(|(n, &i)| {
+            chapters[i].is_some()
+                && unread[n + 1..]
+                    .iter()
+                    .filter(|&&later| chapters[later] > chapters[i])
+                    .count()
+                    < lookahead
+        });
+        match unfinished {
+            Some(n) => unread[n..].iter().map(|&i| comics[i]).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The unread comics that opening the feed leaves unread, oldest first:
+    /// those `@ by chapter` holds back, or everything past the first N with
+    /// `@ max N comics`, whichever is more. Unlike `held_back`, these still
+    /// count towards the feed being ready.
+    pub fn left_for_later(&self) -> Vec<&Comic> {
+        if let Some(picked) = self.random_picks() {
+            return self
+                .unread_comics()
+                .into_iter()
+                .filter(|comic| !picked.contains(comic))
+                .collect();
+        }
+        let held = self.held_back();
+        let unread = self.unread_comics();
+        match self.info.max_per_session() {
+            Some(max) if unread.len().saturating_sub(max) > held.len() => unread[max..].to_vec(),
+            _ => held,
+        }
+    }
+
+    /// Pick what `@ random N comics` opens with `seed`, instead of the
+    /// default of 0. The picks only change with the seed or the unread
+    /// comics, so a run should pick one seed for all of them.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// The unread comics `@ random N comics` picked, oldest first, if the
+    /// feed has it.
+    fn random_picks(&self) -> Option<Vec<&Comic>> {
+        if !self.info.is_random() {
+            return None;
+        }
+        let count = self.info.max_per_session()?;
+        let seed = sample::feed_seed(self.seed, &self.info.name);
+        Some(sample::sample(&self.unread_comics(), count, seed))
+    }
+
+    fn newest_unread_date(&self, held: &[String]) -> Option<DateTime<Utc>> {
+        self.unread_comics()
+            .into_iter()
+            .filter(|comic| !held.contains(&comic.url))
+            .filter_map(|comic| comic.published)
+            .max()
+    }
+
+    /// The comics `@ surprise` could bring back if the feed were opened at
+    /// `now`: those already read and not surprised with before, or any read
+    /// comic once they've all had a turn. Empty when there's no surprise left
+    /// for the week.
+    pub fn surprise_candidates_at(&self, now: DateTime<Utc>) -> Vec<&Comic> {
+        let per_week = self
+            .info
+            .update_policies
+            .iter()
+            .filter_map(|policy| match *policy {
+                UpdateSpec::Surprise(n) => Some(n),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        let mut surprised = HashSet::new();
+        let mut this_week = 0;
+        for event in self.events() {
+            if let FeedEvent::Surprise(date, ref url) = *event {
+                surprised.insert(url.as_str());
+                if now - date < Duration::weeks(1) {
+                    this_week += 1;
                 }
-                UpdateSpec::Every(_)
-                | UpdateSpec::On(_)
-                | UpdateSpec::Overlap(_)
-                | UpdateSpec::Filter(_, _)
-                | UpdateSpec::OpenAll => (),
             }
         }
-        true
+        if this_week >= per_week {
+            return Vec::new();
+        }
+
+        let reading_list = self.get_reading_list();
+        let read: Vec<_> = self
+            .comics()
+            .filter(|comic| !self.unread.contains(&comic.url))
+            .filter(|comic| !reading_list.contains(&comic.url))
+            .collect();
+        let fresh: Vec<_> = read
+            .iter()
+            .cloned()
+            .filter(|comic| !surprised.contains(comic.url.as_str()))
+            .collect();
+        if fresh.is_empty() {
+            read
+        } else {
+            fresh
+        }
     }
 
-    pub fn read(&mut self) {
-        self.new_events.push(FeedEvent::Read(Utc::now()))
+    /// Record that `url` was opened by `@ surprise` at `now`.
+    pub fn surprised_at(&mut self, url: &str, now: DateTime<Utc>) {
+        self.new_events.push(FeedEvent::Surprise(now, url.into()));
     }
 
-    pub fn write_changes<W: Write + Seek>(&mut self, writer: &mut W) -> io::Result<()> {
-        writer.seek(io::SeekFrom::End(0))?;
-        for event in &self.new_events {
-            match *event {
-                FeedEvent::ComicUrl(ref url) => writeln!(writer, "<{}>", url)?,
-                FeedEvent::Read(date) => writeln!(writer, "read {}", date.to_rfc3339())?,
+    /// How many comics have arrived since the feed was last read.
+    pub fn new_comics(&self) -> usize {
+        self.unread.len()
+    }
+
+    /// The comics that have arrived since the feed was last read, oldest
+    /// first.
+    pub fn unread_comics(&self) -> Vec<&Comic> {
+        self.comics()
+            .filter(|comic| self.unread.contains(&comic.url))
+            .collect()
+    }
+
+    /// Everything recorded for this feed, oldest first, including events that
+    /// haven't been written out yet.
+    pub fn events(&self) -> impl Iterator<Item = &FeedEvent> {
+        self.events.iter().chain(&self.new_events)
+    }
+
+    /// A picture of the comic at `url`, if its feed had one.
+    pub fn thumbnail(&self, url: &str) -> Option<&str> {
+        self.thumbnails.get(url).map(String::as_str)
+    }
+
+    /// The comic recorded at `url`, if any.
+    pub fn comic(&self, url: &str) -> Option<&Comic> {
+        self.comics().find(|comic| comic.url == url)
+    }
+
+    /// Every comic recorded for this feed, oldest first, leaving out any that
+    /// were withdrawn.
+    pub fn comics(&self) -> impl Iterator<Item = &Comic> {
+        self.events
+            .iter()
+            .chain(&self.new_events)
+            .filter_map(move |event| match *event {
+                FeedEvent::Comic(ref comic) if !self.withdrawn.contains(&comic.url) => Some(comic),
+                FeedEvent::Comic(_)
+                | FeedEvent::Read(_)
+                | FeedEvent::ReadComic(..)
+                | FeedEvent::Checked(_)
+                | FeedEvent::Ready(_)
+                | FeedEvent::Surprise(..)
+                | FeedEvent::Withdrawn(..)
+                | FeedEvent::Reset(_)
+                | FeedEvent::AcceptReset(_)
+                | FeedEvent::Held(..)
+                | FeedEvent::Order(_)
+                | FeedEvent::Discovered(..)
+                | FeedEvent::Moved(..)
+                | FeedEvent::Thumbnail(..) => None,
+            })
+    }
+
+    /// The unread comics missing from `items`, the feed as just downloaded,
+    /// even though they came out after the oldest comic still in it. Those
+    /// may have been taken down, rather than pushed out by newer comics.
+    pub fn missing_unread(&self, items: &[Item]) -> Vec<&Comic> {
+        let oldest = match items.iter().map(|item| item.published).min() {
+            Some(oldest) => oldest,
+            None => return Vec::new(),
+        };
+        let present: HashSet<&str> = items.iter().map(|item| item.url.as_str()).collect();
+        self.unread_comics()
+            .into_iter()
+            .filter(|comic| {
+                !present.contains(comic.url.as_str())
+                    && !present.contains(self.info.rewrite_url(&comic.url).as_str())
+            })
+            .filter(|comic| match comic.published {
+                Some(published) => published >= oldest,
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Record that the unread comic at `url` was found withdrawn at `now`, so
+    /// it's no longer counted or opened.
+    pub fn withdrawn_at(&mut self, url: &str, now: DateTime<Utc>) {
+        self.unread.remove(url);
+        self.new_events.push(FeedEvent::Withdrawn(now, url.into()));
+        self.withdrawn.insert(url.into());
+    }
+
+    /// How many of `items`, the feed as just downloaded, are new to feedburst
+    /// but look like comics it's already seen: they have the title of a known
+    /// comic, or came out before the newest known one. Lots of these means the
+    /// site republished its archive, like after moving to new URLs.
+    pub fn republished(&self, items: &[Item]) -> usize {
+        let comics: Vec<_> = self.comics().collect();
+        let titles: HashSet<&str> = comics
+            .iter()
+            .filter_map(|comic| comic.title.as_deref())
+            .collect();
+        let newest = comics.iter().filter_map(|comic| comic.published).max();
+        items
+            .iter()
+            .filter(|item| !self.seen_comics.contains(&item.url))
+            .filter(|item| {
+                let older = match newest {
+                    Some(newest) => item.published < newest,
+                    None => false,
+                };
+                older || titles.contains(item.title.as_str())
+            })
+            .count()
+    }
+
+    /// Which way the feed lists its comics, once their dates have told.
+    pub fn list_order(&self) -> Option<ListOrder> {
+        self.list_order
+    }
+
+    /// Remember that the feed lists its comics in `order`, so later downloads
+    /// don't have to guess from dates that may be missing or muddled.
+    pub fn ordered(&mut self, order: ListOrder) {
+        if self.list_order != Some(order) {
+            self.new_events.push(FeedEvent::Order(order));
+            self.list_order = Some(order);
+        }
+    }
+
+    /// The URL to download the feed from: the feed found on the web page at
+    /// its configured URL, if it's been looked for, or else that URL, and
+    /// wherever that has moved to since.
+    pub fn feed_url(&self) -> &str {
+        match self.discovered {
+            Some((ref page, ref url)) if *page == self.info.url => self.follow_moves(url),
+            _ => self.canonical_url(),
+        }
+    }
+
+    /// Where the feed's configured URL has permanently moved to, or that URL
+    /// if it hasn't.
+    pub fn canonical_url(&self) -> &str {
+        self.follow_moves(&self.info.url)
+    }
+
+    /// Where `url` ended up after all the moves recorded for it.
+    fn follow_moves<'a>(&'a self, mut url: &'a str) -> &'a str {
+        for _ in 0..MAX_MOVES {
+            match self.moves.get(url) {
+                Some(to) => url = to,
+                None => break,
+            }
+        }
+        url
+    }
+
+    /// Remember that the feed at `from` has permanently moved to `to`, so
+    /// later downloads go straight there. Nothing is recorded if `to` might
+    /// give away a secret from `{env:NAME}`, which `saved_url` can tell.
+    pub fn moved(&mut self, from: &str, to: &str) {
+        if from != to && self.moves.get(from).map(String::as_str) != Some(to) {
+            if let (Some(saved_from), Some(saved_to)) =
+                (self.info.saved_url(from), self.info.saved_url(to))
+            {
+                self.new_events.push(FeedEvent::Moved(saved_from, saved_to));
+                self.moves.insert(from.to_string(), to.to_string());
+            }
+        }
+    }
+
+    /// The feed's info with `feed_url` as its URL, for downloading it.
+    pub fn fetch_info(&self) -> FeedInfo {
+        self.info.at(self.feed_url())
+    }
+
+    /// Remember that the web page at the feed's URL links to the feed at
+    /// `url`, so later downloads go straight there.
+    pub fn discovered(&mut self, url: &str) {
+        if self.feed_url() != url {
+            if let (Some(page), Some(saved)) = (
+                self.info.saved_url(&self.info.url),
+                self.info.saved_url(url),
+            ) {
+                self.new_events.push(FeedEvent::Discovered(page, saved));
+                self.discovered = Some((self.info.url.clone(), url.to_string()));
             }
         }
+    }
+
+    /// Whether the feed is paused because it seemed to reset.
+    pub fn reset_pending(&self) -> bool {
+        self.reset_pending
+    }
+
+    /// Pause the feed at `now`, since it seemed to republish its archive.
+    pub fn reset_at(&mut self, now: DateTime<Utc>) {
+        if !self.reset_pending {
+            self.new_events.push(FeedEvent::Reset(now));
+            self.reset_pending = true;
+        }
+    }
+
+    /// Accept a reset at `now`: take `items` as the feed's comics from now on,
+    /// counting them and everything before them as already read.
+    pub fn accept_reset_at(&mut self, items: &[Item], now: DateTime<Utc>) {
+        self.add_new_comics(items);
+        self.new_events.push(FeedEvent::AcceptReset(now));
+        self.reset_pending = false;
+        self.ready_since = None;
+        self.unread.clear();
+        self.transition(Trigger::Read(now));
+    }
+
+    /// The URLs of comics that were withdrawn before they were read.
+    pub fn withdrawn(&self) -> impl Iterator<Item = &str> {
+        self.events().filter_map(|event| match *event {
+            FeedEvent::Withdrawn(_, ref url) => Some(url.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Append the events since the feed was loaded to its history. A new
+    /// history starts with the version of the format it's written in.
+    pub fn write_changes<W: Write + Seek>(&mut self, writer: &mut W) -> io::Result<()> {
+        let end = writer.seek(io::SeekFrom::End(0))?;
+        if end == 0 && !self.new_events.is_empty() {
+            writeln!(writer, "version {}", EVENTS_VERSION)?;
+        }
+        for line in self.change_lines() {
+            writeln!(writer, "{}", line)?;
+        }
+        self.changes_written();
+        Ok(())
+    }
+
+    /// The events since the feed was loaded, as lines of its history, for
+    /// storing them somewhere other than a file. Call `changes_written` once
+    /// they're stored.
+    pub fn change_lines(&self) -> Vec<String> {
+        self.new_events
+            .iter()
+            .map(|event| match *event {
+                FeedEvent::Comic(Comic {
+                    ref url,
+                    published: Some(date),
+                    title: Some(ref title),
+                }) => format!("<{}> {} {}", url, event_date(date), title),
+                FeedEvent::Comic(Comic {
+                    ref url,
+                    published: Some(date),
+                    title: None,
+                }) => format!("<{}> {}", url, event_date(date)),
+                FeedEvent::Comic(Comic {
+                    ref url,
+                    published: None,
+                    ..
+                }) => format!("<{}>", url),
+                FeedEvent::Read(date) => format!("read {}", event_date(date)),
+                FeedEvent::ReadComic(date, ref url) => {
+                    format!("read <{}> {}", url, event_date(date))
+                }
+                FeedEvent::Checked(date) => format!("checked {}", event_date(date)),
+                FeedEvent::Ready(date) => format!("ready {}", event_date(date)),
+                FeedEvent::Surprise(date, ref url) => {
+                    format!("surprise {} <{}>", event_date(date), url)
+                }
+                FeedEvent::Withdrawn(date, ref url) => {
+                    format!("withdrawn {} <{}>", event_date(date), url)
+                }
+                FeedEvent::Reset(date) => format!("reset {}", event_date(date)),
+                FeedEvent::AcceptReset(date) => format!("accept-reset {}", event_date(date)),
+                FeedEvent::Held(date, ref url) => format!("held {} <{}>", event_date(date), url),
+                FeedEvent::Order(ListOrder::NewestFirst) => "order newest-first".into(),
+                FeedEvent::Order(ListOrder::OldestFirst) => "order oldest-first".into(),
+                FeedEvent::Discovered(ref page, ref url) => {
+                    format!("discovered <{}> <{}>", page, url)
+                }
+                FeedEvent::Moved(ref from, ref to) => format!("moved <{}> <{}>", from, to),
+                FeedEvent::Thumbnail(ref url, ref image) => {
+                    format!("thumbnail <{}> <{}>", url, image)
+                }
+            })
+            .collect()
+    }
+
+    /// Count the events since the feed was loaded as part of its history.
+    pub fn changes_written(&mut self) {
         trace!(
             "Wrote changes for \"{}\", new events moved to old",
             self.info.name
         );
         self.events.append(&mut self.new_events);
-        Ok(())
     }
 
     pub fn get_reading_list(&self) -> Vec<String> {
+        if let Some(picked) = self.random_picks() {
+            return picked.into_iter().map(|comic| comic.url.clone()).collect();
+        }
         let mut additional = 0;
+        let mut overlap_days = None;
         for policy in &self.info.update_policies {
-            if let UpdateSpec::Overlap(n) = *policy {
-                additional = ::std::cmp::max(n, additional);
+            match *policy {
+                UpdateSpec::Overlap(n) => additional = ::std::cmp::max(n, additional),
+                UpdateSpec::OverlapDays(n) => {
+                    overlap_days = ::std::cmp::max(Some(Duration::days(n as i64)), overlap_days)
+                }
+                _ => (),
             }
         }
         trace!(
-            "Reading list for \"{}\", overlap {}",
+            "Reading list for \"{}\", overlap {} comics or {:?}",
             self.info.name,
-            additional
+            additional,
+            overlap_days,
         );
-        let mut finishing = false;
+        let mut last_read = None;
+        let mut held = HashSet::new();
+        let mut read_singly = HashSet::new();
         let mut result = Vec::new();
         for event in self.events.iter().chain(&self.new_events).rev() {
             match *event {
-                FeedEvent::ComicUrl(ref url) => {
-                    if finishing {
-                        if additional == 0 {
+                FeedEvent::Comic(Comic { ref url, .. }) if self.withdrawn.contains(url) => (),
+                FeedEvent::Comic(Comic { ref url, .. }) if read_singly.contains(url) => (),
+                // Held back at the last read, so still unread
+                FeedEvent::Comic(Comic { ref url, .. }) if held.contains(url) => {
+                    result.push(url.clone())
+                }
+                FeedEvent::Comic(Comic {
+                    ref url, published, ..
+                }) => {
+                    if let Some(last_read) = last_read {
+                        // Comics without a publish date never count as recent
+                        let recent = match (overlap_days, published) {
+                            (Some(days), Some(published)) => published >= last_read - days,
+                            _ => false,
+                        };
+                        if additional == 0 && !recent {
                             break;
                         }
-                        additional -= 1;
+                        additional = additional.saturating_sub(1);
                     }
                     result.push(url.clone());
                 }
-                FeedEvent::Read(when) => {
-                    finishing = true;
+                FeedEvent::Read(when) | FeedEvent::AcceptReset(when) => {
+                    if last_read.is_none() {
+                        last_read = Some(when);
+                    }
                     trace!("Read at {}", when);
                 }
+                FeedEvent::Held(_, ref url) => {
+                    if last_read.is_none() {
+                        held.insert(url);
+                    }
+                }
+                FeedEvent::ReadComic(_, ref url) => {
+                    if last_read.is_none() {
+                        read_singly.insert(url);
+                    }
+                }
+                FeedEvent::Checked(_)
+                | FeedEvent::Ready(_)
+                | FeedEvent::Surprise(..)
+                | FeedEvent::Withdrawn(..)
+                | FeedEvent::Reset(_)
+                | FeedEvent::Order(_)
+                | FeedEvent::Discovered(..)
+                | FeedEvent::Moved(..)
+                | FeedEvent::Thumbnail(..) => (),
             }
         }
         debug!(
@@ -302,6 +2036,773 @@ impl Feed {
             result.len()
         );
         result.reverse();
+        let still_held = self.left_for_later().len();
+        result.truncate(result.len().saturating_sub(still_held));
         result
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse_util::Buffer;
+    use crate::parser::{interpolate_env_vars, parse_config};
+    use chrono::TimeZone;
+
+    fn reading_list(config: &str, events: &str) -> Vec<String> {
+        let info = parse_config(config).unwrap().remove(0);
+        let feed = info.read_feed(&mut events.as_bytes()).unwrap();
+        feed.get_reading_list()
+    }
+
+    #[test]
+    fn test_write_changes_round_trip() {
+        use chrono::TimeZone;
+        let info = parse_config(r#""Feed" <http://example.com>"#)
+            .unwrap()
+            .remove(0);
+        let mut feed = info.read_feed(&mut "".as_bytes()).unwrap();
+        let published = Utc.ymd(2018, 10, 1).and_hms(12, 0, 0);
+        feed.add_new_comics(&[Item {
+            url: "http://example.com/1".into(),
+            title: "Page 1".into(),
+            published,
+            guid: None,
+            language: None,
+            thumbnail: None,
+        }]);
+        feed.checked_at(published + Duration::hours(1));
+        feed.read_at(Utc.ymd(2018, 10, 2).and_hms_micro(8, 30, 0, 492_180));
+        feed.withdrawn_at("http://example.com/1", published + Duration::days(2));
+
+        let mut written = io::Cursor::new(Vec::new());
+        feed.write_changes(&mut written).unwrap();
+        let text = String::from_utf8(written.into_inner()).unwrap();
+        assert_eq!(
+            text,
+            concat!(
+                "version 1\n",
+                "<http://example.com/1> 2018-10-01T12:00:00+00:00 Page 1\n",
+                "checked 2018-10-01T13:00:00+00:00\n",
+                "read 2018-10-02T08:30:00.492180+00:00\n",
+                "withdrawn 2018-10-03T12:00:00+00:00 <http://example.com/1>\n",
+            )
+        );
+        let reloaded = info.read_feed(&mut text.as_bytes()).unwrap();
+        assert!(reloaded.events().eq(feed.events()));
+    }
+
+    /// A feed at `template`, with its `{env:NAME}` filled in by `var`.
+    fn secret_feed(template: &str, var: impl Fn(&str) -> Option<String>) -> FeedInfo {
+        let mut info = parse_config(r#""Feed" <http://example.com>"#)
+            .unwrap()
+            .remove(0);
+        let buf = Buffer {
+            text: template,
+            row: 0,
+            col: 0,
+        };
+        info.url = interpolate_env_vars(&buf, template, var).unwrap();
+        info.url_template = Some(template.into());
+        info
+    }
+
+    #[test]
+    fn test_moves_keep_env_secrets() {
+        let var = |name: &str| Some("hunter2".to_string()).filter(|_| name == "TOKEN");
+        let info = secret_feed("http://example.com/?key={env:TOKEN}", var);
+        let mut feed = info.read_feed(&mut "".as_bytes()).unwrap();
+        feed.discovered("http://example.com/rss");
+        feed.moved("http://example.com/?key=hunter2", "http://example.net/");
+
+        let mut written = io::Cursor::new(Vec::new());
+        feed.write_changes(&mut written).unwrap();
+        let text = String::from_utf8(written.into_inner()).unwrap();
+        assert!(!text.contains("hunter2"), "{}", text);
+        assert!(text.contains("<http://example.com/?key={env:TOKEN}>"));
+
+        let reloaded = info.read_feed(&mut text.as_bytes()).unwrap();
+        assert_eq!(reloaded.feed_url(), "http://example.com/rss");
+        assert_eq!(reloaded.canonical_url(), "http://example.net/");
+    }
+
+    #[test]
+    fn test_move_keeping_secret_query() {
+        let var = |name: &str| match name {
+            "USER" => Some("me".to_string()),
+            "TOKEN" => Some("hunter2".to_string()),
+            _ => None,
+        };
+        let info = secret_feed("http://example.com/{env:USER}/feed?token={env:TOKEN}", var);
+        let mut feed = info.read_feed(&mut "".as_bytes()).unwrap();
+        feed.moved(&info.url, "https://example.com/me/feed?token=hunter2");
+        assert_eq!(
+            info.saved_url(feed.canonical_url()).as_deref(),
+            Some("https://example.com/{env:USER}/feed?token={env:TOKEN}")
+        );
+        assert_eq!(
+            info.hide_secrets("error sending request for url (http://example.com/me/feed?token=hunter2)")
+                .as_deref(),
+            Some("error sending request for url (http://example.com/{env:USER}/feed?token={env:TOKEN})")
+        );
+
+        let mut written = io::Cursor::new(Vec::new());
+        feed.write_changes(&mut written).unwrap();
+        let text = String::from_utf8(written.into_inner()).unwrap();
+        assert!(!text.contains("hunter2"), "{}", text);
+        let reloaded = info.read_feed(&mut text.as_bytes()).unwrap();
+        assert_eq!(
+            reloaded.canonical_url(),
+            "https://example.com/me/feed?token=hunter2"
+        );
+
+        // Can't tell which "a" is the secret, so the move isn't recorded
+        let var = |_: &str| Some("a".to_string());
+        let info = secret_feed("http://example.com/?k={env:A}{env:B}", var);
+        assert_eq!(info.saved_url("https://example.com/?k=aa"), None);
+        let mut feed = info.read_feed(&mut "".as_bytes()).unwrap();
+        feed.moved(&info.url, "https://example.com/?k=aa");
+        assert_eq!(feed.canonical_url(), info.url);
+        assert_eq!(feed.events().count(), 0);
+    }
+
+    #[test]
+    fn test_fetch_info_written_url() {
+        let var = |name: &str| Some("hunter2".to_string()).filter(|_| name == "TOKEN");
+        let info = secret_feed("http://example.com/feed?token={env:TOKEN}", var);
+        let mut feed = info.read_feed(&mut "".as_bytes()).unwrap();
+        assert_eq!(
+            feed.fetch_info().written_url(),
+            "http://example.com/feed?token={env:TOKEN}"
+        );
+
+        // What's logged while downloading from where it moved has no secret
+        feed.moved(&info.url, "https://example.com/rss?token=hunter2");
+        let fetch_info = feed.fetch_info();
+        assert_eq!(fetch_info.url, "https://example.com/rss?token=hunter2");
+        assert_eq!(
+            fetch_info.written_url(),
+            "https://example.com/rss?token={env:TOKEN}"
+        );
+        assert_eq!(
+            info.at("https://example.com/page").written_url(),
+            "https://example.com/page"
+        );
+    }
+
+    #[test]
+    fn test_read_comic() {
+        use chrono::TimeZone;
+        let info = parse_config(r#""Feed" <http://example.com> @ 2 new comics"#)
+            .unwrap()
+            .remove(0);
+        let events = "
+<http://example.com/1> 2018-10-01T12:00:00+00:00
+read 2018-10-02T00:00:00+00:00
+<http://example.com/2> 2018-10-03T12:00:00+00:00
+<http://example.com/3> 2018-10-04T12:00:00+00:00
+<http://example.com/4> 2018-10-05T12:00:00+00:00
+";
+        let mut feed = info.read_feed(&mut events.as_bytes()).unwrap();
+        let now = Utc.ymd(2018, 10, 6).and_hms(0, 0, 0);
+        assert!(feed.read_comic_at("http://example.com/3", now));
+        // Already read
+        assert!(!feed.read_comic_at("http://example.com/3", now));
+        assert!(!feed.read_comic_at("http://example.com/1", now));
+
+        assert_eq!(feed.new_comics(), 2);
+        assert_eq!(
+            feed.get_reading_list(),
+            vec!["http://example.com/2", "http://example.com/4"]
+        );
+        assert_eq!(
+            feed.change_lines(),
+            vec!["read <http://example.com/3> 2018-10-06T00:00:00+00:00"]
+        );
+        let text = format!("{}{}\n", events, feed.change_lines()[0]);
+        let reloaded = info.read_feed(&mut text.as_bytes()).unwrap();
+        assert_eq!(reloaded.unread_comics(), feed.unread_comics());
+        assert_eq!(reloaded.get_reading_list(), feed.get_reading_list());
+
+        // Reading the rest leaves the one read on its own read
+        feed.read_at(now);
+        assert!(feed.unread_comics().is_empty());
+        assert!(feed.get_reading_list().is_empty());
+    }
+
+    #[test]
+    fn test_random() {
+        use chrono::TimeZone;
+        let info = parse_config(r#""Feed" <http://example.com> @ random 3 comics"#)
+            .unwrap()
+            .remove(0);
+        let events: String = (1..=20)
+            .map(|n| format!("<http://example.com/{}>\n", n))
+            .collect();
+        let mut feed = info.read_feed(&mut events.as_bytes()).unwrap();
+        feed.set_seed(7);
+        let picked = feed.get_reading_list();
+        assert_eq!(picked.len(), 3);
+        assert_ne!(
+            picked,
+            vec![
+                "http://example.com/1",
+                "http://example.com/2",
+                "http://example.com/3"
+            ]
+        );
+        assert_eq!(feed.left_for_later().len(), 17);
+        feed.set_seed(8);
+        assert_ne!(feed.get_reading_list(), picked);
+        feed.set_seed(7);
+
+        // Reading leaves everything but the picks unread, wherever they are
+        feed.read_at(Utc.ymd(2018, 10, 2).and_hms(0, 0, 0));
+        assert_eq!(feed.new_comics(), 17);
+        let unread: Vec<_> = feed.unread_comics().into_iter().map(|c| &c.url).collect();
+        assert!(picked.iter().all(|url| !unread.contains(&url)));
+        let text = format!("{}{}\n", events, feed.change_lines().join("\n"));
+        let reloaded = info.read_feed(&mut text.as_bytes()).unwrap();
+        assert_eq!(reloaded.unread_comics(), feed.unread_comics());
+    }
+
+    #[test]
+    fn test_without_last_read() {
+        let history = "version 1
+<http://example.com/1> 2018-10-01T12:00:00+00:00
+<http://example.com/2> 2018-10-02T12:00:00+00:00
+read 2018-10-03T00:00:00+00:00
+held 2018-10-03T00:00:00+00:00 <http://example.com/2>
+checked 2018-10-03T12:00:00+00:00
+";
+        let (text, removed) = without_last_read(history).unwrap();
+        assert_eq!(
+            text,
+            "version 1
+<http://example.com/1> 2018-10-01T12:00:00+00:00
+<http://example.com/2> 2018-10-02T12:00:00+00:00
+checked 2018-10-03T12:00:00+00:00
+"
+        );
+        assert_eq!(
+            removed,
+            FeedEvent::Read(Utc.ymd(2018, 10, 3).and_hms(0, 0, 0))
+        );
+
+        let history = format!(
+            "{}read <http://example.com/2> 2018-10-04T00:00:00+00:00\n",
+            text
+        );
+        let (again, removed) = without_last_read(&history).unwrap();
+        assert_eq!(again, text);
+        assert_eq!(
+            removed,
+            FeedEvent::ReadComic(
+                Utc.ymd(2018, 10, 4).and_hms(0, 0, 0),
+                "http://example.com/2".into()
+            )
+        );
+        assert_eq!(without_last_read(&again), None);
+    }
+
+    #[test]
+    fn test_read_marker() {
+        use chrono::TimeZone;
+        let events = "
+<http://example.com/1> 2018-10-01T12:00:00+00:00
+read 2018-10-02T00:00:00+00:00
+<http://example.com/2> 2018-10-05T12:00:00+00:00
+<http://example.com/3> 2018-10-04T12:00:00+00:00
+<http://example.com/4>
+";
+        let now = Utc.ymd(2018, 10, 10).and_hms(0, 0, 0);
+        let last_read = |config: &str, events: &str| {
+            let info = parse_config(config).unwrap().remove(0);
+            let mut feed = info.read_feed(&mut events.as_bytes()).unwrap();
+            feed.read_at(now);
+            feed.last_read
+        };
+
+        assert_eq!(
+            last_read(r#""Feed" <http://example.com>"#, events),
+            Some(now)
+        );
+        assert_eq!(
+            last_read(
+                "read_marker publish-date\n\"Feed\" <http://example.com>",
+                events
+            ),
+            Some(Utc.ymd(2018, 10, 5).and_hms(12, 0, 0))
+        );
+        // Nothing new has a date, so fall back to now
+        assert_eq!(
+            last_read(
+                "read_marker publish-date\n\"Feed\" <http://example.com>",
+                "<http://example.com/1>"
+            ),
+            Some(now)
+        );
+    }
+
+    #[test]
+    fn test_alias() {
+        let info = parse_config(r#""New Name" <http://new.example.com> @ alias "Old Name""#)
+            .unwrap()
+            .remove(0);
+        assert_eq!(info.aliases().collect::<Vec<_>>(), vec!["Old Name"]);
+        let old = "
+<http://example.com/1>
+<http://example.com/2>
+read 2018-10-02T00:00:00+00:00
+";
+        // The new feed downloaded the whole archive again, plus one new comic
+        let new = "
+<http://example.com/1>
+<http://example.com/2>
+<http://example.com/3>
+";
+        let feed = info
+            .read_feed_with_aliases(
+                &mut new.as_bytes(),
+                &mut [("Old Name".to_string(), old.as_bytes())],
+            )
+            .unwrap();
+        assert_eq!(feed.new_comics(), 1);
+        assert_eq!(feed.comics().count(), 3);
+        assert_eq!(feed.get_reading_list(), vec!["http://example.com/3"]);
+
+        // The old name was read later, but the feed's own read marker counts
+        let synced = "
+<http://example.com/1>
+read 2018-10-01T00:00:00+00:00
+<http://example.com/2>
+";
+        let feed = info
+            .read_feed_with_aliases(
+                &mut synced.as_bytes(),
+                &mut [("Old Name".to_string(), old.as_bytes())],
+            )
+            .unwrap();
+        assert_eq!(
+            feed.last_read(),
+            Some(Utc.ymd(2018, 10, 1).and_hms(0, 0, 0))
+        );
+
+        // Errors point at the file they came from
+        let err = info
+            .read_feed_with_aliases(
+                &mut new.as_bytes(),
+                &mut [("Old Name".to_string(), "bogus".as_bytes())],
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("Error parsing feed \"Old Name\""));
+    }
+
+    #[test]
+    fn test_surprise() {
+        use chrono::TimeZone;
+        let info =
+            parse_config(r#""Feed" <http://example.com> @ surprise 1 per week @ overlap 1 comic"#)
+                .unwrap()
+                .remove(0);
+        let events = "
+<http://example.com/1>
+<http://example.com/2>
+<http://example.com/3>
+read 2018-10-01T12:00:00+00:00
+<http://example.com/4>
+";
+        let urls = |comics: Vec<&Comic>| -> Vec<String> {
+            comics.into_iter().map(|comic| comic.url.clone()).collect()
+        };
+        let mut feed = info.read_feed(&mut events.as_bytes()).unwrap();
+        let now = Utc.ymd(2018, 10, 5).and_hms(12, 0, 0);
+        // Comic 3 is already coming back because of the overlap
+        assert_eq!(
+            urls(feed.surprise_candidates_at(now)),
+            vec!["http://example.com/1", "http://example.com/2"]
+        );
+
+        feed.surprised_at("http://example.com/1", now);
+        assert!(feed.surprise_candidates_at(now).is_empty());
+        let next_week = now + Duration::weeks(1);
+        assert_eq!(
+            urls(feed.surprise_candidates_at(next_week)),
+            vec!["http://example.com/2"]
+        );
+
+        // Once every comic has had a turn, they're all candidates again
+        feed.surprised_at("http://example.com/2", next_week);
+        assert_eq!(
+            urls(feed.surprise_candidates_at(next_week + Duration::weeks(1))),
+            vec!["http://example.com/1", "http://example.com/2"]
+        );
+
+        let plain = parse_config(r#""Feed" <http://example.com>"#)
+            .unwrap()
+            .remove(0);
+        let feed = plain.read_feed(&mut events.as_bytes()).unwrap();
+        assert!(feed.surprise_candidates_at(now).is_empty());
+    }
+
+    #[test]
+    fn test_by_chapter() {
+        use chrono::TimeZone;
+        let events = "
+<http://example.com/1> 2018-10-01T12:00:00+00:00 Ch. 1, page 1
+<http://example.com/2> 2018-10-02T12:00:00+00:00 Ch. 1, page 2
+<http://example.com/3> 2018-10-03T12:00:00+00:00 Sketchbook
+<http://example.com/4> 2018-10-04T12:00:00+00:00 Ch. 2, page 1
+";
+        let load = |config: &str| {
+            let info = parse_config(config).unwrap().remove(0);
+            info.read_feed(&mut events.as_bytes()).unwrap()
+        };
+        let item = |n: usize, title: &str| Item {
+            url: format!("http://example.com/{}", n),
+            title: title.into(),
+            guid: None,
+            published: Utc.ymd(2018, 10, n as u32).and_hms(12, 0, 0),
+            language: None,
+            thumbnail: None,
+        };
+        let urls = |ns: &[usize]| -> Vec<String> {
+            ns.iter()
+                .map(|n| format!("http://example.com/{}", n))
+                .collect()
+        };
+        let now = Local.ymd(2018, 10, 10).and_hms(12, 0, 0);
+
+        // Chapter 1, with the sketchbook page that came after it, is done
+        let mut feed = load(r#""Feed" <http://example.com> @ by chapter /Ch\. (\d+)/"#);
+        assert_eq!(feed.held_back().len(), 1);
+        assert_eq!(feed.get_reading_list(), urls(&[1, 2, 3]));
+        assert!(feed.is_ready_at(now));
+
+        // Reading it leaves chapter 2 unread, but it's not ready on its own
+        feed.read_at(now.with_timezone(&Utc));
+        assert_eq!(feed.new_comics(), 1);
+        assert!(feed.get_reading_list().is_empty());
+        assert!(!feed.is_ready_at(now));
+        feed.add_new_comics(&[item(5, "Ch. 2, page 2")]);
+        assert!(!feed.is_ready_at(now));
+        feed.add_new_comics(&[item(6, "Ch. 3, page 1")]);
+        assert!(feed.is_ready_at(now));
+        assert_eq!(feed.get_reading_list(), urls(&[4, 5]));
+
+        // The held comic is still unread after loading the feed again
+        let mut written = io::Cursor::new(events.as_bytes().to_vec());
+        feed.write_changes(&mut written).unwrap();
+        let reloaded = feed
+            .info
+            .read_feed(&mut written.get_ref().as_slice())
+            .unwrap();
+        assert_eq!(reloaded.new_comics(), 3);
+        assert_eq!(reloaded.get_reading_list(), urls(&[4, 5]));
+
+        // A longer lookahead waits for more of the next chapter
+        let feed =
+            load(r#""Feed" <http://example.com> @ by chapter /Ch\. (\d+)/ lookahead 2 pages"#);
+        assert_eq!(feed.held_back().len(), 4);
+        assert!(feed.get_reading_list().is_empty());
+        assert!(!feed.is_ready_at(now));
+    }
+
+    #[test]
+    fn test_skip_before() {
+        let item = |n, day| Item {
+            url: format!("http://example.com/{}", n),
+            title: String::new(),
+            guid: None,
+            published: Local
+                .ymd(2018, 10, day)
+                .and_hms(12, 0, 0)
+                .with_timezone(&Utc),
+            language: None,
+            thumbnail: None,
+        };
+        let info = parse_config(r#""Feed" <http://example.com> @ skip before 2018-10-02"#)
+            .unwrap()
+            .remove(0);
+        let mut feed = info.read_feed(&mut io::empty()).unwrap();
+
+        // Only the first download skips anything
+        feed.add_new_comics(&[item(1, 1), item(2, 1), item(3, 2)]);
+        assert_eq!(feed.new_comics(), 1);
+        assert_eq!(feed.get_reading_list(), vec!["http://example.com/3"]);
+        feed.add_new_comics(&[item(0, 1)]);
+        assert_eq!(feed.new_comics(), 2);
+
+        // Read as of the newest comic skipped, which survives a reload
+        let mut written = io::Cursor::new(Vec::new());
+        feed.write_changes(&mut written).unwrap();
+        let reloaded = info.read_feed(&mut written.get_ref().as_slice()).unwrap();
+        assert_eq!(reloaded.new_comics(), 2);
+        assert_eq!(
+            reloaded.last_read(),
+            Some(Local.ymd(2018, 10, 1).and_hms(12, 0, 0).with_timezone(&Utc))
+        );
+    }
+
+    #[test]
+    fn test_read_up_to() {
+        use chrono::TimeZone;
+        let events = "
+<http://example.com/1> 2018-10-01T12:00:00+00:00
+<http://example.com/2> 2018-10-02T12:00:00+00:00
+<http://example.com/3> 2018-10-03T12:00:00+00:00
+";
+        let info = parse_config(r#""Feed" <http://example.com> @ open all"#)
+            .unwrap()
+            .remove(0);
+        let mut feed = info.read_feed(&mut events.as_bytes()).unwrap();
+        let now = Local.ymd(2018, 10, 10).and_hms(12, 0, 0);
+
+        // The comics after the last one read are left for next time
+        feed.read_up_to_at(now.with_timezone(&Utc), "http://example.com/1");
+        assert_eq!(feed.new_comics(), 2);
+        assert!(feed.is_ready_at(now));
+        assert_eq!(
+            feed.get_reading_list(),
+            vec!["http://example.com/2", "http://example.com/3"]
+        );
+        feed.read_up_to_at(now.with_timezone(&Utc), "http://example.com/3");
+        assert_eq!(feed.new_comics(), 0);
+        assert!(feed.get_reading_list().is_empty());
+    }
+
+    #[test]
+    fn test_next_due() {
+        use chrono::TimeZone;
+        let events = "
+<http://example.com/1> 2018-10-01T12:00:00+00:00
+read 2018-10-01T12:00:00+00:00
+<http://example.com/2> 2018-10-02T12:00:00+00:00
+";
+        let due = |config: &str, now| {
+            let info = parse_config(config).unwrap().remove(0);
+            let feed = info.read_feed(&mut events.as_bytes()).unwrap();
+            feed.next_due(now)
+        };
+        let at = |d| Utc.ymd(2018, 10, d).and_hms(12, 0, 0).with_timezone(&Local);
+
+        assert_eq!(due(r#""Feed" <http://example.com>"#, at(3)), Due::Now);
+        assert_eq!(
+            due(r#""Feed" <http://example.com> @ every 7 days"#, at(3)),
+            Due::Later {
+                from: Some(at(8)),
+                comics: 0
+            }
+        );
+        assert_eq!(
+            due(r#""Feed" <http://example.com> @ 3 new comics"#, at(3)),
+            Due::Later {
+                from: Some(at(3)),
+                comics: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_auth() {
+        let basic = Auth::Basic {
+            user: "Aladdin".into(),
+            password: "open sesame".into(),
+        };
+        assert_eq!(basic.header_value(), "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==");
+        assert_eq!(base64(b"a"), "YQ==");
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b""), "");
+
+        let mut info =
+            parse_config("header Authorization: Basic old\n\"Feed\" <http://example.com>")
+                .unwrap()
+                .remove(0);
+        info.auth = Some(Auth::Bearer("abc123".into()));
+        assert_eq!(
+            info.request_headers(),
+            vec![("Authorization".to_string(), "Bearer abc123".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_max_per_session() {
+        use chrono::TimeZone;
+        let events = "
+<http://example.com/1> 2018-10-01T12:00:00+00:00
+<http://example.com/2> 2018-10-02T12:00:00+00:00
+<http://example.com/3> 2018-10-03T12:00:00+00:00
+<http://example.com/4> 2018-10-04T12:00:00+00:00
+<http://example.com/5> 2018-10-05T12:00:00+00:00
+";
+        let info = parse_config(r#""Feed" <http://example.com> @ 3 new comics @ max 2 comics"#)
+            .unwrap()
+            .remove(0);
+        let mut feed = info.read_feed(&mut events.as_bytes()).unwrap();
+        let urls = |ns: &[usize]| -> Vec<String> {
+            ns.iter()
+                .map(|n| format!("http://example.com/{}", n))
+                .collect()
+        };
+        let now = Local.ymd(2018, 10, 10).and_hms(12, 0, 0);
+
+        // The backlog opens two at a time, oldest first, and what's left over
+        // still counts towards the feed being ready
+        assert!(feed.is_ready_at(now));
+        assert_eq!(feed.get_reading_list(), urls(&[1, 2]));
+        assert_eq!(feed.left_for_later().len(), 3);
+        feed.read_at(now.with_timezone(&Utc));
+        assert_eq!(feed.new_comics(), 3);
+        assert!(feed.is_ready_at(now));
+        assert_eq!(feed.get_reading_list(), urls(&[3, 4]));
+
+        // Loading the feed again picks up where it left off
+        let mut written = io::Cursor::new(events.as_bytes().to_vec());
+        feed.write_changes(&mut written).unwrap();
+        let reloaded = info.read_feed(&mut written.get_ref().as_slice()).unwrap();
+        assert!(reloaded.is_ready_at(now));
+        assert_eq!(reloaded.get_reading_list(), urls(&[3, 4]));
+
+        // Down to one, which isn't enough for `@ 3 new comics`
+        feed.read_at(now.with_timezone(&Utc));
+        assert!(!feed.is_ready_at(now));
+        assert_eq!(feed.get_reading_list(), urls(&[5]));
+    }
+
+    #[test]
+    fn test_remind_after() {
+        use chrono::TimeZone;
+        let day = |d| Utc.ymd(2018, 10, d).and_hms(12, 0, 0);
+        let info = parse_config(r#""Feed" <http://example.com> @ remind after 3 days"#)
+            .unwrap()
+            .remove(0);
+        let events = "
+<http://example.com/1>
+ready 2018-10-01T12:00:00+00:00
+";
+        let mut feed = info.read_feed(&mut events.as_bytes()).unwrap();
+
+        assert_eq!(feed.overdue_at(day(3)), None);
+        assert_eq!(feed.overdue_at(day(4)), Some(Duration::days(3)));
+        // Still counts from when it first became ready
+        feed.waiting_at(day(4));
+        assert_eq!(feed.overdue_at(day(5)), Some(Duration::days(4)));
+
+        feed.read_at(day(5));
+        assert_eq!(feed.overdue_at(day(30)), None);
+        feed.waiting_at(day(30));
+        assert_eq!(feed.overdue_at(day(31)), None);
+
+        // No reminders without the policy
+        let info = parse_config(r#""Feed" <http://example.com>"#)
+            .unwrap()
+            .remove(0);
+        let feed = info.read_feed(&mut events.as_bytes()).unwrap();
+        assert_eq!(feed.overdue_at(day(30)), None);
+    }
+
+    #[test]
+    fn test_next_scheduled() {
+        use chrono::TimeZone;
+        let events = "read 2018-10-01T12:00:00+00:00\n";
+        let feed = |config: &str| {
+            let info = parse_config(config).unwrap().remove(0);
+            info.read_feed(&mut events.as_bytes()).unwrap()
+        };
+        // 2018-10-01 was a Monday
+        let day = |d| Utc.ymd(2018, 10, d).and_hms(12, 0, 0).with_timezone(&Local);
+        let every = feed(r#""Feed" <http://example.com> @ every 3 days"#);
+        assert_eq!(every.next_scheduled(day(2)), Some(day(4)));
+        let friday = feed(r#""Feed" <http://example.com> @ on Friday"#);
+        assert_eq!(friday.next_scheduled(day(2)), Some(day(5)));
+        assert_eq!(friday.next_scheduled(day(6)), Some(day(6)));
+    }
+
+    #[test]
+    fn test_check_every() {
+        use chrono::TimeZone;
+        let events = "
+<http://example.com/1>
+checked 2018-10-01T12:00:00+00:00
+read 2018-10-01T13:00:00+00:00
+<http://example.com/2>
+";
+        let feed = |config: &str| {
+            let info = parse_config(config).unwrap().remove(0);
+            info.read_feed(&mut events.as_bytes()).unwrap()
+        };
+        let day = |d| Utc.ymd(2018, 10, d).and_hms(12, 0, 0);
+
+        let daily = feed(r#""Feed" <http://example.com>"#);
+        assert!(daily.needs_check_at(day(2)));
+
+        let mut weekly = feed(r#""Feed" <http://example.com> @ check every 7 days"#);
+        assert!(!weekly.needs_check_at(day(7)));
+        assert!(weekly.needs_check_at(day(8)));
+        // Checking doesn't affect reading
+        assert!(weekly.is_ready_at(day(7).with_timezone(&Local)));
+        weekly.checked_at(day(8));
+        assert!(!weekly.needs_check_at(day(9)));
+
+        // Never checked before, so fetch right away
+        let info = parse_config(r#""Feed" <http://example.com> @ check every 7 days"#)
+            .unwrap()
+            .remove(0);
+        assert!(info
+            .read_feed(&mut io::empty())
+            .unwrap()
+            .needs_check_at(day(1)));
+    }
+
+    #[test]
+    fn test_overlap() {
+        let events = "
+<http://example.com/1> 2018-10-01T12:00:00+00:00
+<http://example.com/2> 2018-10-05T12:00:00+00:00
+<http://example.com/3> 2018-10-06T12:00:00+00:00
+read 2018-10-07T00:00:00+00:00
+<http://example.com/4> 2018-10-08T12:00:00+00:00
+";
+        let urls = |ids: &[u32]| -> Vec<String> {
+            ids.iter()
+                .map(|id| format!("http://example.com/{}", id))
+                .collect()
+        };
+
+        assert_eq!(
+            reading_list(r#""Feed" <http://example.com>"#, events),
+            urls(&[4])
+        );
+        assert_eq!(
+            reading_list(r#""Feed" <http://example.com> @ overlap 1 comic"#, events),
+            urls(&[3, 4])
+        );
+        assert_eq!(
+            reading_list(r#""Feed" <http://example.com> @ overlap 3 days"#, events),
+            urls(&[2, 3, 4])
+        );
+        // Both kinds of overlap together reach back as far as either would
+        assert_eq!(
+            reading_list(
+                r#""Feed" <http://example.com> @ overlap 1 day @ overlap 2 comics"#,
+                events
+            ),
+            urls(&[2, 3, 4])
+        );
+        assert_eq!(
+            reading_list(r#""Feed" <http://example.com> @ overlap 1 day"#, events),
+            urls(&[3, 4])
+        );
+
+        // Old events without dates aren't included by time-based overlap
+        let undated = "
+<http://example.com/3>
+read 2018-10-07T00:00:00+00:00
+<http://example.com/4> 2018-10-08T12:00:00+00:00
+";
+        assert_eq!(
+            reading_list(r#""Feed" <http://example.com> @ overlap 3 days"#, undated),
+            urls(&[4])
+        );
+    }
+}
@@ -0,0 +1,704 @@
+use chrono::{DateTime, Utc};
+
+use crate::crawl::crawl;
+use crate::error::Error;
+use crate::extract::Item;
+use crate::extract::{extract_items_in, feed_links, resolve_link};
+use crate::feed::{Feed, FeedInfo};
+use crate::html::Selector;
+
+/// Downloads feed documents. The core never touches the network itself, so
+/// the CLI provides an HTTP implementation and other frontends can provide
+/// their own.
+pub trait Fetcher {
+    /// Download the document for `info`, returning its text.
+    fn fetch(&self, info: &FeedInfo) -> Result<String, Error>;
+
+    /// Like `fetch`, but `None` if the document hasn't changed since it was
+    /// last downloaded. Fetchers that can't tell always download it.
+    fn fetch_if_modified(&self, info: &FeedInfo) -> Result<Option<String>, Error> {
+        self.fetch(info).map(Some)
+    }
+
+    /// Download the document at `url` for the feed `info`, like a feed its
+    /// web page links to. Fetchers that send the feed's own headers, which may
+    /// be a login, must only send them to the host of the feed's own URL.
+    fn fetch_at(&self, info: &FeedInfo, url: &str) -> Result<String, Error> {
+        self.fetch(&info.at(url))
+    }
+
+    /// Download a web page that goes with the feed `info`, like one of its
+    /// comics, rather than the feed itself. Fetchers that remember something
+    /// about each download of a feed should leave this out of it.
+    fn fetch_page(&self, info: &FeedInfo, url: &str) -> Result<String, Error> {
+        self.fetch_at(info, url)
+    }
+
+    /// Whether the page at `url` is gone for good, like when it's a 404.
+    /// Fetchers that can't tell say it isn't.
+    fn is_gone(&self, _url: &str) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    /// Where the last download from `url` was permanently redirected to, if
+    /// it was. Fetchers that don't follow redirects themselves say it wasn't.
+    fn moved_to(&self, _url: &str) -> Option<String> {
+        None
+    }
+}
+
+/// What came of downloading a feed, beyond the new comics.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Update {
+    /// The unread comics that were withdrawn.
+    pub withdrawn: Vec<String>,
+    /// How many of the oldest comics in the download were skipped to stay
+    /// within the feed's `item_limit`.
+    pub skipped: usize,
+    /// The feed is paused because it seemed to republish its archive, and
+    /// nothing new was recorded.
+    pub paused: bool,
+    /// The feed hadn't changed since it was last downloaded, so there was
+    /// nothing to look through.
+    pub not_modified: bool,
+    /// The feed's URL was a web page, and this is the feed it links to,
+    /// which is used from now on.
+    pub discovered: Option<String>,
+    /// The other feeds that page links to, if it links to more than one.
+    pub other_feeds: Vec<String>,
+    /// How many older comics `@ crawl archive` found.
+    pub crawled: usize,
+    /// The feed permanently redirected here, which is used from now on. Any
+    /// secret from `{env:NAME}` in it is written as `{env:NAME}`.
+    pub moved: Option<String>,
+    /// The feed permanently redirected to a URL with a secret from
+    /// `{env:NAME}` in it that can't be told apart from the rest, so the
+    /// move wasn't remembered.
+    pub moved_secretly: bool,
+}
+
+/// When at least this percent of a download is comics that look like ones
+/// already seen, but at new URLs, the feed is taken to have reset.
+pub const RESET_PERCENT: usize = 50;
+
+/// Resets are only noticed when at least this many comics came back, so a
+/// couple of reposts don't pause a feed.
+pub const RESET_MIN_COMICS: usize = 5;
+
+/// Pull the comics out of a download of `feed`, in the order it's known to
+/// list them in. The first time the dates make the order clear, it's recorded.
+fn extract_items_ordered(
+    feed: &mut Feed,
+    content: &str,
+    now: DateTime<Utc>,
+) -> Result<Vec<Item>, Error> {
+    let known = feed.list_order();
+    let (items, order) = extract_items_in(&feed.info, content, now, known)?;
+    if let (None, Some(order)) = (known, order) {
+        feed.ordered(order);
+    }
+    Ok(items)
+}
+
+/// Look for a feed linked from `page`, the web page downloaded from the
+/// feed's URL, and download the first one that works. The feed remembers
+/// which one it was.
+fn discover<F: Fetcher + ?Sized>(
+    fetcher: &F,
+    feed: &mut Feed,
+    page: &str,
+    now: DateTime<Utc>,
+    update: &mut Update,
+) -> Result<Option<String>, Error> {
+    let links: Vec<String> = feed_links(page)
+        .iter()
+        .filter_map(|link| resolve_link(&feed.info.url, link))
+        .collect();
+    for link in &links {
+        let found = fetcher.fetch_at(&feed.info, link).and_then(|content| {
+            extract_items_in(&feed.info.at(link), &content, now, None)?;
+            Ok(content)
+        });
+        match found {
+            Ok(content) => {
+                debug!(
+                    "\"{}\": <{}> is a web page, using the feed it links to, <{}>",
+                    feed.info.name,
+                    feed.info.written_url(),
+                    feed.info.at(link).written_url()
+                );
+                feed.discovered(link);
+                update.discovered = Some(link.clone());
+                update.other_feeds = links.iter().filter(|x| *x != link).cloned().collect();
+                return Ok(Some(content));
+            }
+            Err(err) => debug!(
+                "Skipping feed link <{}>: {}",
+                feed.info.at(link).written_url(),
+                err
+            ),
+        }
+    }
+    Ok(None)
+}
+
+fn looks_reset(feed: &Feed, items: &[Item]) -> bool {
+    let republished = feed.republished(items);
+    republished >= RESET_MIN_COMICS && republished * 100 >= items.len() * RESET_PERCENT
+}
+
+/// Download a feed and record any new comics in it, as of `now`. Unread
+/// comics that vanished from the feed and whose links are gone are marked
+/// withdrawn. Only the newest comics up to the feed's `item_limit` are
+/// considered, so a feed that suddenly lists its whole archive doesn't flood
+/// the history. If the download looks like the site republished its archive
+/// under new URLs, the feed is paused until `accept_reset`. A feed that
+/// already has comics is only downloaded if it changed, when the fetcher can
+/// tell. If the feed permanently redirects, the new URL is remembered.
+pub fn update_feed<F: Fetcher + ?Sized>(
+    fetcher: &F,
+    feed: &mut Feed,
+    now: DateTime<Utc>,
+) -> Result<Update, Error> {
+    let info = feed.fetch_info();
+    debug!("Fetching \"{}\" from <{}>", info.name, info.written_url());
+    // Without any comics, an unchanged feed still has something to offer
+    let content = if feed.comics().next().is_some() {
+        fetcher.fetch_if_modified(&info)?
+    } else {
+        Some(fetcher.fetch(&info)?)
+    };
+    let mut update = Update::default();
+    if let Some(url) = fetcher.moved_to(&info.url) {
+        match feed.info.saved_url(&url) {
+            Some(saved) => {
+                debug!(
+                    "\"{}\": <{}> has moved to <{}>",
+                    feed.info.name,
+                    info.written_url(),
+                    saved
+                );
+                feed.moved(&info.url, &url);
+                update.moved = Some(saved);
+            }
+            None => update.moved_secretly = true,
+        }
+    }
+    let content = match content {
+        Some(content) => content,
+        None => {
+            debug!("\"{}\" hasn't changed", feed.info.name);
+            feed.checked_at(now);
+            return Ok(Update {
+                not_modified: true,
+                ..update
+            });
+        }
+    };
+    let items = match extract_items_ordered(feed, &content, now) {
+        Ok(items) => items,
+        // A web page at the configured URL may link to the real feed
+        Err(err) if info.url == feed.info.url => {
+            match discover(fetcher, feed, &content, now, &mut update)? {
+                Some(content) => extract_items_ordered(feed, &content, now)?,
+                None => return Err(err),
+            }
+        }
+        Err(err) => return Err(err),
+    };
+    feed.checked_at(now);
+
+    if feed.reset_pending() || looks_reset(feed, &items) {
+        debug!("\"{}\" seems to have reset, pausing it", feed.info.name);
+        feed.reset_at(now);
+        return Ok(Update {
+            paused: true,
+            ..update
+        });
+    }
+
+    let missing: Vec<String> = feed
+        .missing_unread(&items)
+        .into_iter()
+        .map(|comic| comic.url.clone())
+        .collect();
+    for url in missing {
+        match fetcher.is_gone(&url) {
+            Ok(true) => {
+                feed.withdrawn_at(&url, now);
+                update.withdrawn.push(url);
+            }
+            Ok(false) => (),
+            Err(err) => debug!("Couldn't tell if <{}> is still there: {}", url, err),
+        }
+    }
+
+    if let Some(limit) = feed.info.item_limit() {
+        update.skipped = items.len().saturating_sub(limit);
+    }
+    if update.skipped > 0 {
+        debug!(
+            "\"{}\" listed {} comics, skipping the oldest {}",
+            feed.info.name,
+            items.len(),
+            update.skipped
+        );
+    }
+    let mut items = items[update.skipped..].to_vec();
+    if let (Some(selector), None) = (feed.info.crawl_archive(), feed.comics().next()) {
+        let older = crawl_before(fetcher, feed, &items, selector);
+        update.crawled = older.len();
+        items.splice(0..0, older);
+    }
+    feed.add_new_comics(&items);
+    Ok(update)
+}
+
+/// The comics `@ crawl archive` finds before the oldest of `items`, the
+/// first download of a new feed. Their dates aren't known, so they're given
+/// the oldest date in the download.
+fn crawl_before<F: Fetcher + ?Sized>(
+    fetcher: &F,
+    feed: &Feed,
+    items: &[Item],
+    selector: Option<&str>,
+) -> Vec<Item> {
+    let oldest = match items.first() {
+        Some(oldest) => oldest,
+        None => return Vec::new(),
+    };
+    let selector = selector.and_then(Selector::parse);
+    let known = items.iter().map(|item| item.url.as_str()).collect();
+    debug!(
+        "\"{}\": crawling the archive back from <{}>",
+        feed.info.name, oldest.url
+    );
+    crawl(fetcher, &feed.info, &oldest.url, selector.as_ref(), &known)
+        .into_iter()
+        .map(|url| feed.info.rewrite_url(&url))
+        .filter(|url| feed.info.filter_url(url))
+        .map(|url| Item {
+            url,
+            title: String::new(),
+            guid: None,
+            published: oldest.published,
+            language: None,
+            thumbnail: None,
+        })
+        .collect()
+}
+
+/// Download a feed that's paused by a reset, and take what's in it now as its
+/// comics from here on, without any of them counting as new. Returns how many
+/// comics there were.
+pub fn accept_reset<F: Fetcher + ?Sized>(
+    fetcher: &F,
+    feed: &mut Feed,
+    now: DateTime<Utc>,
+) -> Result<usize, Error> {
+    let content = fetcher.fetch(&feed.fetch_info())?;
+    let items = extract_items_ordered(feed, &content, now)?;
+    feed.checked_at(now);
+    feed.accept_reset_at(&items, now);
+    Ok(items.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_config;
+    use chrono::{Local, TimeZone};
+    use std::io;
+
+    struct Fixture(&'static str);
+
+    impl Fetcher for Fixture {
+        fn fetch(&self, _info: &FeedInfo) -> Result<String, Error> {
+            let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), self.0);
+            Ok(std::fs::read_to_string(&path)?)
+        }
+
+        fn is_gone(&self, url: &str) -> Result<bool, Error> {
+            Ok(url.ends_with("/gone"))
+        }
+    }
+
+    #[test]
+    fn test_update_feed() {
+        let info = parse_config(r#""Fixture" <http://example.com/feed> @ check every 2 days"#)
+            .unwrap()
+            .remove(0);
+        let mut feed = info.read_feed(&mut io::empty()).unwrap();
+        let now = Utc.ymd(2018, 1, 5).and_hms(0, 0, 0);
+
+        update_feed(&Fixture("atom.xml"), &mut feed, now).unwrap();
+        assert_eq!(feed.new_comics(), 2);
+        assert!(feed.is_ready_at(now.with_timezone(&Local)));
+        assert!(!feed.needs_check_at(now));
+
+        // Nothing new the second time around
+        update_feed(&Fixture("atom.xml"), &mut feed, now).unwrap();
+        assert_eq!(feed.new_comics(), 2);
+
+        assert!(update_feed(&Fixture("missing.xml"), &mut feed, now).is_err());
+    }
+
+    #[test]
+    fn test_list_order() {
+        use crate::extract::ListOrder;
+        let info = parse_config(r#""Fixture" <http://example.com/feed>"#)
+            .unwrap()
+            .remove(0);
+        let mut feed = info.read_feed(&mut io::empty()).unwrap();
+        let now = Utc.ymd(2018, 1, 5).and_hms(0, 0, 0);
+
+        update_feed(&Fixture("oldest-first.rss"), &mut feed, now).unwrap();
+        assert_eq!(feed.list_order(), Some(ListOrder::OldestFirst));
+        assert_eq!(
+            feed.get_reading_list(),
+            (1..=4)
+                .map(|n| format!("http://archive.example.com/{}", n))
+                .collect::<Vec<_>>()
+        );
+
+        // Remembered, even though these dates alone would say otherwise
+        let mut events = Vec::new();
+        feed.write_changes(&mut io::Cursor::new(&mut events))
+            .unwrap();
+        let mut feed = info.read_feed(&mut &events[..]).unwrap();
+        assert_eq!(feed.list_order(), Some(ListOrder::OldestFirst));
+        update_feed(&Fixture("atom.xml"), &mut feed, now).unwrap();
+        assert_eq!(feed.list_order(), Some(ListOrder::OldestFirst));
+        assert_eq!(
+            feed.comics().last().map(|comic| comic.url.as_str()),
+            Some("http://atom.example.com/comic/1")
+        );
+    }
+
+    /// Serves a web page at the feed's URL, linking to a broken feed and then
+    /// the fixture, and remembers what was asked for.
+    struct Site(std::cell::RefCell<Vec<String>>);
+
+    impl Fetcher for Site {
+        fn fetch(&self, info: &FeedInfo) -> Result<String, Error> {
+            self.0.borrow_mut().push(info.url.clone());
+            match info.url.as_str() {
+                "http://example.com/" => Ok(r#"<html><head>
+<link rel="alternate" type="application/rss+xml" href="/missing.xml">
+<link rel="alternate" type="application/atom+xml" href="/atom.xml">
+</head></html>"#
+                    .into()),
+                "http://example.com/atom.xml" => Fixture("atom.xml").fetch(info),
+                _ => Fixture("missing.xml").fetch(info),
+            }
+        }
+
+        fn fetch_at(&self, info: &FeedInfo, url: &str) -> Result<String, Error> {
+            // Still the page's URL, so its headers don't go to other hosts
+            assert_eq!(info.url, "http://example.com/");
+            self.fetch(&FeedInfo {
+                url: url.into(),
+                ..info.clone()
+            })
+        }
+    }
+
+    #[test]
+    fn test_discover() {
+        let info = parse_config(r#""Site" <http://example.com/>"#)
+            .unwrap()
+            .remove(0);
+        let mut feed = info.read_feed(&mut io::empty()).unwrap();
+        let now = Utc.ymd(2018, 1, 5).and_hms(0, 0, 0);
+        let site = Site(Default::default());
+
+        let update = update_feed(&site, &mut feed, now).unwrap();
+        assert_eq!(
+            update.discovered.as_deref(),
+            Some("http://example.com/atom.xml")
+        );
+        assert_eq!(update.other_feeds, vec!["http://example.com/missing.xml"]);
+        assert_eq!(feed.new_comics(), 2);
+
+        // Remembered, so the page isn't downloaded again
+        let mut events = Vec::new();
+        feed.write_changes(&mut io::Cursor::new(&mut events))
+            .unwrap();
+        let mut feed = info.read_feed(&mut &events[..]).unwrap();
+        assert_eq!(feed.feed_url(), "http://example.com/atom.xml");
+        site.0.borrow_mut().clear();
+        let update = update_feed(&site, &mut feed, now).unwrap();
+        assert_eq!(update.discovered, None);
+        assert_eq!(*site.0.borrow(), vec!["http://example.com/atom.xml"]);
+
+        // Until the config points somewhere else
+        let moved = FeedInfo {
+            url: "http://example.com/other/".into(),
+            ..info
+        };
+        let feed = moved.read_feed(&mut &events[..]).unwrap();
+        assert_eq!(feed.feed_url(), "http://example.com/other/");
+    }
+
+    /// Serves the fixture, with the feed's old URL permanently redirecting to
+    /// its new one.
+    struct Redirect;
+
+    impl Fetcher for Redirect {
+        fn fetch(&self, info: &FeedInfo) -> Result<String, Error> {
+            Fixture("atom.xml").fetch(info)
+        }
+
+        fn moved_to(&self, url: &str) -> Option<String> {
+            match url {
+                "http://example.com/feed" => Some("https://example.com/feed".into()),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_moved() {
+        let info = parse_config(r#""Moving" <http://example.com/feed>"#)
+            .unwrap()
+            .remove(0);
+        let mut feed = info.read_feed(&mut io::empty()).unwrap();
+        let now = Utc.ymd(2018, 1, 5).and_hms(0, 0, 0);
+
+        let update = update_feed(&Redirect, &mut feed, now).unwrap();
+        assert_eq!(update.moved.as_deref(), Some("https://example.com/feed"));
+        assert_eq!(feed.new_comics(), 2);
+
+        // Remembered, so the old URL isn't asked for again
+        let mut events = Vec::new();
+        feed.write_changes(&mut io::Cursor::new(&mut events))
+            .unwrap();
+        let mut feed = info.read_feed(&mut &events[..]).unwrap();
+        assert_eq!(feed.feed_url(), "https://example.com/feed");
+        assert_eq!(feed.canonical_url(), "https://example.com/feed");
+        let update = update_feed(&Redirect, &mut feed, now).unwrap();
+        assert_eq!(update.moved, None);
+    }
+
+    /// Serves the fixture as the feed, and archive pages for the comics in it
+    /// going back to page -1.
+    struct Archive;
+
+    impl Fetcher for Archive {
+        fn fetch(&self, info: &FeedInfo) -> Result<String, Error> {
+            Fixture("atom.xml").fetch(info)
+        }
+
+        fn fetch_page(&self, _info: &FeedInfo, url: &str) -> Result<String, Error> {
+            let n: i32 = url.rsplit('/').next().unwrap().parse().unwrap();
+            if n == -1 {
+                return Ok("<p>The first page</p>".into());
+            }
+            Ok(format!("<a rel='prev' href='{}'>Back</a>", n - 1))
+        }
+    }
+
+    #[test]
+    fn test_crawl_archive() {
+        let info = parse_config(r#""Archive" <http://example.com/feed> @ crawl archive"#)
+            .unwrap()
+            .remove(0);
+        let mut feed = info.read_feed(&mut io::empty()).unwrap();
+        let now = Utc.ymd(2018, 1, 5).and_hms(0, 0, 0);
+
+        let update = update_feed(&Archive, &mut feed, now).unwrap();
+        assert_eq!(update.crawled, 2);
+        assert_eq!(
+            feed.get_reading_list(),
+            vec![
+                "http://atom.example.com/comic/-1",
+                "http://atom.example.com/comic/0",
+                "http://atom.example.com/comic/1",
+                "http://atom.example.com/comic/2",
+            ]
+        );
+
+        // Only a new feed is crawled
+        let update = update_feed(&Archive, &mut feed, now).unwrap();
+        assert_eq!(update.crawled, 0);
+    }
+
+    /// Serves the fixture only to fetchers that can't ask if it changed.
+    struct Unchanged(&'static str);
+
+    impl Fetcher for Unchanged {
+        fn fetch(&self, info: &FeedInfo) -> Result<String, Error> {
+            Fixture(self.0).fetch(info)
+        }
+
+        fn fetch_if_modified(&self, _info: &FeedInfo) -> Result<Option<String>, Error> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn test_not_modified() {
+        let info = parse_config(r#""Fixture" <http://example.com/feed>"#)
+            .unwrap()
+            .remove(0);
+        let mut feed = info.read_feed(&mut io::empty()).unwrap();
+        let now = Utc.ymd(2018, 1, 5).and_hms(0, 0, 0);
+
+        // A feed with no comics yet is always downloaded
+        let update = update_feed(&Unchanged("atom.xml"), &mut feed, now).unwrap();
+        assert!(!update.not_modified);
+        assert_eq!(feed.new_comics(), 2);
+
+        let later = now + chrono::Duration::days(1);
+        let update = update_feed(&Unchanged("atom.xml"), &mut feed, later).unwrap();
+        assert!(update.not_modified);
+        assert_eq!(feed.new_comics(), 2);
+        assert_eq!(
+            feed.events().last(),
+            Some(&crate::feed::FeedEvent::Checked(later))
+        );
+    }
+
+    #[test]
+    fn test_reset() {
+        let info = parse_config(r#""Fixture" <http://example.com/feed>"#)
+            .unwrap()
+            .remove(0);
+        let mut events = String::new();
+        for n in 1..=5 {
+            events.push_str(&format!(
+                "<http://old.example.com/?p={}> 2018-01-0{}T12:00:00+00:00 Page {}\n",
+                n, n, n
+            ));
+        }
+        events.push_str("read 2018-01-05T20:00:00+00:00\n");
+        let mut feed = info.read_feed(&mut events.as_bytes()).unwrap();
+        let now = Utc.ymd(2018, 1, 8).and_hms(0, 0, 0);
+
+        // Pages 1 to 5 are back at new URLs, which is most of the feed
+        let update = update_feed(&Fixture("republished.rss"), &mut feed, now).unwrap();
+        assert!(update.paused);
+        assert!(feed.reset_pending());
+        assert_eq!(feed.new_comics(), 0);
+        assert!(!feed.is_ready_at(now.with_timezone(&Local)));
+
+        // Still paused after a reload, and on the next fetch
+        let mut file = io::Cursor::new(events.into_bytes());
+        feed.write_changes(&mut file).unwrap();
+        let mut feed = info.read_feed(&mut file.get_ref().as_slice()).unwrap();
+        assert!(feed.reset_pending());
+        let update = update_feed(&Fixture("republished.rss"), &mut feed, now).unwrap();
+        assert!(update.paused);
+
+        // Accepting takes everything in the feed as read
+        assert_eq!(
+            accept_reset(&Fixture("republished.rss"), &mut feed, now).unwrap(),
+            7
+        );
+        feed.write_changes(&mut file).unwrap();
+        let mut feed = info.read_feed(&mut file.get_ref().as_slice()).unwrap();
+        assert!(!feed.reset_pending());
+        assert_eq!(feed.new_comics(), 0);
+        assert!(feed.get_reading_list().is_empty());
+        let update = update_feed(&Fixture("republished.rss"), &mut feed, now).unwrap();
+        assert_eq!(update, Update::default());
+        assert_eq!(feed.new_comics(), 0);
+    }
+
+    #[test]
+    fn test_item_limit() {
+        let config = r#"max_items_per_fetch 1
+"Limited" <http://example.com/feed>
+"Policy" <http://example.com/feed> @ max 5 comics per fetch
+max_items_per_fetch
+"Unlimited" <http://example.com/feed>"#;
+        let now = Utc.ymd(2018, 1, 5).and_hms(0, 0, 0);
+        let fetched: Vec<_> = parse_config(config)
+            .unwrap()
+            .into_iter()
+            .map(|info| {
+                let mut feed = info.read_feed(&mut io::empty()).unwrap();
+                let update = update_feed(&Fixture("atom.xml"), &mut feed, now).unwrap();
+                (update.skipped, feed.get_reading_list())
+            })
+            .collect();
+        let both = vec![
+            "http://atom.example.com/comic/1".to_string(),
+            "http://atom.example.com/comic/2".to_string(),
+        ];
+        assert_eq!(
+            fetched,
+            vec![
+                (1, vec!["http://atom.example.com/comic/2".to_string()]),
+                (0, both.clone()),
+                (0, both),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_withdrawn() {
+        let info = parse_config(r#""Fixture" <http://example.com/feed>"#)
+            .unwrap()
+            .remove(0);
+        let events = "
+<http://atom.example.com/comic/old> 2017-12-01T12:00:00+00:00
+<http://atom.example.com/comic/gone> 2018-01-02T18:00:00+00:00
+<http://atom.example.com/comic/moved> 2018-01-02T20:00:00+00:00
+";
+        let mut feed = info.read_feed(&mut events.as_bytes()).unwrap();
+        let now = Utc.ymd(2018, 1, 5).and_hms(0, 0, 0);
+
+        // None of them are in the feed anymore, but the old one was just
+        // pushed out, and the moved one's link still works
+        let withdrawn = update_feed(&Fixture("atom.xml"), &mut feed, now)
+            .unwrap()
+            .withdrawn;
+        assert_eq!(withdrawn, vec!["http://atom.example.com/comic/gone"]);
+        assert_eq!(feed.new_comics(), 4);
+        let reading_list = feed.get_reading_list();
+        assert_eq!(reading_list.len(), 4);
+        assert!(!reading_list.contains(&withdrawn[0]));
+
+        let mut file = io::Cursor::new(events.as_bytes().to_vec());
+        feed.write_changes(&mut file).unwrap();
+        let feed = info.read_feed(&mut file.get_ref().as_slice()).unwrap();
+        assert_eq!(feed.new_comics(), 4);
+        assert_eq!(feed.get_reading_list(), reading_list);
+        assert_eq!(
+            feed.withdrawn().collect::<Vec<_>>(),
+            vec!["http://atom.example.com/comic/gone"]
+        );
+    }
+
+    #[test]
+    fn test_rewrite_url() {
+        let info = parse_config(
+            r#""Fixture" <http://example.com/feed> @ rewrite url |^http://atom|https://atom|"#,
+        )
+        .unwrap()
+        .remove(0);
+        let mut feed = info.read_feed(&mut io::empty()).unwrap();
+        let now = Utc.ymd(2018, 1, 5).and_hms(0, 0, 0);
+        update_feed(&Fixture("atom.xml"), &mut feed, now).unwrap();
+        assert_eq!(
+            feed.get_reading_list(),
+            vec![
+                "https://atom.example.com/comic/1",
+                "https://atom.example.com/comic/2"
+            ]
+        );
+
+        // Comics recorded before the rewrite, or with tracking parameters,
+        // aren't new again
+        let events = "
+<http://atom.example.com/comic/1?utm_source=rss> 2018-01-01T12:00:00+00:00
+<http://atom.example.com/comic/2> 2018-01-02T12:00:00+00:00
+read 2018-01-03T12:00:00+00:00
+";
+        let mut feed = info.read_feed(&mut events.as_bytes()).unwrap();
+        update_feed(&Fixture("atom.xml"), &mut feed, now).unwrap();
+        assert_eq!(feed.new_comics(), 0);
+        assert_eq!(feed.comics().count(), 2);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::feed::Comic;
+
+/// How a feed and its comics are written out when feedburst lists them, like
+/// in reminders and the reading summary. Set per feed with the `format`
+/// directive, and shared by everything that prints comics so they all agree.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Format {
+    /// Titles longer than this many characters are cut short, ellipsis
+    /// included.
+    pub max_title_length: Option<usize>,
+    /// Leave out comic URLs, unless a comic has no title to show instead.
+    pub hide_urls: bool,
+    /// Text to put in front of the feed's name, like an emoji to tell groups
+    /// of feeds apart.
+    pub prefix: Option<String>,
+}
+
+impl Format {
+    /// The feed's name with the prefix, if any.
+    pub fn feed_name(&self, name: &str) -> String {
+        match self.prefix {
+            Some(ref prefix) => format!("{} {}", prefix, name),
+            None => name.into(),
+        }
+    }
+
+    /// The title cut down to `max_title_length` characters.
+    pub fn title(&self, title: &str) -> String {
+        match self.max_title_length {
+            Some(max) if title.chars().count() > max => {
+                if max == 0 {
+                    return String::new();
+                }
+                let mut short: String = title.chars().take(max - 1).collect();
+                short.truncate(short.trim_end().len());
+                short.push('…');
+                short
+            }
+            _ => title.into(),
+        }
+    }
+
+    /// One line describing a comic.
+    pub fn comic(&self, comic: &Comic) -> String {
+        match comic.title {
+            Some(ref title) if self.hide_urls => self.title(title),
+            Some(ref title) => format!("{} <{}>", self.title(title), comic.url),
+            None => format!("<{}>", comic.url),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn comic(title: Option<&str>) -> Comic {
+        Comic {
+            url: "http://example.com/1".into(),
+            published: None,
+            title: title.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        let plain = Format::default();
+        assert_eq!(plain.feed_name("Oglaf"), "Oglaf");
+        assert_eq!(
+            plain.comic(&comic(Some("Page 1"))),
+            "Page 1 <http://example.com/1>"
+        );
+        assert_eq!(plain.comic(&comic(None)), "<http://example.com/1>");
+
+        let short = Format {
+            max_title_length: Some(8),
+            hide_urls: true,
+            prefix: Some("📚".into()),
+        };
+        assert_eq!(short.feed_name("Oglaf"), "📚 Oglaf");
+        assert_eq!(short.title("Chapter 1: Beginnings"), "Chapter…");
+        assert_eq!(short.title("Page 1 of many"), "Page 1…");
+        assert_eq!(short.title("Page 12"), "Page 12");
+        assert_eq!(short.title("Ünïcödé titles"), "Ünïcödé…");
+        assert_eq!(short.comic(&comic(Some("Page 1"))), "Page 1");
+        // Without a title, the URL is all there is to show
+        assert_eq!(short.comic(&comic(None)), "<http://example.com/1>");
+    }
+}
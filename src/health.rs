@@ -0,0 +1,370 @@
+//! How each feed's downloads have been going, kept next to its history, and
+//! `feedburst doctor`, which points out the feeds that need looking at.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Local, Utc};
+
+use crate::error::Error;
+use crate::feed::Feed;
+
+/// How many downloads in a row have to fail before a feed backs off, and is
+/// only tried again after a wait.
+pub const BACKOFF_FAILURES: usize = 3;
+
+/// How long a feed waits after its first failure past `BACKOFF_FAILURES`.
+const BACKOFF_START_HOURS: i64 = 12;
+
+/// The longest a feed waits between tries, so one that comes back is noticed.
+const BACKOFF_MAX_DAYS: i64 = 30;
+
+/// What came of a feed's recent downloads.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Health {
+    /// How many downloads in a row have failed.
+    pub failures: usize,
+    /// How many of those got the feed, but couldn't read it.
+    pub parse_failures: usize,
+    /// The HTTP status of the last download, if it got that far.
+    pub status: Option<u16>,
+    /// What went wrong with the last download, if it failed.
+    pub error: Option<String>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_failure: Option<DateTime<Utc>>,
+    /// When a download last found a comic that wasn't in the history yet.
+    pub last_new: Option<DateTime<Utc>>,
+}
+
+impl Health {
+    /// Note a download at `now` that worked, and whether it found new comics.
+    pub fn succeeded(&mut self, now: DateTime<Utc>, status: Option<u16>, found_new: bool) {
+        self.failures = 0;
+        self.parse_failures = 0;
+        self.status = status;
+        self.error = None;
+        self.last_success = Some(now);
+        if found_new {
+            self.last_new = Some(now);
+        }
+    }
+
+    /// Note a download at `now` that failed with `error`. One where the server
+    /// sent the feed, going by `status`, is a failure to read it.
+    pub fn failed(&mut self, now: DateTime<Utc>, status: Option<u16>, error: &Error) {
+        self.failures += 1;
+        self.last_failure = Some(now);
+        if status.is_some_and(|status| (200..300).contains(&status)) {
+            self.parse_failures += 1;
+        }
+        self.status = status;
+        // Each setting is a line, and some errors take several
+        let error = error.to_string();
+        self.error = Some(error.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+
+    /// When to try downloading the feed again, if it's failed enough times in
+    /// a row to back off. The wait doubles with each failure after that.
+    pub fn retry_at(&self) -> Option<DateTime<Utc>> {
+        let extra = self.failures.checked_sub(BACKOFF_FAILURES)?;
+        let wait = Duration::hours(BACKOFF_START_HOURS << extra.min(10))
+            .min(Duration::days(BACKOFF_MAX_DAYS));
+        Some(self.last_failure? + wait)
+    }
+
+    /// Read what `Display` wrote. Lines it doesn't know are skipped, since
+    /// this is only used for `doctor`.
+    pub fn parse(text: &str) -> Health {
+        let mut health = Health::default();
+        let date = |date: &str| {
+            DateTime::parse_from_rfc3339(date)
+                .ok()
+                .map(|date| date.with_timezone(&Utc))
+        };
+        for line in text.lines() {
+            match line.split_once(' ') {
+                Some(("failures", n)) => health.failures = n.parse().unwrap_or_default(),
+                Some(("parse-failures", n)) => {
+                    health.parse_failures = n.parse().unwrap_or_default()
+                }
+                Some(("status", status)) => health.status = status.parse().ok(),
+                Some(("error", error)) => health.error = Some(error.into()),
+                Some(("last-success", when)) => health.last_success = date(when),
+                Some(("last-failure", when)) => health.last_failure = date(when),
+                Some(("last-new", when)) => health.last_new = date(when),
+                _ => debug!("Skipping health line {:?}", line),
+            }
+        }
+        health
+    }
+}
+
+/// One `NAME VALUE` line for each thing known.
+impl fmt::Display for Health {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.failures > 0 {
+            writeln!(fmt, "failures {}", self.failures)?;
+        }
+        if self.parse_failures > 0 {
+            writeln!(fmt, "parse-failures {}", self.parse_failures)?;
+        }
+        if let Some(status) = self.status {
+            writeln!(fmt, "status {}", status)?;
+        }
+        if let Some(ref error) = self.error {
+            writeln!(fmt, "error {}", error)?;
+        }
+        if let Some(when) = self.last_success {
+            writeln!(fmt, "last-success {}", when.to_rfc3339())?;
+        }
+        if let Some(when) = self.last_failure {
+            writeln!(fmt, "last-failure {}", when.to_rfc3339())?;
+        }
+        if let Some(when) = self.last_new {
+            writeln!(fmt, "last-new {}", when.to_rfc3339())?;
+        }
+        Ok(())
+    }
+}
+
+/// The health saved at `path`, or a clean bill of health if there isn't any.
+pub fn load(path: &Path) -> Health {
+    match fs::read_to_string(path) {
+        Ok(text) => Health::parse(&text),
+        Err(err) => {
+            if err.kind() != io::ErrorKind::NotFound {
+                debug!("Couldn't read feed health {}: {}", path.display(), err);
+            }
+            Health::default()
+        }
+    }
+}
+
+pub fn save(path: &Path, health: &Health) -> Result<(), Error> {
+    fs::write(path, health.to_string()).map_err(|err| {
+        Error::Msg(format!(
+            "Error writing feed health {}: {}",
+            path.display(),
+            err
+        ))
+    })
+}
+
+/// Something wrong with a feed, found by `doctor`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    /// The last `runs` downloads failed, the last time with `error`.
+    Failing {
+        runs: usize,
+        error: String,
+        last_success: Option<DateTime<Utc>>,
+    },
+    /// The last `runs` downloads got the feed, but it couldn't be read.
+    Unreadable { runs: usize, error: String },
+    /// No new comics since `since`.
+    Stale { since: DateTime<Utc> },
+    /// The feed permanently redirects to this URL.
+    Moved(String),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let date = |date: DateTime<Utc>| date.with_timezone(&Local).format("%Y-%m-%d");
+        match *self {
+            Problem::Failing {
+                runs,
+                ref error,
+                last_success,
+            } => {
+                write!(fmt, "failed the last {} downloads", runs)?;
+                match last_success {
+                    Some(when) => write!(fmt, ", and last worked {}", date(when))?,
+                    None => write!(fmt, ", and has never worked")?,
+                }
+                write!(fmt, ": {}", error)
+            }
+            Problem::Unreadable { runs, ref error } => write!(
+                fmt,
+                "downloaded the last {} times, but couldn't be read: {}",
+                runs, error
+            ),
+            Problem::Stale { since } => write!(fmt, "no new comics since {}", date(since)),
+            Problem::Moved(ref url) => write!(
+                fmt,
+                "has moved to <{}>. Run `feedburst fix-urls` to put it in the config.",
+                url
+            ),
+        }
+    }
+}
+
+/// What's wrong with `feed`, given `health`, as of `now`: failing for at
+/// least `failures` downloads in a row, no new comics in `stale`, or a URL
+/// that permanently redirects.
+pub fn diagnose(
+    feed: &Feed,
+    health: &Health,
+    now: DateTime<Utc>,
+    failures: usize,
+    stale: Duration,
+) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    if health.failures >= failures.max(1) {
+        let error = health.error.clone().unwrap_or_default();
+        if health.parse_failures >= health.failures {
+            problems.push(Problem::Unreadable {
+                runs: health.failures,
+                error,
+            });
+        } else {
+            problems.push(Problem::Failing {
+                runs: health.failures,
+                error,
+                last_success: health.last_success,
+            });
+        }
+    }
+    // Histories from before health was kept only have publish dates to go by
+    let newest = feed
+        .comics()
+        .filter_map(|comic| comic.published)
+        .chain(health.last_new)
+        .max();
+    if let Some(since) = newest {
+        if now.signed_duration_since(since) >= stale {
+            problems.push(Problem::Stale { since });
+        }
+    }
+    if feed.canonical_url() != feed.info.url {
+        problems.push(Problem::Moved(feed.canonical_url().into()));
+    }
+    problems
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_config;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_health() {
+        let now = Utc.ymd(2018, 10, 3).and_hms(12, 0, 0);
+        let mut health = Health::default();
+        health.succeeded(now, Some(200), true);
+        health.failed(
+            now,
+            Some(404),
+            &Error::Msg("Failed to download:\n\"404\"".into()),
+        );
+        health.failed(now, Some(200), &Error::Msg("Not a feed".into()));
+        assert_eq!(health.failures, 2);
+        assert_eq!(health.parse_failures, 1);
+        let text = health.to_string();
+        assert_eq!(
+            text,
+            "failures 2\nparse-failures 1\nstatus 200\nerror Not a feed\n\
+             last-success 2018-10-03T12:00:00+00:00\nlast-failure 2018-10-03T12:00:00+00:00\n\
+             last-new 2018-10-03T12:00:00+00:00\n"
+        );
+        assert_eq!(Health::parse(&text), health);
+        assert_eq!(Health::parse("garbage\n"), Health::default());
+
+        health.succeeded(now, Some(304), false);
+        assert_eq!(health.failures, 0);
+        assert_eq!(health.error, None);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let error = Error::Msg("Failed to download: \"404 Not Found\"".into());
+        let at = |d, h| Utc.ymd(2018, 10, d).and_hms(h, 0, 0);
+        let mut health = Health::default();
+        health.failed(at(1, 0), Some(404), &error);
+        health.failed(at(2, 0), Some(404), &error);
+        assert_eq!(health.retry_at(), None);
+        health.failed(at(3, 0), Some(404), &error);
+        assert_eq!(health.retry_at(), Some(at(3, 12)));
+        health.failed(at(4, 0), Some(404), &error);
+        assert_eq!(health.retry_at(), Some(at(5, 0)));
+        health.failed(at(5, 0), Some(404), &error);
+        assert_eq!(health.retry_at(), Some(at(7, 0)));
+        for _ in 0..20 {
+            health.failed(at(5, 0), Some(404), &error);
+        }
+        assert_eq!(
+            health.retry_at(),
+            Some(Utc.ymd(2018, 11, 4).and_hms(0, 0, 0))
+        );
+
+        health.succeeded(at(6, 0), Some(200), true);
+        assert_eq!(health.retry_at(), None);
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let info = parse_config("\"Comic\" <http://example.com/rss>\n")
+            .unwrap()
+            .remove(0);
+        let feed = info
+            .read_feed(&mut "<http://example.com/1> 2018-06-01T12:00:00+00:00\n".as_bytes())
+            .unwrap();
+        let now = Utc.ymd(2018, 10, 3).and_hms(12, 0, 0);
+        let stale = Duration::days(90);
+        let mut health = Health::default();
+        assert_eq!(
+            diagnose(&feed, &health, now, 3, stale),
+            vec![Problem::Stale {
+                since: Utc.ymd(2018, 6, 1).and_hms(12, 0, 0)
+            }]
+        );
+
+        // A comic without a publish date was found recently
+        health.succeeded(Utc.ymd(2018, 9, 1).and_hms(12, 0, 0), Some(200), true);
+        assert!(diagnose(&feed, &health, now, 3, stale).is_empty());
+
+        let error = Error::Msg("Failed to download: \"404 Not Found\"".into());
+        for _ in 0..3 {
+            health.failed(now, Some(404), &error);
+        }
+        assert_eq!(
+            diagnose(&feed, &health, now, 3, stale),
+            vec![Problem::Failing {
+                runs: 3,
+                error: error.to_string(),
+                last_success: Some(Utc.ymd(2018, 9, 1).and_hms(12, 0, 0)),
+            }]
+        );
+        assert!(diagnose(&feed, &health, now, 4, stale).is_empty());
+
+        let mut health = Health::default();
+        for _ in 0..3 {
+            health.failed(now, Some(200), &Error::Msg("Not a feed".into()));
+        }
+        let problems = diagnose(&feed, &health, now, 3, Duration::days(365));
+        assert_eq!(
+            problems,
+            vec![Problem::Unreadable {
+                runs: 3,
+                error: "Not a feed".into()
+            }]
+        );
+        assert_eq!(
+            problems[0].to_string(),
+            "downloaded the last 3 times, but couldn't be read: Not a feed"
+        );
+
+        let feed = info
+            .read_feed(
+                &mut "moved <http://example.com/rss> <https://example.com/feed>\n".as_bytes(),
+            )
+            .unwrap();
+        assert_eq!(
+            diagnose(&feed, &Health::default(), now, 3, stale),
+            vec![Problem::Moved("https://example.com/feed".into())]
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use structopt::StructOpt;
+
+use crate::cli::Opt;
+use crate::parser::{DIRECTIVE_SYNTAX, ENGLISH_WEEKDAYS, LOCALES, POLICY_SYNTAX};
+
+pub const FEED_SYNTAX: &str = r#""NAME" <URL> @ POLICY @ POLICY ..."#;
+
+pub const FEED_HELP: &str = "\
+Each feed goes on its own line, with any number of policies after it. The URL
+can include {env:NAME} to fill in the environment variable NAME, which keeps
+secrets like API keys out of the config. Lines starting with # are comments.";
+
+/// The config language reference printed by `feedburst help syntax`, built
+/// from the same tables the parser uses.
+pub fn syntax() -> String {
+    let mut out = String::new();
+    out.push_str(&format!("FEEDS\n    {}\n\n", FEED_SYNTAX));
+    out.push_str(&indent(FEED_HELP, 4));
+    out.push_str("\n\nPOLICIES\n");
+    for &(form, help) in POLICY_SYNTAX {
+        out.push_str(&format!("    {}\n        {}\n", form, help));
+    }
+    out.push_str("\nDIRECTIVES\n");
+    for &(form, help) in DIRECTIVE_SYNTAX {
+        out.push_str(&format!("    {}\n        {}\n", form, help));
+    }
+    out.push_str("\nWEEKDAYS\n");
+    out.push_str(&format!("    {}\n", ENGLISH_WEEKDAYS.join(", ")));
+    for &(code, ref names) in LOCALES {
+        out.push_str(&format!("    {} (locale {})\n", names.join(", "), code));
+    }
+    out
+}
+
+/// A man page for feedburst in roff, for `feedburst help man`.
+pub fn man_page() -> String {
+    let mut help = Vec::new();
+    Opt::clap()
+        .write_long_help(&mut help)
+        .expect("writing to a Vec can't fail");
+    let help = String::from_utf8_lossy(&help);
+
+    let mut out = String::new();
+    out.push_str(&format!(
+        ".TH FEEDBURST 1 \"\" \"feedburst {}\"\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    out.push_str(".SH NAME\nfeedburst \\- present your RSS feeds in chunks\n");
+    out.push_str(".SH SYNOPSIS\n.B feedburst\n[\\fIOPTIONS\\fR] [\\fISUBCOMMAND\\fR]\n");
+    out.push_str(".SH DESCRIPTION\n");
+    out.push_str(&roff_escape(env!("CARGO_PKG_DESCRIPTION").trim()));
+    out.push_str("\n.SH USAGE\n.nf\n");
+    out.push_str(&roff_escape(&help));
+    out.push_str("\n.fi\n.SH CONFIGURATION\n.nf\n");
+    out.push_str(&roff_escape(&syntax()));
+    out.push_str(".fi\n");
+    out
+}
+
+fn indent(text: &str, width: usize) -> String {
+    let prefix = " ".repeat(width);
+    text.lines()
+        .map(|line| format!("{}{}", prefix, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn roff_escape(text: &str) -> String {
+    text.replace('\\', "\\e")
+        .lines()
+        .map(|line| {
+            // Lines starting with these would be read as roff requests
+            if line.starts_with('.') || line.starts_with('\'') {
+                format!("\\&{}", line)
+            } else {
+                line.into()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_syntax() {
+        let syntax = syntax();
+        for &(form, _) in POLICY_SYNTAX.iter().chain(DIRECTIVE_SYNTAX) {
+            assert!(syntax.contains(form), "missing {}", form);
+        }
+        assert!(syntax.contains("montag, dienstag"));
+    }
+
+    #[test]
+    fn test_man_page() {
+        let page = man_page();
+        assert!(page.starts_with(".TH FEEDBURST 1"));
+        assert!(page.contains("--open-with"));
+        assert!(page.contains("@ check every # day(s)"));
+        assert!(!page.contains("\n.."));
+    }
+}
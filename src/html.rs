@@ -0,0 +1,425 @@
+/// Turn a title that may contain HTML into plain text.
+///
+/// Tags are removed, character references (`&amp;`, `&#8217;`, `&#x2019;`) are
+/// decoded, and runs of whitespace are collapsed into single spaces. Anything
+/// that doesn't look like a tag or a known entity (`<3`, `Q&A`) is left alone.
+pub fn strip_html(text: &str) -> String {
+    let text = decode_entities(&strip_tags(text));
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A tag in an HTML or XML document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tag {
+    /// The tag's name, lowercased.
+    pub name: String,
+    /// The tag's attributes as (name, value) pairs, in order. Names are
+    /// lowercased and values have their character references decoded. An
+    /// attribute without a value has an empty one.
+    pub attributes: Vec<(String, String)>,
+    /// Whether this is an end tag, like `</p>`.
+    pub closing: bool,
+    /// Whether the tag closes itself, like `<br/>`.
+    pub self_closing: bool,
+}
+
+impl Tag {
+    /// The value of the attribute called `name`, which should be lowercase.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Every tag in an HTML or XML document, in order. Comments, doctypes, and
+/// XML declarations are left out, and nothing checks that tags are balanced.
+pub fn tags(text: &str) -> Vec<Tag> {
+    let mut tags = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        let tag = &rest[start + 1..];
+        let end = tag.find('>').unwrap_or(tag.len());
+        rest = &tag[end..];
+        let (closing, body) = match tag[..end].strip_prefix('/') {
+            Some(body) => (true, body),
+            None => (false, &tag[..end]),
+        };
+        let name_end = body
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == ':' || c == '-'))
+            .unwrap_or(body.len());
+        if name_end == 0 {
+            continue;
+        }
+        tags.push(Tag {
+            name: body[..name_end].to_ascii_lowercase(),
+            attributes: attributes(&body[name_end..]),
+            closing,
+            self_closing: body.trim_end().ends_with('/'),
+        });
+    }
+    tags
+}
+
+/// A CSS selector for one kind of tag, like `a.prev`, `#previous` or
+/// `a[rel=prev]`. Only a single compound selector is understood: a tag name,
+/// ids, classes, and `[attr]`, `[attr=value]` or `[attr~=word]` tests, with
+/// no combinators.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Selector {
+    name: Option<String>,
+    ids: Vec<String>,
+    classes: Vec<String>,
+    attributes: Vec<(String, Match)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Match {
+    Present,
+    Equal(String),
+    Word(String),
+}
+
+impl Selector {
+    /// Parse `text`, or `None` if it isn't a selector this understands.
+    pub fn parse(text: &str) -> Option<Selector> {
+        let ident = |rest: &str| {
+            rest.find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+                .unwrap_or(rest.len())
+        };
+        let text = text.trim();
+        let mut selector = Selector::default();
+        let mut rest = text;
+        let end = ident(rest);
+        if end > 0 {
+            selector.name = Some(rest[..end].to_ascii_lowercase());
+        } else if rest.starts_with('*') {
+            rest = &rest[1..];
+        }
+        rest = &rest[end..];
+        while let Some(c) = rest.chars().next() {
+            match c {
+                '#' | '.' => {
+                    let end = ident(&rest[1..]);
+                    if end == 0 {
+                        return None;
+                    }
+                    let name = rest[1..=end].to_string();
+                    if c == '#' {
+                        selector.ids.push(name);
+                    } else {
+                        selector.classes.push(name);
+                    }
+                    rest = &rest[end + 1..];
+                }
+                '[' => {
+                    let end = rest.find(']')?;
+                    let test = &rest[1..end];
+                    let unquote = |value: &str| {
+                        value
+                            .trim()
+                            .trim_matches(|c| c == '"' || c == '\'')
+                            .to_string()
+                    };
+                    let (name, test) = match test.find('=') {
+                        Some(i) if test[..i].ends_with('~') => {
+                            (&test[..i - 1], Match::Word(unquote(&test[i + 1..])))
+                        }
+                        Some(i) => (&test[..i], Match::Equal(unquote(&test[i + 1..]))),
+                        None => (test, Match::Present),
+                    };
+                    let name = name.trim();
+                    if name.is_empty() || ident(name) != name.len() {
+                        return None;
+                    }
+                    selector.attributes.push((name.to_ascii_lowercase(), test));
+                    rest = &rest[end + 1..];
+                }
+                _ => return None,
+            }
+        }
+        if selector == Selector::default() && text != "*" {
+            return None;
+        }
+        Some(selector)
+    }
+
+    /// Whether `tag` is one the selector picks out.
+    pub fn matches(&self, tag: &Tag) -> bool {
+        let words = |name| {
+            tag.attribute(name)
+                .unwrap_or("")
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        !tag.closing
+            && self.name.as_ref().is_none_or(|name| *name == tag.name)
+            && self.ids.iter().all(|id| tag.attribute("id") == Some(id))
+            && self
+                .classes
+                .iter()
+                .all(|class| words("class").contains(class))
+            && self.attributes.iter().all(|(name, test)| match *test {
+                Match::Present => tag.attribute(name).is_some(),
+                Match::Equal(ref value) => tag.attribute(name) == Some(value),
+                Match::Word(ref word) => words(name).contains(word),
+            })
+    }
+}
+
+/// The `src` of the first `<img>` in some HTML, like an entry's description.
+pub fn first_image(html: &str) -> Option<String> {
+    tags(html)
+        .into_iter()
+        .find(|tag| tag.name == "img" && !tag.closing)
+        .and_then(|tag| tag.attribute("src").map(String::from))
+        .filter(|src| !src.is_empty())
+}
+
+/// The attributes of every `<link>` tag in an HTML page, in order.
+pub fn link_tags(page: &str) -> Vec<Vec<(String, String)>> {
+    tags(page)
+        .into_iter()
+        .filter(|tag| tag.name == "link" && !tag.closing)
+        .map(|tag| tag.attributes)
+        .collect()
+}
+
+fn attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let mut rest = tag;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            return attrs;
+        }
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+        let mut value = "";
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (found, next) = match after.chars().next() {
+                Some(quote @ '"') | Some(quote @ '\'') => {
+                    let quoted = &after[1..];
+                    match quoted.find(quote) {
+                        Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                        None => (quoted, ""),
+                    }
+                }
+                _ => after.split_at(after.find(char::is_whitespace).unwrap_or(after.len())),
+            };
+            value = found;
+            rest = next;
+        }
+        if !name.is_empty() {
+            attrs.push((name, decode_entities(value)));
+        }
+    }
+}
+
+fn strip_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        let is_tag = tag[1..]
+            .chars()
+            .next()
+            .map(|c| c.is_ascii_alphabetic() || c == '/' || c == '!')
+            .unwrap_or(false);
+        match tag.find('>') {
+            Some(end) if is_tag => {
+                // Line breaks and blocks separate words, so don't glue them together
+                let name: String = tag[1..end]
+                    .trim_start_matches('/')
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphanumeric())
+                    .collect();
+                if ["br", "p", "div", "li"].contains(&&name.to_lowercase()[..]) {
+                    out.push(' ');
+                }
+                rest = &tag[end + 1..];
+            }
+            _ => {
+                out.push('<');
+                rest = &tag[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let entity = &rest[start..];
+        let decoded = entity
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| decode_entity(&entity[1..end]).map(|c| (c, end)));
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &entity[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &entity[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(|c| c == 'x' || c == 'X') {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => number.parse().ok(),
+        };
+        return code.and_then(std::char::from_u32);
+    }
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "ndash" => '\u{2013}',
+        "mdash" => '\u{2014}',
+        "lsquo" => '\u{2018}',
+        "rsquo" => '\u{2019}',
+        "ldquo" => '\u{201C}',
+        "rdquo" => '\u{201D}',
+        "hellip" => '\u{2026}',
+        "copy" => '\u{A9}',
+        "reg" => '\u{AE}',
+        "trade" => '\u{2122}',
+        _ => return None,
+    };
+    Some(c)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_entities() {
+        assert_eq!(strip_html("Cats &amp; Dogs"), "Cats & Dogs");
+        assert_eq!(strip_html("&quot;Hi&quot; &lt;3"), "\"Hi\" <3");
+        assert_eq!(
+            strip_html("Jerry&#8217;s &#x2014; end"),
+            "Jerry\u{2019}s \u{2014} end"
+        );
+        assert_eq!(strip_html("Wait&hellip;"), "Wait\u{2026}");
+
+        // Things that only look like entities are left alone
+        assert_eq!(strip_html("Q&A"), "Q&A");
+        assert_eq!(strip_html("&bogus; & &;"), "&bogus; & &;");
+        assert_eq!(strip_html("&#xZZ;"), "&#xZZ;");
+    }
+
+    #[test]
+    fn test_tags() {
+        assert_eq!(strip_html("<em>Chapter 2</em>: Start"), "Chapter 2: Start");
+        assert_eq!(strip_html("Line<br/>Break"), "Line Break");
+        assert_eq!(strip_html("<!-- hidden -->Shown"), "Shown");
+        assert_eq!(strip_html("I <3 comics"), "I <3 comics");
+        assert_eq!(strip_html("Unclosed <b"), "Unclosed <b");
+    }
+
+    #[test]
+    fn test_link_tags() {
+        let page = r#"<html><head>
+<LINK REL="alternate" type='application/rss+xml' href="/feed?a=1&amp;b=2">
+<link rel=stylesheet href=/style.css />
+<linkage href="nope">
+<link async></head>"#;
+        let pair = |name: &str, value: &str| (name.to_string(), value.to_string());
+        assert_eq!(
+            link_tags(page),
+            vec![
+                vec![
+                    pair("rel", "alternate"),
+                    pair("type", "application/rss+xml"),
+                    pair("href", "/feed?a=1&b=2"),
+                ],
+                vec![pair("rel", "stylesheet"), pair("href", "/style.css")],
+                vec![pair("async", "")],
+            ]
+        );
+        assert!(link_tags("no links here").is_empty());
+        assert_eq!(
+            first_image(r#"<p><a href="/2"><IMG SRC="/thumbs/2.png"></a><img src="/ad.gif">"#),
+            Some("/thumbs/2.png".into())
+        );
+        assert_eq!(first_image("<img alt=\"no source\"> <p>text</p>"), None);
+        assert_eq!(
+            link_tags("<link href='unclosed"),
+            vec![vec![pair("href", "unclosed")]]
+        );
+    }
+
+    #[test]
+    fn test_tag_list() {
+        let text = r#"<?xml version="1.0"?><!-- hi --><body><outline text="A &amp; B"/></body>"#;
+        let names: Vec<_> = tags(text)
+            .into_iter()
+            .map(|tag| (tag.name, tag.closing, tag.self_closing))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("body".to_string(), false, false),
+                ("outline".to_string(), false, true),
+                ("body".to_string(), true, false),
+            ]
+        );
+        assert_eq!(tags(text)[1].attribute("text"), Some("A & B"));
+        assert_eq!(tags(text)[1].attribute("title"), None);
+    }
+
+    #[test]
+    fn test_selector() {
+        let tag = |text: &str| tags(text).remove(0);
+        let link = tag(r#"<a class="nav prev" id="back" rel="prev nofollow" href="/1">"#);
+        let matches = |selector: &str| Selector::parse(selector).unwrap().matches(&link);
+        assert!(matches("a"));
+        assert!(matches("A.prev"));
+        assert!(matches(".nav.prev"));
+        assert!(matches("#back"));
+        assert!(matches("a[href]"));
+        assert!(matches("a[rel~=prev]"));
+        assert!(matches(r#"[href="/1"]"#));
+        assert!(matches("*"));
+        assert!(!matches("link"));
+        assert!(!matches(".next"));
+        assert!(!matches("a[rel=prev]"));
+        assert!(!Selector::parse("a").unwrap().matches(&tag("</a>")));
+
+        assert_eq!(Selector::parse(""), None);
+        assert_eq!(Selector::parse("nav a"), None);
+        assert_eq!(Selector::parse("a > b"), None);
+        assert_eq!(Selector::parse("a[rel"), None);
+        assert_eq!(Selector::parse("a."), None);
+    }
+
+    #[test]
+    fn test_whitespace() {
+        assert_eq!(strip_html("  Page\n\t 1 "), "Page 1");
+        assert_eq!(strip_html("A&nbsp;B"), "A B");
+        assert_eq!(strip_html(""), "");
+    }
+}
@@ -0,0 +1,102 @@
+//! The `ETag` and `Last-Modified` headers from each feed's last download, kept
+//! next to its history, so a feed that hasn't changed isn't downloaded again.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::error::Error;
+
+/// What a server said identifies the version of a feed it sent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// Read what `Display` wrote. Lines it doesn't know are skipped, since the
+    /// cache only saves time.
+    pub fn parse(text: &str) -> Validators {
+        let mut validators = Validators::default();
+        for line in text.lines() {
+            match line.split_once(' ') {
+                Some(("etag", etag)) => validators.etag = Some(etag.into()),
+                Some(("last-modified", date)) => validators.last_modified = Some(date.into()),
+                _ => debug!("Skipping HTTP cache line {:?}", line),
+            }
+        }
+        validators
+    }
+}
+
+/// One `etag VALUE` or `last-modified DATE` line for each header there was.
+impl fmt::Display for Validators {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref etag) = self.etag {
+            writeln!(fmt, "etag {}", etag)?;
+        }
+        if let Some(ref date) = self.last_modified {
+            writeln!(fmt, "last-modified {}", date)?;
+        }
+        Ok(())
+    }
+}
+
+/// The validators saved at `path`, or none if there aren't any.
+pub fn load(path: &Path) -> Validators {
+    match fs::read_to_string(path) {
+        Ok(text) => Validators::parse(&text),
+        Err(err) => {
+            if err.kind() != io::ErrorKind::NotFound {
+                debug!("Couldn't read HTTP cache {}: {}", path.display(), err);
+            }
+            Validators::default()
+        }
+    }
+}
+
+/// Save `validators` at `path`, or remove what's there if there aren't any.
+pub fn save(path: &Path, validators: &Validators) -> Result<(), Error> {
+    let result = if validators.is_empty() {
+        match fs::remove_file(path) {
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    } else {
+        fs::write(path, validators.to_string())
+    };
+    result.map_err(|err| {
+        Error::Msg(format!(
+            "Error writing HTTP cache {}: {}",
+            path.display(),
+            err
+        ))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validators() {
+        let validators = Validators {
+            etag: Some("W/\"5e1-17a\"".into()),
+            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".into()),
+        };
+        let text = validators.to_string();
+        assert_eq!(
+            text,
+            "etag W/\"5e1-17a\"\nlast-modified Wed, 21 Oct 2015 07:28:00 GMT\n"
+        );
+        assert_eq!(Validators::parse(&text), validators);
+        assert_eq!(Validators::parse("garbage\n"), Validators::default());
+        assert!(Validators::default().is_empty());
+    }
+}
@@ -0,0 +1,235 @@
+//! `include` lines, which pull another config file in where they are, so a
+//! long config can be split across files. The files are joined into one text
+//! before parsing, keeping track of where each line came from so errors can
+//! point at the right file.
+
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, ParseError};
+use crate::parser::{include_path, only_guard, Machine};
+
+/// How deep includes can go, in case paths that only look different make a
+/// cycle.
+pub const MAX_DEPTH: usize = 16;
+
+/// A config with its includes pulled in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// The whole config, with each `include` line followed by the lines of
+    /// the file it names.
+    pub text: String,
+    files: Vec<PathBuf>,
+    /// The file, and the row in it, of each line of `text`.
+    rows: Vec<(usize, usize)>,
+}
+
+impl Config {
+    /// A config that's all in one file.
+    pub fn single(path: &Path, text: &str) -> Self {
+        Config {
+            text: text.into(),
+            files: vec![path.into()],
+            rows: (1..=text.lines().count()).map(|row| (0, row)).collect(),
+        }
+    }
+
+    /// The file that `row` of `text` came from, and its row in that file.
+    pub fn locate(&self, row: usize) -> (&Path, usize) {
+        match row.checked_sub(1).and_then(|i| self.rows.get(i)) {
+            Some(&(file, row)) => (&self.files[file], row),
+            None => (&self.files[0], row),
+        }
+    }
+
+    /// An error from parsing `text`, pointed at the file it's in.
+    pub fn error(&self, error: ParseError) -> Error {
+        let ParseError::Expected { msg, row, span } = error;
+        let line = error_line(&self.text, row);
+        let (path, row) = self.locate(row);
+        Error::Parse {
+            what: path.display().to_string(),
+            line,
+            error: ParseError::Expected { msg, row, span },
+        }
+    }
+}
+
+fn error_line(text: &str, row: usize) -> String {
+    text.lines()
+        .nth(row.saturating_sub(1))
+        .unwrap_or_default()
+        .into()
+}
+
+/// Pull the files that the config at `path` includes into it, and the ones
+/// they include, with `load` reading each one. Included paths are relative
+/// to the file including them. Includes in a section that an `only` line
+/// skips on `machine` aren't read.
+pub fn expand<F>(path: &Path, text: &str, machine: &Machine, mut load: F) -> Result<Config, Error>
+where
+    F: FnMut(&Path) -> Result<String, Error>,
+{
+    let mut config = Config {
+        text: String::new(),
+        files: Vec::new(),
+        rows: Vec::new(),
+    };
+    let mut guarded = false;
+    add_file(
+        &mut config,
+        &mut vec![path.to_path_buf()],
+        text,
+        &mut guarded,
+        machine,
+        &mut load,
+    )?;
+    Ok(config)
+}
+
+/// Add the file at the end of `stack`, and what it includes, to `config`.
+fn add_file<F>(
+    config: &mut Config,
+    stack: &mut Vec<PathBuf>,
+    text: &str,
+    guarded: &mut bool,
+    machine: &Machine,
+    load: &mut F,
+) -> Result<(), Error>
+where
+    F: FnMut(&Path) -> Result<String, Error>,
+{
+    let path = stack.last().unwrap().clone();
+    let file = config.files.len();
+    config.files.push(path.clone());
+    for (i, line) in text.lines().enumerate() {
+        config.text.push_str(line);
+        config.text.push('\n');
+        config.rows.push((file, i + 1));
+
+        if let Some(matches) = only_guard(line, machine) {
+            *guarded = !matches;
+            continue;
+        }
+        let (name, span) = match include_path(line) {
+            Some(include) if !*guarded => include,
+            _ => continue,
+        };
+        let included = path.parent().unwrap_or_else(|| Path::new("")).join(name);
+        let problem = if stack.contains(&included) {
+            Some(format!(
+                "a file that isn't already being included (\"{}\" would include itself)",
+                name
+            ))
+        } else if stack.len() >= MAX_DEPTH {
+            Some(format!("at most {} includes inside each other", MAX_DEPTH))
+        } else {
+            None
+        };
+        let included_text = match problem {
+            Some(problem) => Err(problem),
+            None => load(&included).map_err(|err| format!("a file that can be read ({})", err)),
+        };
+        let included_text = included_text.map_err(|msg| Error::Parse {
+            what: path.display().to_string(),
+            line: line.into(),
+            error: ParseError::expected(msg, i + 1, span),
+        })?;
+        stack.push(included);
+        add_file(config, stack, &included_text, guarded, machine, load)?;
+        stack.pop();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_config_for;
+    use std::collections::HashMap;
+
+    fn files() -> HashMap<PathBuf, &'static str> {
+        vec![
+            (
+                "/comics/config.feeds",
+                "root /comics/history\ninclude weekly.feeds\n\"Daily\" <http://daily.example.com>\n",
+            ),
+            (
+                "/comics/weekly.feeds",
+                "default_policy @ on saturday\n\"Weekly\" <http://weekly.example.com>\n",
+            ),
+            ("/comics/loop.feeds", "include ./more/../loop.feeds\n"),
+            ("/comics/self.feeds", "\n include self.feeds\n"),
+            ("/comics/bad.feeds", "\"Bad\" <http://bad.example.com> @ evry day\n"),
+        ]
+        .into_iter()
+        .map(|(path, text)| (PathBuf::from(path), text))
+        .collect()
+    }
+
+    fn expand_file(path: &str) -> Result<Config, Error> {
+        let files = files();
+        let text = files.get(Path::new(path)).copied().unwrap_or(path);
+        let machine = Machine::current(None);
+        expand(
+            Path::new("/comics/config.feeds"),
+            text,
+            &machine,
+            |path| match files.get(&Path::new("/comics").join(path.file_name().unwrap())) {
+                Some(text) => Ok(text.to_string()),
+                None => Err(Error::Msg("No such file".into())),
+            },
+        )
+    }
+
+    #[test]
+    fn test_expand() {
+        let config = expand_file("/comics/config.feeds").unwrap();
+        let machine = Machine::current(None);
+        let feeds = parse_config_for(&config.text, &machine).unwrap();
+        let names: Vec<_> = feeds.iter().map(|info| info.name.as_str()).collect();
+        assert_eq!(names, vec!["Weekly", "Daily"]);
+        // Directives carry on past the end of an included file
+        assert_eq!(feeds[0].update_policies, feeds[1].update_policies);
+        assert_eq!(feeds[0].root, Some("/comics/history".into()));
+        assert_eq!(config.locate(4), (Path::new("/comics/weekly.feeds"), 2));
+        assert_eq!(config.locate(5), (Path::new("/comics/config.feeds"), 3));
+
+        // Parse errors are in the file they came from
+        let config = expand_file("include bad.feeds").unwrap();
+        let err = parse_config_for(&config.text, &machine).unwrap_err();
+        let err = config.error(err).to_string();
+        assert!(
+            err.starts_with(concat!(
+                "Line 1: Error parsing /comics/bad.feeds\n\n",
+                "\"Bad\" <http://bad.example.com> @ evry day\n",
+                "                                 ^^^^^^^^^\n",
+            )),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let err = expand_file("include self.feeds").unwrap_err().to_string();
+        assert!(err.starts_with("Line 2: Error parsing /comics/self.feeds\n\n include self.feeds\n         ^^^^^^^^^^\nExpected a file that isn't already being included"), "{}", err);
+        let err = expand_file("include loop.feeds").unwrap_err().to_string();
+        assert!(err.contains("Expected at most 16 includes"), "{}", err);
+        let err = expand_file("\n\ninclude missing.feeds")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with("Line 3: Error parsing /comics/config.feeds"),
+            "{}",
+            err
+        );
+        assert!(
+            err.ends_with("Expected a file that can be read (No such file)"),
+            "{}",
+            err
+        );
+
+        // Files in sections for other machines aren't read
+        assert!(expand_file("only on host elsewhere\ninclude missing.feeds\nonly\n").is_ok());
+    }
+}
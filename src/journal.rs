@@ -0,0 +1,148 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+/// How many runs the journal keeps before dropping the oldest.
+const JOURNAL_RUNS: usize = 20;
+
+/// What a run of feedburst did, as kept in the run journal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Run {
+    /// Whether this was a `--fetch` run, which only downloads and reminds.
+    pub only_fetch: bool,
+    pub when: DateTime<Utc>,
+    /// The lines that summed up the run, like each feed that was opened.
+    pub summary: Vec<String>,
+}
+
+impl Run {
+    fn kind(&self) -> &'static str {
+        if self.only_fetch {
+            "fetch"
+        } else {
+            "read"
+        }
+    }
+}
+
+/// Read the journal's runs, oldest first. Anything that doesn't look like a
+/// run is skipped, since the journal is only a convenience.
+pub fn parse_journal(text: &str) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for line in text.lines() {
+        if let Some(summary) = line.strip_prefix("  ") {
+            if let Some(run) = runs.last_mut() {
+                run.summary.push(summary.into());
+            }
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let only_fetch = match words.next() {
+            Some("read") => false,
+            Some("fetch") => true,
+            _ => continue,
+        };
+        let when = match words.next().map(DateTime::parse_from_rfc3339) {
+            Some(Ok(when)) => when.with_timezone(&Utc),
+            _ => continue,
+        };
+        runs.push(Run {
+            only_fetch,
+            when,
+            summary: Vec::new(),
+        });
+    }
+    runs
+}
+
+/// Write out the runs, one `read DATE` or `fetch DATE` line each with the
+/// summary indented under it.
+pub fn write_journal(runs: &[Run]) -> String {
+    let mut out = String::new();
+    for run in runs {
+        out.push_str(&format!("{} {}\n", run.kind(), run.when.to_rfc3339()));
+        for line in &run.summary {
+            out.push_str(&format!("  {}\n", line));
+        }
+    }
+    out
+}
+
+/// The most recent run of the same kind, if it was less than `interval` before
+/// `now`.
+pub fn recent_run(
+    runs: &[Run],
+    only_fetch: bool,
+    now: DateTime<Utc>,
+    interval: chrono::Duration,
+) -> Option<&Run> {
+    let last = runs.iter().rev().find(|run| run.only_fetch == only_fetch)?;
+    if now.signed_duration_since(last.when) < interval {
+        Some(last)
+    } else {
+        None
+    }
+}
+
+/// The runs recorded at `path`, or none if it doesn't exist yet.
+pub fn load(path: &Path) -> io::Result<Vec<Run>> {
+    let mut text = String::new();
+    match File::open(path) {
+        Ok(mut file) => file.read_to_string(&mut text)?,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    Ok(parse_journal(&text))
+}
+
+/// Add `run` to the journal at `path`, dropping the oldest runs past
+/// `JOURNAL_RUNS`.
+pub fn record(path: &Path, run: Run) -> io::Result<()> {
+    let mut runs = load(path)?;
+    runs.push(run);
+    let extra = runs.len().saturating_sub(JOURNAL_RUNS);
+    fs::write(path, write_journal(&runs[extra..]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_journal() {
+        let runs = vec![
+            Run {
+                only_fetch: false,
+                when: Utc.ymd(2018, 10, 1).and_hms(8, 0, 0),
+                summary: vec!["Witchy (2 comics)".into(), "Oglaf (1 comic)".into()],
+            },
+            Run {
+                only_fetch: true,
+                when: Utc.ymd(2018, 10, 1).and_hms(12, 0, 0),
+                summary: vec![],
+            },
+        ];
+        let text = write_journal(&runs);
+        assert_eq!(
+            text,
+            concat!(
+                "read 2018-10-01T08:00:00+00:00\n",
+                "  Witchy (2 comics)\n",
+                "  Oglaf (1 comic)\n",
+                "fetch 2018-10-01T12:00:00+00:00\n",
+            )
+        );
+        assert_eq!(parse_journal(&text), runs);
+        assert_eq!(parse_journal(&format!("garbage\n{}", text)), runs);
+
+        let now = Utc.ymd(2018, 10, 1).and_hms(13, 0, 0);
+        let six_hours = Duration::hours(6);
+        assert_eq!(recent_run(&runs, false, now, six_hours), Some(&runs[0]));
+        assert_eq!(recent_run(&runs, true, now, six_hours), Some(&runs[1]));
+        assert_eq!(recent_run(&runs, false, now, Duration::hours(4)), None);
+        assert_eq!(recent_run(&[], false, now, six_hours), None);
+    }
+}
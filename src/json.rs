@@ -0,0 +1,115 @@
+/// Just enough JSON for feedburst's machine-readable output, without pulling
+/// in a serialization library.
+pub enum Json {
+    Str(String),
+    Num(usize),
+    Arr(Vec<Json>),
+    Obj(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    pub fn str<S: Into<String>>(s: S) -> Json {
+        Json::Str(s.into())
+    }
+
+    pub fn strs<'a, I: IntoIterator<Item = &'a str>>(items: I) -> Json {
+        Json::Arr(items.into_iter().map(Json::str).collect())
+    }
+
+    /// Indented over several lines, for people to read too.
+    pub fn pretty(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, Some(0));
+        out
+    }
+
+    /// All on one line, for streams with one value per line.
+    pub fn compact(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, None);
+        out
+    }
+
+    fn write(&self, out: &mut String, indent: Option<usize>) {
+        let inner = indent.map(|indent| indent + 1);
+        let (open_pad, close_pad, separator) = match indent {
+            Some(indent) => ("  ".repeat(indent + 1), "  ".repeat(indent), ": "),
+            None => (String::new(), String::new(), ":"),
+        };
+        let newline = if indent.is_some() { "\n" } else { "" };
+        let comma = if indent.is_some() { ",\n" } else { "," };
+        match *self {
+            Json::Str(ref s) => {
+                out.push('"');
+                for c in s.chars() {
+                    match c {
+                        '"' => out.push_str("\\\""),
+                        '\\' => out.push_str("\\\\"),
+                        '\n' => out.push_str("\\n"),
+                        '\t' => out.push_str("\\t"),
+                        c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+                        c => out.push(c),
+                    }
+                }
+                out.push('"');
+            }
+            Json::Num(n) => out.push_str(&n.to_string()),
+            Json::Arr(ref items) if items.is_empty() => out.push_str("[]"),
+            Json::Arr(ref items) => {
+                out.push('[');
+                out.push_str(newline);
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(&open_pad);
+                    item.write(out, inner);
+                    out.push_str(if i + 1 < items.len() { comma } else { newline });
+                }
+                out.push_str(&close_pad);
+                out.push(']');
+            }
+            Json::Obj(ref fields) => {
+                out.push('{');
+                out.push_str(newline);
+                for (i, &(key, ref value)) in fields.iter().enumerate() {
+                    out.push_str(&format!("{}\"{}\"{}", open_pad, key, separator));
+                    value.write(out, inner);
+                    out.push_str(if i + 1 < fields.len() { comma } else { newline });
+                }
+                out.push_str(&close_pad);
+                out.push('}');
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_json() {
+        let value = Json::Obj(vec![
+            ("name", Json::str("\"Quoted\"\n")),
+            ("line", Json::Num(3)),
+            ("empty", Json::Arr(vec![])),
+            ("list", Json::strs(vec!["a", "b"])),
+        ]);
+        assert_eq!(
+            value.compact(),
+            r#"{"name":"\"Quoted\"\n","line":3,"empty":[],"list":["a","b"]}"#
+        );
+        assert_eq!(
+            value.pretty(),
+            concat!(
+                "{\n",
+                "  \"name\": \"\\\"Quoted\\\"\\n\",\n",
+                "  \"line\": 3,\n",
+                "  \"empty\": [],\n",
+                "  \"list\": [\n",
+                "    \"a\",\n",
+                "    \"b\"\n",
+                "  ]\n",
+                "}",
+            )
+        );
+    }
+}
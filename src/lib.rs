@@ -0,0 +1,47 @@
+//! The parts of feedburst that don't need a filesystem or network: the config
+//! parser, the policy engine, and the feed event model.
+//!
+//! With `default-features = false` this builds for targets like
+//! `wasm32-unknown-unknown`, so that a browser extension or web page can
+//! validate configs and preview schedules with the same code as the CLI.
+//! Downloading feeds goes through the `fetch::Fetcher` trait, and event logs
+//! are read and written through `std::io` traits, so the caller decides where
+//! they come from.
+//!
+//! The types a frontend needs most are also at the top of the crate: parse a
+//! config with `parse_config`, load each feed's history with
+//! `FeedInfo::read_feed`, and ask `plan_session` what's ready to read.
+
+#[macro_use]
+extern crate log;
+
+pub mod clock;
+pub mod crawl;
+pub mod dedupe;
+pub mod digest;
+pub mod edit;
+pub mod error;
+pub mod extract;
+pub mod feed;
+pub mod fetch;
+pub mod format;
+pub mod html;
+pub mod include;
+pub mod output;
+mod parse_util;
+pub mod parser;
+pub mod quoting;
+pub mod reload;
+pub mod sample;
+pub mod session;
+pub mod simulate;
+pub mod state;
+pub mod stats;
+pub mod validate;
+
+pub use crate::clock::{Clock, FixedClock, SystemClock};
+pub use crate::error::{Error, ParseError};
+pub use crate::feed::{Comic, Feed, FeedEvent, FeedInfo, UpdateSpec};
+pub use crate::fetch::{update_feed, Fetcher};
+pub use crate::parser::{parse_config, parse_settings_for, Machine, Settings};
+pub use crate::session::{plan_reading, plan_session, ReadingPlan};
@@ -0,0 +1,157 @@
+//! What feedburst is doing and why, on stderr with `-v` or `RUST_LOG`, and in
+//! the config's `log_file` too. Both are `env_logger` loggers; lines logged
+//! while working on a feed say which feed, so it's easy to follow one through
+//! a run.
+
+use std::cell::RefCell;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::{Local, SecondsFormat};
+use env_logger::{Builder, Target};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// The least a `log_file` gets, whatever `-v` says.
+const FILE_LEVEL: LevelFilter = LevelFilter::Debug;
+
+/// The start of the target of everything logged by feedburst itself.
+const OWN_TARGET: &str = "feedburst";
+
+/// How much of its own logging `-v` asked for.
+static VERBOSE: Mutex<LevelFilter> = Mutex::new(LevelFilter::Off);
+
+/// The log file's logger, once the config names one.
+static FILE: Mutex<Option<env_logger::Logger>> = Mutex::new(None);
+
+thread_local! {
+    /// The feed this thread is working on, if any.
+    static FEED: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Sends each record to stderr and the log file, if they want it.
+struct Logger {
+    stderr: env_logger::Logger,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.stderr.enabled(metadata)
+            || FILE
+                .lock()
+                .unwrap()
+                .as_ref()
+                .is_some_and(|file| file.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        self.stderr.log(record);
+        if let Some(ref file) = *FILE.lock().unwrap() {
+            file.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+        if let Some(ref file) = *FILE.lock().unwrap() {
+            file.flush();
+        }
+    }
+}
+
+/// Start logging to stderr: errors, or whatever `RUST_LOG` asks for, and
+/// feedburst's debugging messages for a `verbosity` of 1, or everything it
+/// logs for 2 or more.
+pub fn init(verbosity: u64) {
+    let verbose = verbosity_level(verbosity);
+    *VERBOSE.lock().unwrap() = verbose;
+    let mut builder = Builder::new();
+    builder.filter_level(LevelFilter::Error);
+    if verbose > LevelFilter::Off {
+        builder.filter_module(OWN_TARGET, verbose);
+    }
+    // What's asked for by name wins over `-v`
+    if let Ok(spec) = env::var("RUST_LOG") {
+        builder.parse_filters(&spec);
+    }
+    builder.format(|buf, record| {
+        writeln!(
+            buf,
+            "{}",
+            format_line(record.level(), record.target(), &record.args().to_string())
+        )
+    });
+    let stderr = builder.build();
+    let max_level = stderr.filter();
+    if log::set_boxed_logger(Box::new(Logger { stderr })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Also log to the end of the file at `path`, with the time on each line.
+/// It gets feedburst's debugging messages at least, and more with `-vv`.
+pub fn log_to_file(path: &Path) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let verbose = *VERBOSE.lock().unwrap();
+    let mut builder = Builder::new();
+    builder
+        .filter_level(LevelFilter::Off)
+        .filter_module(OWN_TARGET, FILE_LEVEL.max(verbose))
+        .target(Target::Pipe(Box::new(file)))
+        .format(|buf, record| {
+            let now = Local::now().to_rfc3339_opts(SecondsFormat::Secs, false);
+            let line = format_line(record.level(), record.target(), &record.args().to_string());
+            writeln!(buf, "{} {}", now, line)
+        });
+    let file = builder.build();
+    log::set_max_level(log::max_level().max(file.filter()));
+    *FILE.lock().unwrap() = Some(file);
+    Ok(())
+}
+
+/// Run `work` on the feed called `name`, marking everything it logs with the
+/// name.
+pub fn in_feed<T>(name: &str, work: impl FnOnce() -> T) -> T {
+    let outer = FEED.with(|feed| feed.replace(Some(name.into())));
+    let result = work();
+    FEED.with(|feed| *feed.borrow_mut() = outer);
+    result
+}
+
+fn verbosity_level(verbosity: u64) -> LevelFilter {
+    match verbosity {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// A line of the log, like `DEBUG feedburst::state [Witchy] Skipping ...`,
+/// with the feed this thread is working on, if any.
+fn format_line(level: Level, target: &str, message: &str) -> String {
+    FEED.with(|feed| match *feed.borrow() {
+        Some(ref feed) => format!("{:<5} {} [{}] {}", level, target, feed, message),
+        None => format!("{:<5} {} {}", level, target, message),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_line() {
+        assert_eq!(
+            format_line(Level::Warn, "feedburst", "Hm"),
+            "WARN  feedburst Hm"
+        );
+        let line = in_feed("Oglaf", || {
+            in_feed("Witchy", || ());
+            format_line(Level::Debug, "feedburst::state", "Skipping")
+        });
+        assert_eq!(line, "DEBUG feedburst::state [Oglaf] Skipping");
+        assert_eq!(FEED.with(|feed| feed.borrow().clone()), None);
+    }
+}
@@ -0,0 +1,257 @@
+//! Sending `deliver email` digests. Just enough SMTP to hand one message to
+//! the mail server in the config, over TLS unless the server is this machine.
+
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use native_tls::TlsConnector;
+
+use crate::error::Error;
+use crate::feed::base64;
+use crate::parser::Smtp;
+
+/// How long to wait on the mail server, which can be slow to greet.
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The port where the connection is TLS from the start, instead of after
+/// STARTTLS.
+const IMPLICIT_TLS_PORT: u16 = 465;
+
+/// Either kind of connection to the server, plain or TLS.
+trait Stream: Read + Write {}
+
+impl<T: Read + Write> Stream for T {}
+
+/// Email the HTML page `html` to `to`, from `to` as well, through `smtp`.
+pub fn send(
+    smtp: &Smtp,
+    to: &str,
+    subject: &str,
+    html: &str,
+    when: DateTime<Utc>,
+    timeout: Duration,
+) -> Result<(), Error> {
+    let addr = (smtp.host.as_str(), smtp.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| Error::Msg(format!("Couldn't find an address for {}", smtp.host)))?;
+    let tcp = TcpStream::connect_timeout(&addr, timeout)?;
+    tcp.set_read_timeout(Some(timeout))?;
+    tcp.set_write_timeout(Some(timeout))?;
+    let mut stream: Box<dyn Stream> = if smtp.port == IMPLICIT_TLS_PORT {
+        Box::new(tls(&smtp.host, tcp.try_clone()?)?)
+    } else {
+        Box::new(tcp.try_clone()?)
+    };
+    expect(&mut stream, &[220])?;
+    let mut extensions = command(&mut stream, "EHLO feedburst", &[250])?;
+    if smtp.port != IMPLICIT_TLS_PORT {
+        if extensions
+            .iter()
+            .any(|line| line.eq_ignore_ascii_case("STARTTLS"))
+        {
+            command(&mut stream, "STARTTLS", &[220])?;
+            stream = Box::new(tls(&smtp.host, tcp.try_clone()?)?);
+            extensions = command(&mut stream, "EHLO feedburst", &[250])?;
+        } else if !addr.ip().is_loopback() && !is_local(&smtp.host) {
+            return Err(Error::Msg(format!(
+                "The mail server {} doesn't offer STARTTLS, so the email would be sent \
+                 unencrypted. Use port 465 if it takes TLS from the start.",
+                smtp.host
+            )));
+        }
+    }
+    if let Some((ref user, ref password)) = smtp.login {
+        if !extensions
+            .iter()
+            .any(|line| line.to_uppercase().starts_with("AUTH") && line.contains("PLAIN"))
+        {
+            return Err(Error::Msg(format!(
+                "The mail server {} doesn't take a username and password",
+                smtp.host
+            )));
+        }
+        let login = base64(format!("\0{}\0{}", user, password).as_bytes());
+        command(&mut stream, &format!("AUTH PLAIN {}", login), &[235])?;
+    }
+    command(&mut stream, &format!("MAIL FROM:<{}>", to), &[250])?;
+    command(&mut stream, &format!("RCPT TO:<{}>", to), &[250, 251])?;
+    command(&mut stream, "DATA", &[354])?;
+    stream.write_all(message(to, subject, html, when).as_bytes())?;
+    command(&mut stream, ".", &[250])?;
+    // The message is already accepted, so a server that hangs up is fine
+    let _ = command(&mut stream, "QUIT", &[221]);
+    Ok(())
+}
+
+/// Whether `host` names this machine.
+fn is_local(host: &str) -> bool {
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().is_ok_and(|addr| addr.is_loopback())
+}
+
+fn tls(host: &str, tcp: TcpStream) -> Result<native_tls::TlsStream<TcpStream>, Error> {
+    let connector = TlsConnector::new().map_err(|err| Error::Msg(format!("TLS error: {}", err)))?;
+    connector
+        .connect(host, tcp)
+        .map_err(|err| Error::Msg(format!("TLS connection to {} failed: {}", host, err)))
+}
+
+/// Send `line`, and read the reply, which should have one of `codes`.
+fn command(stream: &mut Box<dyn Stream>, line: &str, codes: &[u16]) -> Result<Vec<String>, Error> {
+    stream.write_all(format!("{}\r\n", line).as_bytes())?;
+    stream.flush()?;
+    expect(stream, codes).map_err(|err| {
+        // The rest of the line can be a password
+        let verb = line.split(' ').take(2).collect::<Vec<_>>().join(" ");
+        let verb = if verb.starts_with("AUTH") {
+            "AUTH"
+        } else {
+            &verb
+        };
+        Error::Msg(format!("{} (after {})", err, verb))
+    })
+}
+
+/// Read a reply, which can take several lines, and check it has one of
+/// `codes`. The text of each line, after the code.
+fn expect(stream: &mut Box<dyn Stream>, codes: &[u16]) -> Result<Vec<String>, Error> {
+    let mut lines = Vec::new();
+    loop {
+        let line = read_line(stream)?;
+        let code = line.get(..3).and_then(|code| code.parse::<u16>().ok());
+        let code = code.ok_or_else(|| Error::Msg(format!("Malformed SMTP reply: {}", line)))?;
+        let text = line.get(4..).unwrap_or_default().to_string();
+        let last = line.as_bytes().get(3) != Some(&b'-');
+        if !codes.contains(&code) {
+            return Err(Error::Msg(format!("The mail server said: {}", line)));
+        }
+        lines.push(text);
+        if last {
+            return Ok(lines);
+        }
+    }
+}
+
+/// One line from the server, a byte at a time so nothing past it is read
+/// before switching to TLS.
+fn read_line(stream: &mut Box<dyn Stream>) -> Result<String, Error> {
+    let mut line = Vec::new();
+    let mut byte = [0];
+    loop {
+        if stream.read(&mut byte)? == 0 {
+            return Err(Error::Msg("The mail server hung up".into()));
+        }
+        if byte[0] == b'\n' {
+            break;
+        }
+        line.push(byte[0]);
+    }
+    let line = String::from_utf8_lossy(&line);
+    Ok(line.trim_end_matches('\r').to_string())
+}
+
+/// The email itself, ending with the line that ends `DATA`. The page is sent
+/// as base64, which keeps its lines short and has no lines starting with `.`.
+fn message(to: &str, subject: &str, html: &str, when: DateTime<Utc>) -> String {
+    let mut out = format!(
+        "From: feedburst <{to}>\r\nTo: <{to}>\r\nSubject: {}\r\nDate: {}\r\n\
+         MIME-Version: 1.0\r\nContent-Type: text/html; charset=utf-8\r\n\
+         Content-Transfer-Encoding: base64\r\n\r\n",
+        subject,
+        when.to_rfc2822(),
+        to = to
+    );
+    let body = base64(html.as_bytes());
+    for chunk in body.as_bytes().chunks(76) {
+        out.push_str(&String::from_utf8_lossy(chunk));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_send() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // A server that takes any message, and says what it was sent
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut stream = stream;
+            let mut received = Vec::new();
+            stream.write_all(b"220 mail.example.com\r\n").unwrap();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end().to_string();
+                let reply: &[u8] = if in_data {
+                    if line == "." {
+                        in_data = false;
+                        b"250 Queued\r\n"
+                    } else {
+                        received.push(line);
+                        continue;
+                    }
+                } else if line.starts_with("EHLO") {
+                    b"250-mail.example.com\r\n250 AUTH PLAIN LOGIN\r\n"
+                } else if line.starts_with("AUTH") {
+                    received.push(line.clone());
+                    b"235 OK\r\n"
+                } else if line == "DATA" {
+                    in_data = true;
+                    b"354 Go ahead\r\n"
+                } else if line == "QUIT" {
+                    stream.write_all(b"221 Bye\r\n").unwrap();
+                    break;
+                } else {
+                    received.push(line.clone());
+                    b"250 OK\r\n"
+                };
+                stream.write_all(reply).unwrap();
+            }
+            received
+        });
+
+        let smtp = Smtp {
+            host: "127.0.0.1".into(),
+            port,
+            login: Some(("me".into(), "hunter2".into())),
+        };
+        let when = Utc.ymd(2018, 10, 3).and_hms(12, 0, 0);
+        let html = "<h1>Comics</h1>";
+        send(
+            &smtp,
+            "me@example.com",
+            "Comics",
+            html,
+            when,
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        let received = server.join().unwrap();
+        assert_eq!(
+            received[0],
+            format!("AUTH PLAIN {}", base64(b"\0me\0hunter2"))
+        );
+        assert_eq!(received[1], "MAIL FROM:<me@example.com>");
+        assert_eq!(received[2], "RCPT TO:<me@example.com>");
+        assert!(received.contains(&"Subject: Comics".to_string()));
+        assert!(received.contains(&"Content-Type: text/html; charset=utf-8".to_string()));
+        assert!(received.contains(&base64(html.as_bytes())));
+    }
+}
@@ -1,275 +1,2214 @@
 #[macro_use]
 extern crate log;
-use pretty_env_logger;
 
 use reqwest;
 
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::Read;
-use std::str::FromStr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-use chrono::Local;
-use clap::{App, Arg};
+use chrono::{DateTime, Local, Utc};
+use regex::Regex;
+use structopt::StructOpt;
 
+use feedburst::{
+    clock, dedupe, digest, edit, error, extract, feed, fetch, include, output, parser, quoting,
+    reload, session, simulate, stats, validate,
+};
+
+mod bundle;
+mod cli;
+mod compression;
 mod config;
-mod error;
-mod feed;
-mod parse_util;
-mod parser;
+mod daemon;
+mod explain;
+mod export;
+mod health;
+mod help;
+mod http_cache;
+mod journal;
+mod json;
+mod logging;
+mod mail;
+mod opener;
+mod opml;
+mod picker;
+mod pin;
 mod platform;
+mod playground;
+mod pool;
+mod progress;
+mod report;
+mod schema;
+mod selftest;
+mod status;
+mod storage;
+mod summary;
 
-use crate::error::{Error, ParseError, Span};
-use crate::feed::Feed;
+use crate::cli::{Command, Export, Import, Opt};
+use crate::dedupe::ReadComics;
+use crate::edit::PolicyEdit;
+use crate::error::{Error, ParseError};
+use crate::feed::{Feed, FeedEvent, FeedInfo};
+use crate::fetch::Fetcher;
+use crate::progress::Progress;
+use crate::report::{ColorChoice, ErrorFormat, Reporter};
+use crate::summary::FeedSummary;
 
 const APP_NAME: &str = env!("CARGO_PKG_NAME");
 
+/// Reading streaks shorter than this aren't worth mentioning.
+const SHOW_STREAK_AFTER: usize = 3;
+
+/// How many feeds to download at once, unless `--jobs` or `jobs` says otherwise.
+const DEFAULT_JOBS: usize = 4;
+
 fn main() {
-    if let Err(err) = run() {
-        eprintln!("{}", err);
+    let opt = Opt::from_args();
+    logging::init(opt.verbose);
+    let format = match opt.error_format.as_str() {
+        "json" => ErrorFormat::Json,
+        _ => ErrorFormat::Human,
+    };
+    let color = match opt.color.as_str() {
+        "always" => ColorChoice::Always,
+        "never" => ColorChoice::Never,
+        _ => ColorChoice::Auto,
+    };
+    let reporter = Reporter::new(format, color);
+    if let Err(err) = run(opt, reporter) {
+        reporter.error(&err);
         std::process::exit(1);
     }
 }
 
-fn run() -> Result<(), Error> {
-    pretty_env_logger::init();
-    let matches = App::new(APP_NAME)
-        .version(env!("CARGO_PKG_VERSION"))
-        .author(env!("CARGO_PKG_AUTHORS"))
-        .about("Presents you your RSS feeds in chunks")
-        .arg(
-            Arg::with_name("config")
-                .long("config")
-                .value_name("FILE")
-                .help("The config file to load feeds from")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("feeds")
-                .long("feeds")
-                .value_name("PATH")
-                .help("The folder where feeds are stored")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("open-with")
-                .long("open-with")
-                .value_name("COMMAND")
-                .help(concat!(
-                    "The command to open the comic with. Any instance of @URL ",
-                    "will be replaced with the comic URL, and if @URL isn't ",
-                    "mentioned, the URL will be placed at the end of the command.",
-                ))
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("fetch")
-                .long("fetch")
-                .help("Only download feeds, don't view them"),
-        )
-        .max_term_width(120)
-        .get_matches();
-
-    let only_fetch = matches.value_of("fetch").is_some();
+fn run(mut opt: Opt, reporter: Reporter) -> Result<(), Error> {
+    let only_fetch = opt.fetch;
     let args = config::Args::new(
         only_fetch,
-        matches.value_of("feeds"),
-        matches.value_of("config"),
-        matches.value_of("open-with"),
-    )?;
-
-    let feeds = {
-        let mut file = args.config_file()?;
-        let mut text = String::new();
-        file.read_to_string(&mut text)?;
-
-        let make_error_message = |row: usize, span: Span, msg: &str| -> Error {
-            let mut message = format!(
-                "Line {}: Error parsing {}\n\n",
-                row,
-                args.config_path().display(),
-            );
-            let line = text.lines().nth(row - 1).unwrap_or_default();
-            message.push_str(&format!("{}\n", line));
-            match span {
-                None => message.push('\n'),
-                Some((l, r)) => {
-                    let underline = format!("{}{}\n", " ".repeat(l), "^".repeat(r - l + 1));
-                    message.push_str(&underline);
+        opt.feeds.as_deref(),
+        opt.config.as_deref(),
+        opt.open_with.as_deref(),
+        opt.now.as_deref(),
+        opt.sandbox,
+    )?
+    .with_json_output(opt.output == "json")
+    .with_seed(opt.seed.unwrap_or_else(random_seed));
+    let proxy = match opt.proxy {
+        Some(ref proxy) => {
+            Some(parser::parse_proxy(proxy).map_err(|err| Error::parse("--proxy", proxy, err))?)
+        }
+        None => None,
+    };
+    let timezone = match opt.timezone {
+        Some(ref zone) => Some(
+            parser::parse_timezone(zone).map_err(|err| Error::parse("--timezone", zone, err))?,
+        ),
+        None => None,
+    };
+    let args = with_settings(args.with_proxy(proxy).with_timezone(timezone), reporter);
+
+    let feeds = match opt.command.take() {
+        None if opt.explain_config => return explain_config(&args, opt.jobs),
+        None => load_config(&args)?,
+        Some(Command::Add {
+            name,
+            url,
+            on,
+            every,
+            no_check,
+        }) => return add_feed(&args, &name, &url, on.as_deref(), every, !no_check),
+        Some(Command::Remove {
+            feed,
+            archive,
+            delete_history,
+        }) => return remove_feed(&args, &feed, archive, delete_history),
+        Some(Command::Rename { feed, new_name }) => return rename_feed(&args, &feed, &new_name),
+        Some(Command::Where { feed }) => {
+            let info = find_feed(&load_config(&args)?, &feed)?;
+            let paths = args.paths(&info)?;
+            if let Some(dir) = paths.dir {
+                println!("Folder:     {}", dir.display());
+            }
+            match args.storage() {
+                parser::Backend::Files => println!("History:    {}", paths.history.display()),
+                parser::Backend::Sqlite => {
+                    let database = args.database_path(&info)?;
+                    println!("History:    {} (in SQLite)", database.display())
                 }
             }
-
-            message.push_str(&format!("Expected {}", msg));
-            Error::Msg(message)
-        };
-
-        match parser::parse_config(&text) {
-            Ok(feeds) => feeds,
-            Err(ParseError::Expected { msg, row, span }) => {
-                return Err(make_error_message(row, span, &msg));
+            println!("HTTP cache: {}", paths.cache.display());
+            println!("Health:     {}", paths.health.display());
+            return Ok(());
+        }
+        Some(Command::ConfigRestore { backup }) => return config_restore(&args, backup),
+        Some(Command::Policy {
+            action,
+            policy,
+            matching,
+        }) => return edit_policy(&args, &action, &policy, &matching),
+        Some(Command::FixUrls) => return fix_urls(&args),
+        Some(Command::AcceptReset { feed }) => {
+            let feeds = load_config(&args)?;
+            return accept_reset(&args, &feeds, &feed);
+        }
+        Some(Command::MarkRead { feed, url }) => {
+            let feeds = load_config(&args)?;
+            return mark_read(&args, &feeds, &feed, url.as_deref());
+        }
+        Some(Command::MarkUnread { feed }) => {
+            let feeds = load_config(&args)?;
+            return mark_unread(&args, &feeds, &feed);
+        }
+        Some(Command::Export {
+            what: Export::Blogroll { markdown },
+        }) => return export_blogroll(&args, reporter, markdown),
+        Some(Command::Export { what: Export::Opml }) => {
+            print!("{}", export::opml(&load_config(&args)?));
+            return Ok(());
+        }
+        Some(Command::Import {
+            what: Import::Opml { file },
+        }) => return import_opml(&args, &file),
+        Some(Command::Status) => return show_status(&opt, &args, reporter),
+        Some(Command::Validate) => return validate(&args, reporter),
+        Some(Command::Doctor { failures, stale }) => {
+            return doctor(&opt, &args, reporter, failures, stale)
+        }
+        Some(Command::Tui) => return pick_comics(&opt, &args, reporter),
+        Some(Command::Groups) => {
+            print!("{}", list_groups(&load_config(&args)?));
+            return Ok(());
+        }
+        Some(Command::Daemon { ref serve }) => {
+            return daemon::daemon(&opt, &args, serve.as_deref(), reporter)
+        }
+        Some(Command::Playground) => {
+            let machine = machine_for(&args);
+            let stdin = std::io::stdin();
+            playground::playground(stdin.lock(), &mut std::io::stdout(), &machine)?;
+            return Ok(());
+        }
+        Some(Command::Selftest) => {
+            if !selftest::selftest()? {
+                std::process::exit(1);
             }
+            return Ok(());
+        }
+        Some(Command::DebugBundle { output }) => return debug_bundle(&args, &output),
+        Some(Command::Schema { json }) => {
+            if json {
+                print!("{}", schema::schema_json());
+            } else {
+                print!("{}", help::syntax());
+            }
+            return Ok(());
+        }
+        Some(Command::Help { topic }) => return show_help(topic.as_deref()),
+        Some(Command::Simulate {
+            feed,
+            days,
+            policies,
+        }) => {
+            let feeds = load_config(&args)?;
+            return simulate(&args, &feeds, &feed, days, policies.as_deref());
         }
     };
 
+    let settings = load_settings(&args)?;
+    run_feeds(&opt, &args, &settings, reporter, feeds)?;
+    Ok(())
+}
+
+/// Download the feeds that are due and open the ones that are ready, as one
+/// run. Returns the feeds as they stand afterwards, leaving out any that
+/// couldn't be loaded or downloaded.
+fn run_feeds(
+    opt: &Opt,
+    args: &config::Args,
+    settings: &parser::Settings,
+    reporter: Reporter,
+    feeds: Vec<FeedInfo>,
+) -> Result<Vec<Feed>, Error> {
+    let only_fetch = opt.fetch;
+    let json_output = opt.output == "json";
+    let html_output = opt.output == "html";
+    let now = args.clock().now();
+    let feeds = match opt.group {
+        Some(ref group) => in_group(feeds, group)?,
+        None => feeds,
+    };
     if feeds.is_empty() {
-        println!(
+        args.say(&format!(
             "You're not following any comics. Add some to your config file at {}",
             args.config_path().display(),
-        );
-        return Ok(());
+        ));
+        if json_output {
+            println!("{}", summary::json(now, &[]).pretty());
+        }
+        return Ok(Vec::new());
     }
 
+    let journal_path = args.journal_path()?;
+    let dry_run = opt.dry_run;
+    if let (Some(interval), false) = (settings.min_run_interval, opt.force || dry_run) {
+        let runs = journal::load(&journal_path)?;
+        if let Some(run) = journal::recent_run(&runs, only_fetch, now, interval) {
+            args.say(&format!(
+                "Already ran at {}, which is within min_run_interval. Last time:",
+                run.when
+                    .with_timezone(&Local)
+                    .format("%H:%M on %a %Y-%m-%d"),
+            ));
+            if run.summary.is_empty() {
+                args.say("  Nothing was ready.");
+            }
+            for line in &run.summary {
+                args.say(&format!("  {}", line));
+            }
+            if json_output {
+                println!("{}", summary::json(now, &[]).pretty());
+            }
+            return Ok(Vec::new());
+        }
+    }
+
+    let mut summaries = Vec::new();
+    let mut done = Vec::new();
     let mut feeds: Vec<_> = feeds
         .into_iter()
-        .map(|info| {
-            let mut feed_file = args.feed_file(&info)?;
-            info.read_feed(&mut feed_file)
-        })
-        .filter_map(|feed| match feed {
+        .filter_map(|info| match load_feed(args, &info) {
             Ok(feed) => Some(feed),
             Err(err) => {
-                eprintln!("{}", err);
+                reporter.feed_error(&info.name, &err);
+                summaries.push(FeedSummary {
+                    errors: vec![err.to_string()],
+                    ..FeedSummary::new(&info.name)
+                });
                 None
             }
         })
         .collect();
 
+    // Only the first time, so feeds left on the default don't nag every run
+    for feed in feeds.iter().filter(|feed| feed.info.default_policy) {
+        if feed.events().next().is_none() {
+            let mut policies: Vec<_> = feed
+                .info
+                .update_policies
+                .iter()
+                .map(|x| x.to_string())
+                .collect();
+            policies.sort();
+            args.say(&format!(
+                "{}: has no policies, so it's using the default `{}`. \
+                 Add some after its URL, or change the default with a `default_policy` line.",
+                feed.info.name,
+                policies.join(" ")
+            ));
+        }
+    }
+
     // Fetch the feeds that are currently scheduled, not those that are unscheduled
-    feeds.sort_by_key(|feed| !feed.is_scheduled(Local::now()));
-
-    let rx = {
-        let (tx, rx) = std::sync::mpsc::channel();
-        const NUM_THREADS: usize = 4;
-        let mut groups: Vec<Vec<Feed>> = vec![vec![]; NUM_THREADS];
-        for (i, feed) in feeds.into_iter().enumerate() {
-            groups[i % NUM_THREADS].push(feed);
-        }
-
-        for group in groups {
-            let tx = tx.clone();
-            let args = args.clone();
-            std::thread::spawn(move || {
-                for feed in group {
-                    let name = feed.info.name.clone();
-                    match fetch_feed(&args, feed) {
-                        Ok(feed) => tx.send(feed).unwrap(),
-                        Err(Error::Msg(err)) => eprintln!("{}", err),
-                        Err(err) => eprintln!("Error in feed {}: {}", name, err),
-                    }
+    feeds.sort_by_key(|feed| !feed.is_scheduled(args.clock().local()));
+
+    let mut read_comics = if settings.dedupe {
+        Some(ReadComics::new(&feeds))
+    } else {
+        None
+    };
+    let jobs = opt.jobs.or(settings.jobs).unwrap_or(DEFAULT_JOBS);
+    let mut num_read = 0;
+    let mut summary = Vec::new();
+    let mut sections = Vec::new();
+    let mut emails: BTreeMap<String, Vec<digest::Section>> = BTreeMap::new();
+    let progress = Arc::new(Progress::new(
+        feeds.len(),
+        progress::wanted(opt.quiet || opt.verbose > 0),
+    ));
+    let args = &args.clone().with_progress(progress.clone());
+    pool::run_in_order(
+        feeds,
+        jobs,
+        |feed| {
+            let name = feed.info.name.clone();
+            let (fetched, backed_off) = logging::in_feed(&name, || {
+                if !feed.needs_check(args.clock()) {
+                    (Ok(feed), None)
+                } else if let Some(until) = backed_off(opt, args, &feed.info) {
+                    (Ok(feed), Some(until))
+                } else {
+                    progress.start(&name);
+                    (fetch_feed(args, settings, feed), None)
                 }
             });
+            progress.finish(&name);
+            (name, fetched, backed_off)
+        },
+        |(name, fetched, backed_off)| {
+            logging::in_feed(&name, || {
+                let mut feed_summary = FeedSummary::new(&name);
+                if let Some(until) = backed_off {
+                    let line = format!(
+                        "{}: skipped due to backoff after failing to download again and again, \
+                         until {}",
+                        name,
+                        until.with_timezone(&Local).format("%H:%M on %a %Y-%m-%d")
+                    );
+                    args.say(&format!("{}. Use --force to try it now.", line));
+                    summary.push(line);
+                    feed_summary.skipped_until = Some(until.with_timezone(&Local));
+                }
+                let mut feed = match fetched {
+                    Ok(feed) => feed,
+                    Err(err) => {
+                        progress.suspend(|| reporter.feed_error(&name, &err));
+                        feed_summary.errors.push(err.to_string());
+                        summaries.push(feed_summary);
+                        return;
+                    }
+                };
+                if feed.is_ready(args.clock()) {
+                    let result = if dry_run {
+                        Ok(preview_feed(args, &feed))
+                    } else if only_fetch {
+                        remind(args, &mut feed).map(|line| {
+                            line.map(|heading| Reading {
+                                heading,
+                                opened: Vec::new(),
+                            })
+                        })
+                    } else {
+                        let digest = match args.deliver_to(&feed.info) {
+                            Some(to) => Some(emails.entry(to.to_string()).or_default()),
+                            None if html_output => Some(&mut sections),
+                            None => None,
+                        };
+                        read_feed(args, &mut feed, read_comics.as_mut(), digest)
+                    };
+                    match result {
+                        Ok(reading) => {
+                            num_read += 1;
+                            if let Some(reading) = reading {
+                                summary.push(reading.heading);
+                                feed_summary.opened = reading.opened;
+                            }
+                        }
+                        Err(err) => {
+                            progress.suspend(|| reporter.feed_error(&feed.info.name, &err));
+                            feed_summary.errors.push(err.to_string());
+                        }
+                    }
+                }
+                feed_summary.unread = feed.unread_comics().len();
+                feed_summary.next_scheduled = feed.next_scheduled(args.clock().local());
+                summaries.push(feed_summary);
+                done.push(feed);
+            })
+        },
+    );
+    progress.clear();
+
+    if json_output {
+        println!("{}", summary::json(now, &summaries).pretty());
+    }
+
+    if dry_run {
+        if num_read == 0 {
+            args.say("Nothing would be opened.");
         }
+        return Ok(Vec::new());
+    }
 
-        rx
+    let run = journal::Run {
+        only_fetch,
+        when: now,
+        summary,
     };
+    if let Err(err) = journal::record(&journal_path, run) {
+        reporter.warning(&format!(
+            "Couldn't record this run in {}: {}",
+            journal_path.display(),
+            err
+        ));
+    }
 
-    let mut num_read = 0;
-    for mut feed in rx {
-        if feed.is_ready() && !only_fetch {
-            if let Err(err) = read_feed(&args, &mut feed) {
-                eprintln!("Error in feed {}: {}", feed.info.name, err);
-            } else {
-                num_read += 1;
+    // A run of just one group only knows about some of the feeds
+    if let (Some((format, path)), None) = (args.output_feed(settings), &opt.group) {
+        let entries = output::ready_entries(&done, args.clock(), settings.dedupe);
+        let text = output::write_feed(format, "feedburst", &entries, now);
+        if let Err(err) = write_output_feed(&path, &text) {
+            reporter.warning(&format!(
+                "Couldn't write the output feed {}: {}",
+                path.display(),
+                err
+            ));
+        }
+    }
+
+    for (to, sections) in &emails {
+        let page = digest::write_digest(sections, now, opt.thumbnails);
+        let count: usize = sections.iter().map(|section| section.comics.len()).sum();
+        match deliver_digest(settings, to, &page, now) {
+            Ok(()) => args.say(&format!(
+                "Emailed {} {} to {}",
+                count,
+                if count == 1 { "comic" } else { "comics" },
+                to
+            )),
+            Err(err) => {
+                // They're already marked read, so keep them somewhere
+                let path = journal_path.with_file_name(format!("undelivered-{}.html", to));
+                let kept = match std::fs::write(&path, page) {
+                    Ok(()) => format!("They're in {} instead.", path.display()),
+                    Err(write_err) => format!("Couldn't keep them either: {}", write_err),
+                };
+                reporter.warning(&format!(
+                    "Couldn't email the comics to {}: {}. {}",
+                    to, err, kept
+                ));
             }
         }
     }
 
+    if html_output && !sections.is_empty() {
+        let path = args.digest_path(opt.digest.as_deref())?;
+        let page = digest::write_digest(&sections, now, opt.thumbnails);
+        std::fs::write(&path, page)
+            .map_err(|err| Error::Msg(format!("Couldn't write {}: {}", path.display(), err)))?;
+        args.say(&format!("Wrote the comics to {}", path.display()));
+        args.open_page(&path)?;
+    }
+
     if num_read == 0 && !only_fetch {
         // @Todo: Provide a better estimate of when new comics will be available.
-        println!("No new comics. Check back tomorrow!");
+        args.say("No new comics. Check back tomorrow!");
+    }
+
+    Ok(done)
+}
+
+/// Email the digest `page` to `to` through the config's `smtp` server.
+fn deliver_digest(
+    settings: &parser::Settings,
+    to: &str,
+    page: &str,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<(), Error> {
+    let smtp = settings.smtp.as_ref().ok_or_else(|| {
+        Error::Msg("there's no `smtp` line in the config to send it through".into())
+    })?;
+    let subject = format!("Comics for {}", now.format("%Y-%m-%d"));
+    mail::send(smtp, to, &subject, page, now, mail::TIMEOUT)
+}
+
+/// Replace the `output_feed` at `path` with `text` all at once, so a feed
+/// reader never sees half of it.
+fn write_output_feed(path: &std::path::Path, text: &str) -> std::io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    std::fs::write(&temp_path, text)?;
+    std::fs::rename(&temp_path, path)
+}
+
+/// The machine the config is loaded on, for `only on` guards and whether
+/// `--sandbox` allows `{env:NAME}`.
+fn machine_for(args: &config::Args) -> parser::Machine {
+    parser::Machine::current(platform::hostname()).with_sandbox(args.is_sandbox())
+}
+
+/// The config file with the files it includes pulled in.
+fn read_config(args: &config::Args) -> Result<include::Config, Error> {
+    let mut file = args.config_file()?;
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+
+    let machine = machine_for(args);
+    include::expand(args.config_path(), &text, &machine, |path| {
+        Ok(std::fs::read_to_string(path)?)
+    })
+}
+
+fn load_config(args: &config::Args) -> Result<Vec<FeedInfo>, Error> {
+    let config = read_config(args)?;
+    let machine = machine_for(args);
+    let mut feeds =
+        parser::parse_config_for(&config.text, &machine).map_err(|err| config.error(err))?;
+    if let Some(proxy) = args.proxy_flag() {
+        for info in &mut feeds {
+            info.proxy = Some(proxy.clone());
+        }
+    }
+    if let Some(zone) = args.timezone_flag() {
+        for info in &mut feeds {
+            info.timezone = Some(zone);
+        }
     }
+    Ok(feeds)
+}
+
+fn load_settings(args: &config::Args) -> Result<parser::Settings, Error> {
+    let config = read_config(args)?;
+    let machine = machine_for(args);
+    parser::parse_settings_for(&config.text, &machine).map_err(|err| config.error(err))
+}
 
+/// Print where each feed's settings come from, for `--explain-config`.
+fn explain_config(args: &config::Args, jobs: Option<usize>) -> Result<(), Error> {
+    let config = read_config(args)?;
+    let machine = machine_for(args);
+    let parse_error = |err| config.error(err);
+    let settings = parser::parse_settings_for(&config.text, &machine).map_err(parse_error)?;
+    let feeds = parser::parse_config_spans(&config.text, &machine).map_err(parse_error)?;
+    print!(
+        "{}",
+        explain::explain_config(args, &config, &settings, &feeds, jobs)?
+    );
     Ok(())
 }
 
-fn fetch_feed(args: &config::Args, mut feed: Feed) -> Result<Feed, Error> {
-    debug!("Fetching \"{}\" from <{}>", feed.info.name, feed.info.url);
-    let client = reqwest::ClientBuilder::new()
-        .timeout(std::time::Duration::from_secs(5))
-        .build()?;
-    let mut resp = client.get(&feed.info.url).send()?;
-    if !resp.status().is_success() {
-        debug!(
-            "Error \"{}\" fetching feed {} from {}",
-            resp.status(),
-            feed.info.name,
-            feed.info.url,
+/// Check the config and every feed's history without downloading anything,
+/// for `feedburst validate`. Every problem is reported, and then it's an error
+/// if there were any.
+fn validate(args: &config::Args, reporter: Reporter) -> Result<(), Error> {
+    let config = read_config(args)?;
+    let machine = machine_for(args);
+    let (feeds, errors) = validate::every_error(&config.text, |text| {
+        parser::parse_config_spans(text, &machine)
+    });
+    let feeds = feeds.unwrap_or_default();
+    let mut problems = Vec::new();
+    problems.extend(errors.into_iter().map(|err| config.error(err)));
+    problems.extend(
+        validate::duplicate_names(&config.text, &feeds)
+            .into_iter()
+            .map(|err| config.error(err)),
+    );
+
+    match args.feeds_flag() {
+        Some(root) if !root.is_dir() => problems.push(Error::Msg(format!(
+            "The --feeds folder {} doesn't exist",
+            root.display()
+        ))),
+        Some(_) => (),
+        None if args.is_sandbox() => (),
+        None => {
+            let mut rows = HashSet::new();
+            for (info, spans) in &feeds {
+                let (root, row) = match (&info.root, spans.directives.get("root")) {
+                    (Some(root), Some(&row)) => (root, row),
+                    _ => continue,
+                };
+                let root = match config::expand(&root.to_string_lossy()) {
+                    Ok(root) => PathBuf::from(root),
+                    Err(err) => {
+                        if rows.insert(row) {
+                            problems.push(err);
+                        }
+                        continue;
+                    }
+                };
+                if root.is_dir() || !rows.insert(row) {
+                    continue;
+                }
+                let line = config.text.lines().nth(row - 1).unwrap_or_default();
+                let path = line.trim().trim_start_matches("root").trim_start();
+                let start = line.find(path).unwrap_or_default();
+                problems.push(config.error(ParseError::expected(
+                    format!("a folder that exists ({} isn't one)", root.display()),
+                    row,
+                    (start, start + path.len().saturating_sub(1)),
+                )));
+            }
+        }
+    }
+
+    let mut histories = 0;
+    for (info, _) in &feeds {
+        let (path, text) = match history_text(args, info) {
+            Ok(Some(found)) => found,
+            Ok(None) => continue,
+            Err(err) => {
+                problems.push(err);
+                continue;
+            }
+        };
+        let (_, errors) = validate::every_error(&text, parser::parse_events);
+        let what = format!("feed \"{}\" ({})", info.name, path.display());
+        problems.extend(
+            errors
+                .into_iter()
+                .map(|err| Error::parse(what.as_str(), &text, err)),
         );
+        histories += 1;
+    }
+
+    for problem in &problems {
+        reporter.error(problem);
+    }
+    match problems.len() {
+        0 => {
+            args.say(&format!(
+                "No problems in {} or the histories of {} feeds",
+                args.config_path().display(),
+                histories
+            ));
+            Ok(())
+        }
+        1 => Err(Error::Msg("Found 1 problem".into())),
+        n => Err(Error::Msg(format!("Found {} problems", n))),
+    }
+}
+
+/// The history of the feed `info` for `validate`, and where it's kept, read
+/// without changing anything: the feed database's with `storage sqlite`, if
+/// it has it, or else the feed file's. `None` if it has no history yet.
+fn history_text(args: &config::Args, info: &FeedInfo) -> Result<Option<(PathBuf, String)>, Error> {
+    if let parser::Backend::Sqlite = args.storage() {
+        if let Some(text) = storage::stored_history(args, info, &info.name)? {
+            return Ok(Some((args.database_path(info)?, text)));
+        }
+    }
+    let path = match args.history_path(info, &info.name) {
+        Ok(path) if path.exists() => path,
+        _ => return Ok(None),
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(text) => Ok(Some((path, text))),
+        Err(err) => Err(Error::Msg(format!(
+            "Couldn't read the history of \"{}\" ({}): {}",
+            info.name,
+            path.display(),
+            err
+        ))),
+    }
+}
+
+/// Change a policy across every feed matching `pattern`, and save the config.
+fn edit_policy(
+    args: &config::Args,
+    action: &str,
+    policy: &str,
+    pattern: &str,
+) -> Result<(), Error> {
+    let mut policies =
+        parser::parse_policy_list(policy).map_err(|err| Error::parse("the policy", policy, err))?;
+    if policies.len() != 1 {
         return Err(Error::Msg(format!(
-            "{} (Failed to download: \"{}\")",
-            feed.info.name,
-            resp.status(),
+            "Expected exactly one policy, got \"{}\"",
+            policy
         )));
     }
-    let mut content = String::new();
-    resp.read_to_string(&mut content)?;
-    let links: Vec<_> = {
-        use syndication::Feed;
-        let feed_info = &feed.info;
-        match Feed::from_str(&content).map_err(|x| Error::Msg(x.into()))? {
-            Feed::Atom(feed) => {
-                debug!("Parsed feed <{}> as Atom", feed_info.url);
-                feed.entries
-                    .into_iter()
-                    .rev()
-                    .filter(|x| {
-                        let keep = feed_info.filter_title(&x.title);
-                        if !keep {
-                            debug!("skipping by title: {}", x.title);
-                        }
-                        keep
-                    })
-                    .filter_map(|x| x.links.first().cloned())
-                    .map(|x| x.href)
-                    .filter(|url| feed_info.filter_url(&url))
-                    .collect()
+    let policy = policies.remove(0);
+    let edit = match action {
+        "add" => PolicyEdit::Add(policy),
+        "remove" => PolicyEdit::Remove(policy),
+        _ => PolicyEdit::Replace(policy),
+    };
+    let pattern = Regex::new(pattern)
+        .map_err(|err| Error::Msg(format!("Invalid pattern /{}/: {}", pattern, err)))?;
+
+    let mut text = String::new();
+    args.config_file()?.read_to_string(&mut text)?;
+    let machine = machine_for(args);
+    let matches = |info: &FeedInfo| pattern.is_match(&info.name) || pattern.is_match(&info.url);
+    let edited = edit::edit_policies(&text, &machine, matches, &edit)
+        .map_err(|err| Error::parse(args.config_path().display().to_string(), &text, err))?;
+
+    if edited.changed.is_empty() {
+        println!("No feeds needed changing.");
+        return Ok(());
+    }
+    config::write_config(args.writable_config_path()?, &edited.text)?;
+    for name in &edited.changed {
+        println!("Changed {}", name);
+    }
+    Ok(())
+}
+
+/// Rewrite the config with the URLs that feeds have permanently moved to,
+/// as recorded in their histories.
+fn fix_urls(args: &config::Args) -> Result<(), Error> {
+    let mut moves = HashMap::new();
+    for info in load_config(args)? {
+        let feed = load_feed(args, &info)?;
+        if feed.canonical_url() != info.url {
+            match info.saved_url(feed.canonical_url()) {
+                Some(to) => {
+                    moves.insert(info.name.clone(), (info.url.clone(), to));
+                }
+                None => println!(
+                    "{} has moved to a URL with a secret from {{env:NAME}} in it that can't be \
+                     told apart from the rest. Change its URL in the config by hand.",
+                    info.name
+                ),
             }
-            Feed::RSS(feed) => {
-                debug!("Parsed feed <{}> as RSS", feed_info.url);
-                feed.items
-                    .into_iter()
-                    .rev()
-                    .filter(|x| {
-                        let title = &x.title;
-                        let title = title.as_ref().map(|x| &x[..]).unwrap_or("");
-                        let keep = feed_info.filter_title(&title);
-                        if !keep {
-                            debug!("skipping by title: {:?}", x.title);
-                        }
-                        keep
-                    })
-                    .filter_map(|x| x.link)
-                    .filter(|url| feed_info.filter_url(&url))
-                    .collect()
+        }
+    }
+    if moves.is_empty() {
+        println!("No feeds have moved.");
+        return Ok(());
+    }
+
+    let mut text = String::new();
+    args.config_file()?.read_to_string(&mut text)?;
+    let machine = machine_for(args);
+    let new_url = |info: &FeedInfo| match moves.get(&info.name) {
+        Some((from, to)) if *from == info.url => Some(to.clone()),
+        _ => None,
+    };
+    let edited = edit::replace_urls(&text, &machine, new_url)
+        .map_err(|err| Error::parse(args.config_path().display().to_string(), &text, err))?;
+
+    if edited.changed.is_empty() {
+        println!("No feeds needed changing.");
+        return Ok(());
+    }
+    config::write_config(args.writable_config_path()?, &edited.text)?;
+    for name in &edited.changed {
+        println!("Changed {} to <{}>", name, moves[name].1);
+    }
+    Ok(())
+}
+
+/// Add a feed to the end of the config. With `check`, the URL is downloaded
+/// first to make sure it's a feed, and if it's a web page instead, the feed it
+/// links to is added in its place.
+fn add_feed(
+    args: &config::Args,
+    name: &str,
+    url: &str,
+    on: Option<&str>,
+    every: Option<usize>,
+    check: bool,
+) -> Result<(), Error> {
+    check_name(name)?;
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|err| Error::Msg(format!("Invalid URL <{}>: {}", url, err)))?;
+    if !["http", "https"].contains(&parsed.scheme()) || url.contains('>') {
+        return Err(Error::Msg(format!(
+            "Expected an http or https URL, got <{}>",
+            url
+        )));
+    }
+    let mut policies = String::new();
+    if let Some(days) = on {
+        policies.push_str(&format!("@ on {} ", days));
+    }
+    if let Some(days) = every {
+        policies.push_str(&format!("@ every {} days", days));
+    }
+    let policies = parser::parse_policy_list(&policies)
+        .map_err(|err| Error::parse("the policies", &policies, err))?;
+
+    let path = args.writable_config_path()?;
+    if load_config(args)?.iter().any(|info| info.name == name) {
+        return Err(Error::Msg(format!(
+            "There's already a feed named \"{}\" in the config",
+            name
+        )));
+    }
+    let url = if check {
+        discover_feed(args, name, url)?
+    } else {
+        url.to_string()
+    };
+
+    let line = edit::feed_line(name, &url, &policies);
+    let mut text = String::new();
+    args.config_file()?.read_to_string(&mut text)?;
+    let machine = machine_for(args);
+    let text = edit::append_feed(&text, &machine, &line)
+        .map_err(|err| Error::parse(args.config_path().display().to_string(), &text, err))?;
+    config::write_config(path, &text)?;
+    println!("Added {}", line);
+    Ok(())
+}
+
+/// Make sure `name` can be written as a feed's name in the config.
+fn check_name(name: &str) -> Result<(), Error> {
+    if name.trim().is_empty() || name.contains('"') {
+        return Err(Error::Msg(format!(
+            "Feed names can't be blank or contain '\"', got \"{}\"",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Take the feed called `name` out of the config, and with `archive` or
+/// `delete`, its history out of storage.
+fn remove_feed(args: &config::Args, name: &str, archive: bool, delete: bool) -> Result<(), Error> {
+    let path = args.writable_config_path()?;
+    let info = find_feed(&load_config(args)?, name)?;
+    let mut text = String::new();
+    args.config_file()?.read_to_string(&mut text)?;
+    let machine = machine_for(args);
+    let mut document = edit::Document::new(&text);
+    let removed = document
+        .remove_feed(&machine, name)
+        .map_err(|err| Error::parse(args.config_path().display().to_string(), &text, err))?;
+    if !removed {
+        return Err(not_in_main_config(name));
+    }
+
+    // Archived before anything is changed, so a failed write loses nothing
+    let storage = storage::open(args);
+    let archived = if archive {
+        let archived = args.archive_path(&info)?;
+        std::fs::write(&archived, storage.history_text(&info)?)?;
+        Some(archived)
+    } else {
+        None
+    };
+    config::write_config(path, &document.text())?;
+    if archive || delete {
+        storage.remove(&info)?;
+    }
+
+    if let Some(archived) = archived {
+        println!(
+            "Removed \"{}\", and moved its history to {}",
+            name,
+            archived.display()
+        );
+    } else if delete {
+        println!("Removed \"{}\", and deleted its history", name);
+    } else {
+        println!(
+            "Removed \"{}\". Its history is kept, in case it's added back.",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Call the feed `name` `new_name` in the config, and move its history to go
+/// with the new name.
+fn rename_feed(args: &config::Args, name: &str, new_name: &str) -> Result<(), Error> {
+    check_name(new_name)?;
+    let path = args.writable_config_path()?;
+    let feeds = load_config(args)?;
+    let info = find_feed(&feeds, name)?;
+    if feeds.iter().any(|info| info.name == new_name) {
+        return Err(Error::Msg(format!(
+            "There's already a feed named \"{}\" in the config",
+            new_name
+        )));
+    }
+    let mut text = String::new();
+    args.config_file()?.read_to_string(&mut text)?;
+    let machine = machine_for(args);
+    let mut document = edit::Document::new(&text);
+    let renamed = document
+        .rename_feed(&machine, name, new_name)
+        .map_err(|err| Error::parse(args.config_path().display().to_string(), &text, err))?;
+    if !renamed {
+        return Err(not_in_main_config(name));
+    }
+
+    let storage = storage::open(args);
+    storage.rename(&info, new_name)?;
+    if let Err(err) = config::write_config(path, &document.text()) {
+        // Put the history back with the name the config still has
+        let renamed = FeedInfo {
+            name: new_name.into(),
+            ..info
+        };
+        storage.rename(&renamed, name)?;
+        return Err(err);
+    }
+    println!("Renamed \"{}\" to \"{}\"", name, new_name);
+    Ok(())
+}
+
+/// The error for a feed that's only in an included file, which commands that
+/// rewrite the config don't change.
+fn not_in_main_config(name: &str) -> Error {
+    Error::Msg(format!(
+        "\"{}\" is in a file the config includes, so change it there",
+        name
+    ))
+}
+
+/// Add the feeds in an OPML file to the end of the config, leaving out any
+/// that are already in it.
+fn import_opml(args: &config::Args, file: &str) -> Result<(), Error> {
+    let opml = std::fs::read_to_string(file)
+        .map_err(|err| Error::Msg(format!("Cannot read {}: {}", file, err)))?;
+    let outlines = opml::outlines(&opml);
+    if outlines.is_empty() {
+        return Err(Error::Msg(format!("There are no feeds in {}", file)));
+    }
+
+    let path = args.writable_config_path()?;
+    let feeds = load_config(args)?;
+    let names = feeds.iter().map(|info| info.name.clone()).collect();
+    let urls = feeds.iter().map(|info| info.url.clone()).collect();
+    let (lines, count) = opml::config_lines(&outlines, &names, &urls)?;
+    if count == 0 {
+        println!("Every feed in {} is already in the config.", file);
+        return Ok(());
+    }
+
+    let mut text = String::new();
+    args.config_file()?.read_to_string(&mut text)?;
+    let machine = machine_for(args);
+    let lines = format!("\n# Imported from {}\n{}", file, lines.trim_start());
+    let text = edit::append_feed(&text, &machine, &lines)
+        .map_err(|err| Error::parse(args.config_path().display().to_string(), &text, err))?;
+    config::write_config(path, &text)?;
+    println!(
+        "Imported {} of the {} feeds in {}",
+        count,
+        outlines.len(),
+        file
+    );
+    Ok(())
+}
+
+/// The URL of the feed at `url`: `url` itself if it downloads as a feed, or
+/// else the first feed that the web page there links to.
+fn discover_feed(args: &config::Args, name: &str, url: &str) -> Result<String, Error> {
+    let fetcher = HttpFetcher::new()?;
+    let now = args.clock().now();
+    let info_for = |url: &str| -> Result<FeedInfo, Error> {
+        let line = edit::feed_line(name, url, &[]);
+        let mut feeds =
+            parser::parse_config(&line).map_err(|err| Error::parse("the feed", &line, err))?;
+        let mut info = feeds.remove(0);
+        info.proxy = args.proxy_flag().cloned();
+        Ok(info)
+    };
+    let info = info_for(url)?;
+    let page = fetcher.fetch(&info)?;
+    match extract::extract_items(&info, &page, now) {
+        Ok(_) => return Ok(url.to_string()),
+        Err(err) => debug!("<{}> isn't a feed ({}), looking for links to one", url, err),
+    }
+    for link in extract::feed_links(&page) {
+        let link = match extract::resolve_link(url, &link) {
+            Some(link) => link,
+            None => continue,
+        };
+        let info = info_for(&link)?;
+        let found = fetcher
+            .fetch(&info)
+            .and_then(|content| extract::extract_items(&info, &content, now));
+        match found {
+            Ok(_) => {
+                println!("<{}> is a web page, using the feed it links to", url);
+                return Ok(link);
             }
+            Err(err) => debug!("Skipping feed link <{}>: {}", link, err),
+        }
+    }
+    Err(Error::Msg(format!(
+        "<{}> isn't an RSS or Atom feed, and doesn't link to one",
+        url
+    )))
+}
+
+fn show_help(topic: Option<&str>) -> Result<(), Error> {
+    match topic {
+        Some("syntax") => print!("{}", help::syntax()),
+        Some("man") => print!("{}", help::man_page()),
+        Some(topic) => {
+            // Let clap print the subcommand's help, or complain that it doesn't exist
+            let argv = vec!["feedburst", topic, "--help"];
+            Opt::clap()
+                .get_matches_from_safe(argv)
+                .map_err(|err| match err.kind {
+                    structopt::clap::ErrorKind::HelpDisplayed => Error::Msg(err.message),
+                    _ => Error::Msg(format!(
+                        "No help for \"{}\". Try a subcommand, \"syntax\", or \"man\".",
+                        topic
+                    )),
+                })?;
         }
+        None => {
+            Opt::clap()
+                .print_long_help()
+                .map_err(|err| Error::Msg(err.message))?;
+            println!();
+        }
+    }
+    Ok(())
+}
+
+fn config_restore(args: &config::Args, backup: usize) -> Result<(), Error> {
+    if backup == 0 {
+        return Err(Error::Msg("Backup number must be 1 or more".into()));
+    }
+    let before = load_config(args).ok();
+    let restored = config::restore_config(args.writable_config_path()?, backup)?;
+    println!(
+        "Restored {} from {}",
+        args.config_path().display(),
+        restored.display(),
+    );
+    if let (Some(before), Ok(after)) = (before, load_config(args)) {
+        print!("{}", reload::diff_configs(&before, &after));
+    }
+    Ok(())
+}
+
+fn find_feed(feeds: &[FeedInfo], name: &str) -> Result<FeedInfo, Error> {
+    match feeds.iter().find(|info| info.name == name) {
+        Some(info) => Ok(info.clone()),
+        None => {
+            let msg = format!("There's no feed named \"{}\" in the config", name);
+            Err(Error::Msg(msg))
+        }
+    }
+}
+
+/// Print `feedburst status`, for the feeds in `--group` if it was given.
+fn show_status(opt: &Opt, args: &config::Args, reporter: Reporter) -> Result<(), Error> {
+    let infos = load_config(args)?;
+    let infos = match opt.group {
+        Some(ref group) => in_group(infos, group)?,
+        None => infos,
     };
+    let mut feeds = Vec::new();
+    for info in &infos {
+        match load_feed(args, info) {
+            Ok(feed) => {
+                let health = health::load(&args.health_path(info)?);
+                let validators = http_cache::load(&args.cache_path(info)?);
+                feeds.push((feed, health, validators));
+            }
+            Err(err) => reporter.feed_error(&info.name, &err),
+        }
+    }
+    print!("{}", status::status(&feeds, args.clock().local()));
+    Ok(())
+}
 
-    let mut feed_file = args.feed_file(&feed.info)?;
-    feed.add_new_comics(&links);
-    feed.write_changes(&mut feed_file)?;
-    Ok(feed)
+/// `feedburst doctor`: list the feeds that need looking at, going by what
+/// their recent downloads recorded.
+fn doctor(
+    opt: &Opt,
+    args: &config::Args,
+    reporter: Reporter,
+    failures: usize,
+    stale: i64,
+) -> Result<(), Error> {
+    let infos = load_config(args)?;
+    let infos = match opt.group {
+        Some(ref group) => in_group(infos, group)?,
+        None => infos,
+    };
+    let now = args.clock().now();
+    let mut healthy = true;
+    for info in &infos {
+        let feed = match load_feed(args, info) {
+            Ok(feed) => feed,
+            Err(err) => {
+                reporter.feed_error(&info.name, &err);
+                continue;
+            }
+        };
+        let health = health::load(&args.health_path(info)?);
+        let problems =
+            health::diagnose(&feed, &health, now, failures, chrono::Duration::days(stale));
+        for problem in problems {
+            println!("{}: {}", info.name, problem);
+            healthy = false;
+        }
+    }
+    if healthy {
+        println!("No problems found.");
+    }
+    Ok(())
+}
+
+/// `feedburst tui`: download the feeds like a normal run, then open the
+/// comics picked from the ready feeds. Each feed is marked read up to the
+/// last comic picked from it, so the ones after that are left for next time.
+fn pick_comics(opt: &Opt, args: &config::Args, reporter: Reporter) -> Result<(), Error> {
+    let settings = load_settings(args)?;
+    let infos = load_config(args)?;
+    let infos = match opt.group {
+        Some(ref group) => in_group(infos, group)?,
+        None => infos,
+    };
+    let mut feeds = Vec::new();
+    for info in &infos {
+        match load_feed(args, info) {
+            Ok(feed) => feeds.push(feed),
+            Err(err) => reporter.feed_error(&info.name, &err),
+        }
+    }
+
+    let jobs = opt.jobs.or(settings.jobs).unwrap_or(DEFAULT_JOBS);
+    let mut ready = Vec::new();
+    let progress = Arc::new(Progress::new(
+        feeds.len(),
+        progress::wanted(opt.quiet || opt.verbose > 0),
+    ));
+    let fetch_args = args.clone().with_progress(progress.clone());
+    pool::run_in_order(
+        feeds,
+        jobs,
+        |feed| {
+            let name = feed.info.name.clone();
+            let fetched = logging::in_feed(&name, || {
+                if !feed.needs_check(args.clock()) || backed_off(opt, args, &feed.info).is_some() {
+                    Ok(feed)
+                } else {
+                    progress.start(&name);
+                    fetch_feed(&fetch_args, &settings, feed)
+                }
+            });
+            progress.finish(&name);
+            (name, fetched)
+        },
+        |(name, fetched)| match fetched {
+            Ok(feed) => {
+                if feed.is_ready(args.clock()) && !feed.get_reading_list().is_empty() {
+                    ready.push(feed);
+                }
+            }
+            Err(err) => progress.suspend(|| reporter.feed_error(&name, &err)),
+        },
+    );
+    progress.clear();
+    if ready.is_empty() {
+        args.say("No new comics. Check back tomorrow!");
+        return Ok(());
+    }
+
+    let choices = ready.iter().map(picker::Choice::new).collect();
+    let choices = match picker::pick(choices)? {
+        Some(choices) => choices,
+        None => return Ok(()),
+    };
+    for (feed, choice) in ready.iter_mut().zip(&choices) {
+        let picked = choice.picked();
+        if let Err(err) = open_picked(args, feed, &picked) {
+            reporter.feed_error(&feed.info.name, &err);
+        }
+    }
+    Ok(())
 }
 
-fn read_feed(args: &config::Args, feed: &mut Feed) -> Result<(), Error> {
-    let mut feed_file = args.feed_file(&feed.info)?;
-    let items = feed.get_reading_list();
-    if items.is_empty() {
+/// Open the comics picked from a feed, or just the first with the rest
+/// linked from it unless it's `@ open all`, and mark them read.
+fn open_picked(args: &config::Args, feed: &mut Feed, picked: &[&str]) -> Result<(), Error> {
+    if picked.is_empty() {
         return Ok(());
     }
-    let plural_feeds = if items.len() == 1 { "comic" } else { "comics" };
-    println!("{} ({} {})", feed.info.name, items.len(), plural_feeds);
-    if feed
+    args.say(&format!(
+        "{} ({} {})",
+        feed.info.format.feed_name(&feed.info.name),
+        picked.len(),
+        if picked.len() == 1 { "comic" } else { "comics" }
+    ));
+    let open_all = feed
         .info
         .update_policies
-        .contains(&feed::UpdateSpec::OpenAll)
+        .contains(&feed::UpdateSpec::OpenAll);
+    let urls = if open_all { picked } else { &picked[..1] };
+    for url in urls {
+        open_comic(args, feed, url)?;
+    }
+    feed.open();
+    let now = args.clock().now();
+    for url in picked {
+        feed.read_comic_at(url, now);
+    }
+    save_feed(args, feed)
+}
+
+/// The feeds in `group`, which has to be named by some `group` line.
+fn in_group(feeds: Vec<FeedInfo>, group: &str) -> Result<Vec<FeedInfo>, Error> {
+    if !feeds
+        .iter()
+        .any(|info| info.group.as_deref() == Some(group))
     {
-        // Open all the comics instead of just the earliest one
-        for item in &items {
-            args.open_url(&feed.info, item)?;
+        let mut msg = format!("There's no group named \"{}\" in the config", group);
+        let groups: Vec<_> = group_counts(&feeds)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        if !groups.is_empty() {
+            msg.push_str(&format!(". Try one of: \"{}\"", groups.join("\", \"")));
         }
+        return Err(Error::Msg(msg));
+    }
+    Ok(feeds
+        .into_iter()
+        .filter(|info| info.group.as_deref() == Some(group))
+        .collect())
+}
+
+/// Each group in the config, in the order they first appear, with how many
+/// feeds are in it.
+fn group_counts(feeds: &[FeedInfo]) -> Vec<(&str, usize)> {
+    let mut groups: Vec<(&str, usize)> = Vec::new();
+    for group in feeds.iter().filter_map(|info| info.group.as_deref()) {
+        match groups.iter_mut().find(|(name, _)| *name == group) {
+            Some((_, count)) => *count += 1,
+            None => groups.push((group, 1)),
+        }
+    }
+    groups
+}
+
+/// The listing for `feedburst groups`.
+fn list_groups(feeds: &[FeedInfo]) -> String {
+    let groups = group_counts(feeds);
+    let mut out = String::new();
+    if groups.is_empty() {
+        out.push_str(
+            "There are no groups in the config. Start one with a `group \"NAME\"` line.\n",
+        );
+    }
+    for (group, count) in groups {
+        out.push_str(&format!(
+            "\"{}\": {} {}\n",
+            group,
+            count,
+            if count == 1 { "feed" } else { "feeds" }
+        ));
+    }
+    let ungrouped = feeds.iter().filter(|info| info.group.is_none()).count();
+    if ungrouped > 0 && ungrouped < feeds.len() {
+        out.push_str(&format!("Not in a group: {}\n", ungrouped));
+    }
+    out
+}
+
+fn simulate(
+    args: &config::Args,
+    feeds: &[FeedInfo],
+    name: &str,
+    days: i64,
+    policies: Option<&str>,
+) -> Result<(), Error> {
+    let mut info = find_feed(feeds, name)?;
+    // Load with the config's policies, so any aliases still count
+    let feed = load_feed(args, &info)?;
+    if let Some(policies) = policies {
+        info.update_policies = match parser::parse_policy_list(policies) {
+            Ok(policies) => {
+                info.rewrites = policies
+                    .iter()
+                    .filter(|p| p.is_rewrite())
+                    .cloned()
+                    .collect();
+                policies.into_iter().collect()
+            }
+            Err(ParseError::Expected { msg, .. }) => {
+                let msg = format!("Error parsing policies: expected {}", msg);
+                return Err(Error::Msg(msg));
+            }
+        };
+    }
+
+    let comics: Vec<_> = feed.comics().cloned().collect();
+    let start = args.clock().local() - chrono::Duration::days(days);
+    let dated = comics
+        .iter()
+        .filter_map(|x| x.published)
+        .filter(|&date| date >= start)
+        .count();
+
+    let mut policies: Vec<_> = info.update_policies.iter().map(|x| x.to_string()).collect();
+    policies.sort();
+    println!(
+        "Simulating \"{}\" over the last {} days with {}",
+        info.name,
+        days,
+        if policies.is_empty() {
+            "no policies".into()
+        } else {
+            policies.join(" ")
+        },
+    );
+    println!("({} comics with known publish dates in that time)\n", dated);
+
+    let sessions = simulate::simulate(&info, &comics, start, days);
+    for session in &sessions {
+        println!(
+            "{}  would open {} ({} new)",
+            session.when.format("%a %Y-%m-%d"),
+            session.total,
+            session.new,
+        );
+    }
+    if sessions.is_empty() {
+        println!("Would not have opened at all.");
     } else {
-        args.open_url(&feed.info, items.first().unwrap())?;
+        let total: usize = sessions.iter().map(|x| x.total).sum();
+        println!(
+            "\nWould have opened {} times, {:.1} comics each on average.",
+            sessions.len(),
+            total as f64 / sessions.len() as f64,
+        );
+    }
+    Ok(())
+}
+
+/// Unpause a feed that seemed to republish its archive.
+fn accept_reset(args: &config::Args, feeds: &[FeedInfo], name: &str) -> Result<(), Error> {
+    let info = find_feed(feeds, name)?;
+    let mut feed = load_feed(args, &info)?;
+    if !feed.reset_pending() {
+        let msg = format!("\"{}\" isn't paused, so there's no reset to accept", name);
+        return Err(Error::Msg(msg));
     }
-    feed.read();
-    feed.write_changes(&mut feed_file)?;
+    let fetcher = HttpFetcher::new()?.with_settings(&load_settings(args)?)?;
+    let count = fetch::accept_reset(&fetcher, &mut feed, args.clock().now())?;
+    save_feed(args, &mut feed)?;
+    println!(
+        "Unpaused \"{}\". The {} comics in it now count as already read.",
+        name, count
+    );
+    Ok(())
+}
+
+/// Mark the feed `name` read now, or just the comic at `url` in it.
+fn mark_read(
+    args: &config::Args,
+    feeds: &[FeedInfo],
+    name: &str,
+    url: Option<&str>,
+) -> Result<(), Error> {
+    let info = find_feed(feeds, name)?;
+    let now = args.clock().now();
+    let mut message = String::new();
+    storage::open(args).update(&info, &mut |feed| {
+        feed.set_seed(args.seed());
+        message = match url {
+            Some(url) => {
+                if !feed.read_comic_at(url, now) {
+                    return Err(Error::Msg(format!(
+                        "<{}> isn't an unread comic in \"{}\"",
+                        url, name
+                    )));
+                }
+                format!("Marked <{}> in \"{}\" read.", url, name)
+            }
+            None => {
+                let count = feed.unread_comics().len();
+                feed.read_at(now);
+                let read = count - feed.unread_comics().len();
+                format!(
+                    "Marked \"{}\" read, with {} new {}.",
+                    name,
+                    read,
+                    if read == 1 { "comic" } else { "comics" }
+                )
+            }
+        };
+        Ok(())
+    })?;
+    println!("{}", message);
     Ok(())
 }
+
+/// Take the last `read` line out of the history of the feed `name`.
+fn mark_unread(args: &config::Args, feeds: &[FeedInfo], name: &str) -> Result<(), Error> {
+    let info = find_feed(feeds, name)?;
+    let mut removed = None;
+    storage::open(args).rewrite(&info, &mut |history| {
+        let (text, event) = feed::without_last_read(history).ok_or_else(|| {
+            Error::Msg(format!(
+                "\"{}\" hasn't been read, so there's nothing to undo",
+                name
+            ))
+        })?;
+        removed = Some(event);
+        Ok(text)
+    })?;
+    match removed {
+        Some(FeedEvent::ReadComic(_, url)) => {
+            println!("Marked <{}> in \"{}\" unread again.", url, name)
+        }
+        Some(FeedEvent::Read(date)) => println!(
+            "Undid reading \"{}\" at {}.",
+            name,
+            date.with_timezone(&Local).format("%H:%M on %a %Y-%m-%d")
+        ),
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Write a zip file for a bug report, with the config, the run journal, what
+/// this machine is, and what goes wrong with each feed that doesn't work.
+fn debug_bundle(args: &config::Args, output: &str) -> Result<(), Error> {
+    let mut text = String::new();
+    args.config_file()?.read_to_string(&mut text)?;
+    // With the files it includes, if they can be read
+    if let Ok(config) = read_config(args) {
+        text = config.text;
+    }
+    let mut about = format!(
+        "feedburst {}\nos: {} ({})\narch: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::FAMILY,
+        std::env::consts::ARCH,
+    );
+    let mut files = vec![("config.feeds".to_string(), bundle::redact(&text))];
+    if let Ok(journal) = std::fs::read_to_string(args.journal_path()?) {
+        files.push(("runs.journal".into(), bundle::redact(&journal)));
+    }
+
+    let mut failing = 0;
+    match load_config(args) {
+        Ok(feeds) => {
+            let settings = load_settings(args).unwrap_or_default();
+            let fetcher = HttpFetcher::new()?.with_settings(&settings)?;
+            let now = args.clock().now();
+            let jobs = settings.jobs.unwrap_or(DEFAULT_JOBS);
+            pool::run_in_order(
+                feeds,
+                jobs,
+                |info| {
+                    let problem = match fetcher.fetch(&info) {
+                        Err(err) => Some(format!(
+                            "Failed to download <{}>: {}\n",
+                            info.written_url(),
+                            err
+                        )),
+                        Ok(body) => match extract::extract_items(&info, &body, now) {
+                            Ok(_) => None,
+                            Err(err) => Some(format!(
+                                "Failed to read <{}>: {}\n\n{}",
+                                info.written_url(),
+                                err,
+                                body
+                            )),
+                        },
+                    };
+                    // Errors repeat the URL, secrets and all
+                    let problem = problem.map(|problem| {
+                        info.hide_secrets(&problem)
+                            .unwrap_or_else(|| problem.replace(&info.url, info.written_url()))
+                    });
+                    (info.name, problem)
+                },
+                |(name, problem)| {
+                    if let Some(problem) = problem {
+                        let path = format!("feeds/{}.txt", bundle::file_name(&name));
+                        files.push((path, bundle::redact(&problem)));
+                        failing += 1;
+                    }
+                },
+            );
+        }
+        Err(err) => about.push_str(&format!("\nThe config doesn't load:\n{}\n", err)),
+    }
+    files.insert(0, ("about.txt".into(), bundle::redact(&about)));
+
+    let file = std::fs::File::create(output)
+        .map_err(|err| Error::Msg(format!("Cannot create {}: {}", output, err)))?;
+    bundle::write_zip(file, &files)?;
+    println!(
+        "Wrote {} with {} failing {}. Look it over before attaching it to a bug report.",
+        output,
+        failing,
+        if failing == 1 { "feed" } else { "feeds" }
+    );
+    Ok(())
+}
+
+/// Print a blogroll of every feed in the config, finding each comic's site
+/// from its feed.
+fn export_blogroll(args: &config::Args, reporter: Reporter, markdown: bool) -> Result<(), Error> {
+    let fetcher = HttpFetcher::new()?;
+    let mut entries = Vec::new();
+    for info in load_config(args)? {
+        let link = match fetcher.fetch(&info) {
+            Ok(content) => extract::site_link(&content),
+            Err(err) => {
+                reporter.feed_error(&info.name, &err);
+                None
+            }
+        };
+        entries.push(export::Entry {
+            link: link.unwrap_or_else(|| export::origin(&info.url)),
+            name: info.name,
+        });
+    }
+    print!("{}", export::blogroll(&entries, markdown));
+    Ok(())
+}
+
+/// What to ask for when downloading a feed, for servers that pick a format
+/// based on the `Accept` header.
+const FEED_TYPES: &str =
+    "application/atom+xml, application/rss+xml, application/xml;q=0.9, text/xml;q=0.9, */*;q=0.8";
+
+/// How long to wait on a server before giving up on a download, unless
+/// `timeout` says otherwise.
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long to wait before trying a failed download again. Each try after that
+/// waits twice as long as the last, up to `MAX_RETRY_DELAY`.
+const RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// The longest to wait between tries of a failed download.
+const MAX_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How many redirects to follow before giving up on a download.
+const MAX_REDIRECTS: usize = 10;
+
+/// Downloads feeds over HTTP.
+struct HttpFetcher {
+    /// A client that gives up after `timeout`.
+    client: reqwest::Client,
+    timeout: std::time::Duration,
+    /// How many more times to try a download that failed in a way that might
+    /// not happen again, for feeds without `@ retries`.
+    retries: usize,
+    /// Where to keep the headers for conditional requests, if anywhere.
+    cache_path: Option<PathBuf>,
+    /// The headers from the last download, until `save_cache`.
+    downloaded: Mutex<Option<http_cache::Validators>>,
+    /// Where downloads were permanently redirected to, by the URL asked for.
+    moved: Mutex<HashMap<String, String>>,
+    /// The HTTP status of the last response to a download of the feed.
+    status: Mutex<Option<u16>>,
+    /// Where to count the bytes downloaded, if anywhere.
+    progress: Option<Arc<Progress>>,
+}
+
+impl HttpFetcher {
+    fn new() -> Result<Self, Error> {
+        Ok(HttpFetcher {
+            client: build_client(FETCH_TIMEOUT, None)?,
+            timeout: FETCH_TIMEOUT,
+            retries: 0,
+            cache_path: None,
+            downloaded: Mutex::new(None),
+            moved: Mutex::new(HashMap::new()),
+            status: Mutex::new(None),
+            progress: None,
+        })
+    }
+
+    /// Use the config's `timeout` and `retries`, for feeds without policies of
+    /// their own.
+    fn with_settings(self, settings: &parser::Settings) -> Result<Self, Error> {
+        let timeout = match settings.timeout.map(|timeout| timeout.to_std()) {
+            Some(Ok(timeout)) => timeout,
+            _ => FETCH_TIMEOUT,
+        };
+        Ok(HttpFetcher {
+            client: build_client(timeout, None)?,
+            timeout,
+            retries: settings.retries.unwrap_or(0),
+            ..self
+        })
+    }
+
+    /// How long to wait on the server for `info`.
+    fn timeout_for(&self, info: &FeedInfo) -> std::time::Duration {
+        match info.timeout().map(|timeout| timeout.to_std()) {
+            Some(Ok(timeout)) => timeout,
+            _ => self.timeout,
+        }
+    }
+
+    /// Run `attempt`, and run it again while `transient` says the result might
+    /// be different next time, up to the retries for `info`. The wait between
+    /// tries doubles each time, up to a minute.
+    fn with_retries<T>(
+        &self,
+        info: &FeedInfo,
+        mut attempt: impl FnMut() -> Result<T, Error>,
+        transient: impl Fn(&Result<T, Error>) -> bool,
+    ) -> Result<T, Error> {
+        let retries = info.retries().unwrap_or(self.retries);
+        let mut delay = RETRY_DELAY;
+        for retry in 1..=retries {
+            let result = attempt();
+            if !transient(&result) {
+                return result;
+            }
+            debug!(
+                "Downloading \"{}\" failed, trying again in {:?} ({} of {})",
+                info.name, delay, retry, retries
+            );
+            std::thread::sleep(delay);
+            delay = std::cmp::min(delay * 2, MAX_RETRY_DELAY);
+        }
+        attempt()
+    }
+
+    /// A client with the timeout and proxy for `info`.
+    fn client_for(&self, info: &FeedInfo) -> Result<reqwest::Client, Error> {
+        let timeout = self.timeout_for(info);
+        if timeout == self.timeout && info.proxy.is_none() {
+            Ok(self.client.clone())
+        } else {
+            build_client(timeout, info.proxy.as_deref())
+        }
+    }
+
+    /// Send the request `build` makes for `start` with a client that has the
+    /// timeout and proxy for `info`, retrying when the server can't be
+    /// reached, times out, or says it's overloaded. Redirects from `start` are
+    /// followed, and remembered for `moved_to` if they're permanent.
+    fn send(
+        &self,
+        info: &FeedInfo,
+        start: &str,
+        build: impl Fn(&reqwest::Client, &str) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Error> {
+        let client = self.client_for(info)?;
+        let (resp, moved) = follow_redirects(start, |url| {
+            self.with_retries(
+                info,
+                || Ok(build(&client, url).send()?),
+                |result| match *result {
+                    Ok(ref resp) => {
+                        resp.status().is_server_error()
+                            || resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS
+                    }
+                    Err(_) => true,
+                },
+            )
+        })?;
+        if let Some(moved) = moved {
+            self.moved.lock().unwrap().insert(start.to_string(), moved);
+        }
+        *self.status.lock().unwrap() = Some(resp.status().as_u16());
+        Ok(resp)
+    }
+
+    /// Count the bytes downloaded in `progress`, if given.
+    fn with_progress(self, progress: Option<Arc<Progress>>) -> Self {
+        HttpFetcher { progress, ..self }
+    }
+
+    /// Make conditional requests, keeping the headers they need at `path`.
+    fn with_cache(self, path: PathBuf) -> Self {
+        HttpFetcher {
+            cache_path: Some(path),
+            ..self
+        }
+    }
+
+    /// Save the headers from the last download, so the next one can be
+    /// skipped if the feed hasn't changed. Only do this once whatever came of
+    /// the download is recorded, or it could be skipped for good.
+    fn save_cache(&self) -> Result<(), Error> {
+        let downloaded = self.downloaded.lock().unwrap().take();
+        match (&self.cache_path, downloaded) {
+            (Some(path), Some(validators)) => http_cache::save(path, &validators),
+            _ => Ok(()),
+        }
+    }
+
+    /// The HTTP status of the last response to a download of the feed, if
+    /// there was one.
+    fn status(&self) -> Option<u16> {
+        *self.status.lock().unwrap()
+    }
+
+    /// A request for `url` on behalf of the feed `info`, like the feed after
+    /// a redirect or a page it links to. The feed's own headers, which may be
+    /// a login, are only sent to its own host.
+    fn request(client: &reqwest::Client, url: &str, info: &FeedInfo) -> reqwest::RequestBuilder {
+        let mut request = client
+            .get(url)
+            .header(reqwest::header::ACCEPT, FEED_TYPES)
+            .header(
+                reqwest::header::ACCEPT_ENCODING,
+                compression::ACCEPT_ENCODING,
+            );
+        for (name, value) in Self::headers_for(url, info) {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        request
+    }
+
+    /// The feed's own headers, if `url` is on the same host as the feed.
+    fn headers_for(url: &str, info: &FeedInfo) -> Vec<(String, String)> {
+        if host(url) == host(&info.url) {
+            info.request_headers()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn read_response(&self, info: &FeedInfo, mut resp: reqwest::Response) -> Result<String, Error> {
+        if !resp.status().is_success() {
+            debug!(
+                "Error \"{}\" fetching feed {} from {}",
+                resp.status(),
+                info.name,
+                info.written_url(),
+            );
+            return Err(Error::Msg(format!(
+                "Failed to download: \"{}\"",
+                resp.status()
+            )));
+        }
+        if self.cache_path.is_some() {
+            let header = |name| {
+                resp.headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(String::from)
+            };
+            *self.downloaded.lock().unwrap() = Some(http_cache::Validators {
+                etag: header(reqwest::header::ETAG),
+                last_modified: header(reqwest::header::LAST_MODIFIED),
+            });
+        }
+        let encoding = resp
+            .headers()
+            .get(reqwest::header::CONTENT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+        let mut body = Vec::new();
+        resp.read_to_end(&mut body)?;
+        if let Some(ref progress) = self.progress {
+            progress.downloaded(body.len());
+        }
+        let body = compression::decode(encoding.as_deref(), body)?;
+        String::from_utf8(body).map_err(|_| Error::Msg("The feed isn't valid UTF-8".into()))
+    }
+}
+
+impl Fetcher for HttpFetcher {
+    fn fetch(&self, info: &FeedInfo) -> Result<String, Error> {
+        self.fetch_at(info, &info.url)
+    }
+
+    fn fetch_at(&self, info: &FeedInfo, url: &str) -> Result<String, Error> {
+        if let Some(fingerprint) = info.pinned_certificate() {
+            if let Some(ref proxy) = info.proxy {
+                return Err(Error::Msg(format!(
+                    "Feeds with @ pin sha256 are downloaded directly, not through the proxy {}",
+                    proxy
+                )));
+            }
+            let timeout = self.timeout_for(info);
+            return self.with_retries(
+                info,
+                || pin::fetch(url, fingerprint, &Self::headers_for(url, info), timeout),
+                |result| matches!(*result, Err(Error::Io(_))),
+            );
+        }
+        let resp = self.send(info, url, |client, url| Self::request(client, url, info))?;
+        self.read_response(info, resp)
+    }
+
+    fn fetch_if_modified(&self, info: &FeedInfo) -> Result<Option<String>, Error> {
+        let path = match self.cache_path {
+            Some(ref path) if info.pinned_certificate().is_none() => path,
+            _ => return self.fetch(info).map(Some),
+        };
+        let cached = http_cache::load(path);
+        let resp = self.send(info, &info.url, |client, url| {
+            let mut request = Self::request(client, url, info);
+            if let Some(ref etag) = cached.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag.as_str());
+            }
+            if let Some(ref date) = cached.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, date.as_str());
+            }
+            request
+        })?;
+        if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        self.read_response(info, resp).map(Some)
+    }
+
+    fn fetch_page(&self, info: &FeedInfo, url: &str) -> Result<String, Error> {
+        // The cache is for the feed, so a page mustn't replace what it keeps
+        let kept = self.downloaded.lock().unwrap().clone();
+        let status = self.status();
+        let page = self.fetch_at(info, url);
+        *self.downloaded.lock().unwrap() = kept;
+        *self.status.lock().unwrap() = status;
+        page
+    }
+
+    fn is_gone(&self, url: &str) -> Result<bool, Error> {
+        let (resp, _) = follow_redirects(url, |url| Ok(self.client.head(url).send()?))?;
+        let status = resp.status();
+        debug!("Checking <{}>: \"{}\"", url, status);
+        Ok(status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::GONE)
+    }
+
+    fn moved_to(&self, url: &str) -> Option<String> {
+        self.moved.lock().unwrap().get(url).cloned()
+    }
+}
+
+/// The host `url` is on, if it's a URL at all.
+fn host(url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(url).ok()?;
+    url.host_str().map(String::from)
+}
+
+/// Send the request `send` makes for `url`, and again for wherever it
+/// redirects to. Also where it ended up, if every redirect on the way there
+/// was permanent.
+fn follow_redirects(
+    url: &str,
+    send: impl Fn(&str) -> Result<reqwest::Response, Error>,
+) -> Result<(reqwest::Response, Option<String>), Error> {
+    let mut current = url.to_string();
+    let mut permanent = true;
+    for _ in 0..=MAX_REDIRECTS {
+        let resp = send(&current)?;
+        let status = resp.status();
+        let location = resp
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|value| value.to_str().ok());
+        let next = match location.filter(|_| status.is_redirection()) {
+            Some(location) => extract::resolve_link(&current, location).ok_or_else(|| {
+                Error::Msg(format!(
+                    "<{}> redirects to a bad URL {:?}",
+                    current, location
+                ))
+            })?,
+            None => {
+                let moved = Some(current).filter(|current| permanent && current != url);
+                return Ok((resp, moved));
+            }
+        };
+        debug!("<{}> redirects to <{}>: \"{}\"", current, next, status);
+        permanent &= status == reqwest::StatusCode::MOVED_PERMANENTLY
+            || status == reqwest::StatusCode::PERMANENT_REDIRECT;
+        current = next;
+    }
+    Err(Error::Msg(format!("Too many redirects from <{}>", url)))
+}
+
+fn build_client(
+    timeout: std::time::Duration,
+    proxy: Option<&str>,
+) -> Result<reqwest::Client, Error> {
+    // Downloads are decompressed by `compression`, which knows more than gzip,
+    // and redirects are followed by `follow_redirects`, to notice permanent ones
+    let mut builder = reqwest::ClientBuilder::new()
+        .timeout(timeout)
+        .gzip(false)
+        .redirect(reqwest::RedirectPolicy::none());
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    Ok(builder.build()?)
+}
+
+/// Pick up the config's `storage` line, and start logging to its `log_file`,
+/// if there's a config yet. A config that doesn't parse is reported by
+/// whatever reads it next.
+fn with_settings(args: config::Args, reporter: Reporter) -> config::Args {
+    if !args.config_path().exists() {
+        return args;
+    }
+    let settings = match load_settings(&args) {
+        Ok(settings) => settings,
+        Err(_) => return args,
+    };
+    if let Some(path) = args.log_file(&settings) {
+        if let Err(err) = logging::log_to_file(&path) {
+            reporter.warning(&format!(
+                "Couldn't open the log file {}: {}",
+                path.display(),
+                err
+            ));
+        }
+    }
+    args.with_storage(settings.storage)
+}
+
+/// Read a feed's history, and those of its aliases.
+fn load_feed(args: &config::Args, info: &FeedInfo) -> Result<Feed, Error> {
+    let mut feed = storage::open(args).load(info)?;
+    feed.set_seed(args.seed());
+    Ok(feed)
+}
+
+/// Add the events since a feed was loaded to its history.
+fn save_feed(args: &config::Args, feed: &mut Feed) -> Result<(), Error> {
+    storage::open(args).save(feed)
+}
+
+/// When a feed that keeps failing to download will be tried again, if that's
+/// later than now and `--force` isn't trying it anyway.
+fn backed_off(opt: &Opt, args: &config::Args, info: &FeedInfo) -> Option<DateTime<Utc>> {
+    if opt.force {
+        return None;
+    }
+    let health = health::load(&args.health_path(info).ok()?);
+    health
+        .retry_at()
+        .filter(|&retry_at| retry_at > args.clock().now())
+}
+
+fn fetch_feed(
+    args: &config::Args,
+    settings: &parser::Settings,
+    mut feed: Feed,
+) -> Result<Feed, Error> {
+    let fetcher = HttpFetcher::new()?
+        .with_settings(settings)?
+        .with_cache(args.cache_path(&feed.info)?)
+        .with_progress(args.progress().cloned());
+    let now = args.clock().now();
+    let comics = feed.comics().count();
+    let started = Instant::now();
+    let update = fetch::update_feed(&fetcher, &mut feed, now);
+    debug!(
+        "Downloading took {:.2}s, and {}",
+        started.elapsed().as_secs_f64(),
+        match update {
+            Ok(_) => format!("found {} new comics", feed.comics().count() - comics),
+            Err(ref err) => format!("failed: {}", err),
+        }
+    );
+    let health_path = args.health_path(&feed.info)?;
+    let mut health = health::load(&health_path);
+    match update {
+        Ok(_) => health.succeeded(now, fetcher.status(), feed.comics().count() > comics),
+        Err(ref err) => health.failed(now, fetcher.status(), err),
+    }
+    // Only `doctor` needs it, so it's no reason to stop
+    if let Err(err) = health::save(&health_path, &health) {
+        debug!("{}", err);
+    }
+    let update = update?;
+    if update.paused {
+        args.say(&format!(
+            "{} seems to have republished its archive at new URLs, so it's paused. \
+             Run `feedburst accept-reset \"{}\"` to pick up from what's in it now.",
+            feed.info.name, feed.info.name
+        ));
+    }
+    if let Some(ref url) = update.discovered {
+        args.say(&format!(
+            "{}: <{}> is a web page, so using the feed it links to, <{}>",
+            feed.info.name,
+            feed.info.written_url(),
+            feed.info.at(url).written_url()
+        ));
+    }
+    if let Some(ref url) = update.moved {
+        args.say(&format!(
+            "{}: the feed has moved to <{}>. Run `feedburst fix-urls` to put it in the config.",
+            feed.info.name, url
+        ));
+    }
+    if update.moved_secretly {
+        args.say(&format!(
+            "{}: the feed has moved to a URL with a secret from {{env:NAME}} in it that can't be \
+             told apart from the rest, so it wasn't remembered. Change its URL in the config by hand.",
+            feed.info.name
+        ));
+    }
+    if !update.other_feeds.is_empty() {
+        args.say(&format!(
+            "{}: the page also links to {}. Put the feed you want in the config to use another one.",
+            feed.info.name,
+            update
+                .other_feeds
+                .iter()
+                .map(|url| format!("<{}>", url))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    if update.crawled > 0 {
+        args.say(&format!(
+            "{}: found {} older comics by following the archive back",
+            feed.info.name, update.crawled
+        ));
+    }
+    if update.skipped > 0 {
+        args.say(&format!(
+            "{}: skipped the oldest {} comics to stay within its limit per fetch",
+            feed.info.name, update.skipped
+        ));
+    }
+    for url in update.withdrawn {
+        args.say(&format!(
+            "{}: <{}> was taken down, so it won't be opened",
+            feed.info.name, url
+        ));
+    }
+    save_feed(args, &mut feed)?;
+    fetcher.save_cache()?;
+    Ok(feed)
+}
+
+/// Note that a ready feed was left unread, and remind about it if it's been
+/// waiting too long. Returns the reminder, if there was one.
+fn remind(args: &config::Args, feed: &mut Feed) -> Result<Option<String>, Error> {
+    let now = args.clock().now();
+    feed.waiting_at(now);
+    let mut reminder = None;
+    if let Some(waiting) = feed.overdue_at(now) {
+        let days = waiting.num_days();
+        let format = &feed.info.format;
+        let line = format!(
+            "Reminder: {} has had new comics waiting for {} {}",
+            format.feed_name(&feed.info.name),
+            days,
+            if days == 1 { "day" } else { "days" },
+        );
+        args.say(&line);
+        for comic in feed.unread_comics() {
+            args.say(&format!("  {}", format.comic(comic)));
+        }
+        reminder = Some(line);
+    }
+    save_feed(args, feed)?;
+    Ok(reminder)
+}
+
+/// A feed's reading list being opened: the line that announced it, and the
+/// comics opened.
+struct Reading {
+    heading: String,
+    opened: Vec<String>,
+}
+
+/// Open a ready feed's reading list and mark it read. With `read_comics`,
+/// for `dedupe on`, comics already read in another feed are left out, and
+/// the ones opened are marked read in the other feeds that have them. With
+/// `digest`, for `--output html`, every unread comic is added to the page as
+/// a section instead of being opened.
+fn read_feed(
+    args: &config::Args,
+    feed: &mut Feed,
+    mut read_comics: Option<&mut ReadComics>,
+    digest: Option<&mut Vec<digest::Section>>,
+) -> Result<Option<Reading>, Error> {
+    let plan = match session::plan_reading(feed, args.clock(), read_comics.as_deref_mut()) {
+        Some(plan) => plan,
+        None => return Ok(None),
+    };
+    let heading = reading_heading(feed, &plan);
+    args.say(&heading);
+    match plan.already_read.len() {
+        0 => (),
+        1 => args.say("  Skipping 1 comic already read in another feed"),
+        n => args.say(&format!(
+            "  Skipping {} comics already read in other feeds",
+            n
+        )),
+    }
+    let mut opened = Vec::new();
+    let to_open = match digest {
+        Some(_) => plan
+            .reading_list
+            .iter()
+            .filter(|url| !plan.already_read.contains(url))
+            .cloned()
+            .collect(),
+        None => plan.open,
+    };
+    for item in to_open {
+        if digest.is_none() {
+            open_comic(args, feed, &item)?;
+        }
+        opened.push(item);
+    }
+    let now = args.clock().now();
+    if plan.surprise {
+        let candidates = feed.surprise_candidates_at(now);
+        let url = candidates[random_index(candidates.len())].url.clone();
+        args.say("  Plus a surprise from the archive");
+        if digest.is_none() {
+            open_comic(args, feed, &url)?;
+        }
+        feed.surprised_at(&url, now);
+        opened.push(url);
+    }
+    if let Some(sections) = digest {
+        sections.push(digest::Section::new(feed, &opened));
+    }
+    feed.open();
+    feed.read_at(now);
+    save_feed(args, feed)?;
+    if let Some(read_comics) = read_comics {
+        for url in &opened {
+            for (info, url) in read_comics.unread_elsewhere(&feed.info.name, url) {
+                storage::open(args).update(info, &mut |other| {
+                    other.read_comic_at(url, now);
+                    Ok(())
+                })?;
+            }
+        }
+    }
+    let streak = stats::reading_streak(feed);
+    if streak >= SHOW_STREAK_AFTER {
+        args.say(&format!("  Read on schedule {} times in a row!", streak));
+    }
+    Ok(Some(Reading { heading, opened }))
+}
+
+/// Open one of a feed's comics, passing on its title for `{title}`.
+fn open_comic(args: &config::Args, feed: &Feed, url: &str) -> Result<(), Error> {
+    let title = feed.comic(url).and_then(|comic| comic.title.as_deref());
+    args.open_url(&feed.info, url, title)
+}
+
+/// List what reading a ready feed would open, for `--dry-run`, leaving its
+/// history alone.
+fn preview_feed(args: &config::Args, feed: &Feed) -> Option<Reading> {
+    let plan = session::plan_reading(feed, args.clock(), None)?;
+    let heading = reading_heading(feed, &plan);
+    args.say(&heading);
+    for item in &plan.open {
+        args.say(&format!("  Would open <{}>", item));
+    }
+    if plan.surprise {
+        args.say("  Plus a surprise from the archive");
+    }
+    Some(Reading {
+        heading,
+        opened: plan.open,
+    })
+}
+
+/// The line announcing a feed's reading list, like `Feed (3 comics)`, or
+/// `Feed (10 comics, 40 left for next time)` when `@ max N comics` holds
+/// some back.
+fn reading_heading(feed: &Feed, plan: &session::ReadingPlan) -> String {
+    let count = plan.reading_list.len();
+    let plural_feeds = if count == 1 { "comic" } else { "comics" };
+    let left = if plan.left_for_later > 0 {
+        format!(", {} left for next time", plan.left_for_later)
+    } else {
+        String::new()
+    };
+    format!(
+        "{} ({} {}{})",
+        feed.info.format.feed_name(&feed.info.name),
+        count,
+        plural_feeds,
+        left
+    )
+}
+
+/// A random number below `len`.
+fn random_index(len: usize) -> usize {
+    (random_seed() % len as u64) as usize
+}
+
+/// A random number, using the randomly seeded hasher from std rather than
+/// pulling in a crate for the occasional surprise.
+fn random_seed() -> u64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    RandomState::new().build_hasher().finish()
+}
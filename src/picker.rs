@@ -0,0 +1,292 @@
+//! `feedburst tui`: a full-screen list of the ready feeds and their comics,
+//! for picking which to open before anything is opened or marked read.
+
+use std::io;
+
+use crossterm::event::{self, Event, KeyCode};
+use crossterm::execute;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use tui::backend::CrosstermBackend;
+use tui::layout::{Constraint, Direction, Layout};
+use tui::style::{Modifier, Style};
+use tui::widgets::{List, ListItem, ListState, Paragraph};
+use tui::Terminal;
+
+use crate::error::Error;
+use crate::feed::Feed;
+
+const HELP: &str = "↑↓ move  space pick  → show comics  ← hide  enter open  q quit";
+
+/// A ready feed in the list, with its reading list.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Choice {
+    pub name: String,
+    pub comics: Vec<Pick>,
+    expanded: bool,
+}
+
+/// One comic on a feed's reading list, and whether it's picked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pick {
+    pub url: String,
+    pub label: String,
+    pub picked: bool,
+}
+
+impl Choice {
+    /// The feed's reading list, all picked, which is what a normal run would
+    /// read.
+    pub fn new(feed: &Feed) -> Self {
+        let format = &feed.info.format;
+        let comics = feed
+            .get_reading_list()
+            .into_iter()
+            .map(|url| {
+                let label = match feed.comic(&url) {
+                    Some(comic) => format.comic(comic),
+                    None => format!("<{}>", url),
+                };
+                Pick {
+                    url,
+                    label,
+                    picked: true,
+                }
+            })
+            .collect();
+        Choice {
+            name: format.feed_name(&feed.info.name),
+            comics,
+            expanded: false,
+        }
+    }
+
+    /// The URLs of the picked comics, oldest first.
+    pub fn picked(&self) -> Vec<&str> {
+        self.comics
+            .iter()
+            .filter(|comic| comic.picked)
+            .map(|comic| comic.url.as_str())
+            .collect()
+    }
+}
+
+/// A key the picker understands, apart from the terminal it came from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Toggle,
+    Expand,
+    Collapse,
+    Open,
+    Quit,
+}
+
+impl Key {
+    fn from_code(code: KeyCode) -> Option<Key> {
+        Some(match code {
+            KeyCode::Up | KeyCode::Char('k') => Key::Up,
+            KeyCode::Down | KeyCode::Char('j') => Key::Down,
+            KeyCode::Char(' ') => Key::Toggle,
+            KeyCode::Right | KeyCode::Char('l') => Key::Expand,
+            KeyCode::Left | KeyCode::Char('h') => Key::Collapse,
+            KeyCode::Enter => Key::Open,
+            KeyCode::Esc | KeyCode::Char('q') => Key::Quit,
+            _ => return None,
+        })
+    }
+}
+
+/// How a session with the picker ended.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Done {
+    Open,
+    Quit,
+}
+
+/// The list and where the cursor is in it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Picker {
+    pub choices: Vec<Choice>,
+    cursor: usize,
+}
+
+impl Picker {
+    pub fn new(choices: Vec<Choice>) -> Self {
+        Picker { choices, cursor: 0 }
+    }
+
+    /// The rows on screen, as a feed and, for a comic's row, which comic.
+    fn rows(&self) -> Vec<(usize, Option<usize>)> {
+        let mut rows = Vec::new();
+        for (i, choice) in self.choices.iter().enumerate() {
+            rows.push((i, None));
+            if choice.expanded {
+                rows.extend((0..choice.comics.len()).map(|j| (i, Some(j))));
+            }
+        }
+        rows
+    }
+
+    /// Act on a key, returning how the session ended if it did.
+    pub fn press(&mut self, key: Key) -> Option<Done> {
+        let rows = self.rows();
+        let (feed, comic) = rows[self.cursor];
+        match key {
+            Key::Up => self.cursor = self.cursor.saturating_sub(1),
+            Key::Down => self.cursor = std::cmp::min(self.cursor + 1, rows.len() - 1),
+            Key::Toggle => {
+                let comics = &mut self.choices[feed].comics;
+                match comic {
+                    Some(j) => comics[j].picked = !comics[j].picked,
+                    None => {
+                        let picked = !comics.iter().any(|comic| comic.picked);
+                        for comic in comics {
+                            comic.picked = picked;
+                        }
+                    }
+                }
+            }
+            Key::Expand => self.choices[feed].expanded = true,
+            Key::Collapse => {
+                self.choices[feed].expanded = false;
+                self.cursor = self
+                    .rows()
+                    .iter()
+                    .position(|&row| row == (feed, None))
+                    .unwrap();
+            }
+            Key::Open => return Some(Done::Open),
+            Key::Quit => return Some(Done::Quit),
+        }
+        None
+    }
+
+    fn items(&self) -> Vec<ListItem<'static>> {
+        let check = |picked| if picked { "[x]" } else { "[ ]" };
+        self.rows()
+            .into_iter()
+            .map(|(i, comic)| {
+                let choice = &self.choices[i];
+                let text = match comic {
+                    Some(j) => {
+                        let comic = &choice.comics[j];
+                        format!("    {} {}", check(comic.picked), comic.label)
+                    }
+                    None => {
+                        let picked = choice.picked().len();
+                        format!(
+                            "{} {} {} ({} of {} {})",
+                            if choice.expanded { "▾" } else { "▸" },
+                            check(picked > 0),
+                            choice.name,
+                            picked,
+                            choice.comics.len(),
+                            if choice.comics.len() == 1 {
+                                "comic"
+                            } else {
+                                "comics"
+                            },
+                        )
+                    }
+                };
+                ListItem::new(text)
+            })
+            .collect()
+    }
+}
+
+/// Show the picker until the reader opens their picks or quits, returning
+/// the choices as they were left, or `None` on quitting.
+pub fn pick(choices: Vec<Choice>) -> Result<Option<Vec<Choice>>, Error> {
+    let mut picker = Picker::new(choices);
+    terminal::enable_raw_mode()?;
+    let _restore = Restore;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    loop {
+        terminal.draw(|frame| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(1), Constraint::Length(1)])
+                .split(frame.size());
+            let list = List::new(picker.items())
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            let mut state = ListState::default();
+            state.select(Some(picker.cursor));
+            frame.render_stateful_widget(list, chunks[0], &mut state);
+            frame.render_widget(Paragraph::new(HELP), chunks[1]);
+        })?;
+        if let Event::Key(key) = event::read()? {
+            match Key::from_code(key.code).and_then(|key| picker.press(key)) {
+                Some(Done::Open) => return Ok(Some(picker.choices)),
+                Some(Done::Quit) => return Ok(None),
+                None => (),
+            }
+        }
+    }
+}
+
+/// Puts the terminal back the way it was, however the picker ends.
+struct Restore;
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, crossterm::cursor::Show);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn choice(name: &str, urls: &[&str]) -> Choice {
+        Choice {
+            name: name.into(),
+            comics: urls
+                .iter()
+                .map(|&url| Pick {
+                    url: url.into(),
+                    label: format!("<{}>", url),
+                    picked: true,
+                })
+                .collect(),
+            expanded: false,
+        }
+    }
+
+    #[test]
+    fn test_picker() {
+        let mut picker = Picker::new(vec![
+            choice("One", &["http://one.example.com/1"]),
+            choice(
+                "Two",
+                &["http://two.example.com/1", "http://two.example.com/2"],
+            ),
+        ]);
+
+        // Unpicking a feed unpicks all its comics
+        assert_eq!(picker.press(Key::Toggle), None);
+        assert!(picker.choices[0].picked().is_empty());
+
+        // Single comics can be unpicked once the feed is shown
+        picker.press(Key::Down);
+        picker.press(Key::Expand);
+        picker.press(Key::Down);
+        picker.press(Key::Down);
+        picker.press(Key::Down);
+        picker.press(Key::Toggle);
+        assert_eq!(picker.choices[1].picked(), vec!["http://two.example.com/1"]);
+
+        // Hiding the comics goes back to the feed's row
+        picker.press(Key::Collapse);
+        assert_eq!(picker.cursor, 1);
+        picker.press(Key::Up);
+        picker.press(Key::Up);
+        assert_eq!(picker.cursor, 0);
+        assert_eq!(picker.press(Key::Open), Some(Done::Open));
+        assert_eq!(picker.press(Key::Quit), Some(Done::Quit));
+    }
+}
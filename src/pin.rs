@@ -0,0 +1,204 @@
+//! Downloading feeds with `@ pin`. reqwest won't say which certificate a
+//! server presented, so pinned feeds are fetched over a TLS connection of our
+//! own, and nothing is sent until the certificate checks out.
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use native_tls::TlsConnector;
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+
+use crate::compression;
+use crate::error::Error;
+
+/// Download `url`, as long as its server presents the certificate with the
+/// SHA-256 `fingerprint`, sending `headers` along with the request. Redirects
+/// aren't followed, since they could lead anywhere.
+pub fn fetch(
+    url: &str,
+    fingerprint: &str,
+    headers: &[(String, String)],
+    timeout: Duration,
+) -> Result<String, Error> {
+    let parsed =
+        Url::parse(url).map_err(|err| Error::Msg(format!("Bad feed URL <{}>: {}", url, err)))?;
+    if parsed.scheme() != "https" {
+        return Err(Error::Msg(format!(
+            "@ pin needs an https:// URL, got <{}>",
+            url
+        )));
+    }
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| Error::Msg(format!("No host in <{}>", url)))?;
+    let port = parsed.port_or_known_default().unwrap_or(443);
+    let addr = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| Error::Msg(format!("Couldn't find an address for {}", host)))?;
+
+    let stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    let connector = TlsConnector::new().map_err(tls_error)?;
+    let mut stream = connector
+        .connect(host, stream)
+        .map_err(|err| Error::Msg(format!("TLS connection to {} failed: {}", host, err)))?;
+
+    let certificate = stream
+        .peer_certificate()
+        .map_err(tls_error)?
+        .ok_or_else(|| Error::Msg(format!("{} didn't present a certificate", host)))?;
+    let found = fingerprint_of(&certificate.to_der().map_err(tls_error)?);
+    if found != fingerprint {
+        return Err(Error::Msg(format!(
+            "The certificate from {} doesn't match @ pin: expected sha256 {}, got {}. \
+             Something may be intercepting the connection, or the site changed its \
+             certificate.",
+            host, fingerprint, found
+        )));
+    }
+    debug!("Certificate from {} matches @ pin", host);
+
+    let mut target = parsed.path().to_string();
+    if let Some(query) = parsed.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    let host_header = match parsed.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.into(),
+    };
+    let mut request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nAccept: {}\r\nAccept-Encoding: {}\r\nConnection: close\r\n",
+        target,
+        host_header,
+        crate::FEED_TYPES,
+        compression::ACCEPT_ENCODING
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes())?;
+    stream.flush()?;
+
+    let mut response = Vec::new();
+    // Plenty of servers hang up without a TLS close_notify, so an error after
+    // some of the response arrived is the end of it
+    if let Err(err) = stream.read_to_end(&mut response) {
+        if response.is_empty() {
+            return Err(err.into());
+        }
+        debug!("Reading from {} ended with {}", host, err);
+    }
+    parse_response(&response)
+}
+
+fn tls_error(err: native_tls::Error) -> Error {
+    Error::Msg(format!("TLS error: {}", err))
+}
+
+/// The SHA-256 fingerprint of a DER-encoded certificate, as `@ pin` writes it.
+fn fingerprint_of(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// The body of a raw HTTP response, if it was a success.
+fn parse_response(response: &[u8]) -> Result<String, Error> {
+    let malformed = || Error::Msg("Malformed HTTP response".into());
+    let split = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(malformed)?;
+    let head = String::from_utf8_lossy(&response[..split]);
+    let body = &response[split + 4..];
+
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_once(' '))
+        .map(|(_, status)| status.trim())
+        .ok_or_else(malformed)?;
+    if !status.starts_with('2') {
+        return Err(Error::Msg(format!("Failed to download: \"{}\"", status)));
+    }
+    let mut chunked = false;
+    let mut encoding = None;
+    for (name, value) in lines.filter_map(|line| line.split_once(':')) {
+        let name = name.trim();
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked |= value.to_ascii_lowercase().contains("chunked");
+        } else if name.eq_ignore_ascii_case("content-encoding") {
+            encoding = Some(value.trim());
+        }
+    }
+    let body = if chunked {
+        dechunk(body).ok_or_else(malformed)?
+    } else {
+        body.to_vec()
+    };
+    let body = compression::decode(encoding, body)?;
+    String::from_utf8(body).map_err(|_| Error::Msg("The feed isn't valid UTF-8".into()))
+}
+
+/// Undo `Transfer-Encoding: chunked`.
+fn dechunk(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    loop {
+        let line_end = body.windows(2).position(|window| window == b"\r\n")?;
+        let size = std::str::from_utf8(&body[..line_end]).ok()?;
+        let size = size.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Some(out);
+        }
+        out.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_of() {
+        assert_eq!(
+            fingerprint_of(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_parse_response() {
+        assert_eq!(
+            parse_response(b"HTTP/1.1 200 OK\r\nContent-Type: application/atom+xml\r\n\r\n<feed/>")
+                .unwrap(),
+            "<feed/>"
+        );
+        assert_eq!(
+            parse_response(
+                b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n<fee\r\n3;x=y\r\nd/>\r\n0\r\n\r\n"
+            )
+            .unwrap(),
+            "<feed/>"
+        );
+        assert_eq!(
+            parse_response(b"HTTP/1.1 301 Moved Permanently\r\nLocation: /\r\n\r\n")
+                .unwrap_err()
+                .to_string(),
+            "Failed to download: \"301 Moved Permanently\""
+        );
+        let mut gzipped = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\n\r\n".to_vec();
+        gzipped.extend(compression::encode("gzip", b"<feed/>").unwrap());
+        assert_eq!(parse_response(&gzipped).unwrap(), "<feed/>");
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\n").is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use std::ffi::OsStr;
-use std::process::Command;
+use std::io::Write;
+use std::process::{Command, Stdio};
 
 use crate::error::Error;
 
@@ -24,3 +25,34 @@ pub fn open_url<T: AsRef<OsStr>>(url: T) -> Result<(), Error> {
     }
     last_err
 }
+
+/// Put `text` on the clipboard, with whichever of the Wayland and X11
+/// clipboard tools is installed.
+pub fn copy_to_clipboard(text: &str) -> Result<(), Error> {
+    let tools: &[&[&str]] = &[
+        &["wl-copy"],
+        &["xclip", "-selection", "clipboard"],
+        &["xsel", "--clipboard", "--input"],
+    ];
+    for tool in tools {
+        let mut child = match Command::new(tool[0])
+            .args(&tool[1..])
+            .stdin(Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) => {
+                debug!("Unable to run {}: {}", tool[0], err);
+                continue;
+            }
+        };
+        child.stdin.take().unwrap().write_all(text.as_bytes())?;
+        if child.wait()?.success() {
+            return Ok(());
+        }
+        return Err(Error::Msg(format!("{} couldn't copy the URLs", tool[0])));
+    }
+    Err(Error::Msg(
+        "Copying to the clipboard needs wl-copy, xclip, or xsel installed".into(),
+    ))
+}
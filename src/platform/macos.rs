@@ -1,5 +1,6 @@
 use std::ffi::OsStr;
-use std::process::Command;
+use std::io::Write;
+use std::process::{Command, Stdio};
 
 use crate::error::Error;
 
@@ -12,3 +13,14 @@ pub fn open_url<T: AsRef<OsStr>>(url: T) -> Result<(), Error> {
         Err(Error::Msg(msg))
     }
 }
+
+/// Put `text` on the clipboard, with `pbcopy`.
+pub fn copy_to_clipboard(text: &str) -> Result<(), Error> {
+    let mut child = Command::new("pbcopy").stdin(Stdio::piped()).spawn()?;
+    child.stdin.take().unwrap().write_all(text.as_bytes())?;
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        Err(Error::Msg("pbcopy couldn't copy the URLs".into()))
+    }
+}
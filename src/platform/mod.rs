@@ -1,20 +1,23 @@
 #[cfg(target_os = "linux")]
 mod linux;
 #[cfg(target_os = "linux")]
-pub use self::linux::open_url;
+pub use self::linux::{copy_to_clipboard, open_url};
 
 #[cfg(target_os = "windows")]
 mod windows;
 #[cfg(target_os = "windows")]
-pub use self::windows::{config_path, data_path, open_url};
+pub use self::windows::{
+    config_path, copy_to_clipboard, data_path, hostname, open_command, open_url, watch_signals,
+    HOME_VAR,
+};
 
 #[cfg(target_os = "macos")]
 mod macos;
 #[cfg(target_os = "macos")]
-pub use self::macos::open_url;
+pub use self::macos::{copy_to_clipboard, open_url};
 
 /// For code that's the same on macOS and Linux
 #[cfg(unix)]
 mod unix;
 #[cfg(unix)]
-pub use self::unix::{config_path, data_path};
+pub use self::unix::{config_path, data_path, hostname, open_command, watch_signals, HOME_VAR};
@@ -1,8 +1,14 @@
 use std::env;
 use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
 use crate::error::Error;
 
+/// The environment variable holding the home folder, for `~` in paths.
+pub const HOME_VAR: &str = "HOME";
+
 pub fn data_path(path: &str) -> Result<PathBuf, Error> {
     if let Some(path) = env::var_os("XDG_DATA_HOME") {
         Ok(path.into())
@@ -32,3 +38,33 @@ pub fn config_path() -> Result<PathBuf, Error> {
         }
     }
 }
+
+/// The process for an open command, run directly with its parts as they are.
+pub fn open_command(parts: &[String]) -> Command {
+    let mut command = Command::new(&parts[0]);
+    command.args(&parts[1..]);
+    command
+}
+
+pub fn hostname() -> Option<String> {
+    let output = Command::new("hostname").output().ok()?;
+    let name = String::from_utf8(output.stdout).ok()?;
+    let name = name.trim();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.into())
+    }
+}
+
+/// Set `stop` on Ctrl-C or SIGTERM, and `reload` on SIGHUP. A second Ctrl-C
+/// or SIGTERM before stopping exits right away.
+pub fn watch_signals(stop: &Arc<AtomicBool>, reload: &Arc<AtomicBool>) -> Result<(), Error> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+    for &signal in &[SIGINT, SIGTERM] {
+        signal_hook::flag::register_conditional_shutdown(signal, 1, Arc::clone(stop))?;
+        signal_hook::flag::register(signal, Arc::clone(stop))?;
+    }
+    signal_hook::flag::register(SIGHUP, Arc::clone(reload))?;
+    Ok(())
+}
@@ -1,9 +1,17 @@
 use std::ffi::OsStr;
+use std::io::Write;
+use std::os::windows::process::CommandExt;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 use std::{env, fs};
 
 use crate::error::Error;
+use crate::quoting;
+
+/// The environment variable holding the home folder, for `~` in paths.
+pub const HOME_VAR: &str = "USERPROFILE";
 
 fn app_data_dir() -> Result<PathBuf, Error> {
     if let Some(app_data_dir) = env::var_os("APPDATA") {
@@ -35,19 +43,52 @@ pub fn config_path() -> Result<PathBuf, Error> {
     Ok(path.join("config.feeds"))
 }
 
-pub fn open_url<T: AsRef<OsStr>>(url: T) -> Result<(), Error> {
+pub fn hostname() -> Option<String> {
+    env::var("COMPUTERNAME").ok()
+}
+
+/// Set `stop` on Ctrl-C. A second Ctrl-C before stopping exits right away.
+/// Windows has no SIGHUP, so `reload` is never set.
+pub fn watch_signals(stop: &Arc<AtomicBool>, _reload: &Arc<AtomicBool>) -> Result<(), Error> {
+    use signal_hook::consts::SIGINT;
+    signal_hook::flag::register_conditional_shutdown(SIGINT, 1, Arc::clone(stop))?;
+    signal_hook::flag::register(SIGINT, Arc::clone(stop))?;
+    Ok(())
+}
+
+/// The process for an open command, run by `cmd.exe` the way it would run
+/// the command typed at a prompt, so `.bat` scripts and built-in commands
+/// like `start` work too. The parts are quoted for the program and escaped
+/// for `cmd.exe` here, since Rust's own quoting doesn't know about either.
+pub fn open_command(parts: &[String]) -> Command {
+    let line = quoting::escape_cmd(&quoting::join_windows(parts));
     let mut cmd = Command::new("cmd");
-    cmd.arg("/C").arg("start");
-    if let Some(s) = url.as_ref().to_str() {
-        cmd.arg(s.replace("&", "^&"));
-    } else {
-        cmd.arg(url.as_ref());
-    }
-    let exit_status = cmd.spawn()?.wait()?;
+    cmd.raw_arg(format!("/D /S /C \"{}\"", line));
+    cmd
+}
+
+/// Open the URL with whatever Windows opens links with, through `start`. The
+/// empty part is the window title, without which `start` would take a quoted
+/// URL for the title.
+pub fn open_url<T: AsRef<OsStr>>(url: T) -> Result<(), Error> {
+    let url = url.as_ref().to_string_lossy();
+    let parts = ["start".to_string(), String::new(), url.to_string()];
+    let exit_status = open_command(&parts).spawn()?.wait()?;
     if exit_status.success() {
         Ok(())
     } else {
-        let msg = format!("Failed opening url {}", url.as_ref().to_string_lossy());
+        let msg = format!("Failed opening url {}", url);
         Err(Error::Msg(msg))
     }
 }
+
+/// Put `text` on the clipboard, with `clip`.
+pub fn copy_to_clipboard(text: &str) -> Result<(), Error> {
+    let mut child = Command::new("clip").stdin(Stdio::piped()).spawn()?;
+    child.stdin.take().unwrap().write_all(text.as_bytes())?;
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        Err(Error::Msg("clip couldn't copy the URLs".into()))
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::{self, BufRead, Write};
+
+use crate::feed::{FeedInfo, ReadMarker};
+use crate::parser::{self, Machine};
+
+const INTRO: &str = "\
+Type config lines to see how feedburst reads them. Directives like `root` and
+`locale` carry over to later lines, just like in a config file. A blank line
+starts over, and Ctrl-D quits.";
+
+/// Read config lines one at a time from `input`, explaining each one on
+/// `output` as it comes in. Lines with errors are shown and then forgotten,
+/// so they don't get in the way of the next try.
+pub fn playground<R: BufRead, W: Write>(
+    input: R,
+    output: &mut W,
+    machine: &Machine,
+) -> io::Result<()> {
+    writeln!(output, "{}\n", INTRO)?;
+    let mut config = String::new();
+    let mut num_feeds = 0;
+    write!(output, "> ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            config.clear();
+            num_feeds = 0;
+            writeln!(output, "(Starting over)")?;
+        } else {
+            let candidate = format!("{}{}\n", config, line);
+            match parser::parse_config_for(&candidate, machine) {
+                Ok(feeds) => {
+                    match feeds.get(num_feeds) {
+                        Some(info) => explain(output, info)?,
+                        None => writeln!(output, "OK, applies to the feeds after this")?,
+                    }
+                    num_feeds = feeds.len();
+                    config = candidate;
+                }
+                Err(err) => writeln!(output, "{}", err.render("the playground", &candidate))?,
+            }
+        }
+        write!(output, "\n> ")?;
+        output.flush()?;
+    }
+    writeln!(output)?;
+    Ok(())
+}
+
+fn explain<W: Write>(output: &mut W, info: &FeedInfo) -> io::Result<()> {
+    writeln!(output, "Feed \"{}\" from <{}>", info.name, info.url)?;
+    let mut policies: Vec<_> = info.update_policies.iter().collect();
+    policies.sort_by_key(|policy| policy.to_string());
+    if info.default_policy {
+        writeln!(output, "  Has no policies, so it uses the default_policy:")?;
+    }
+    for policy in policies {
+        writeln!(output, "  {}: {}", policy, policy.describe())?;
+    }
+    if let Some(ref root) = info.root {
+        writeln!(output, "  Stored in {}", root.display())?;
+    }
+    if let Some(ref open_with) = info.open_with {
+        writeln!(output, "  Opened with {}", open_with)?;
+    }
+    if info.read_marker == ReadMarker::PublishDate {
+        writeln!(output, "  Read time is the newest comic's publish date")?;
+    }
+    if info.format.prefix.is_some() {
+        writeln!(output, "  Listed as {}", info.format.feed_name(&info.name))?;
+    }
+    if let Some(length) = info.format.max_title_length {
+        writeln!(output, "  Titles cut to {} characters", length)?;
+    }
+    if info.format.hide_urls {
+        writeln!(output, "  Comics listed without their URLs")?;
+    }
+    if let Some(max) = info.max_items_per_fetch {
+        writeln!(
+            output,
+            "  Takes at most {} comics per download, unless a policy says otherwise",
+            max
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_playground() {
+        let input = r#"root /comics
+"Goodbye to Halos" <http://goodbyetohalos.com/feed/> @ every 2 days
+"Bad" <http://example.com> @ evry 2 days
+
+"Good" <http://example.com>
+"#;
+        let mut output = Vec::new();
+        playground(input.as_bytes(), &mut output, &Machine::current(None)).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("OK, applies to the feeds after this"));
+        assert!(output.contains(concat!(
+            "Feed \"Goodbye to Halos\" from <http://goodbyetohalos.com/feed/>\n",
+            "  @ every 2 days: Wait at least 2 days after you last read them\n",
+            "  Stored in /comics\n",
+        )));
+        assert!(output.contains("Line 3: Error parsing the playground"));
+        assert!(output.contains(concat!(
+            "(Starting over)\n\n",
+            "> Feed \"Good\" from <http://example.com>\n",
+            "  Has no policies, so it uses the default_policy:\n",
+            "  @ 1 new comic: Wait until there is at least 1 new comic\n",
+        )));
+    }
+}
@@ -0,0 +1,77 @@
+//! Doing work on several threads at once, without losing track of the order.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+
+/// Run `work` on every item, on up to `jobs` threads at once, and hand each
+/// result to `done` in the order of `items`. A result is handed over as soon as
+/// it and everything before it are finished, so `done` can get started while
+/// later items are still being worked on.
+pub fn run_in_order<T, R, W, D>(items: Vec<T>, jobs: usize, work: W, mut done: D)
+where
+    T: Send,
+    R: Send,
+    W: Fn(T) -> R + Sync,
+    D: FnMut(R),
+{
+    let jobs = jobs.max(1).min(items.len());
+    let queue = Mutex::new(items.into_iter().enumerate().collect::<VecDeque<_>>());
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            let tx = tx.clone();
+            let (queue, work) = (&queue, &work);
+            scope.spawn(move || loop {
+                let next = queue.lock().unwrap().pop_front();
+                let (index, item) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+                if tx.send((index, work(item))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+
+        let mut finished = BTreeMap::new();
+        let mut next = 0;
+        for (index, result) in rx {
+            finished.insert(index, result);
+            while let Some(result) = finished.remove(&next) {
+                done(result);
+                next += 1;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_run_in_order() {
+        // The first items take the longest, so they finish last
+        let items: Vec<u64> = (0..8).rev().collect();
+        let mut results = Vec::new();
+        run_in_order(
+            items.clone(),
+            4,
+            |n| {
+                thread::sleep(Duration::from_millis(n * 5));
+                n * 10
+            },
+            |n| results.push(n),
+        );
+        let expected: Vec<_> = items.iter().map(|n| n * 10).collect();
+        assert_eq!(results, expected);
+
+        let mut results = Vec::new();
+        run_in_order(Vec::<u64>::new(), 4, |n| n, |n| results.push(n));
+        assert!(results.is_empty());
+    }
+}
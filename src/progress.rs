@@ -0,0 +1,188 @@
+//! A line at the bottom of the terminal showing how downloading the feeds is
+//! going, so a run with many feeds doesn't look stuck.
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+
+/// How wide the bar itself is, in characters.
+const BAR_WIDTH: usize = 20;
+
+/// How wide to assume the terminal is when it can't be asked.
+const DEFAULT_COLUMNS: usize = 80;
+
+/// How far along the downloads are.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct State {
+    total: usize,
+    done: usize,
+    /// The feeds being downloaded right now, in the order they started.
+    current: Vec<String>,
+    bytes: u64,
+    /// How many things are printing, so the line mustn't be drawn.
+    printing: usize,
+    /// Whether the downloads are done, and the line is gone for good.
+    cleared: bool,
+    /// Whether the line is on the screen, and needs clearing before anything
+    /// else is printed.
+    drawn: bool,
+}
+
+/// The progress of a run's downloads, drawn to stderr if it's turned on.
+/// Feeds are downloaded on several threads at once, so it can be shared.
+pub struct Progress {
+    enabled: bool,
+    state: Mutex<State>,
+}
+
+/// Whether to show progress: only when both stdout and stderr are a terminal,
+/// and `--quiet` wasn't given.
+pub fn wanted(quiet: bool) -> bool {
+    !quiet && io::stdout().is_terminal() && io::stderr().is_terminal()
+}
+
+impl Progress {
+    /// Progress through downloading `total` feeds, drawn only if `enabled`.
+    pub fn new(total: usize, enabled: bool) -> Progress {
+        Progress {
+            enabled,
+            state: Mutex::new(State {
+                total,
+                ..State::default()
+            }),
+        }
+    }
+
+    /// Note that downloading the feed called `name` has started.
+    pub fn start(&self, name: &str) {
+        self.update(|state| state.current.push(name.into()));
+    }
+
+    /// Note that `bytes` more have been downloaded.
+    pub fn downloaded(&self, bytes: usize) {
+        self.update(|state| state.bytes += bytes as u64);
+    }
+
+    /// Note that the feed called `name` is done with, whether or not it had to
+    /// be downloaded.
+    pub fn finish(&self, name: &str) {
+        self.update(|state| {
+            if let Some(i) = state.current.iter().position(|x| x == name) {
+                state.current.remove(i);
+            }
+            state.done += 1;
+        });
+    }
+
+    /// Run `print`, which prints something, with the line out of its way.
+    pub fn suspend<T>(&self, print: impl FnOnce() -> T) -> T {
+        self.update(|state| state.printing += 1);
+        let result = print();
+        self.update(|state| state.printing -= 1);
+        result
+    }
+
+    /// Take the line off the screen for good, once the downloads are done.
+    pub fn clear(&self) {
+        self.update(|state| state.cleared = true);
+    }
+
+    fn update(&self, change: impl FnOnce(&mut State)) {
+        let mut state = self.state.lock().unwrap();
+        change(&mut state);
+        if !self.enabled {
+            return;
+        }
+        let mut stderr = io::stderr();
+        if state.drawn {
+            let _ = write!(stderr, "\r\x1b[K");
+        }
+        state.drawn = state.printing == 0 && !state.cleared;
+        if state.drawn {
+            let _ = write!(stderr, "{}", line(&state, columns()));
+        }
+        let _ = stderr.flush();
+    }
+}
+
+/// How wide the terminal is.
+fn columns() -> usize {
+    crossterm::terminal::size()
+        .map(|(columns, _)| usize::from(columns))
+        .unwrap_or(DEFAULT_COLUMNS)
+}
+
+/// The line for `state`, like `[#####---------------] 3/12  1.2 MB  Witchy`,
+/// cut to fit in `columns`.
+fn line(state: &State, columns: usize) -> String {
+    let filled = match state.total {
+        0 => BAR_WIDTH,
+        total => state.done.min(total) * BAR_WIDTH / total,
+    };
+    let mut line = format!(
+        "[{}{}] {}/{}  {}",
+        "#".repeat(filled),
+        "-".repeat(BAR_WIDTH - filled),
+        state.done,
+        state.total,
+        bytes(state.bytes)
+    );
+    if !state.current.is_empty() {
+        line.push_str("  ");
+        line.push_str(&state.current.join(", "));
+    }
+    // The last column is left empty, so the line never wraps
+    line.chars().take(columns.saturating_sub(1)).collect()
+}
+
+/// `bytes` in the largest unit that keeps it over 1, like `1.2 MB`.
+fn bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KB", "MB", "GB"];
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit + 1 < UNITS.len() {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        let mut state = State {
+            total: 12,
+            done: 3,
+            current: vec!["Witchy".into(), "Oglaf".into()],
+            bytes: 1_234_567,
+            ..State::default()
+        };
+        assert_eq!(
+            line(&state, 80),
+            "[#####---------------] 3/12  1.2 MB  Witchy, Oglaf"
+        );
+        assert_eq!(line(&state, 32), "[#####---------------] 3/12  1.");
+
+        state.done = 12;
+        state.current.clear();
+        state.bytes = 999;
+        assert_eq!(line(&state, 80), "[####################] 12/12  999 B");
+
+        assert_eq!(bytes(1000), "1.0 KB");
+        assert_eq!(bytes(5_500_000_000_000), "5500.0 GB");
+
+        // Turned off, it still keeps count
+        let progress = Progress::new(2, false);
+        progress.start("Witchy");
+        progress.downloaded(100);
+        progress.finish("Witchy");
+        let state = progress.state.lock().unwrap();
+        assert_eq!((state.done, state.bytes), (1, 100));
+        assert!(state.current.is_empty());
+    }
+}
@@ -0,0 +1,264 @@
+//! How the parts of an open command are written as one line, and read back,
+//! on each platform. Windows passes programs a single command line that each
+//! program splits for itself, so the parts have to be quoted the way the C
+//! runtime splits them, and escaped again for `cmd.exe` when it runs the
+//! command. Elsewhere the parts are passed as they are, and the line is only
+//! for showing the command the way a shell would take it.
+
+/// One part of a Windows command line, quoted so the C runtime reads it back
+/// unchanged.
+pub fn quote_windows(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\x0b', '"']) {
+        return arg.into();
+    }
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Backslashes only escape anything right before a quote
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+pub fn join_windows<S: AsRef<str>>(args: &[S]) -> String {
+    let parts: Vec<_> = args.iter().map(|arg| quote_windows(arg.as_ref())).collect();
+    parts.join(" ")
+}
+
+/// Split a command line the way the C runtime does for a program's
+/// arguments. The program name itself follows simpler rules, which the
+/// quoting above also satisfies.
+pub fn split_windows(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut started = false;
+    let mut quoted = false;
+    let mut backslashes = 0;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                backslashes += 1;
+                started = true;
+                continue;
+            }
+            '"' => {
+                arg.push_str(&"\\".repeat(backslashes / 2));
+                if backslashes % 2 == 1 {
+                    arg.push('"');
+                } else if quoted && chars.peek() == Some(&'"') {
+                    // A doubled quote inside quotes is a quote
+                    chars.next();
+                    arg.push('"');
+                } else {
+                    quoted = !quoted;
+                }
+                started = true;
+            }
+            ' ' | '\t' if !quoted => {
+                arg.push_str(&"\\".repeat(backslashes));
+                if started {
+                    args.push(std::mem::take(&mut arg));
+                }
+                started = false;
+            }
+            _ => {
+                arg.push_str(&"\\".repeat(backslashes));
+                arg.push(c);
+                started = true;
+            }
+        }
+        backslashes = 0;
+    }
+    arg.push_str(&"\\".repeat(backslashes));
+    if started {
+        args.push(arg);
+    }
+    args
+}
+
+/// A Windows command line escaped for `cmd /S /C`, so `cmd.exe` passes it on
+/// as it is instead of reading `&`, `|`, `%` and the like in a URL as its own.
+pub fn escape_cmd(line: &str) -> String {
+    let mut escaped = String::with_capacity(line.len());
+    for c in line.chars() {
+        if matches!(c, '(' | ')' | '%' | '!' | '^' | '"' | '<' | '>' | '&' | '|') {
+            escaped.push('^');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// One part of a command, quoted for a POSIX shell if it needs it.
+pub fn quote_unix(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./_-".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.into()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+pub fn join_unix<S: AsRef<str>>(args: &[S]) -> String {
+    let parts: Vec<_> = args.iter().map(|arg| quote_unix(arg.as_ref())).collect();
+    parts.join(" ")
+}
+
+/// Split a command line the way a POSIX shell splits words, without any of
+/// its expansions.
+pub fn split_unix(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut started = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                arg.extend(chars.by_ref().take_while(|&c| c != '\''));
+                started = true;
+            }
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some(c @ '$') | Some(c @ '`') | Some(c @ '"') | Some(c @ '\\') => {
+                                arg.push(c)
+                            }
+                            Some('\n') => (),
+                            Some(c) => {
+                                arg.push('\\');
+                                arg.push(c);
+                            }
+                            None => arg.push('\\'),
+                        },
+                        _ => arg.push(c),
+                    }
+                }
+                started = true;
+            }
+            '\\' => {
+                match chars.next() {
+                    Some('\n') | None => (),
+                    Some(c) => arg.push(c),
+                }
+                started = true;
+            }
+            c if c.is_whitespace() => {
+                if started {
+                    args.push(std::mem::take(&mut arg));
+                }
+                started = false;
+            }
+            _ => {
+                arg.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        args.push(arg);
+    }
+    args
+}
+
+/// A command as one line, quoted the way this platform reads it, for
+/// messages.
+#[cfg(windows)]
+pub fn join<S: AsRef<str>>(args: &[S]) -> String {
+    join_windows(args)
+}
+
+/// A command as one line, quoted the way this platform reads it, for
+/// messages.
+#[cfg(not(windows))]
+pub fn join<S: AsRef<str>>(args: &[S]) -> String {
+    join_unix(args)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn awkward() -> Vec<Vec<&'static str>> {
+        vec![
+            vec!["firefox", "--new-tab", "http://example.com/comic?a=1&b=2"],
+            vec![
+                "C:\\Program Files\\Browser\\browser.exe",
+                "http://example.com",
+            ],
+            vec!["notify", "Title \"with quotes\"", "", "trailing\\"],
+            vec!["x", "back\\\\slashes\\\"", "tab\there", "it's", "$HOME `x`"],
+            vec!["x", "100%", "(1) ^ ! < > |", "ünïcødé"],
+        ]
+    }
+
+    #[test]
+    fn test_quote_windows() {
+        assert_eq!(quote_windows("plain"), "plain");
+        assert_eq!(quote_windows(""), "\"\"");
+        assert_eq!(quote_windows("a b"), "\"a b\"");
+        assert_eq!(quote_windows("a\"b"), r#""a\"b""#);
+        assert_eq!(quote_windows("a\\b c\\"), r#""a\b c\\""#);
+        assert_eq!(quote_windows("C:\\dir\\"), "C:\\dir\\");
+        assert_eq!(
+            split_windows(r#"a "b c" d\"e f""g "h""i" \\\\"j k""#),
+            vec!["a", "b c", "d\"e", "fg", "h\"i", "\\\\j k"]
+        );
+        for args in awkward() {
+            assert_eq!(split_windows(&join_windows(&args)), args);
+        }
+    }
+
+    #[test]
+    fn test_escape_cmd() {
+        assert_eq!(
+            escape_cmd(&join_windows(&[
+                "start",
+                "",
+                "http://example.com/?a=1&b=%20"
+            ])),
+            r#"start ^"^" http://example.com/?a=1^&b=^%20"#
+        );
+        // Every character cmd.exe would act on is escaped
+        for args in awkward() {
+            let line = escape_cmd(&join_windows(&args));
+            let mut chars = line.chars();
+            while let Some(c) = chars.next() {
+                assert!(!"()%!\"<>&|".contains(c), "{} in {}", c, line);
+                if c == '^' {
+                    chars.next();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_quote_unix() {
+        assert_eq!(quote_unix("http://example.com/1"), "http://example.com/1");
+        assert_eq!(quote_unix(""), "''");
+        assert_eq!(quote_unix("it's here"), r"'it'\''s here'");
+        assert_eq!(
+            split_unix(r#"a 'b c' "d \"e\" \x" f\ g''h"#),
+            vec!["a", "b c", "d \"e\" \\x", "f gh"]
+        );
+        for args in awkward() {
+            assert_eq!(split_unix(&join_unix(&args)), args);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Working out what changed between two versions of a config, so a long-running
+//! frontend can pick up edits without reloading every feed.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::error::Error;
+use crate::feed::{Feed, FeedInfo};
+
+/// How the feeds in a config changed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConfigDiff {
+    /// Feeds that are new in the config.
+    pub added: Vec<FeedInfo>,
+    /// Feeds that are gone from the config.
+    pub removed: Vec<FeedInfo>,
+    /// Feeds that are in both, as `(old, new)`, but set up differently. A
+    /// feed renamed with an `@ alias` for its old name counts as changed.
+    pub changed: Vec<(FeedInfo, FeedInfo)>,
+}
+
+/// Compare the feeds of two configs, matching them up by name.
+pub fn diff_configs(old: &[FeedInfo], new: &[FeedInfo]) -> ConfigDiff {
+    let mut diff = ConfigDiff::default();
+    let mut matched = HashSet::new();
+    for info in new {
+        let previous = old.iter().find(|old| old.name == info.name).or_else(|| {
+            // Only a rename if the old name is really gone
+            info.aliases()
+                .filter(|alias| !new.iter().any(|info| info.name == *alias))
+                .find_map(|alias| old.iter().find(|old| old.name == alias))
+        });
+        match previous {
+            Some(previous) => {
+                matched.insert(previous.name.as_str());
+                if previous != info {
+                    diff.changed.push((previous.clone(), info.clone()));
+                }
+            }
+            None => diff.added.push(info.clone()),
+        }
+    }
+    diff.removed = old
+        .iter()
+        .filter(|info| !matched.contains(info.name.as_str()))
+        .cloned()
+        .collect();
+    diff
+}
+
+/// What's different about a feed, in words, like `policies -@ every 3 days
+/// +@ every 5 days`.
+pub fn describe_changes(old: &FeedInfo, new: &FeedInfo) -> Vec<String> {
+    let mut changes = Vec::new();
+    if old.name != new.name {
+        changes.push(format!("renamed from \"{}\"", old.name));
+    }
+    if old.url != new.url {
+        changes.push(format!("URL <{}> -> <{}>", old.url, new.url));
+    }
+    if old.update_policies != new.update_policies {
+        let policies = |info: &FeedInfo| -> HashSet<String> {
+            info.update_policies.iter().map(|p| p.to_string()).collect()
+        };
+        let (old_policies, new_policies) = (policies(old), policies(new));
+        let mut words: Vec<_> = old_policies
+            .difference(&new_policies)
+            .map(|policy| format!("-{}", policy))
+            .chain(
+                new_policies
+                    .difference(&old_policies)
+                    .map(|policy| format!("+{}", policy)),
+            )
+            .collect();
+        words.sort_by(|a, b| a[1..].cmp(&b[1..]).then(a.cmp(b)));
+        changes.push(format!("policies {}", words.join(" ")));
+    }
+    if old.root != new.root {
+        changes.push("root".into());
+    }
+    if old.open_with != new.open_with {
+        changes.push("open with".into());
+    }
+    if old.read_marker != new.read_marker {
+        changes.push("read_marker".into());
+    }
+    if old.format != new.format {
+        changes.push("format".into());
+    }
+    if old.max_items_per_fetch != new.max_items_per_fetch {
+        changes.push("max_items_per_fetch".into());
+    }
+    if old.group != new.group {
+        changes.push("group".into());
+    }
+    if old.headers != new.headers {
+        changes.push("headers".into());
+    }
+    if old.auth != new.auth {
+        changes.push("auth".into());
+    }
+    changes
+}
+
+/// Whether a feed's history has to be read again after its config changes
+/// from `old` to `new`, rather than kept as it is. That's when the history
+/// would come from somewhere else.
+fn needs_reload(old: &FeedInfo, new: &FeedInfo) -> bool {
+    let aliases = |info: &FeedInfo| -> HashSet<String> { info.aliases().map(From::from).collect() };
+    old.name != new.name || old.root != new.root || aliases(old) != aliases(new)
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Bring `feeds`, loaded from the old config, up to date with the new one.
+    /// Feeds that didn't change are left alone, and changed feeds keep their
+    /// history and any changes not written out yet, unless their history has
+    /// to be read from somewhere else. `load` reads in the history of added
+    /// feeds, and of those. Feeds end up in the order of `order`, the names in
+    /// the new config.
+    pub fn apply<F>(&self, feeds: &mut Vec<Feed>, order: &[&str], mut load: F) -> Result<(), Error>
+    where
+        F: FnMut(&FeedInfo) -> Result<Feed, Error>,
+    {
+        feeds.retain(|feed| !self.removed.iter().any(|info| info.name == feed.info.name));
+        for (old, new) in &self.changed {
+            let index = match feeds.iter().position(|feed| feed.info.name == old.name) {
+                Some(index) => index,
+                None => continue,
+            };
+            if needs_reload(old, new) {
+                feeds[index] = load(new)?;
+            } else {
+                feeds[index].info = new.clone();
+            }
+        }
+        for info in &self.added {
+            feeds.push(load(info)?);
+        }
+        feeds.sort_by_key(|feed| order.iter().position(|&name| name == feed.info.name));
+        Ok(())
+    }
+}
+
+/// One line per feed that changed, like `+ "New Comic"`, `- "Old Comic"`,
+/// or `~ "Comic": policies -@ every 3 days +@ every 5 days`.
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for info in &self.added {
+            writeln!(fmt, "+ \"{}\"", info.name)?;
+        }
+        for info in &self.removed {
+            writeln!(fmt, "- \"{}\"", info.name)?;
+        }
+        for (old, new) in &self.changed {
+            writeln!(
+                fmt,
+                "~ \"{}\": {}",
+                new.name,
+                describe_changes(old, new).join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_config;
+
+    #[test]
+    fn test_diff_configs() {
+        let old = parse_config(
+            r#"
+"Same" <http://same.example.com>
+"Gone" <http://gone.example.com>
+"Policies" <http://policies.example.com> @ every 3 days @ open all
+"Old Name" <http://renamed.example.com>
+"#,
+        )
+        .unwrap();
+        let new = parse_config(
+            r#"
+"Policies" <http://policies.example.com> @ every 5 days @ open all
+"Same" <http://same.example.com>
+"New Name" <http://renamed.example.com> @ alias "Old Name"
+"Fresh" <http://fresh.example.com>
+"#,
+        )
+        .unwrap();
+        let diff = diff_configs(&old, &new);
+        assert_eq!(diff.added, vec![new[3].clone()]);
+        assert_eq!(diff.removed, vec![old[1].clone()]);
+        assert_eq!(
+            diff.changed,
+            vec![
+                (old[2].clone(), new[0].clone()),
+                (old[3].clone(), new[2].clone()),
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            concat!(
+                "+ \"Fresh\"\n",
+                "- \"Gone\"\n",
+                "~ \"Policies\": policies -@ every 3 days +@ every 5 days\n",
+                "~ \"New Name\": renamed from \"Old Name\", policies -@ 1 new comic +@ alias \"Old Name\"\n",
+            )
+        );
+        assert!(diff_configs(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_apply() {
+        let old = parse_config(
+            r#"
+"Same" <http://same.example.com>
+"Gone" <http://gone.example.com>
+"Policies" <http://policies.example.com> @ every 3 days
+"#,
+        )
+        .unwrap();
+        let new = parse_config(
+            r#"
+"Fresh" <http://fresh.example.com>
+"Policies" <http://policies.example.com> @ every 5 days
+"Same" <http://same.example.com>
+"#,
+        )
+        .unwrap();
+        let history = "<http://example.com/1>\n";
+        let mut feeds: Vec<Feed> = old
+            .iter()
+            .map(|info| info.read_feed(&mut history.as_bytes()).unwrap())
+            .collect();
+        // Changes that haven't been written out yet survive the reload
+        feeds[2].read_at(chrono::Utc::now());
+
+        let mut loaded = Vec::new();
+        let order: Vec<_> = new.iter().map(|info| info.name.as_str()).collect();
+        diff_configs(&old, &new)
+            .apply(&mut feeds, &order, |info| {
+                loaded.push(info.name.clone());
+                info.read_feed(&mut "".as_bytes())
+            })
+            .unwrap();
+        assert_eq!(loaded, vec!["Fresh"]);
+        let infos: Vec<_> = feeds.iter().map(|feed| feed.info.clone()).collect();
+        assert_eq!(infos, new);
+        assert_eq!(feeds[1].new_comics(), 0);
+        assert_eq!(feeds[2].new_comics(), 1);
+    }
+}
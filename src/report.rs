@@ -0,0 +1,305 @@
+use std::env;
+use std::io::{self, IsTerminal};
+
+use crate::error::{Error, ParseError, Span};
+use crate::json::Json;
+
+/// How errors are written out, picked with `--error-format`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorFormat {
+    /// For people: colored when stderr is a terminal, with parse errors shown
+    /// in context.
+    Human,
+    /// One JSON object per line, for tools that run feedburst.
+    Json,
+}
+
+/// When to color errors, picked with `--color`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorChoice {
+    /// When stderr is a terminal and `NO_COLOR` isn't set.
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Severity {
+    Error,
+    Warning,
+}
+
+/// Something to report, and where it came from.
+struct Diagnostic<'a> {
+    severity: Severity,
+    /// The feed it's about, for errors fetching or reading one.
+    feed: Option<&'a str>,
+    message: String,
+    source: Option<Source<'a>>,
+}
+
+/// The text a parse error was found in.
+struct Source<'a> {
+    what: &'a str,
+    line: &'a str,
+    row: usize,
+    span: Span,
+}
+
+impl<'a> Diagnostic<'a> {
+    fn new(severity: Severity, feed: Option<&'a str>, err: &'a Error) -> Self {
+        match *err {
+            Error::Parse {
+                ref what,
+                ref line,
+                error: ParseError::Expected { ref msg, row, span },
+            } => Diagnostic {
+                severity,
+                feed,
+                message: format!("Expected {}", msg),
+                source: Some(Source {
+                    what,
+                    line,
+                    row,
+                    span,
+                }),
+            },
+            _ => Diagnostic {
+                severity,
+                feed,
+                message: err.to_string(),
+                source: None,
+            },
+        }
+    }
+}
+
+/// The color of the `-->` and line number gutter around a line shown with an
+/// error.
+const GUTTER: &str = "1;34";
+
+/// Writes every error and warning to stderr in one format, whether it came
+/// from the config, a feed, or anywhere else.
+#[derive(Clone, Copy, Debug)]
+pub struct Reporter {
+    format: ErrorFormat,
+    color: bool,
+}
+
+impl Reporter {
+    pub fn new(format: ErrorFormat, color: ColorChoice) -> Self {
+        let color = format == ErrorFormat::Human
+            && match color {
+                ColorChoice::Auto => {
+                    io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none()
+                }
+                ColorChoice::Always => true,
+                ColorChoice::Never => false,
+            };
+        Reporter { format, color }
+    }
+
+    pub fn error(&self, err: &Error) {
+        self.emit(&Diagnostic::new(Severity::Error, None, err));
+    }
+
+    /// Report an error with one feed, which doesn't stop the others.
+    pub fn feed_error(&self, feed: &str, err: &Error) {
+        self.emit(&Diagnostic::new(Severity::Error, Some(feed), err));
+    }
+
+    pub fn warning(&self, message: &str) {
+        self.emit(&Diagnostic {
+            severity: Severity::Warning,
+            feed: None,
+            message: message.into(),
+            source: None,
+        });
+    }
+
+    fn emit(&self, diagnostic: &Diagnostic<'_>) {
+        eprintln!("{}", self.render(diagnostic));
+    }
+
+    fn render(&self, diagnostic: &Diagnostic<'_>) -> String {
+        match self.format {
+            ErrorFormat::Human => self.human(diagnostic),
+            ErrorFormat::Json => json(diagnostic).compact(),
+        }
+    }
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.into()
+        }
+    }
+
+    fn human(&self, diagnostic: &Diagnostic<'_>) -> String {
+        let (label, code) = match diagnostic.severity {
+            Severity::Error => ("error", "1;31"),
+            Severity::Warning => ("warning", "1;33"),
+        };
+        let label = match diagnostic.feed {
+            Some(feed) => format!("{} in feed \"{}\":", label, feed),
+            None => format!("{}:", label),
+        };
+        let source = match diagnostic.source {
+            Some(ref source) => source,
+            None => return format!("{} {}", self.paint(code, &label), diagnostic.message),
+        };
+        // Long messages, like lists of what was expected, go after the line
+        let (headline, rest) = match diagnostic.message.split_once('\n') {
+            Some((headline, rest)) => (headline, Some(rest)),
+            None => (diagnostic.message.as_str(), None),
+        };
+        let mut out = format!("{} {}", self.paint(code, &label), headline);
+        // Laid out like rustc's: where it is, then the line with a
+        // gutter of its number, and the mistake underlined below
+        let number = source.row.to_string();
+        let blank = " ".repeat(number.len());
+        let column = source.span.map_or(1, |(l, _)| l + 1);
+        out.push_str(&format!(
+            "\n{}{} {}:{}:{}\n",
+            blank,
+            self.paint(GUTTER, "-->"),
+            source.what,
+            source.row,
+            column
+        ));
+        let margin = self.paint(GUTTER, &format!("{} |", blank));
+        out.push_str(&format!("{}\n", margin));
+        out.push_str(&self.paint(GUTTER, &format!("{} |", number)));
+        if !source.line.is_empty() {
+            out.push_str(&format!(" {}", source.line));
+        }
+        out.push('\n');
+        out.push_str(&margin);
+        if let Some((l, r)) = source.span {
+            out.push_str(&" ".repeat(l + 1));
+            out.push_str(&self.paint(code, &"^".repeat(r - l + 1)));
+        }
+        if let Some(rest) = rest {
+            out.push('\n');
+            out.push_str(rest);
+        }
+        out
+    }
+}
+
+fn json(diagnostic: &Diagnostic<'_>) -> Json {
+    let severity = match diagnostic.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    let mut fields = vec![
+        ("severity", Json::str(severity)),
+        ("message", Json::str(diagnostic.message.as_str())),
+    ];
+    if let Some(feed) = diagnostic.feed {
+        fields.push(("feed", Json::str(feed)));
+    }
+    if let Some(ref source) = diagnostic.source {
+        fields.push(("file", Json::str(source.what)));
+        fields.push(("line", Json::Num(source.row)));
+        if let Some((l, r)) = source.span {
+            fields.push(("columns", Json::Arr(vec![Json::Num(l), Json::Num(r)])));
+        }
+        fields.push(("source", Json::str(source.line)));
+    }
+    Json::Obj(fields)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse_error() -> Error {
+        let text = "root /comics\n\"Bad\" <http://example.com> @ evry 2 days\n";
+        let error = ParseError::expected("a policy", 2, (29, 32));
+        Error::parse("config.feeds", text, error)
+    }
+
+    #[test]
+    fn test_human() {
+        let reporter = Reporter {
+            format: ErrorFormat::Human,
+            color: false,
+        };
+        let err = parse_error();
+        assert_eq!(
+            reporter.render(&Diagnostic::new(Severity::Error, None, &err)),
+            concat!(
+                "error: Expected a policy\n",
+                " --> config.feeds:2:30\n",
+                "  |\n",
+                "2 | \"Bad\" <http://example.com> @ evry 2 days\n",
+                "  |                              ^^^^",
+            )
+        );
+        // The rest of a long message comes after the line
+        let err = Error::parse(
+            "config.feeds",
+            "\"Bad\" <http://example.com> @ evry 2 days",
+            ParseError::expected("a policy. One of:\n - \"@ every # day(s)\"", 1, (29, 32)),
+        );
+        assert_eq!(
+            reporter.render(&Diagnostic::new(Severity::Error, None, &err)),
+            concat!(
+                "error: Expected a policy. One of:\n",
+                " --> config.feeds:1:30\n",
+                "  |\n",
+                "1 | \"Bad\" <http://example.com> @ evry 2 days\n",
+                "  |                              ^^^^\n",
+                " - \"@ every # day(s)\"",
+            )
+        );
+        let err = Error::Msg("Failed to download: \"404 Not Found\"".into());
+        assert_eq!(
+            reporter.render(&Diagnostic::new(Severity::Error, Some("Witchy"), &err)),
+            "error in feed \"Witchy\": Failed to download: \"404 Not Found\""
+        );
+
+        let colored = Reporter {
+            color: true,
+            ..reporter
+        };
+        assert_eq!(
+            colored.render(&Diagnostic::new(Severity::Error, None, &err)),
+            "\x1b[1;31merror:\x1b[0m Failed to download: \"404 Not Found\""
+        );
+        let err = parse_error();
+        let rendered = colored.render(&Diagnostic::new(Severity::Warning, None, &err));
+        assert!(rendered.starts_with("\x1b[1;33mwarning:\x1b[0m Expected a policy\n"));
+        assert!(rendered.contains("\x1b[1;34m2 |\x1b[0m \"Bad\""));
+        assert!(rendered.ends_with("\x1b[1;33m^^^^\x1b[0m"));
+    }
+
+    #[test]
+    fn test_color_choice() {
+        for &format in &[ErrorFormat::Human, ErrorFormat::Json] {
+            assert!(!Reporter::new(format, ColorChoice::Never).color);
+        }
+        assert!(Reporter::new(ErrorFormat::Human, ColorChoice::Always).color);
+        // JSON is never colored
+        assert!(!Reporter::new(ErrorFormat::Json, ColorChoice::Always).color);
+    }
+
+    #[test]
+    fn test_json() {
+        let reporter = Reporter {
+            format: ErrorFormat::Json,
+            color: false,
+        };
+        let err = parse_error();
+        assert_eq!(
+            reporter.render(&Diagnostic::new(Severity::Error, Some("Bad"), &err)),
+            concat!(
+                r#"{"severity":"error","message":"Expected a policy","feed":"Bad","#,
+                r#""file":"config.feeds","line":2,"columns":[29,32],"#,
+                r#""source":"\"Bad\" <http://example.com> @ evry 2 days"}"#,
+            )
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! `@ random N comics`: picking comics from a backlog at random. The picks
+//! only depend on the seed, so a run given `--seed` can be repeated exactly.
+
+/// A seed for picking from the feed called `name`, so feeds sharing a seed
+/// don't all pick the same way.
+pub fn feed_seed(seed: u64, name: &str) -> u64 {
+    // FNV-1a, which unlike std's hashers is the same in every build
+    let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    hash ^ seed
+}
+
+/// The next number from a SplitMix64 generator at `state`.
+fn next(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// `count` of `items` picked at random from `seed`, in the order they were
+/// in. All of them if there aren't more than `count`.
+pub fn sample<T: Clone>(items: &[T], count: usize, seed: u64) -> Vec<T> {
+    let mut state = seed;
+    let mut indices: Vec<usize> = (0..items.len()).collect();
+    let count = count.min(items.len());
+    for i in 0..count {
+        let j = i + (next(&mut state) % (items.len() - i) as u64) as usize;
+        indices.swap(i, j);
+    }
+    let mut picked = indices[..count].to_vec();
+    picked.sort_unstable();
+    picked.into_iter().map(|i| items[i].clone()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        let items: Vec<usize> = (0..100).collect();
+        let picked = sample(&items, 5, 42);
+        assert_eq!(picked.len(), 5);
+        assert!(picked.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(sample(&items, 5, 42), picked);
+        assert_ne!(sample(&items, 5, 43), picked);
+        assert_eq!(sample(&items[..3], 5, 42), vec![0, 1, 2]);
+        assert!(sample(&items, 0, 42).is_empty());
+
+        assert_eq!(feed_seed(1, "Comic"), feed_seed(1, "Comic"));
+        assert_ne!(feed_seed(1, "Comic"), feed_seed(1, "Other Comic"));
+    }
+}
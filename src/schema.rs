@@ -0,0 +1,129 @@
+use crate::help::{FEED_HELP, FEED_SYNTAX};
+use crate::json::Json;
+use crate::parser::{DIRECTIVE_SYNTAX, ENGLISH_WEEKDAYS, LOCALES, POLICY_SYNTAX};
+
+/// The config language as JSON, for `feedburst schema --json`. Every form in
+/// the parser's syntax tables is broken into parts: literal keywords, choices
+/// between keywords, and typed arguments.
+pub fn schema_json() -> String {
+    let mut weekdays = vec![("en", Json::strs(ENGLISH_WEEKDAYS.iter().cloned()))];
+    for &(code, ref names) in LOCALES {
+        weekdays.push((code, Json::strs(names.iter().cloned())));
+    }
+    let schema = Json::Obj(vec![
+        ("version", Json::str(env!("CARGO_PKG_VERSION"))),
+        (
+            "feed",
+            Json::Obj(vec![
+                ("syntax", Json::str(FEED_SYNTAX)),
+                ("help", Json::str(FEED_HELP.replace('\n', " "))),
+            ]),
+        ),
+        ("policies", forms(POLICY_SYNTAX)),
+        ("directives", forms(DIRECTIVE_SYNTAX)),
+        ("weekdays", Json::Obj(weekdays)),
+    ]);
+    let mut out = schema.pretty();
+    out.push('\n');
+    out
+}
+
+fn forms(syntax: &[(&str, &str)]) -> Json {
+    Json::Arr(
+        syntax
+            .iter()
+            .map(|&(form, help)| {
+                let parts = form.trim_start_matches("@ ").split(' ').map(part);
+                Json::Obj(vec![
+                    ("syntax", Json::str(form)),
+                    ("help", Json::str(help)),
+                    ("parts", Json::Arr(parts.collect())),
+                ])
+            })
+            .collect(),
+    )
+}
+
+/// Describe one word of a syntax form. Placeholders are in capitals (or `#`),
+/// and `a|b` or `word(s)` offer a choice of keywords.
+fn part(word: &str) -> Json {
+    let argument = |kind: &str| Json::Obj(vec![("argument", Json::str(kind))]);
+    match word {
+        "#" | "N" => argument("integer"),
+        "WEEKDAYS" => argument("weekdays"),
+        "DAY" => argument("day of the month"),
+        "PATH" => argument("path"),
+        "URL" => argument("url"),
+        "HOST" => argument("host"),
+        "ADDRESS" => argument("email address"),
+        "COMMAND" => argument("command"),
+        "CONSUMER_KEY" | "ACCESS_TOKEN" | "USER" | "PASSWORD" | "CLIENT_ID" | "CLIENT_SECRET" => {
+            argument("credential")
+        }
+        "NAMES" => argument("names"),
+        "TEXT" => argument("text"),
+        "INTERVAL" => argument("interval"),
+        "DATE" => argument("date"),
+        "TIME" => argument("time of day"),
+        "ZONE" => argument("time zone"),
+        "LANG" => argument("language"),
+        "FINGERPRINT" => argument("fingerprint"),
+        "POLICIES" => argument("policies"),
+        "\"NAME\"" => argument("quoted name"),
+        "\"SELECTOR\"" => argument("quoted css selector"),
+        "/pattern/" => argument("pattern"),
+        "/pattern/replacement/" => argument("pattern and replacement"),
+        "CODE" => Json::Obj(vec![
+            ("argument", Json::str("locale")),
+            ("values", Json::strs(LOCALES.iter().map(|&(code, _)| code))),
+        ]),
+        _ if word.ends_with("(s)") => {
+            let singular = word.trim_end_matches("(s)");
+            Json::Obj(vec![(
+                "choice",
+                Json::Arr(vec![
+                    Json::str(singular),
+                    Json::str(format!("{}s", singular)),
+                ]),
+            )])
+        }
+        _ if word.contains('|') => Json::Obj(vec![("choice", Json::strs(word.split('|')))]),
+        _ if word
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') =>
+        {
+            Json::Obj(vec![("literal", Json::str(word))])
+        }
+        _ => unreachable!("no schema for \"{}\", add it to schema::part", word),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_schema() {
+        // Building it at all checks every form has a schema
+        let schema = schema_json();
+        for &(form, _) in POLICY_SYNTAX.iter().chain(DIRECTIVE_SYNTAX) {
+            let form = form.replace('"', "\\\"");
+            assert!(schema.contains(&format!("\"syntax\": \"{}\"", form)));
+        }
+        assert!(schema.contains(concat!(
+            "      \"parts\": [\n",
+            "        {\n",
+            "          \"literal\": \"every\"\n",
+            "        },\n",
+            "        {\n",
+            "          \"argument\": \"integer\"\n",
+            "        },\n",
+            "        {\n",
+            "          \"choice\": [\n",
+            "            \"day\",\n",
+            "            \"days\"\n",
+            "          ]\n",
+        )));
+        assert!(schema.contains(r#""syntax": "\"NAME\" <URL> @ POLICY @ POLICY ...""#));
+    }
+}
@@ -0,0 +1,385 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::compression::encode;
+use crate::config::Args;
+use crate::error::Error;
+use crate::feed::{Feed, OpenWith, ReadLater};
+use crate::fetch::Fetcher;
+use crate::parser::Settings;
+
+/// The feeds the mock server hands out, by path, with their content types.
+const ROUTES: &[(&str, &str, &str)] = &[
+    (
+        "/atom.xml",
+        "application/atom+xml",
+        include_str!("../tests/fixtures/atom.xml"),
+    ),
+    (
+        "/cdata.rss",
+        "application/rss+xml",
+        include_str!("../tests/fixtures/cdata.rss"),
+    ),
+    (
+        "/flaky.xml",
+        "application/atom+xml",
+        include_str!("../tests/fixtures/atom.xml"),
+    ),
+];
+
+/// Whether `/flaky.xml` has failed yet. It fails the first time only, like an
+/// overloaded server, to check that downloads are retried.
+static FLAKED: AtomicBool = AtomicBool::new(false);
+
+/// How many times the mock Wallabag API was logged in to.
+static WALLABAG_LOGINS: AtomicUsize = AtomicUsize::new(0);
+
+/// The `Authorization` header of each comic queued on the mock Wallabag API.
+static WALLABAG_QUEUED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Run feedburst end to end against a mock server on localhost, in a
+/// throwaway directory, printing each step as it goes. Returns whether every
+/// step passed.
+pub fn selftest() -> Result<bool, Error> {
+    let addr = serve()?;
+    let dir = std::env::temp_dir().join(format!("feedburst-selftest-{}", std::process::id()));
+    fs::create_dir_all(dir.join("feeds"))?;
+    println!("Running in {}", dir.display());
+
+    let result = run_steps(addr, &dir);
+    if let Err(err) = fs::remove_dir_all(&dir) {
+        warn!("Couldn't clean up {}: {}", dir.display(), err);
+    }
+
+    let failures = result?;
+    if failures == 0 {
+        println!("\nAll steps passed.");
+    } else {
+        println!("\n{} step(s) failed.", failures);
+    }
+    Ok(failures == 0)
+}
+
+/// Run each step, returning how many failed.
+fn run_steps(addr: SocketAddr, dir: &Path) -> Result<usize, Error> {
+    let config_path = dir.join("config.feeds");
+    let config = format!(
+        concat!(
+            "\"Atom\" <http://{addr}/atom.xml>\n",
+            "\"RSS\" <http://{addr}/cdata.rss> @ 3 new comics\n",
+            "\"Broken\" <http://{addr}/missing.xml>\n",
+            "\"Flaky\" <http://{addr}/flaky.xml> @ 3 new comics @ retries 1\n",
+        ),
+        addr = addr
+    );
+    fs::write(&config_path, config)?;
+    let feeds_dir = dir.join("feeds").to_string_lossy().into_owned();
+    let config_path = config_path.to_string_lossy().into_owned();
+    let args = Args::new(
+        true,
+        Some(&feeds_dir),
+        Some(&config_path),
+        None,
+        None,
+        false,
+    )?;
+
+    let mut failures = 0;
+    let mut check = |name: &str, result: Result<(), String>| match result {
+        Ok(()) => println!("ok      {}", name),
+        Err(msg) => {
+            println!("FAILED  {}: {}", name, msg);
+            failures += 1;
+        }
+    };
+
+    let feeds = match crate::load_config(&args) {
+        Ok(feeds) => feeds,
+        Err(err) => {
+            check("Load the config", Err(err.to_string()));
+            return Ok(failures);
+        }
+    };
+    check("Load the config", expect_eq(feeds.len(), 4, "feeds"));
+
+    let mut fetched = Vec::new();
+    for info in feeds {
+        let feed = info.read_feed(&mut args.feed_file(&info)?)?;
+        match crate::fetch_feed(&args, &Settings::default(), feed) {
+            Ok(feed) => fetched.push(feed),
+            Err(_) if info.name == "Broken" => check("Report a missing feed as an error", Ok(())),
+            Err(err) => check(&format!("Fetch \"{}\"", info.name), Err(err.to_string())),
+        }
+    }
+    check("Fetch feeds", expect_eq(fetched.len(), 3, "fetched feeds"));
+    check(
+        "Retry a download the server was too busy for",
+        expect_eq(
+            fetched.iter().any(|feed| feed.info.name == "Flaky"),
+            true,
+            "fetched",
+        ),
+    );
+
+    for feed in &mut fetched {
+        let name = feed.info.name.clone();
+        check(
+            &format!("Find new comics in \"{}\"", name),
+            expect_eq(feed.new_comics(), 2, "new comics"),
+        );
+        let should_be_ready = name == "Atom";
+        check(
+            &format!("Apply the policies of \"{}\"", name),
+            expect_eq(feed.is_ready(args.clock()), should_be_ready, "ready"),
+        );
+        if feed.is_ready(args.clock()) {
+            feed.open();
+            feed.read(args.clock());
+            feed.write_changes(&mut args.feed_file(&feed.info)?)?;
+        }
+    }
+
+    // Everything should survive a round trip through the feed files
+    let reloaded = reload(&args, &fetched)?;
+    for feed in &reloaded {
+        let expected = if feed.info.name == "Atom" { 0 } else { 2 };
+        check(
+            &format!("Remember what was read in \"{}\"", feed.info.name),
+            expect_eq(feed.new_comics(), expected, "new comics"),
+        );
+    }
+
+    for feed in &reloaded {
+        let cached = args.cache_path(&feed.info)?.exists();
+        check(
+            &format!("Remember the ETag of \"{}\"", feed.info.name),
+            expect_eq(cached, true, "cached"),
+        );
+    }
+
+    let mut refetched = 0;
+    for feed in reloaded {
+        let before = feed.comics().count();
+        let name = feed.info.name.clone();
+        match crate::fetch_feed(&args, &Settings::default(), feed) {
+            Ok(feed) => {
+                let step = format!("Skip comics already seen in \"{}\"", name);
+                check(&step, expect_eq(feed.comics().count(), before, "comics"));
+                refetched += 1;
+            }
+            Err(err) => check(&format!("Fetch \"{}\" again", name), Err(err.to_string())),
+        }
+    }
+    check(
+        "Fetch feeds again",
+        expect_eq(refetched, 3, "fetched feeds"),
+    );
+
+    let fetcher = crate::HttpFetcher::new()?;
+    for &encoding in &["gzip", "deflate", "br"] {
+        let url = format!("http://{}/atom.xml?encoding={}", addr, encoding);
+        let info = crate::parser::parse_config(&format!("\"Compressed\" <{}>", url))
+            .map_err(|err| Error::Msg(format!("{:?}", err)))?
+            .remove(0);
+        let content = fetcher.fetch(&info).map_err(|err| err.to_string());
+        check(
+            &format!("Decode a feed sent with {}", encoding),
+            content.and_then(|content| {
+                expect_eq(
+                    content.as_str(),
+                    include_str!("../tests/fixtures/atom.xml"),
+                    "feed",
+                )
+            }),
+        );
+    }
+
+    let moved = format!("http://{}/moved.xml", addr);
+    let info = crate::parser::parse_config(&format!("\"Moved\" <{}>", moved))
+        .map_err(|err| Error::Msg(format!("{:?}", err)))?
+        .remove(0);
+    let content = fetcher.fetch(&info).map_err(|err| err.to_string());
+    check(
+        "Follow a feed that moved for good",
+        content.and_then(|_| {
+            expect_eq(
+                fetcher.moved_to(&moved),
+                Some(format!("http://{}/atom.xml", addr)),
+                "new URL",
+            )
+        }),
+    );
+
+    let wallabag = OpenWith::ReadLater(ReadLater::Wallabag {
+        url: format!("http://{}", addr),
+        client_id: "client".into(),
+        client_secret: "secret".into(),
+        user: "me".into(),
+        password: "hunter2".into(),
+    });
+    let opener = crate::opener::opener(&wallabag);
+    let queued = (1..=2)
+        .map(|page| {
+            let comic = format!("http://{}/comic/{}", addr, page);
+            opener.open(&comic, Some("A Comic"))
+        })
+        .collect::<Result<Vec<()>, Error>>()
+        .map_err(|err| err.to_string());
+    check(
+        "Queue comics on Wallabag",
+        queued.and_then(|_| {
+            expect_eq(
+                (
+                    WALLABAG_LOGINS.load(Ordering::SeqCst),
+                    WALLABAG_QUEUED.lock().unwrap().clone(),
+                ),
+                (1, vec!["Bearer selftest-token".to_string(); 2]),
+                "logins and queued comics",
+            )
+        }),
+    );
+
+    Ok(failures)
+}
+
+fn reload(args: &Args, feeds: &[Feed]) -> Result<Vec<Feed>, Error> {
+    feeds
+        .iter()
+        .map(|feed| feed.info.read_feed(&mut args.feed_file(&feed.info)?))
+        .collect()
+}
+
+fn expect_eq<T: PartialEq + std::fmt::Debug>(
+    got: T,
+    expected: T,
+    what: &str,
+) -> Result<(), String> {
+    if got == expected {
+        Ok(())
+    } else {
+        Err(format!("expected {:?} {}, got {:?}", expected, what, got))
+    }
+}
+
+/// Start a tiny HTTP server on a free localhost port that serves `ROUTES`,
+/// and 404s for anything else. It runs until the process exits.
+fn serve() -> Result<SocketAddr, Error> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    debug!("Mock server listening on {}", addr);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            if let Err(err) = stream.and_then(respond) {
+                warn!("Mock server error: {}", err);
+            }
+        }
+    });
+    Ok(addr)
+}
+
+fn respond(stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut if_none_match = None;
+    let mut accept_encoding = String::new();
+    let mut authorization = String::new();
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("if-none-match") {
+                if_none_match = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("accept-encoding") {
+                accept_encoding = value.trim().to_string();
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = value.trim().to_string();
+            } else if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    // Read the whole request, so closing the connection doesn't reset it
+    let mut request_body = vec![0; content_length];
+    reader.read_exact(&mut request_body)?;
+
+    // `?encoding=gzip` and the like compress the document, if the client
+    // accepts it
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let encoding = query
+        .strip_prefix("encoding=")
+        .filter(|&encoding| accept_encoding.split(',').any(|e| e.trim() == encoding));
+    let (status, content_type, body) = match ROUTES.iter().find(|&&(route, ..)| route == path) {
+        Some(&(_, content_type, body)) => ("200 OK", content_type, body),
+        // A Wallabag API, that takes any login
+        None if path == "/oauth/v2/token" => {
+            WALLABAG_LOGINS.fetch_add(1, Ordering::SeqCst);
+            (
+                "200 OK",
+                "application/json",
+                r#"{"access_token": "selftest-token", "token_type": "bearer"}"#,
+            )
+        }
+        None if path == "/api/entries.json" => {
+            WALLABAG_QUEUED.lock().unwrap().push(authorization);
+            ("200 OK", "application/json", "{}")
+        }
+        None => ("404 Not Found", "text/plain", "Not Found"),
+    };
+    let (status, body) = if path == "/flaky.xml" && !FLAKED.swap(true, Ordering::SeqCst) {
+        ("503 Service Unavailable", "Try again later")
+    } else {
+        (status, body)
+    };
+    // `/moved.xml` is the Atom feed's old URL
+    let (status, body, location) = if path == "/moved.xml" {
+        ("301 Moved Permanently", "", "Location: /atom.xml\r\n")
+    } else {
+        (status, body, "")
+    };
+    // The documents never change, so their length will do as a version
+    let etag = format!("\"{}\"", body.len());
+    let (status, body) = if if_none_match.as_ref() == Some(&etag) {
+        ("304 Not Modified", "")
+    } else {
+        (status, body)
+    };
+    let (body, content_encoding) = match encoding.and_then(|e| encode(e, body.as_bytes())) {
+        Some(compressed) => (
+            compressed,
+            format!("Content-Encoding: {}\r\n", encoding.unwrap()),
+        ),
+        None => (body.as_bytes().to_vec(), String::new()),
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nETag: {}\r\n{}{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        etag,
+        location,
+        content_encoding,
+        body.len(),
+    )?;
+    stream.write_all(&body)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_selftest() {
+        assert!(selftest().unwrap());
+    }
+}
@@ -0,0 +1,139 @@
+//! Planning a reading session: which feeds are ready, and what reading each
+//! of them would open. Planning leaves the feeds alone, so a frontend can
+//! show the plan first, and then open the comics its own way and record
+//! them read with `Feed::open` and `Feed::read_at`.
+
+use crate::clock::Clock;
+use crate::dedupe::ReadComics;
+use crate::feed::{Feed, UpdateSpec};
+
+/// What reading one ready feed would do.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReadingPlan {
+    /// The feed's name, as written in the config.
+    pub name: String,
+    /// The feed's whole reading list, oldest first.
+    pub reading_list: Vec<String>,
+    /// The comics on the reading list that were already read in another
+    /// feed, with `dedupe on`.
+    pub already_read: Vec<String>,
+    /// The comics to open: just the earliest of the rest of the reading list,
+    /// which links on to the others, or all of them with `@ open all`.
+    pub open: Vec<String>,
+    /// How many unread comics are left for a later session, by `@ max N
+    /// comics` or `@ by chapter`.
+    pub left_for_later: usize,
+    /// Whether `@ surprise` also brings back a comic from the archive.
+    pub surprise: bool,
+}
+
+/// Plan reading `feed`, if it's ready now and has anything to read. With
+/// `read_comics`, for `dedupe on`, comics already read in another feed are
+/// left out, and the rest are counted as read for the feeds planned after it.
+pub fn plan_reading(
+    feed: &Feed,
+    clock: &dyn Clock,
+    read_comics: Option<&mut ReadComics>,
+) -> Option<ReadingPlan> {
+    if !feed.is_ready(clock) {
+        return None;
+    }
+    let reading_list = feed.get_reading_list();
+    if reading_list.is_empty() {
+        return None;
+    }
+    let mut rest = reading_list.clone();
+    let mut already_read = Vec::new();
+    if let Some(read_comics) = read_comics {
+        let (first, again): (Vec<String>, Vec<String>) = reading_list
+            .iter()
+            .cloned()
+            .partition(|url| read_comics.first_read(url));
+        rest = first;
+        already_read = again;
+    }
+    let open = if feed.info.update_policies.contains(&UpdateSpec::OpenAll) {
+        rest
+    } else {
+        rest.into_iter().take(1).collect()
+    };
+    let left_for_later = match feed.info.max_per_session() {
+        Some(_) => feed.left_for_later().len(),
+        None => 0,
+    };
+    Some(ReadingPlan {
+        name: feed.info.name.clone(),
+        reading_list,
+        already_read,
+        open,
+        left_for_later,
+        surprise: !feed.surprise_candidates_at(clock.now()).is_empty(),
+    })
+}
+
+/// Plan reading each of `feeds` that's ready now, in order. With `dedupe`,
+/// a comic that several feeds share is only opened in the first of them.
+pub fn plan_session(feeds: &[Feed], clock: &dyn Clock, dedupe: bool) -> Vec<ReadingPlan> {
+    let mut read_comics = if dedupe {
+        Some(ReadComics::new(feeds))
+    } else {
+        None
+    };
+    feeds
+        .iter()
+        .filter_map(|feed| plan_reading(feed, clock, read_comics.as_mut()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::parser::parse_config;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_plan_session() {
+        let infos = parse_config(
+            r#""Collective" <http://collective.example.com/rss> @ 1 new comic @ open all
+"Member" <http://member.example.com/rss> @ 1 new comic
+"Waiting" <http://waiting.example.com/rss> @ 5 new comics
+"#,
+        )
+        .unwrap();
+        let histories = [
+            "
+<http://member.example.com/1> 2018-10-01T12:00:00+00:00
+<http://member.example.com/2> 2018-10-02T12:00:00+00:00
+",
+            "
+<http://member.example.com/2> 2018-10-02T12:00:00+00:00
+<http://member.example.com/3> 2018-10-03T12:00:00+00:00
+",
+            "
+<http://waiting.example.com/1> 2018-10-02T12:00:00+00:00
+",
+        ];
+        let feeds: Vec<Feed> = infos
+            .iter()
+            .zip(histories.iter())
+            .map(|(info, history)| info.read_feed(&mut history.as_bytes()).unwrap())
+            .collect();
+        let clock = FixedClock(Utc.ymd(2018, 10, 4).and_hms(12, 0, 0));
+
+        let plans = plan_session(&feeds, &clock, false);
+        let names: Vec<_> = plans.iter().map(|plan| plan.name.as_str()).collect();
+        assert_eq!(names, vec!["Collective", "Member"]);
+        assert_eq!(plans[0].open, plans[0].reading_list);
+        assert_eq!(plans[1].open, vec!["http://member.example.com/2"]);
+        assert!(plans[1].already_read.is_empty());
+
+        // The member's second comic was already in the collective's list
+        let plans = plan_session(&feeds, &clock, true);
+        assert_eq!(plans[1].already_read, vec!["http://member.example.com/2"]);
+        assert_eq!(plans[1].open, vec!["http://member.example.com/3"]);
+
+        // Planning doesn't change anything
+        assert_eq!(plan_session(&feeds, &clock, true), plans);
+    }
+}
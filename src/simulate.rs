@@ -0,0 +1,106 @@
+use chrono::{DateTime, Duration, Local, Utc};
+use std::io;
+
+use crate::extract::Item;
+use crate::feed::{Comic, FeedInfo};
+
+/// One run of feedburst in which the simulated feed would have opened.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub when: DateTime<Local>,
+    /// Comics that arrived since the previous session.
+    pub new: usize,
+    /// Everything that would be in the reading list, including overlap.
+    pub total: usize,
+}
+
+/// Replay a feed's publish history against its policies, as if feedburst had
+/// been run once a day at the time of day of `start`, for `days` days.
+///
+/// Comics published before `start` are treated as already read, and comics
+/// without a recorded publish date are ignored, since there's no way to tell
+/// when they would have shown up.
+pub fn simulate(
+    info: &FeedInfo,
+    comics: &[Comic],
+    start: DateTime<Local>,
+    days: i64,
+) -> Vec<Session> {
+    let mut feed = info
+        .read_feed(&mut io::empty())
+        .expect("an empty event log always parses");
+    let to_item = |comic: &Comic, published: DateTime<Utc>| Item {
+        url: comic.url.clone(),
+        title: comic.title.clone().unwrap_or_default(),
+        guid: None,
+        published,
+        language: None,
+        thumbnail: None,
+    };
+
+    let (before, mut pending): (Vec<_>, Vec<_>) = comics
+        .iter()
+        .filter_map(|comic| comic.published.map(|date| to_item(comic, date)))
+        .partition(|item| item.published < start);
+    feed.add_new_comics(&before);
+    feed.read_at(start.with_timezone(&Utc));
+
+    let mut sessions = Vec::new();
+    for day in 1..=days {
+        let now = start + Duration::days(day);
+        let (arrived, rest): (Vec<_>, Vec<_>) =
+            pending.into_iter().partition(|item| item.published <= now);
+        pending = rest;
+        feed.add_new_comics(&arrived);
+
+        if feed.is_ready_at(now) {
+            sessions.push(Session {
+                when: now,
+                new: feed.new_comics(),
+                total: feed.get_reading_list().len(),
+            });
+            feed.read_at(now.with_timezone(&Utc));
+        }
+    }
+    sessions
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_config;
+    use chrono::TimeZone;
+
+    fn comic(day: u32) -> Comic {
+        Comic {
+            url: format!("http://example.com/{}", day),
+            published: Some(Utc.ymd(2018, 10, day).and_hms(12, 0, 0)),
+            title: None,
+        }
+    }
+
+    #[test]
+    fn test_simulate() {
+        // One comic a day on the 1st through the 14th of October 2018, which
+        // started on a Monday
+        let comics: Vec<_> = (1..15).map(comic).collect();
+        let start = Local.ymd(2018, 10, 1).and_hms(0, 0, 0);
+        let sessions = |config: &str| -> Vec<(u32, usize, usize)> {
+            use chrono::Datelike;
+            let info = parse_config(config).unwrap().remove(0);
+            simulate(&info, &comics, start, 14)
+                .into_iter()
+                .map(|x| (x.when.day(), x.new, x.total))
+                .collect()
+        };
+
+        assert_eq!(
+            sessions(r#""Daily" <http://example.com/feed> @ every 5 days @ overlap 1 comic"#),
+            vec![(6, 5, 5), (11, 5, 6)],
+        );
+        assert_eq!(
+            sessions(r#""Weekly" <http://example.com/feed> @ on Sunday @ 3 new comics"#),
+            vec![(7, 6, 6), (14, 7, 7)],
+        );
+    }
+}
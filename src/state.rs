@@ -0,0 +1,524 @@
+//! Where a feed is in its reading cycle, and what moves it along.
+//!
+//! ```text
+//!            Fetched          Tick (policies pass)           Open
+//!  Unseen ──────────▶ Pending ────────────────────▶ Ready ───────▶ Opened
+//!                       ▲     ◀────────────────────                  │
+//!                       │      Tick (policies fail)                  │ Read
+//!                       │ Fetched                                    ▼
+//!                       └──────────────────────────────────────── Read
+//! ```
+//!
+//! `Fetched` and `Read` come from the feed's event log, `Open` from actually
+//! opening the reading list, and `Tick` from the clock. A `Read` moves any
+//! state to `Read`, so that marking a feed read by hand always works.
+
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+};
+
+use crate::feed::{FeedInfo, UpdateSpec};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum State {
+    /// No comics have ever been fetched for the feed.
+    Unseen,
+    /// There are unread comics, but the policies say to wait.
+    Pending,
+    /// There are unread comics and the policies say to show them.
+    Ready,
+    /// The reading list has been opened, but not yet recorded as read.
+    Opened,
+    /// Everything fetched so far has been read.
+    Read,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    /// A fetch found this many comics that weren't seen before.
+    Fetched(usize),
+    /// Time has passed, so the policies should be checked again.
+    Tick(DateTime<Local>),
+    /// The reading list was opened.
+    Open,
+    /// The feed was marked read at the given time.
+    Read(DateTime<Utc>),
+}
+
+/// What the policies need to know about a feed's history to decide if it's
+/// ready.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub last_read: Option<DateTime<Utc>>,
+    pub new_comics: usize,
+}
+
+impl State {
+    /// The state after `trigger` happens to a feed in this state.
+    pub fn next(self, trigger: Trigger, info: &FeedInfo, progress: Progress) -> State {
+        use self::State::*;
+        let next = match (self, trigger) {
+            (_, Trigger::Read(_)) => Read,
+            (Unseen, Trigger::Fetched(n)) | (Read, Trigger::Fetched(n)) if n > 0 => Pending,
+            (Pending, Trigger::Tick(now)) | (Ready, Trigger::Tick(now)) => {
+                if is_scheduled(info, progress.last_read, now)
+                    && has_enough_comics(info, progress.new_comics)
+                {
+                    Ready
+                } else {
+                    Pending
+                }
+            }
+            (Pending, Trigger::Open) | (Ready, Trigger::Open) => Opened,
+            (state, _) => state,
+        };
+        if next != self {
+            trace!(
+                "\"{}\": {:?} -> {:?} on {:?}",
+                info.name,
+                self,
+                next,
+                trigger
+            );
+        }
+        next
+    }
+}
+
+/// What the clock says at `datetime` in the feed's `timezone`, or here if it
+/// doesn't have one.
+pub fn wall_clock<T: TimeZone>(info: &FeedInfo, datetime: &DateTime<T>) -> NaiveDateTime {
+    match info.timezone {
+        Some(zone) => datetime.with_timezone(&zone).naive_local(),
+        None => datetime.with_timezone(&Local).naive_local(),
+    }
+}
+
+/// When the clock in the feed's `timezone`, or here, says `wall_clock`. The
+/// earlier time if a DST change says it twice, and none if one skips it.
+pub fn from_wall_clock(info: &FeedInfo, wall_clock: NaiveDateTime) -> Option<DateTime<Local>> {
+    match info.timezone {
+        Some(zone) => zone
+            .from_local_datetime(&wall_clock)
+            .earliest()
+            .map(|datetime| datetime.with_timezone(&Local)),
+        None => Local.from_local_datetime(&wall_clock).earliest(),
+    }
+}
+
+/// The same time of day `days` days after `datetime` on the feed's clock, so
+/// a day with a DST change in it is still a day. Exactly `days` times 24 hours
+/// later if that time is skipped.
+pub fn days_later(info: &FeedInfo, datetime: DateTime<Utc>, days: i64) -> DateTime<Utc> {
+    from_wall_clock(info, wall_clock(info, &datetime) + Duration::days(days))
+        .map(|later| later.with_timezone(&Utc))
+        .unwrap_or(datetime + Duration::days(days))
+}
+
+/// Whether `@ pause until` or `@ between` keep the feed from being downloaded
+/// or shown on the day of `datetime`.
+pub fn is_paused(info: &FeedInfo, datetime: DateTime<Local>) -> bool {
+    let today = wall_clock(info, &datetime).date();
+    info.update_policies.iter().any(|policy| match *policy {
+        UpdateSpec::PauseUntil(date) => today < date,
+        UpdateSpec::Between(start, end) => today < start || today > end,
+        _ => false,
+    })
+}
+
+/// The times of day `@ after` and `@ before` allow showing the feed between,
+/// the latest `@ after` and the earliest `@ before`.
+fn time_window(info: &FeedInfo) -> (Option<NaiveTime>, Option<NaiveTime>) {
+    let mut after = None;
+    let mut before = None;
+    for policy in &info.update_policies {
+        match *policy {
+            UpdateSpec::After(time) => after = after.max(Some(time)),
+            UpdateSpec::Before(time) => {
+                before = Some(before.map_or(time, |x: NaiveTime| x.min(time)))
+            }
+            _ => (),
+        }
+    }
+    (after, before)
+}
+
+/// Whether `@ after` and `@ before` allow showing the feed at `time` of day.
+/// With `@ after` later than `@ before`, like `@ after 22:00 @ before 02:00`,
+/// the window runs past midnight.
+fn in_time_window(info: &FeedInfo, time: NaiveTime) -> bool {
+    match time_window(info) {
+        (Some(after), Some(before)) if after > before => time >= after || time < before,
+        (after, before) => {
+            after.is_none_or(|after| time >= after) && before.is_none_or(|before| time < before)
+        }
+    }
+}
+
+/// The first time from `datetime` on that `@ after` and `@ before` allow
+/// showing the feed.
+pub fn next_window(info: &FeedInfo, datetime: DateTime<Local>) -> DateTime<Local> {
+    let now = wall_clock(info, &datetime);
+    let time = now.time();
+    if in_time_window(info, time) {
+        return datetime;
+    }
+    let (after, _) = time_window(info);
+    let (day, opens) = match after {
+        Some(after) if time < after => (now.date(), after),
+        _ => (
+            now.date() + Duration::days(1),
+            after.unwrap_or(NaiveTime::MIN),
+        ),
+    };
+    // A time skipped by a DST change opens at the next one that exists
+    (0..=120)
+        .filter_map(|minutes| {
+            from_wall_clock(info, day.and_time(opens) + Duration::minutes(minutes))
+        })
+        .next()
+        .unwrap_or(datetime)
+}
+
+/// Whether `date` is the day of the month `@ on the` some `day` means: that
+/// day, or the last day of a month too short for it.
+fn is_month_day(date: NaiveDate, day: u8) -> bool {
+    let last_day = (date + Duration::days(1)).day() == 1;
+    u32::from(day) == date.day() || (last_day && u32::from(day) > date.day())
+}
+
+/// Whether the time-based policies (`@ on`, `@ every`, `@ after`, `@ before`,
+/// and the pauses) allow showing the feed at `datetime`, given when it was
+/// last read.
+pub fn is_scheduled(
+    info: &FeedInfo,
+    last_read: Option<DateTime<Utc>>,
+    datetime: DateTime<Local>,
+) -> bool {
+    if is_paused(info, datetime) {
+        debug!("Skipping \"{}\" because it's paused", info.name);
+        return false;
+    }
+    let now = wall_clock(info, &datetime);
+    if !in_time_window(info, now.time()) {
+        debug!("Skipping \"{}\" because of the time of day", info.name);
+        return false;
+    }
+    let last_read = match last_read {
+        Some(last_read) => last_read,
+        None => return true,
+    };
+
+    // Counted on the feed's clock, so a day with a DST change in it is a day
+    let last_read = wall_clock(info, &last_read);
+    let elapsed_time = now.signed_duration_since(last_read);
+    let mut day_passed = false;
+    let mut day_relevant = false;
+
+    for policy in &info.update_policies {
+        match *policy {
+            UpdateSpec::Every(num_days) => {
+                trace!(
+                    "Rule for \"{}\": @ every {} days (has been {})",
+                    info.name,
+                    num_days,
+                    elapsed_time.num_days()
+                );
+                if elapsed_time.num_days() < num_days as i64 {
+                    debug!("Skipping \"{}\" because of @every", info.name);
+                    return false;
+                }
+                trace!("Rule passed!");
+            }
+            UpdateSpec::On(ref days) => {
+                trace!("Rule for \"{}\": @ on {:?}", info.name, days);
+                day_relevant = true;
+                let mut last_day = last_read.weekday();
+                for _ in 0..elapsed_time.num_days() {
+                    last_day = last_day.succ();
+                    if days.contains(&last_day) {
+                        day_passed = true;
+                        trace!("Rule passed!");
+                        break;
+                    }
+                }
+            }
+            UpdateSpec::MonthDay(day) => {
+                trace!("Rule for \"{}\": @ on the {}", info.name, day);
+                day_relevant = true;
+                let passed = (1..=elapsed_time.num_days())
+                    .map(|days| last_read.date() + Duration::days(days))
+                    .any(|date| is_month_day(date, day));
+                if passed {
+                    day_passed = true;
+                    trace!("Rule passed!");
+                }
+            }
+            UpdateSpec::Overlap(_)
+            | UpdateSpec::OverlapDays(_)
+            | UpdateSpec::Comics(_)
+            | UpdateSpec::CheckEvery(_)
+            | UpdateSpec::RemindAfter(_)
+            | UpdateSpec::Filter(_, _)
+            | UpdateSpec::OpenAll
+            | UpdateSpec::OpenEnclosure
+            | UpdateSpec::Alias(_)
+            | UpdateSpec::Surprise(_)
+            | UpdateSpec::MaxPerFetch(_)
+            | UpdateSpec::Language(_)
+            | UpdateSpec::ByChapter(..)
+            | UpdateSpec::Pin(_)
+            | UpdateSpec::Timeout(_)
+            | UpdateSpec::Retries(_)
+            | UpdateSpec::MaxPerSession(_)
+            | UpdateSpec::SkipBefore(_)
+            | UpdateSpec::CrawlArchive(_)
+            | UpdateSpec::RewriteTitle(..)
+            | UpdateSpec::RewriteUrl(..)
+            | UpdateSpec::Random(_)
+            | UpdateSpec::PauseUntil(_)
+            | UpdateSpec::Between(..)
+            | UpdateSpec::After(_)
+            | UpdateSpec::Before(_) => (),
+        }
+    }
+
+    if day_relevant && !day_passed {
+        debug!("Skipping \"{}\" because of @on", info.name);
+        false
+    } else {
+        true
+    }
+}
+
+/// Whether there are enough new comics to satisfy `@ # new comics`.
+pub fn has_enough_comics(info: &FeedInfo, new_comics: usize) -> bool {
+    if new_comics < 1 {
+        return false;
+    }
+
+    for policy in &info.update_policies {
+        match *policy {
+            UpdateSpec::Comics(num_comics) => {
+                trace!(
+                    "Rule for \"{}\": @ {} new comics (has {})",
+                    info.name,
+                    num_comics,
+                    new_comics
+                );
+                if new_comics < num_comics {
+                    debug!("Skipping \"{}\" because of @comics", info.name);
+                    return false;
+                }
+                trace!("Rule passed!");
+            }
+            UpdateSpec::Every(_)
+            | UpdateSpec::On(_)
+            | UpdateSpec::MonthDay(_)
+            | UpdateSpec::Overlap(_)
+            | UpdateSpec::OverlapDays(_)
+            | UpdateSpec::CheckEvery(_)
+            | UpdateSpec::RemindAfter(_)
+            | UpdateSpec::Filter(_, _)
+            | UpdateSpec::OpenAll
+            | UpdateSpec::OpenEnclosure
+            | UpdateSpec::Alias(_)
+            | UpdateSpec::Surprise(_)
+            | UpdateSpec::MaxPerFetch(_)
+            | UpdateSpec::Language(_)
+            | UpdateSpec::ByChapter(..)
+            | UpdateSpec::Pin(_)
+            | UpdateSpec::Timeout(_)
+            | UpdateSpec::Retries(_)
+            | UpdateSpec::MaxPerSession(_)
+            | UpdateSpec::SkipBefore(_)
+            | UpdateSpec::CrawlArchive(_)
+            | UpdateSpec::RewriteTitle(..)
+            | UpdateSpec::RewriteUrl(..)
+            | UpdateSpec::Random(_)
+            | UpdateSpec::PauseUntil(_)
+            | UpdateSpec::Between(..)
+            | UpdateSpec::After(_)
+            | UpdateSpec::Before(_) => (),
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_config;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_several_weekdays() {
+        let info = parse_config(r#""Feed" <http://example.com> @ on Wednesday, Friday"#)
+            .unwrap()
+            .remove(0);
+        // 2018-10-01 was a Monday
+        let monday = Utc.ymd(2018, 10, 1).and_hms(12, 0, 0);
+        let day = |d| Local.ymd(2018, 10, d).and_hms(12, 0, 0);
+        assert!(!is_scheduled(&info, Some(monday), day(2)));
+        assert!(is_scheduled(&info, Some(monday), day(3)));
+        // Read on Wednesday, Friday is next
+        let wednesday = Utc.ymd(2018, 10, 3).and_hms(12, 0, 0);
+        assert!(!is_scheduled(&info, Some(wednesday), day(4)));
+        assert!(is_scheduled(&info, Some(wednesday), day(5)));
+    }
+
+    #[test]
+    fn test_month_day() {
+        let info = parse_config(r#""Feed" <http://example.com> @ on the 31st"#)
+            .unwrap()
+            .remove(0);
+        let read = |m, d| Some(Utc.ymd(2018, m, d).and_hms(12, 0, 0));
+        let day = |m, d| Local.ymd(2018, m, d).and_hms(12, 0, 0);
+        assert!(!is_scheduled(&info, read(10, 1), day(10, 30)));
+        assert!(is_scheduled(&info, read(10, 1), day(10, 31)));
+        // November has no 31st, so its 30th counts
+        assert!(!is_scheduled(&info, read(11, 1), day(11, 29)));
+        assert!(is_scheduled(&info, read(11, 1), day(11, 30)));
+        // And February's last day is the 28th in 2018
+        assert!(is_scheduled(&info, read(2, 1), day(2, 28)));
+        // Read on the day, it waits for the next month's
+        assert!(!is_scheduled(&info, read(10, 31), day(11, 29)));
+
+        let first = parse_config(r#""Feed" <http://example.com> @ monthly on 1"#)
+            .unwrap()
+            .remove(0);
+        assert!(!is_scheduled(&first, read(10, 2), day(10, 31)));
+        assert!(is_scheduled(&first, read(10, 2), day(11, 1)));
+    }
+
+    #[test]
+    fn test_pauses() {
+        let info = parse_config(
+            r#""Feed" <http://example.com> @ pause until 2018-10-03 @ between 2018-10-01 and 2018-10-05"#,
+        )
+        .unwrap()
+        .remove(0);
+        let day = |d| Local.ymd(2018, 10, d).and_hms(12, 0, 0);
+        assert!(is_paused(&info, day(2)));
+        assert!(!is_scheduled(&info, None, day(2)));
+        assert!(!is_paused(&info, day(3)));
+        assert!(is_scheduled(&info, None, day(3)));
+        assert!(!is_paused(&info, day(5)));
+        assert!(is_paused(&info, day(6)));
+    }
+
+    #[test]
+    fn test_time_of_day() {
+        let evening = parse_config(r#""Feed" <http://example.com> @ after 18:00"#)
+            .unwrap()
+            .remove(0);
+        let night = parse_config(r#""Feed" <http://example.com> @ after 22:00 @ before 02:00"#)
+            .unwrap()
+            .remove(0);
+        let at = |d, h, m| Local.ymd(2018, 10, d).and_hms(h, m, 0);
+        assert!(!is_scheduled(&evening, None, at(2, 17, 59)));
+        assert!(is_scheduled(&evening, None, at(2, 18, 0)));
+        assert!(!is_scheduled(&night, None, at(2, 12, 0)));
+        assert!(is_scheduled(&night, None, at(2, 23, 0)));
+        assert!(is_scheduled(&night, None, at(3, 1, 0)));
+
+        assert_eq!(next_window(&evening, at(2, 9, 0)), at(2, 18, 0));
+        assert_eq!(next_window(&evening, at(2, 19, 0)), at(2, 19, 0));
+        assert_eq!(next_window(&night, at(2, 3, 0)), at(2, 22, 0));
+        let morning = parse_config(r#""Feed" <http://example.com> @ before 09:00"#)
+            .unwrap()
+            .remove(0);
+        assert_eq!(next_window(&morning, at(2, 10, 0)), at(3, 0, 0));
+    }
+
+    #[test]
+    fn test_timezone() {
+        let config = |policies| {
+            let mut info = parse_config(&format!(r#""Feed" <http://example.com> {}"#, policies))
+                .unwrap()
+                .remove(0);
+            info.timezone = Some(chrono_tz::America::New_York);
+            info
+        };
+        let at = |d, h, m| Utc.ymd(2018, 11, d).and_hms(h, m, 0);
+        let local = |datetime: DateTime<Utc>| datetime.with_timezone(&Local);
+
+        // Clocks in New York went back an hour on 2018-11-04, so noon there
+        // came 25 hours after noon the day before
+        let daily = config("@ every 1 day");
+        let noon = at(3, 16, 0);
+        assert!(!is_scheduled(&daily, Some(noon), local(at(4, 16, 59))));
+        assert!(is_scheduled(&daily, Some(noon), local(at(4, 17, 0))));
+        assert_eq!(days_later(&daily, noon, 1), at(4, 17, 0));
+        // And they went forward an hour on 2018-03-11, so it came 23 hours after
+        let noon = Utc.ymd(2018, 3, 10).and_hms(17, 0, 0);
+        let next_noon = Utc.ymd(2018, 3, 11).and_hms(16, 0, 0);
+        assert!(is_scheduled(&daily, Some(noon), local(next_noon)));
+        assert_eq!(days_later(&daily, noon, 1), next_noon);
+
+        // 23:00 on Friday 2018-11-02 in New York is Saturday in UTC
+        let weekly = config("@ on Saturday");
+        let friday_night = at(3, 3, 0);
+        assert!(is_scheduled(
+            &weekly,
+            Some(friday_night),
+            local(at(4, 4, 0))
+        ));
+        let mut utc = weekly.clone();
+        utc.timezone = Some(chrono_tz::UTC);
+        assert!(!is_scheduled(&utc, Some(friday_night), local(at(4, 4, 0))));
+
+        // Time of day and pauses go by New York's clock too
+        let evening = config("@ after 18:00 @ pause until 2018-11-03");
+        assert!(!is_scheduled(&evening, None, local(at(3, 21, 59))));
+        assert!(is_scheduled(&evening, None, local(at(3, 22, 0))));
+        assert!(is_paused(&evening, local(at(3, 3, 0))));
+        assert_eq!(
+            next_window(&evening, local(at(4, 12, 0))),
+            local(at(4, 23, 0))
+        );
+    }
+
+    #[test]
+    fn test_transitions() {
+        let info = parse_config(r#""Feed" <http://example.com> @ on Friday @ 2 new comics"#)
+            .unwrap()
+            .remove(0);
+        // 2018-10-01 was a Monday
+        let monday = Utc.ymd(2018, 10, 1).and_hms(12, 0, 0);
+        let day = |d| Local.ymd(2018, 10, d).and_hms(12, 0, 0);
+        let progress = |new_comics| Progress {
+            last_read: Some(monday),
+            new_comics,
+        };
+        let step =
+            |state: State, trigger, new_comics| state.next(trigger, &info, progress(new_comics));
+
+        assert_eq!(step(State::Unseen, Trigger::Fetched(0), 0), State::Unseen);
+        assert_eq!(step(State::Unseen, Trigger::Fetched(1), 1), State::Pending);
+        assert_eq!(step(State::Read, Trigger::Fetched(2), 2), State::Pending);
+
+        // Waiting for Friday, then for a second comic
+        assert_eq!(
+            step(State::Pending, Trigger::Tick(day(3)), 2),
+            State::Pending
+        );
+        assert_eq!(
+            step(State::Pending, Trigger::Tick(day(6)), 1),
+            State::Pending
+        );
+        assert_eq!(step(State::Pending, Trigger::Tick(day(6)), 2), State::Ready);
+        assert_eq!(step(State::Ready, Trigger::Tick(day(3)), 2), State::Pending);
+
+        // Only a feed with something to read can be opened
+        assert_eq!(step(State::Read, Trigger::Open, 0), State::Read);
+        assert_eq!(step(State::Ready, Trigger::Open, 2), State::Opened);
+        assert_eq!(step(State::Opened, Trigger::Fetched(1), 3), State::Opened);
+        assert_eq!(step(State::Opened, Trigger::Tick(day(7)), 2), State::Opened);
+
+        // Reading always works
+        for &state in &[State::Unseen, State::Pending, State::Ready, State::Opened] {
+            assert_eq!(step(state, Trigger::Read(monday), 0), State::Read);
+        }
+    }
+}
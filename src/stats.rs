@@ -0,0 +1,132 @@
+use chrono::{DateTime, Duration, Local, Utc};
+
+use crate::feed::{Feed, FeedEvent, FeedInfo};
+use crate::state;
+
+/// How many days after a feed is ready it can still be read on schedule.
+pub const STREAK_GRACE_DAYS: i64 = 1;
+
+/// How many times in a row, counting back from the most recent, the feed was
+/// read within `STREAK_GRACE_DAYS` of being ready.
+///
+/// When a feed became ready is worked out from its policies and the publish
+/// dates of the comics that arrived since the read before, or from a `ready`
+/// event if that's earlier. Reads where that can't be worked out, because
+/// comics are missing their publish dates, are given the benefit of the doubt.
+pub fn reading_streak(feed: &Feed) -> usize {
+    let mut on_time = Vec::new();
+    let mut last_read = None;
+    let mut pending = Vec::new();
+    let mut ready_event = None;
+    for event in feed.events() {
+        match *event {
+            FeedEvent::Comic(ref comic) => pending.push(comic.published),
+            FeedEvent::Ready(date) => {
+                ready_event.get_or_insert(date);
+            }
+            FeedEvent::Read(date) => {
+                let ready = ready_at(&feed.info, last_read, &pending, date);
+                let ready = match (ready, ready_event) {
+                    (Some(ready), Some(event)) => Some(std::cmp::min(ready, event)),
+                    (ready, event) => ready.or(event),
+                };
+                on_time.push(match ready {
+                    Some(ready) => date - ready <= Duration::days(STREAK_GRACE_DAYS),
+                    None => true,
+                });
+                last_read = Some(date);
+                pending.clear();
+                ready_event = None;
+            }
+            FeedEvent::AcceptReset(_) => pending.clear(),
+            FeedEvent::ReadComic(..)
+            | FeedEvent::Checked(_)
+            | FeedEvent::Surprise(..)
+            | FeedEvent::Withdrawn(..)
+            | FeedEvent::Reset(_)
+            | FeedEvent::Held(..)
+            | FeedEvent::Order(_)
+            | FeedEvent::Discovered(..)
+            | FeedEvent::Moved(..)
+            | FeedEvent::Thumbnail(..) => (),
+        }
+    }
+    on_time.iter().rev().take_while(|&&on_time| on_time).count()
+}
+
+/// When the feed became ready to read, if that can be told from the publish
+/// dates of the `pending` comics. It's never later than `read`.
+fn ready_at(
+    info: &FeedInfo,
+    last_read: Option<DateTime<Utc>>,
+    pending: &[Option<DateTime<Utc>>],
+    read: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    // The first moment enough comics had been published
+    let needed = (1..=pending.len()).find(|&n| state::has_enough_comics(info, n))?;
+    let mut dates = pending.iter().cloned().collect::<Option<Vec<_>>>()?;
+    dates.sort();
+    let published = dates[needed - 1];
+
+    // Then wait for the schedule, an hour at a time
+    (0..)
+        .map(|hours| published + Duration::hours(hours))
+        .find(|&time| {
+            time >= read || state::is_scheduled(info, last_read, time.with_timezone(&Local))
+        })
+        .map(|time| std::cmp::min(time, read))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_config;
+
+    fn streak(config: &str, events: &str) -> usize {
+        let info = parse_config(config).unwrap().remove(0);
+        reading_streak(&info.read_feed(&mut events.as_bytes()).unwrap())
+    }
+
+    #[test]
+    fn test_reading_streak() {
+        let events = "
+<http://example.com/1> 2018-10-01T12:00:00+00:00
+read 2018-10-01T20:00:00+00:00
+<http://example.com/2> 2018-10-03T12:00:00+00:00
+read 2018-10-06T20:00:00+00:00
+<http://example.com/3> 2018-10-07T12:00:00+00:00
+read 2018-10-08T08:00:00+00:00
+<http://example.com/4> 2018-10-09T12:00:00+00:00
+read 2018-10-10T08:00:00+00:00
+";
+        // Comic 2 waited three days to be read, breaking the streak
+        assert_eq!(streak(r#""Feed" <http://example.com>"#, events), 2);
+        // But with @ every 5 days it wasn't ready until the 6th. The 4th comic
+        // wasn't ready until the 13th, and reading early is fine.
+        assert_eq!(
+            streak(r#""Feed" <http://example.com> @ every 5 days"#, events),
+            4
+        );
+        // Reading before there are enough comics is never late
+        assert_eq!(
+            streak(r#""Feed" <http://example.com> @ 2 new comics"#, events),
+            4
+        );
+        assert_eq!(streak(r#""Feed" <http://example.com>"#, ""), 0);
+    }
+
+    #[test]
+    fn test_streak_ready_events() {
+        // Comics without dates fall back on when the feed was seen ready
+        let events = "
+<http://example.com/1>
+read 2018-10-01T20:00:00+00:00
+<http://example.com/2>
+ready 2018-10-02T12:00:00+00:00
+read 2018-10-05T20:00:00+00:00
+<http://example.com/3>
+read 2018-10-06T20:00:00+00:00
+";
+        assert_eq!(streak(r#""Feed" <http://example.com>"#, events), 1);
+    }
+}
@@ -0,0 +1,203 @@
+use chrono::{DateTime, Local, Utc};
+
+use crate::feed::{Due, Feed};
+use crate::health::Health;
+use crate::http_cache::Validators;
+
+/// A table of where each feed stands, for `feedburst status`: how many
+/// comics are unread, when it was last read and downloaded and what that
+/// found, when the server last said it changed, and when it will next open.
+/// Each feed comes with how its downloads have been going and the headers
+/// its last one saved.
+pub fn status(feeds: &[(Feed, Health, Validators)], now: DateTime<Local>) -> String {
+    let date = |date: Option<DateTime<Utc>>| {
+        date.map_or_else(
+            || "never".into(),
+            |date| date.with_timezone(&Local).format("%Y-%m-%d").to_string(),
+        )
+    };
+    let mut rows = vec![[
+        "Feed".to_string(),
+        "Unread".into(),
+        "Last read".into(),
+        "Checked".into(),
+        "Changed".into(),
+        "Next".into(),
+    ]];
+    for (feed, health, validators) in feeds {
+        rows.push([
+            feed.info.name.clone(),
+            feed.new_comics().to_string(),
+            date(feed.last_read()),
+            checked_text(feed, health, &date),
+            changed_text(validators, &date),
+            due_text(feed.next_due(now), now),
+        ]);
+    }
+
+    let mut widths = [0; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = std::cmp::max(*width, cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for row in &rows {
+        let cells: Vec<_> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// When the feed was last downloaded, and whether that got anything: a
+/// server that says the feed hasn't changed didn't send it at all, which is
+/// different from sending it without any new comics in it.
+fn checked_text(
+    feed: &Feed,
+    health: &Health,
+    date: &dyn Fn(Option<DateTime<Utc>>) -> String,
+) -> String {
+    if health.failures > 0 {
+        return format!("failed {}", date(health.last_failure));
+    }
+    let checked = date(feed.last_checked());
+    if feed.last_checked().is_none() || health.last_success.is_none() {
+        checked
+    } else if health.status == Some(304) {
+        format!("{}, unchanged", checked)
+    } else if health.last_new < health.last_success {
+        format!("{}, nothing new", checked)
+    } else {
+        checked
+    }
+}
+
+/// When the server last said the feed changed, from the `Last-Modified`
+/// header its last download saved.
+fn changed_text(validators: &Validators, date: &dyn Fn(Option<DateTime<Utc>>) -> String) -> String {
+    match validators.last_modified {
+        Some(ref modified) => match DateTime::parse_from_rfc2822(modified) {
+            Ok(modified) => date(Some(modified.with_timezone(&Utc))),
+            Err(_) => "unknown".into(),
+        },
+        None => "unknown".into(),
+    }
+}
+
+fn due_text(due: Due, now: DateTime<Local>) -> String {
+    let comics = |n: usize| {
+        format!(
+            "after {} more {}",
+            n,
+            if n == 1 { "comic" } else { "comics" }
+        )
+    };
+    match due {
+        Due::Now => "now".into(),
+        Due::Paused => "paused, see accept-reset".into(),
+        Due::Later { from: None, .. } => "not within a year".into(),
+        Due::Later {
+            from: Some(from),
+            comics: 0,
+        } if from <= now => "now".into(),
+        Due::Later {
+            from: Some(from),
+            comics: 0,
+        } => from.format("%a %Y-%m-%d").to_string(),
+        Due::Later {
+            from: Some(from),
+            comics: n,
+        } if from <= now => comics(n),
+        Due::Later {
+            from: Some(from),
+            comics: n,
+        } => {
+            format!("{}, {}", from.format("%a %Y-%m-%d"), comics(n))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_config;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_status() {
+        let config = r#""Ready" <http://example.com>
+"Weekly Comic" <http://example.com> @ every 7 days @ 2 new comics
+"Quiet" <http://example.com>
+"Broken" <http://example.com>
+"#;
+        let events = "
+<http://example.com/1> 2018-10-01T12:00:00+00:00
+read 2018-10-01T12:00:00+00:00
+checked 2018-10-02T12:00:00+00:00
+<http://example.com/2> 2018-10-02T12:00:00+00:00
+";
+        let checked = Utc.ymd(2018, 10, 2).and_hms(12, 0, 0);
+        let found_new = Health {
+            last_success: Some(checked),
+            last_new: Some(checked),
+            status: Some(200),
+            ..Health::default()
+        };
+        let unchanged = Health {
+            status: Some(304),
+            last_new: None,
+            ..found_new.clone()
+        };
+        let mut failing = found_new.clone();
+        failing.failed(
+            Utc.ymd(2018, 10, 3).and_hms(6, 0, 0),
+            Some(404),
+            &crate::error::Error::Msg("Not found".into()),
+        );
+        let modified = Validators {
+            etag: None,
+            last_modified: Some("Mon, 01 Oct 2018 12:00:00 GMT".into()),
+        };
+        let healths = [found_new.clone(), Health::default(), unchanged, failing];
+        let validators = [
+            modified.clone(),
+            Validators::default(),
+            modified,
+            Validators::default(),
+        ];
+        let feeds: Vec<_> = parse_config(config)
+            .unwrap()
+            .iter()
+            .map(|info| info.read_feed(&mut events.as_bytes()).unwrap())
+            .zip(healths.iter().cloned().zip(validators.iter().cloned()))
+            .map(|(feed, (health, validators))| (feed, health, validators))
+            .collect();
+        let now = Utc.ymd(2018, 10, 3).and_hms(12, 0, 0).with_timezone(&Local);
+        let next = Utc.ymd(2018, 10, 8).and_hms(12, 0, 0).with_timezone(&Local);
+        assert_eq!(
+            status(&feeds, now),
+            format!(
+                concat!(
+                    "Feed          Unread  Last read   Checked                Changed     Next\n",
+                    "Ready         1       2018-10-01  2018-10-02             2018-10-01  now\n",
+                    "Weekly Comic  1       2018-10-01  2018-10-02             unknown     {}, after 1 more comic\n",
+                    "Quiet         1       2018-10-01  2018-10-02, unchanged  2018-10-01  now\n",
+                    "Broken        1       2018-10-01  failed 2018-10-03      unknown     now\n",
+                ),
+                next.format("%a %Y-%m-%d")
+            )
+        );
+
+        let nothing_new = Health {
+            last_new: Some(Utc.ymd(2018, 9, 30).and_hms(12, 0, 0)),
+            ..found_new
+        };
+        let feeds = vec![(feeds[0].0.clone(), nothing_new, Validators::default())];
+        assert!(status(&feeds, now).contains("2018-10-02, nothing new"));
+    }
+}
@@ -0,0 +1,642 @@
+//! Where feed histories are kept: a text file for each feed, or one SQLite
+//! database with `storage sqlite`. Both hold the same event log, line for
+//! line, so a feed reads the same either way.
+
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OpenFlags, TransactionBehavior};
+
+use crate::config::Args;
+use crate::error::Error;
+use crate::feed::{Feed, FeedInfo, EVENTS_VERSION};
+use crate::parser::Backend;
+
+/// How long to wait for another feed being written at the same time.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub trait Storage {
+    /// Read a feed's history, and those of its aliases.
+    fn load(&self, info: &FeedInfo) -> Result<Feed, Error>;
+
+    /// Add the events since the feed was loaded to its history.
+    fn save(&self, feed: &mut Feed) -> Result<(), Error>;
+
+    /// Load the feed, `change` it, and save it, without anything else
+    /// writing the feed in between.
+    fn update(
+        &self,
+        info: &FeedInfo,
+        change: &mut dyn FnMut(&mut Feed) -> Result<(), Error>,
+    ) -> Result<(), Error>;
+
+    /// Replace the text of the feed's own history, leaving its aliases'
+    /// alone, with what `change` makes of it, without anything else writing
+    /// the feed in between.
+    fn rewrite(
+        &self,
+        info: &FeedInfo,
+        change: &mut dyn FnMut(&str) -> Result<String, Error>,
+    ) -> Result<(), Error>;
+
+    /// Give the feed's own history and other files to the feed called
+    /// `name`, for renaming it. Fails if that feed already has a history.
+    fn rename(&self, info: &FeedInfo, name: &str) -> Result<(), Error>;
+
+    /// The text of the feed's own history, the way a feed file has it, for
+    /// archiving it.
+    fn history_text(&self, info: &FeedInfo) -> Result<String, Error>;
+
+    /// Delete the feed's own history and other files.
+    fn remove(&self, info: &FeedInfo) -> Result<(), Error>;
+}
+
+/// The storage the config's `storage` line picked.
+pub fn open(args: &Args) -> Box<dyn Storage + '_> {
+    match args.storage() {
+        Backend::Files => Box::new(Files(args)),
+        Backend::Sqlite => Box::new(Sqlite(args)),
+    }
+}
+
+/// A text file for each feed, in the feed's folder.
+pub struct Files<'a>(pub &'a Args);
+
+impl<'a> Storage for Files<'a> {
+    fn load(&self, info: &FeedInfo) -> Result<Feed, Error> {
+        let mut feed_file = self.0.feed_file(info)?;
+        info.read_feed_with_aliases(&mut feed_file, &mut self.0.alias_files(info)?)
+    }
+
+    fn save(&self, feed: &mut Feed) -> Result<(), Error> {
+        // Other runs could be writing the same feed. The lock goes with the
+        // file when it's closed.
+        let file = self.0.feed_file(&feed.info)?;
+        file.lock()?;
+        if feed.replaces_checked() {
+            drop_checked_line(&file)?;
+        }
+        feed.write_changes(&mut &file)?;
+        Ok(())
+    }
+
+    fn update(
+        &self,
+        info: &FeedInfo,
+        change: &mut dyn FnMut(&mut Feed) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let file = self.0.feed_file(info)?;
+        file.lock()?;
+        let mut feed = info.read_feed_with_aliases(&mut &file, &mut self.0.alias_files(info)?)?;
+        change(&mut feed)?;
+        if feed.replaces_checked() {
+            drop_checked_line(&file)?;
+        }
+        feed.write_changes(&mut &file)?;
+        Ok(())
+    }
+
+    fn rewrite(
+        &self,
+        info: &FeedInfo,
+        change: &mut dyn FnMut(&str) -> Result<String, Error>,
+    ) -> Result<(), Error> {
+        let mut file = self.0.feed_file(info)?;
+        file.lock()?;
+        let mut text = String::new();
+        file.read_to_string(&mut text)?;
+        let text = change(&text)?;
+        file.seek(io::SeekFrom::Start(0))?;
+        file.set_len(0)?;
+        file.write_all(text.as_bytes())?;
+        Ok(())
+    }
+
+    fn rename(&self, info: &FeedInfo, name: &str) -> Result<(), Error> {
+        self.0.move_feed_files(info, name)
+    }
+
+    fn history_text(&self, info: &FeedInfo) -> Result<String, Error> {
+        let path = self.0.paths(info)?.history;
+        if path.exists() {
+            Ok(fs::read_to_string(&path)?)
+        } else {
+            Ok(String::new())
+        }
+    }
+
+    fn remove(&self, info: &FeedInfo) -> Result<(), Error> {
+        self.0.remove_feed_files(info)
+    }
+}
+
+/// Cut the last line off a feed file if it's a `checked` line.
+fn drop_checked_line(mut file: &fs::File) -> io::Result<()> {
+    let end = file.seek(io::SeekFrom::End(0))?;
+    // Plenty for a `checked` line, and anything longer isn't one
+    let start = end.saturating_sub(128);
+    file.seek(io::SeekFrom::Start(start))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    let body = tail.strip_suffix(b"\n").unwrap_or(&tail);
+    let line = match body.iter().rposition(|&byte| byte == b'\n') {
+        Some(newline) => newline + 1,
+        None if start == 0 => 0,
+        None => return Ok(()),
+    };
+    if body[line..].starts_with(b"checked ") {
+        file.set_len(start + line as u64)?;
+    }
+    Ok(())
+}
+
+/// One database for every feed under the same folder, with a row for each
+/// line a feed file would have.
+pub struct Sqlite<'a>(pub &'a Args);
+
+impl<'a> Sqlite<'a> {
+    fn connect(&self, info: &FeedInfo) -> Result<Connection, Error> {
+        let path = self.0.database_path(info)?;
+        debug!("Using feed database {}", path.display());
+        let conn = Connection::open(&path)?;
+        // Feeds are downloaded, and so written, several at a time
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY,
+                feed TEXT NOT NULL,
+                line TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS events_by_feed ON events (feed, id);",
+        )?;
+        Ok(conn)
+    }
+
+    /// Move the feed file for the history recorded under `name` into the
+    /// database, if the database doesn't have that history yet. The file is
+    /// renamed rather than deleted, in case it's wanted back.
+    fn migrate(&self, conn: &mut Connection, info: &FeedInfo, name: &str) -> Result<(), Error> {
+        let path = self.0.history_path(info, name)?;
+        if !path.exists() {
+            return Ok(());
+        }
+        if has_history(conn, name)? {
+            debug!(
+                "Leaving {} alone, since the database already has \"{}\"",
+                path.display(),
+                name
+            );
+            return Ok(());
+        }
+        let text = fs::read_to_string(&path)?;
+        let tx = conn.transaction()?;
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            tx.execute(
+                "INSERT INTO events (feed, line) VALUES (?1, ?2)",
+                params![name, line],
+            )?;
+        }
+        tx.commit()?;
+        let moved = path.with_extension("feed.migrated");
+        info!("Moved {} into the feed database", path.display());
+        fs::rename(&path, &moved)?;
+        Ok(())
+    }
+
+    /// Move the histories of the feed and its aliases into the database, if
+    /// they're still in files.
+    fn migrate_all(&self, conn: &mut Connection, info: &FeedInfo) -> Result<(), Error> {
+        for alias in info.aliases() {
+            self.migrate(conn, info, alias)?;
+        }
+        self.migrate(conn, info, &info.name)
+    }
+}
+
+/// The history recorded under `name`, as the text of a feed file.
+fn history(conn: &Connection, name: &str) -> Result<String, Error> {
+    let mut statement = conn.prepare("SELECT line FROM events WHERE feed = ?1 ORDER BY id")?;
+    let lines = statement
+        .query_map(params![name], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(lines.join("\n"))
+}
+
+/// The feed with the histories of it and its aliases in the database.
+fn read_feed(conn: &Connection, info: &FeedInfo) -> Result<Feed, Error> {
+    let mut aliases = Vec::new();
+    for alias in info.aliases() {
+        let text = history(conn, alias)?;
+        if !text.is_empty() {
+            aliases.push((alias.to_string(), io::Cursor::new(text)));
+        }
+    }
+    let text = history(conn, &info.name)?;
+    info.read_feed_with_aliases(&mut text.as_bytes(), &mut aliases)
+}
+
+/// Add what's changed in `feed` to the end of its history, as part of the
+/// transaction `tx`.
+fn append_changes(tx: &Connection, feed: &Feed) -> Result<(), Error> {
+    let name = feed.info.name.as_str();
+    if feed.replaces_checked() {
+        tx.execute(
+            "DELETE FROM events WHERE id = (SELECT MAX(id) FROM events WHERE feed = ?1) \
+             AND line LIKE 'checked %'",
+            params![name],
+        )?;
+    }
+    // A new history starts with its version, like a new feed file
+    if !has_history(tx, name)? {
+        let version = format!("version {}", EVENTS_VERSION);
+        tx.execute(
+            "INSERT INTO events (feed, line) VALUES (?1, ?2)",
+            params![name, version],
+        )?;
+    }
+    for line in &feed.change_lines() {
+        tx.execute(
+            "INSERT INTO events (feed, line) VALUES (?1, ?2)",
+            params![name, line],
+        )?;
+    }
+    Ok(())
+}
+
+impl<'a> Storage for Sqlite<'a> {
+    fn load(&self, info: &FeedInfo) -> Result<Feed, Error> {
+        let mut conn = self.connect(info)?;
+        self.migrate_all(&mut conn, info)?;
+        read_feed(&conn, info)
+    }
+
+    fn save(&self, feed: &mut Feed) -> Result<(), Error> {
+        if feed.change_lines().is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.connect(&feed.info)?;
+        let tx = conn.transaction()?;
+        append_changes(&tx, feed)?;
+        tx.commit()?;
+        feed.changes_written();
+        Ok(())
+    }
+
+    fn update(
+        &self,
+        info: &FeedInfo,
+        change: &mut dyn FnMut(&mut Feed) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut conn = self.connect(info)?;
+        self.migrate_all(&mut conn, info)?;
+        // Taking the write lock up front keeps anything else from adding to
+        // the history between reading it and writing the changes
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let mut feed = read_feed(&tx, info)?;
+        change(&mut feed)?;
+        if !feed.change_lines().is_empty() {
+            append_changes(&tx, &feed)?;
+        }
+        tx.commit()?;
+        feed.changes_written();
+        Ok(())
+    }
+
+    fn rewrite(
+        &self,
+        info: &FeedInfo,
+        change: &mut dyn FnMut(&str) -> Result<String, Error>,
+    ) -> Result<(), Error> {
+        let mut conn = self.connect(info)?;
+        self.migrate(&mut conn, info, &info.name)?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let name = info.name.as_str();
+        let lines = tx
+            .prepare("SELECT line FROM events WHERE feed = ?1 ORDER BY id")?
+            .query_map(params![name], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        let text = change(&lines.join("\n"))?;
+        tx.execute("DELETE FROM events WHERE feed = ?1", params![name])?;
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            tx.execute(
+                "INSERT INTO events (feed, line) VALUES (?1, ?2)",
+                params![name, line],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn rename(&self, info: &FeedInfo, name: &str) -> Result<(), Error> {
+        let mut conn = self.connect(info)?;
+        self.migrate(&mut conn, info, &info.name)?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        if has_history(&tx, name)? {
+            return Err(Error::Msg(format!(
+                "There's already a history for \"{}\" in {}",
+                name,
+                self.0.database_path(info)?.display()
+            )));
+        }
+        // The cache and any migrated feed file go too
+        self.0.move_feed_files(info, name)?;
+        tx.execute(
+            "UPDATE events SET feed = ?1 WHERE feed = ?2",
+            params![name, info.name],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn history_text(&self, info: &FeedInfo) -> Result<String, Error> {
+        let mut conn = self.connect(info)?;
+        self.migrate(&mut conn, info, &info.name)?;
+        let mut text = history(&conn, &info.name)?;
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        Ok(text)
+    }
+
+    fn remove(&self, info: &FeedInfo) -> Result<(), Error> {
+        let mut conn = self.connect(info)?;
+        self.migrate(&mut conn, info, &info.name)?;
+        conn.execute("DELETE FROM events WHERE feed = ?1", params![info.name])?;
+        self.0.remove_feed_files(info)
+    }
+}
+
+/// The history recorded under `name` in the feed database for `info`, as
+/// the text of a feed file, without making the database or moving feed files
+/// into it. `None` if the database doesn't have it.
+pub fn stored_history(args: &Args, info: &FeedInfo, name: &str) -> Result<Option<String>, Error> {
+    let path = args.database_path(info)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let conn = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let has_events: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'events')",
+        params![],
+        |row| row.get(0),
+    )?;
+    if !has_events || !has_history(&conn, name)? {
+        return Ok(None);
+    }
+    history(&conn, name).map(Some)
+}
+
+fn has_history(conn: &Connection, name: &str) -> Result<bool, Error> {
+    Ok(conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM events WHERE feed = ?1)",
+        params![name],
+        |row| row.get(0),
+    )?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_config;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_sqlite() {
+        let root = std::env::temp_dir().join(format!("feedburst-storage-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let args = Args::new(
+            false,
+            root.to_str(),
+            Some("config.feeds"),
+            None,
+            None,
+            false,
+        )
+        .unwrap()
+        .with_storage(Backend::Sqlite);
+        let config = r#""Old" <http://old.example.com>
+"New" <http://new.example.com> @ alias "Old"
+"#;
+        let infos = parse_config(config).unwrap();
+
+        // An existing feed file is moved into the database
+        let mut old = Files(&args).load(&infos[0]).unwrap();
+        old.read_at(chrono::Utc::now());
+        Files(&args).save(&mut old).unwrap();
+        let file = args.history_path(&infos[0], "Old").unwrap();
+        let text = fs::read_to_string(&file).unwrap();
+        let storage = Sqlite(&args);
+        let mut feed = storage.load(&infos[0]).unwrap();
+        assert!(!file.exists());
+        assert!(file.with_extension("feed.migrated").exists());
+        assert_eq!(feed.events().count(), 1);
+
+        // Changes are appended, and the history reads back the same
+        feed.read_at(chrono::Utc::now());
+        storage.save(&mut feed).unwrap();
+        assert_eq!(storage.load(&infos[0]).unwrap().events().count(), 2);
+        let conn = storage.connect(&infos[0]).unwrap();
+        let history = history(&conn, "Old").unwrap();
+        assert!(history.starts_with(text.trim_end()));
+        assert_eq!(history.lines().count(), 3);
+
+        // Aliases come from the database too
+        let mut new = storage.load(&infos[1]).unwrap();
+        assert_eq!(new.events().count(), 2);
+        storage.save(&mut new).unwrap();
+        assert!(!has_history(&conn, "New").unwrap());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_stored_history() {
+        let root = std::env::temp_dir().join(format!("feedburst-stored-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let args = Args::new(
+            false,
+            root.to_str(),
+            Some("config.feeds"),
+            None,
+            None,
+            false,
+        )
+        .unwrap()
+        .with_storage(Backend::Sqlite);
+        let info = parse_config(r#""Feed" <http://example.com>"#)
+            .unwrap()
+            .remove(0);
+        let mut feed = Files(&args).load(&info).unwrap();
+        feed.read_at(chrono::Utc::now());
+        Files(&args).save(&mut feed).unwrap();
+
+        // Looking doesn't make the database or move the feed file into it
+        assert_eq!(stored_history(&args, &info, "Feed").unwrap(), None);
+        assert!(!args.database_path(&info).unwrap().exists());
+        let file = args.history_path(&info, "Feed").unwrap();
+        assert!(file.exists());
+
+        let text = fs::read_to_string(&file).unwrap();
+        Sqlite(&args).load(&info).unwrap();
+        let stored = stored_history(&args, &info, "Feed").unwrap().unwrap();
+        assert_eq!(stored, text.trim_end());
+        assert_eq!(stored_history(&args, &info, "Other").unwrap(), None);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_update_and_rewrite() {
+        let root = std::env::temp_dir().join(format!("feedburst-rewrite-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let args = |folder: &str| {
+            let folder = root.join(folder);
+            fs::create_dir_all(&folder).unwrap();
+            Args::new(
+                false,
+                folder.to_str(),
+                Some("config.feeds"),
+                None,
+                None,
+                false,
+            )
+            .unwrap()
+        };
+        let info = parse_config(r#""Feed" <http://example.com>"#)
+            .unwrap()
+            .remove(0);
+        // Apart, so the feed file isn't moved into the database
+        let files = args("files");
+        let sqlite = args("sqlite").with_storage(Backend::Sqlite);
+        let backends: [Box<dyn Storage>; 2] = [Box::new(Files(&files)), Box::new(Sqlite(&sqlite))];
+        for storage in &backends {
+            storage
+                .update(&info, &mut |feed| {
+                    feed.read_at(chrono::Utc::now());
+                    Ok(())
+                })
+                .unwrap();
+            assert_eq!(storage.load(&info).unwrap().events().count(), 1);
+            storage
+                .rewrite(&info, &mut |text| Ok(text.replace("read", "checked")))
+                .unwrap();
+            let feed = storage.load(&info).unwrap();
+            assert!(matches!(
+                feed.events().collect::<Vec<_>>()[..],
+                [&crate::feed::FeedEvent::Checked(_)]
+            ));
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_checked_replaced() {
+        let root = std::env::temp_dir().join(format!("feedburst-checked-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let args = |folder: &str| {
+            let folder = root.join(folder);
+            fs::create_dir_all(&folder).unwrap();
+            Args::new(
+                false,
+                folder.to_str(),
+                Some("config.feeds"),
+                None,
+                None,
+                false,
+            )
+            .unwrap()
+        };
+        let info = parse_config(r#""Feed" <http://example.com>"#)
+            .unwrap()
+            .remove(0);
+        let files = args("files");
+        let sqlite = args("sqlite").with_storage(Backend::Sqlite);
+        let backends: [Box<dyn Storage>; 2] = [Box::new(Files(&files)), Box::new(Sqlite(&sqlite))];
+        let day = |day| chrono::Utc.ymd(2018, 10, day).and_hms(12, 0, 0);
+        for storage in &backends {
+            for (date, read) in [(1, false), (2, true), (3, false), (4, false)] {
+                storage
+                    .update(&info, &mut |feed| {
+                        if read {
+                            feed.read_at(day(date));
+                        }
+                        feed.checked_at(day(date));
+                        feed.checked_at(day(date) + chrono::Duration::hours(1));
+                        Ok(())
+                    })
+                    .unwrap();
+            }
+            // Each quiet check takes the place of the one before
+            assert_eq!(
+                storage.history_text(&info).unwrap(),
+                concat!(
+                    "version 1\n",
+                    "checked 2018-10-01T13:00:00+00:00\n",
+                    "read 2018-10-02T12:00:00+00:00\n",
+                    "checked 2018-10-04T13:00:00+00:00\n",
+                )
+            );
+            let feed = storage.load(&info).unwrap();
+            assert_eq!(
+                feed.last_checked(),
+                Some(day(4) + chrono::Duration::hours(1))
+            );
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_rename_and_remove() {
+        let root = std::env::temp_dir().join(format!("feedburst-rename-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let args = |folder: &str| {
+            let folder = root.join(folder);
+            fs::create_dir_all(&folder).unwrap();
+            Args::new(
+                false,
+                folder.to_str(),
+                Some("config.feeds"),
+                None,
+                None,
+                false,
+            )
+            .unwrap()
+        };
+        let infos = parse_config(
+            r#""Old" <http://example.com>
+"New" <http://example.com>
+"Other" <http://other.example.com>
+"#,
+        )
+        .unwrap();
+        let files = args("files");
+        let sqlite = args("sqlite").with_storage(Backend::Sqlite);
+        let backends: [Box<dyn Storage>; 2] = [Box::new(Files(&files)), Box::new(Sqlite(&sqlite))];
+        for storage in &backends {
+            for info in &[&infos[0], &infos[2]] {
+                let mut feed = storage.load(info).unwrap();
+                feed.read_at(chrono::Utc::now());
+                storage.save(&mut feed).unwrap();
+            }
+
+            storage.rename(&infos[0], "New").unwrap();
+            assert_eq!(storage.load(&infos[0]).unwrap().events().count(), 0);
+            assert_eq!(storage.load(&infos[1]).unwrap().events().count(), 1);
+            assert!(storage.rename(&infos[2], "New").is_err());
+            assert_eq!(storage.load(&infos[2]).unwrap().events().count(), 1);
+
+            let text = storage.history_text(&infos[1]).unwrap();
+            storage.remove(&infos[1]).unwrap();
+            assert!(text.starts_with("version"));
+            assert_eq!(text.lines().count(), 2);
+            assert_eq!(storage.load(&infos[1]).unwrap().events().count(), 0);
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
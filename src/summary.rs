@@ -0,0 +1,98 @@
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+
+use crate::json::Json;
+
+/// What happened to one feed in a run, for `--output json`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FeedSummary {
+    pub name: String,
+    /// Comics still unread after the run.
+    pub unread: usize,
+    /// The comics opened, or the ones that would have been on a dry run.
+    pub opened: Vec<String>,
+    pub errors: Vec<String>,
+    /// When the feed's time-based policies next allow showing it.
+    pub next_scheduled: Option<DateTime<Local>>,
+    /// When downloading the feed will be tried again, if it wasn't tried this
+    /// run because it kept failing.
+    pub skipped_until: Option<DateTime<Local>>,
+}
+
+impl FeedSummary {
+    pub fn new(name: &str) -> Self {
+        FeedSummary {
+            name: name.into(),
+            ..FeedSummary::default()
+        }
+    }
+}
+
+/// The whole run as one JSON object, with a summary for each feed.
+pub fn json(when: DateTime<Utc>, feeds: &[FeedSummary]) -> Json {
+    let feeds = feeds
+        .iter()
+        .map(|feed| {
+            let mut fields = vec![
+                ("name", Json::str(feed.name.as_str())),
+                ("unread", Json::Num(feed.unread)),
+                ("opened", Json::strs(feed.opened.iter().map(|x| x.as_str()))),
+                ("errors", Json::strs(feed.errors.iter().map(|x| x.as_str()))),
+            ];
+            if let Some(next) = feed.next_scheduled {
+                let next = next.format("%Y-%m-%d").to_string();
+                fields.push(("next_scheduled", Json::str(next)));
+            }
+            if let Some(until) = feed.skipped_until {
+                let until = until
+                    .with_timezone(&Utc)
+                    .to_rfc3339_opts(SecondsFormat::Secs, true);
+                fields.push(("skipped_until", Json::str(until)));
+            }
+            Json::Obj(fields)
+        })
+        .collect();
+    Json::Obj(vec![
+        (
+            "when",
+            Json::str(when.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        ),
+        ("feeds", Json::Arr(feeds)),
+    ])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_json() {
+        let when = Utc.ymd(2018, 10, 1).and_hms(12, 0, 0);
+        let read = FeedSummary {
+            unread: 1,
+            opened: vec!["http://example.com/1".into()],
+            next_scheduled: Some(Local.ymd(2018, 10, 5).and_hms(12, 0, 0)),
+            ..FeedSummary::new("Read")
+        };
+        let broken = FeedSummary {
+            errors: vec!["Failed to download: \"404 Not Found\"".into()],
+            ..FeedSummary::new("Broken")
+        };
+        let dead = FeedSummary {
+            skipped_until: Some(Utc.ymd(2018, 10, 2).and_hms(0, 0, 0).with_timezone(&Local)),
+            ..FeedSummary::new("Dead")
+        };
+        assert_eq!(
+            json(when, &[read, broken, dead]).compact(),
+            concat!(
+                r#"{"when":"2018-10-01T12:00:00Z","feeds":["#,
+                r#"{"name":"Read","unread":1,"opened":["http://example.com/1"],"#,
+                r#""errors":[],"next_scheduled":"2018-10-05"},"#,
+                r#"{"name":"Broken","unread":0,"opened":[],"#,
+                r#""errors":["Failed to download: \"404 Not Found\""]},"#,
+                r#"{"name":"Dead","unread":0,"opened":[],"errors":[],"#,
+                r#""skipped_until":"2018-10-02T00:00:00Z"}]}"#,
+            )
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! `feedburst validate`: finding every mistake in a config and the feed
+//! histories at once, instead of stopping at the first like a normal run.
+
+use std::collections::HashMap;
+
+use crate::error::ParseError;
+use crate::feed::FeedInfo;
+use crate::parser::FeedSpans;
+
+/// Every error in `text`, found by parsing it with `parse` again and again,
+/// each time with the line the last error was on left blank. Also what
+/// parsing gives once all those lines are blank, unless there's an error that
+/// blanking lines doesn't get rid of.
+pub fn every_error<T, F>(text: &str, parse: F) -> (Option<T>, Vec<ParseError>)
+where
+    F: Fn(&str) -> Result<T, ParseError>,
+{
+    let mut lines: Vec<&str> = text.lines().collect();
+    let mut errors = Vec::new();
+    loop {
+        let blanked = lines.join("\n");
+        let err = match parse(&blanked) {
+            Ok(parsed) => return (Some(parsed), errors),
+            Err(err) => err,
+        };
+        let ParseError::Expected { row, .. } = err;
+        let line = row.checked_sub(1).and_then(|i| lines.get_mut(i));
+        errors.push(err);
+        match line {
+            Some(line) if !line.is_empty() => *line = "",
+            _ => return (None, errors),
+        }
+    }
+}
+
+/// An error for each feed with the same name as one before it, which would
+/// share its history.
+pub fn duplicate_names(text: &str, feeds: &[(FeedInfo, FeedSpans)]) -> Vec<ParseError> {
+    let mut first_rows = HashMap::new();
+    let mut errors = Vec::new();
+    for (info, spans) in feeds {
+        let first = *first_rows.entry(info.name.as_str()).or_insert(spans.row);
+        if first == spans.row {
+            continue;
+        }
+        let line = text.lines().nth(spans.row - 1).unwrap_or_default();
+        let quoted = format!("\"{}\"", info.name);
+        let span = line
+            .find(&quoted)
+            .map(|start| (start, start + quoted.len() - 1));
+        errors.push(ParseError::Expected {
+            msg: format!(
+                "a name that no other feed has (it's the same as the feed on line {})",
+                first
+            ),
+            row: spans.row,
+            span,
+        });
+    }
+    errors
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{parse_config_spans, parse_events, Machine};
+
+    #[test]
+    fn test_every_error() {
+        let config = r#""One" <http://one.example.com> @ evry day
+"Two" <http://two.example.com> @ every 2 days
+"Three" http://three.example.com
+"Four" <http://four.example.com> @ on saturday
+"#;
+        let machine = Machine::current(None);
+        let (feeds, errors) = every_error(config, |text| parse_config_spans(text, &machine));
+        let rows: Vec<_> = errors
+            .iter()
+            .map(|&ParseError::Expected { row, .. }| row)
+            .collect();
+        assert_eq!(rows, vec![1, 3]);
+        let names: Vec<_> = feeds
+            .unwrap()
+            .into_iter()
+            .map(|(info, _)| info.name)
+            .collect();
+        assert_eq!(names, vec!["Two", "Four"]);
+
+        // Invalid patterns are among the errors
+        let (_, errors) = every_error(
+            r#""One" <http://one.example.com> @ keep title /(/"#,
+            |text| parse_config_spans(text, &machine),
+        );
+        assert_eq!(errors.len(), 1);
+
+        let events = "
+<http://example.com/1> 2018-10-01T12:00:00+00:00
+read yesterday
+reed 2018-10-02T12:00:00+00:00
+";
+        let (parsed, errors) = every_error(events, parse_events);
+        let rows: Vec<_> = errors
+            .iter()
+            .map(|&ParseError::Expected { row, .. }| row)
+            .collect();
+        assert_eq!(rows, vec![3, 4]);
+        assert_eq!(parsed.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let config = r#""Comic" <http://one.example.com>
+"Other" <http://other.example.com>
+"Comic" <http://two.example.com>
+"#;
+        let feeds = parse_config_spans(config, &Machine::current(None)).unwrap();
+        assert_eq!(
+            duplicate_names(config, &feeds),
+            vec![ParseError::expected(
+                "a name that no other feed has (it's the same as the feed on line 1)",
+                3,
+                (0, 6)
+            )]
+        );
+    }
+}